@@ -0,0 +1,19 @@
+//! Compile arbitrary bytes as a pattern
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ogex::Regex;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(pattern) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(regex) = Regex::new(pattern) else {
+        return;
+    };
+    // A compiled program loads back as the same regex
+    let restored = Regex::from_bytes(&regex.to_bytes()).expect("a compiled program loads");
+    assert_eq!(restored.as_str(), regex.as_str());
+    assert_eq!(restored.group_count(), regex.group_count());
+});
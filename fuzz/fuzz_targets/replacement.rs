@@ -0,0 +1,50 @@
+//! Parse arbitrary replacement templates and replace with them
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use ogex::{Regex, Replacement, ReplacementOptions, ReplacementSyntax};
+
+#[derive(Debug, Arbitrary)]
+enum Syntax {
+    Ogex,
+    Dollar,
+    Mixed,
+    Braces,
+}
+
+#[derive(Debug, Arbitrary)]
+struct Input<'a> {
+    template: &'a str,
+    syntax: Syntax,
+    expand_escapes: bool,
+    haystack: &'a str,
+}
+
+fuzz_target!(|input: Input<'_>| {
+    let syntax = match input.syntax {
+        Syntax::Ogex => ReplacementSyntax::Ogex,
+        Syntax::Dollar => ReplacementSyntax::Dollar,
+        Syntax::Mixed => ReplacementSyntax::Mixed,
+        Syntax::Braces => ReplacementSyntax::Braces,
+    };
+    let mut options = ReplacementOptions::with_syntax(syntax);
+    options.expand_escapes = input.expand_escapes;
+    let Ok(replacement) = Replacement::parse_with(input.template, &options) else {
+        return;
+    };
+
+    // A pattern with numbered, named and optional groups for the template
+    // to refer to; references it does not define are errors, not panics
+    let regex = Regex::new(r"(\w)(name:\d+)?|(\s)").expect("the pattern is valid");
+    if let Ok(replaced) = regex.replace_all(input.haystack, &replacement)
+        && !regex.is_match(input.haystack)
+    {
+        assert_eq!(replaced, input.haystack);
+    }
+    if let Some(m) = regex.find(input.haystack) {
+        let mut expanded = String::new();
+        let _ = m.expand(input.haystack, input.template, &mut expanded);
+    }
+});
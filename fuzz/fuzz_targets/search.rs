@@ -0,0 +1,99 @@
+//! Search arbitrary haystacks with arbitrary patterns, checking that every
+//! offset reported can be sliced and that the search methods agree
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use ogex::{Match, RegexBuilder};
+
+/// Most steps one search may take, so slow patterns end instead of timing out
+const STEP_LIMIT: usize = 100_000;
+
+#[derive(Debug, Arbitrary)]
+struct Input<'a> {
+    pattern: &'a str,
+    haystack: &'a str,
+    case_insensitive: bool,
+    multiline: bool,
+    dotall: bool,
+    /// Where `find_at` starts, taken modulo the haystack's length
+    start: usize,
+}
+
+/// Check that a span lies in `haystack` on character boundaries
+fn check_span(haystack: &str, (start, end): (usize, usize)) {
+    assert!(start <= end, "span {start}..{end} is reversed");
+    assert!(
+        haystack.get(start..end).is_some(),
+        "span {start}..{end} does not slice {haystack:?}"
+    );
+}
+
+fn check_match(haystack: &str, m: &Match, groups: usize) {
+    check_span(haystack, (m.start, m.end));
+    assert_eq!(m.as_str(haystack), &haystack[m.start..m.end]);
+    // Groups inside lookarounds may lie outside the match, but never
+    // outside the haystack
+    for index in 1..=groups {
+        if let Some(span) = m.group(index as u32) {
+            check_span(haystack, span);
+        }
+    }
+    for (name, span) in &m.named_groups {
+        assert_eq!(m.named_group(name), Some(*span));
+        check_span(haystack, *span);
+    }
+}
+
+fuzz_target!(|input: Input<'_>| {
+    let Ok(regex) = RegexBuilder::new(input.pattern)
+        .case_insensitive(input.case_insensitive)
+        .multiline(input.multiline)
+        .dotall(input.dotall)
+        .step_limit(STEP_LIMIT)
+        .build()
+    else {
+        return;
+    };
+    let haystack = input.haystack;
+    let groups = regex.group_count();
+
+    let first = regex.find(haystack);
+    assert_eq!(regex.is_match(haystack), first.is_some());
+    if let Some(m) = &first {
+        check_match(haystack, m, groups);
+    }
+
+    let mut locs = regex.capture_locations();
+    let span = regex.captures_read(&mut locs, haystack);
+    assert_eq!(span, first.as_ref().map(|m| (m.start, m.end)));
+    for index in 0..=groups {
+        if let Some(span) = locs.get(index) {
+            check_span(haystack, span);
+        }
+    }
+
+    let mut previous_end = 0;
+    for (count, m) in regex.find_iter(haystack).take(1000).enumerate() {
+        if count == 0 {
+            assert_eq!(Some(&m), first.as_ref());
+        }
+        check_match(haystack, &m, groups);
+        assert!(m.start >= previous_end, "matches overlap");
+        previous_end = m.end;
+    }
+
+    let mut start = input.start % (haystack.len() + 1);
+    while !haystack.is_char_boundary(start) {
+        start -= 1;
+    }
+    if let Some(m) = regex.find_at(haystack, start) {
+        assert!(m.start >= start, "match at {} before {start}", m.start);
+        check_match(haystack, &m, groups);
+    }
+
+    for piece in regex.split(haystack).take(1000) {
+        assert!(piece.len() <= haystack.len());
+    }
+});
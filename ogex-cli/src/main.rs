@@ -1,8 +1,21 @@
-use clap::{Parser, Subcommand};
-use colored::Colorize;
+use std::collections::VecDeque;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use colored::{Color, Colorize};
+use ogex::algebra::Language;
 use ogex::{
-    Regex, convert_all, explain, transpile, transpile_debug, transpile_to_ogex, transpile_to_python,
+    Diagnostic, Dialect, Generator, HighlightSpan, Match, Regex, RegexBuilder, RegexError,
+    RegexSet, Replacement, ReplacementOptions, ReplacementSyntax, StreamMatch, StreamMatcher,
+    TraceEvent, TranspileError, Weighting, convert_all, explain, transpile, transpile_debug,
+    transpile_from_legacy, transpile_to_dialect, transpile_to_ogex, transpile_to_python,
 };
+use serde_json::{Value, json};
 
 #[derive(Parser)]
 #[command(name = "ogex")]
@@ -11,6 +24,65 @@ use ogex::{
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// When to color the output; `auto` honors NO_COLOR and colors only terminals
+    #[arg(long, value_enum, global = true, default_value = "auto")]
+    color: ColorChoice,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+/// The dialects `convert --dialect` can write
+#[derive(Clone, Copy, ValueEnum)]
+enum DialectArg {
+    Pcre2,
+    Js,
+    Python,
+    Re2,
+    Posix,
+}
+
+impl From<DialectArg> for Dialect {
+    fn from(arg: DialectArg) -> Self {
+        match arg {
+            DialectArg::Pcre2 => Dialect::Pcre2,
+            DialectArg::Js => Dialect::Js,
+            DialectArg::Python => Dialect::Python,
+            DialectArg::Re2 => Dialect::Re2,
+            DialectArg::Posix => Dialect::Posix,
+        }
+    }
+}
+
+/// How `generate --weighting` chooses between alternation branches
+#[derive(Clone, Copy, ValueEnum)]
+enum WeightingArg {
+    /// Every branch is equally likely
+    Uniform,
+    /// Every innermost alternative is equally likely
+    Alternatives,
+}
+
+impl From<WeightingArg> for Weighting {
+    fn from(arg: WeightingArg) -> Self {
+        match arg {
+            WeightingArg::Uniform => Weighting::Uniform,
+            WeightingArg::Alternatives => Weighting::Alternatives,
+        }
+    }
+}
+
+/// The syntaxes `convert --from` can read
+#[derive(Clone, Copy, ValueEnum)]
+enum SourceSyntax {
+    /// Ogex syntax, which also accepts `(?<name>...)` and `(?P<name>...)`
+    Ogex,
+    /// PCRE/Python syntax, such as `(?=...)` and `\k<name>`
+    Legacy,
 }
 
 #[derive(Subcommand)]
@@ -24,6 +96,9 @@ enum Commands {
         /// Show detailed match information
         #[arg(short, long)]
         verbose: bool,
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// Convert regex syntax between flavors
     Convert {
@@ -41,47 +116,278 @@ enum Commands {
         /// Show AST debug output
         #[arg(short, long)]
         debug: bool,
+        /// Write the pattern in another regex dialect
+        #[arg(long, value_enum, conflicts_with_all = ["ogex", "python", "pcre", "debug"])]
+        dialect: Option<DialectArg>,
+        /// The syntax the pattern is written in
+        #[arg(long, value_enum, default_value = "ogex")]
+        from: SourceSyntax,
+        /// Only check that the pattern is valid (and can be written in
+        /// --dialect), exiting with 1 if not
+        #[arg(long, conflicts_with_all = ["ogex", "python", "pcre", "debug"])]
+        check: bool,
+        /// Print the conversions as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// Find all matches in input
     Find {
-        /// The regex pattern
-        pattern: String,
-        /// The input string
-        input: String,
+        /// The regex pattern (with -e or -f, the first file to search)
+        #[arg(required_unless_present_any = ["regexp", "pattern_file"])]
+        pattern: Option<String>,
+        /// The input string, or the files to search with --files
+        inputs: Vec<String>,
+        #[command(flatten)]
+        grep: GrepArgs,
+        /// Print each match as TEMPLATE, e.g. `{name} -> {2}`, with `{0}` for
+        /// the whole match and `{{`/`}}` for literal braces
+        #[arg(long, value_name = "TEMPLATE", conflicts_with = "json")]
+        format: Option<String>,
+        /// Print the matches as JSON, one record per line with --files
+        #[arg(long)]
+        json: bool,
     },
     /// Check if pattern matches
     Match {
+        /// The regex pattern (with -e or -f, the first file to search)
+        #[arg(required_unless_present_any = ["regexp", "pattern_file"])]
+        pattern: Option<String>,
+        /// The input string, or the files to search with --files
+        inputs: Vec<String>,
+        #[command(flatten)]
+        grep: GrepArgs,
+    },
+    /// Replace matches in files or stdin, like `sed s/.../.../g`
+    Replace {
+        /// The regex pattern
+        pattern: String,
+        /// The replacement template, e.g. `[\1]` or `\g{name}`
+        template: String,
+        /// Files to rewrite, printing the result (`-` or none reads stdin)
+        files: Vec<String>,
+        /// Edit files in place, keeping a backup with SUFFIX appended if given
+        #[arg(
+            short,
+            long,
+            value_name = "SUFFIX",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = ""
+        )]
+        in_place: Option<String>,
+        /// Replace at most N matches in each input
+        #[arg(short = 'n', long, value_name = "N")]
+        count: Option<usize>,
+    },
+    /// Interactively test inputs against a pattern
+    Repl {
+        /// The pattern to start with (prompted for if not given)
+        pattern: Option<String>,
+    },
+    /// Print the compiled NFA as a Graphviz DOT graph
+    Dot {
+        /// The regex pattern
+        pattern: String,
+        /// Output format; `json` describes the NFA as JSON, and anything but
+        /// `dot` is rendered by Graphviz's `dot -T<FORMAT>`
+        #[arg(long, default_value = "dot")]
+        format: String,
+    },
+    /// Show each step the matcher takes, to see why a pattern does or does
+    /// not match
+    Trace {
         /// The regex pattern
         pattern: String,
-        /// The input string
+        /// The input string to match against
         input: String,
     },
+    /// Print random strings the pattern matches
+    Generate {
+        /// The regex pattern
+        pattern: String,
+        /// How many strings to print
+        #[arg(short = 'n', long, value_name = "N", default_value_t = 1)]
+        count: usize,
+        /// Most times `*`, `+` and `{n,}` repeat beyond their minimum
+        #[arg(long, value_name = "K", default_value_t = 3)]
+        max_repeat: u32,
+        /// Seed for the random number generator, to repeat a run
+        #[arg(long)]
+        seed: Option<u64>,
+        /// How branches of alternations are chosen
+        #[arg(long, value_enum, default_value = "uniform")]
+        weighting: WeightingArg,
+    },
+    /// Check whether two patterns match exactly the same strings, printing
+    /// a string only one of them matches if not; exits with 1 if they differ
+    Equiv {
+        /// The first pattern
+        first: String,
+        /// The second pattern
+        second: String,
+    },
     /// Explain a regex pattern in human-readable format
     Explain {
         /// The regex pattern to explain
         pattern: String,
     },
+    /// Print a shell completion script, e.g. `ogex completions bash`
+    Completions {
+        /// The shell to complete for
+        shell: Shell,
+    },
 }
 
+/// Options for searching files line by line, shared by `find` and `match`
+#[derive(Args)]
+struct GrepArgs {
+    /// Search files line by line instead of a string (`-` reads stdin)
+    #[arg(long)]
+    files: bool,
+    /// Search for PATTERN; repeat to match lines matching any of the patterns
+    #[arg(short = 'e', long, value_name = "PATTERN", requires = "files")]
+    regexp: Vec<String>,
+    /// Read patterns to search for from FILE, one per line
+    #[arg(short = 'f', long, value_name = "FILE", requires = "files")]
+    pattern_file: Vec<String>,
+    /// Print each match instead of the whole line (always on for `find`)
+    #[arg(short, long, requires = "files")]
+    only_matching: bool,
+    /// Prefix each line with its line number
+    #[arg(short = 'n', long, requires = "files")]
+    line_number: bool,
+    /// Print NUM lines of context after each matching line
+    #[arg(short = 'A', long, value_name = "NUM", requires = "files")]
+    after_context: Option<usize>,
+    /// Print NUM lines of context before each matching line
+    #[arg(short = 'B', long, value_name = "NUM", requires = "files")]
+    before_context: Option<usize>,
+    /// Print NUM lines of context around each matching line
+    #[arg(short = 'C', long, value_name = "NUM", requires = "files")]
+    context: Option<usize>,
+    /// Highlight each capture group in its own color within matches
+    #[arg(long, requires = "files")]
+    color_groups: bool,
+    /// Print nothing; exit with 0 as soon as anything matches
+    #[arg(short, long, requires = "files")]
+    quiet: bool,
+    /// Print how many lines (or matches, for `find` and -o) match in each file
+    #[arg(short, long, requires = "files")]
+    count: bool,
+    /// Search each file as a whole, so matches can span lines and `^`/`$`
+    /// match at every line break; each match is printed with the line it
+    /// starts on
+    #[arg(
+        long,
+        requires = "files",
+        conflicts_with_all = ["after_context", "before_context", "context"]
+    )]
+    multiline_file: bool,
+    /// Print only the names of files with a match
+    #[arg(short = 'l', long, requires = "files")]
+    files_with_matches: bool,
+    /// End each output record with a NUL byte instead of a newline, for `xargs -0`
+    #[arg(short = '0', long, requires = "files")]
+    null: bool,
+}
+
+/// Colors cycled through for capture groups, by group number
+const GROUP_COLORS: [Color; 5] = [
+    Color::Yellow,
+    Color::Cyan,
+    Color::Blue,
+    Color::Red,
+    Color::Magenta,
+];
+
 fn main() {
     let cli = Cli::parse();
+    // `auto` leaves the decision to `colored`, which checks NO_COLOR and
+    // whether stdout is a terminal
+    match cli.color {
+        ColorChoice::Auto => {}
+        ColorChoice::Always => colored::control::set_override(true),
+        ColorChoice::Never => colored::control::set_override(false),
+    }
 
     match cli.command {
         Commands::Test {
             pattern,
             input,
             verbose,
-        } => cmd_test(&pattern, &input, verbose),
+            json,
+        } => match json {
+            true => cmd_test_json(&pattern, &input),
+            false => cmd_test(&pattern, &input, verbose),
+        },
         Commands::Convert {
             pattern,
             ogex,
             python,
             pcre,
             debug,
-        } => cmd_convert(pattern.as_deref(), ogex, python, pcre, debug),
-        Commands::Find { pattern, input } => cmd_find(&pattern, &input),
-        Commands::Match { pattern, input } => cmd_match(&pattern, &input),
+            dialect,
+            from,
+            check,
+            json,
+        } => {
+            let pattern = pattern.map(|pattern| match from {
+                SourceSyntax::Ogex => pattern,
+                SourceSyntax::Legacy => from_legacy(&pattern, json),
+            });
+            let dialect = dialect.map(Dialect::from);
+            match (pattern, check, dialect, json) {
+                (Some(pattern), true, _, _) => cmd_convert_check(&pattern, dialect, json),
+                (Some(pattern), false, Some(dialect), _) => {
+                    cmd_convert_dialect(&pattern, dialect, json)
+                }
+                (Some(pattern), false, None, true) => {
+                    cmd_convert_json(&pattern, ogex, python, pcre)
+                }
+                (pattern, ..) => cmd_convert(pattern.as_deref(), ogex, python, pcre, debug),
+            }
+        }
+        Commands::Find {
+            pattern,
+            inputs,
+            grep,
+            format,
+            json,
+        } => match (grep.files, json) {
+            (true, _) => cmd_grep(pattern, inputs, &grep, true, format.as_deref(), json),
+            (false, true) => cmd_find_json(&required(pattern), single_input(&inputs)),
+            (false, false) => {
+                cmd_find(&required(pattern), single_input(&inputs), format.as_deref())
+            }
+        },
+        Commands::Match {
+            pattern,
+            inputs,
+            grep,
+        } => match grep.files {
+            true => cmd_grep(pattern, inputs, &grep, false, None, false),
+            false => cmd_match(&required(pattern), single_input(&inputs)),
+        },
+        Commands::Replace {
+            pattern,
+            template,
+            files,
+            in_place,
+            count,
+        } => cmd_replace(&pattern, &template, &files, in_place.as_deref(), count),
+        Commands::Repl { pattern } => cmd_repl(pattern),
+        Commands::Dot { pattern, format } => cmd_dot(&pattern, &format),
+        Commands::Trace { pattern, input } => cmd_trace(&pattern, &input),
+        Commands::Generate {
+            pattern,
+            count,
+            max_repeat,
+            seed,
+            weighting,
+        } => cmd_generate(&pattern, count, max_repeat, seed, weighting.into()),
+        Commands::Equiv { first, second } => cmd_equiv(&first, &second),
         Commands::Explain { pattern } => cmd_explain(&pattern),
+        Commands::Completions { shell } => cmd_completions(shell),
     }
 }
 
@@ -93,10 +399,7 @@ fn cmd_test(pattern: &str, input: &str, verbose: bool) {
 
     let regex = match Regex::new(pattern) {
         Ok(r) => r,
-        Err(e) => {
-            eprintln!("{} {}", "Error:".red().bold(), e);
-            std::process::exit(1);
-        }
+        Err(e) => pattern_error(pattern, e),
     };
 
     if let Some(m) = regex.find(input) {
@@ -110,15 +413,16 @@ fn cmd_test(pattern: &str, input: &str, verbose: bool) {
             println!("{}", "Capture groups:".bold());
 
             // Show numbered groups
-            for (idx, group) in m.groups.iter().enumerate() {
-                if let Some((start, end)) = group {
-                    println!(
+            for (idx, group) in m.groups.iter().enumerate().skip(1) {
+                match group {
+                    Some((start, end)) => println!(
                         "  Group {}: {}..{} = {}",
                         idx,
                         start,
                         end,
                         &input[*start..*end].green()
-                    );
+                    ),
+                    None => println!("  Group {}: {}", idx, "(did not participate)".dimmed()),
                 }
             }
 
@@ -146,6 +450,101 @@ fn cmd_test(pattern: &str, input: &str, verbose: bool) {
     }
 }
 
+/// Report a pattern that failed to compile, pointing at the error if possible
+fn pattern_error(pattern: &str, err: RegexError) -> ! {
+    print_pattern_error(pattern, &err);
+    std::process::exit(1);
+}
+
+fn print_pattern_error(pattern: &str, err: &RegexError) {
+    match err.spanned() {
+        Some(spanned) => eprintln!("{}\n{}", "Error:".red().bold(), spanned.render(pattern)),
+        None => eprintln!("{} {}", "Error:".red().bold(), err),
+    }
+}
+
+/// A match as a JSON record, with `null` for groups that did not participate
+fn match_json(regex: &Regex, m: &Match, input: &str) -> Value {
+    let groups: Vec<Value> = regex
+        .capture_names()
+        .zip(0..)
+        .skip(1)
+        .map(|(name, index)| match m.group(index) {
+            Some((start, end)) => json!({
+                "index": index,
+                "name": name,
+                "start": start,
+                "end": end,
+                "text": &input[start..end],
+            }),
+            None => Value::Null,
+        })
+        .collect();
+    json!({
+        "start": m.start,
+        "end": m.end,
+        "text": m.as_str(input),
+        "groups": groups,
+    })
+}
+
+/// Compile a pattern for a JSON command, printing an error record and
+/// exiting if it is invalid
+fn compile_json(pattern: &str) -> Regex {
+    Regex::new(pattern).unwrap_or_else(|err| json_error(pattern, &err))
+}
+
+/// Print a pattern error as JSON, with its code and span, and exit
+fn json_error(pattern: &str, err: &RegexError) -> ! {
+    print_json_error(pattern, err);
+    std::process::exit(1);
+}
+
+fn print_json_error(pattern: &str, err: &RegexError) {
+    let record = json!({ "pattern": pattern, "error": Diagnostic::from(err) });
+    println!("{}", record);
+}
+
+fn cmd_test_json(pattern: &str, input: &str) {
+    let regex = compile_json(pattern);
+    let found = regex.find(input);
+    let record = json!({
+        "pattern": pattern,
+        "input": input,
+        "match": found.map(|m| match_json(&regex, &m, input)),
+    });
+    println!("{}", record);
+}
+
+fn cmd_find_json(pattern: &str, input: &str) {
+    let regex = compile_json(pattern);
+    let matches: Vec<Value> = regex
+        .find_iter(input)
+        .map(|m| match_json(&regex, &m, input))
+        .collect();
+    println!(
+        "{}",
+        json!({ "pattern": pattern, "input": input, "matches": matches })
+    );
+}
+
+/// Print the requested conversions (all of them if none is selected) as JSON
+fn cmd_convert_json(pattern: &str, to_ogex: bool, to_python: bool, to_pcre: bool) {
+    let result = convert_all(pattern).unwrap_or_else(|err| json_error(pattern, &err));
+    let show_all = !to_ogex && !to_python && !to_pcre;
+    let mut record = json!({ "pattern": pattern });
+    for (selected, flavor, output) in [
+        (to_ogex, "ogex", result.ogex),
+        (to_python, "python", result.python),
+        (to_pcre, "pcre", result.pcre),
+    ] {
+        if selected || show_all {
+            record[flavor] = Value::String(output);
+        }
+    }
+    println!("{}", record);
+}
+
 fn cmd_convert(pattern: Option<&str>, to_ogex: bool, to_python: bool, to_pcre: bool, debug: bool) {
     // Show help if no pattern provided
     let pattern = match pattern {
@@ -163,6 +562,9 @@ fn cmd_convert(pattern: Option<&str>, to_ogex: bool, to_python: bool, to_pcre: b
             println!("  ogex convert --ogex \"(?<name>p)\"       Convert legacy TO Ogex");
             println!("  ogex convert --python \"(?P<name>p)\"    Output as Python syntax");
             println!("  ogex convert --pcre \"(?<name>p)\"       Output as PCRE syntax");
+            println!("  ogex convert --dialect re2 \"(n:p)\"     Output in another dialect");
+            println!("  ogex convert --from legacy \"(?=p)\"     Read PCRE/Python syntax");
+            println!("  ogex convert --check \"(n:p)\"           Only validate the pattern");
             println!();
             println!("Flavors:");
             println!("  Ogex:   (name:pattern)     - Ogex native syntax");
@@ -230,18 +632,88 @@ fn cmd_convert(pattern: Option<&str>, to_ogex: bool, to_python: bool, to_pcre: b
     }
 }
 
-fn cmd_find(pattern: &str, input: &str) {
-    let regex = match Regex::new(pattern) {
-        Ok(r) => r,
+/// Rewrite a pattern in legacy syntax into Ogex syntax, exiting with the
+/// error if that fails
+fn from_legacy(pattern: &str, json: bool) -> String {
+    match transpile_from_legacy(pattern) {
+        Ok(converted) => converted,
+        Err(e) if json => json_error(pattern, &e),
         Err(e) => {
             eprintln!("{} {}", "Error:".red().bold(), e);
             std::process::exit(1);
         }
+    }
+}
+
+/// Print a pattern written in another dialect
+fn cmd_convert_dialect(pattern: &str, dialect: Dialect, json: bool) {
+    let output = transpile_to_dialect(pattern, dialect)
+        .unwrap_or_else(|e| transpile_error(pattern, dialect, &e, json));
+    if json {
+        println!("{}", json!({ "pattern": pattern, dialect.id(): output }));
+    } else {
+        println!("{}:", dialect.name().bold());
+        println!("  {}", output.green());
+    }
+}
+
+/// Check that a pattern parses and, with a dialect, that it can be written
+/// in that dialect, printing nothing but errors unless `json` is set
+fn cmd_convert_check(pattern: &str, dialect: Option<Dialect>, json: bool) {
+    match dialect {
+        Some(dialect) => {
+            if let Err(e) = transpile_to_dialect(pattern, dialect) {
+                transpile_error(pattern, dialect, &e, json);
+            }
+        }
+        None => {
+            if let Err(e) = ogex::parse(pattern) {
+                match json {
+                    true => json_error(pattern, &e),
+                    false => pattern_error(pattern, e),
+                }
+            }
+        }
+    }
+    if json {
+        println!("{}", json!({ "pattern": pattern, "valid": true }));
+    }
+}
+
+/// Report why a pattern could not be written in a dialect, and exit
+fn transpile_error(pattern: &str, dialect: Dialect, err: &TranspileError, json: bool) -> ! {
+    match (err, json) {
+        (TranspileError::Pattern(e), true) => json_error(pattern, e),
+        (TranspileError::Pattern(e), false) => pattern_error(pattern, e.clone()),
+        (TranspileError::Unsupported { .. }, true) => {
+            let error = json!({ "severity": "error", "message": err.to_string() });
+            println!(
+                "{}",
+                json!({ "pattern": pattern, "dialect": dialect.id(), "error": error })
+            );
+            std::process::exit(1);
+        }
+        (TranspileError::Unsupported { .. }, false) => {
+            eprintln!("{} {}", "Error:".red().bold(), err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn cmd_find(pattern: &str, input: &str, format: Option<&str>) {
+    let regex = match Regex::new(pattern) {
+        Ok(r) => r,
+        Err(e) => pattern_error(pattern, e),
     };
 
     let matches = regex.find_all(input);
 
-    if matches.is_empty() {
+    if let Some(template) = format {
+        let template = format_template(template, std::slice::from_ref(&regex));
+        for m in &matches {
+            println!("{}", expand_format(&template, input, m));
+        }
+    } else if matches.is_empty() {
         println!("{}", "No matches found".red());
     } else {
         println!(
@@ -266,10 +738,7 @@ fn cmd_find(pattern: &str, input: &str) {
 fn cmd_match(pattern: &str, input: &str) {
     let regex = match Regex::new(pattern) {
         Ok(r) => r,
-        Err(e) => {
-            eprintln!("{} {}", "Error:".red().bold(), e);
-            std::process::exit(1);
-        }
+        Err(e) => pattern_error(pattern, e),
     };
 
     if regex.is_match(input) {
@@ -281,6 +750,1004 @@ fn cmd_match(pattern: &str, input: &str) {
     }
 }
 
+/// Parse a `--format` template, checking that every group it refers to
+/// exists in each of the regexes
+fn format_template(template: &str, regexes: &[Regex]) -> Replacement {
+    let options = ReplacementOptions::with_syntax(ReplacementSyntax::Braces).expand_escapes(true);
+    let parsed = Replacement::parse_with(template, &options).and_then(|replacement| {
+        regexes
+            .iter()
+            .try_for_each(|regex| replacement.validate(regex.group_registry()))
+            .map(|()| replacement)
+    });
+    match parsed {
+        Ok(replacement) => replacement,
+        Err(e) => {
+            eprintln!("{} {}", "Error:".red().bold(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// A match laid out by a `--format` template
+fn expand_format(template: &Replacement, text: &str, m: &Match) -> String {
+    let mut result = String::new();
+    template
+        .append_match(&mut result, text, m)
+        .expect("groups that did not participate expand to empty text");
+    result
+}
+
+/// The positional pattern, which clap only leaves out alongside `-e` or
+/// `-f`, and those need `--files`
+fn required(pattern: Option<String>) -> String {
+    pattern.expect("pattern is required without --files")
+}
+
+/// The one input string expected when not searching files
+fn single_input(inputs: &[String]) -> &str {
+    match inputs {
+        [input] => input,
+        _ => Cli::command()
+            .error(
+                clap::error::ErrorKind::WrongNumberOfValues,
+                "expected exactly one input string; use --files to search files",
+            )
+            .exit(),
+    }
+}
+
+/// Scan files line by line, printing `file: text` for each matching line
+/// (or for each match with `only_matching`)
+///
+/// With `-e` or `-f` a line matches if any of the patterns does, and the
+/// positional pattern is taken as the first file. When there is more than
+/// one pattern, output is labelled with the pattern that matched.
+///
+/// With `format`, each match is printed laid out by that template instead
+/// of highlighted.
+///
+/// With `json`, each match is printed as a JSON record on its own line
+/// instead.
+///
+/// With no files, stdin is read. As in grep, exits with 0 if anything
+/// matched, 1 if nothing did and 2 if a pattern is invalid or a file could
+/// not be read, unless `quiet` found a match first.
+fn cmd_grep(
+    pattern: Option<String>,
+    mut paths: Vec<String>,
+    args: &GrepArgs,
+    only_matching: bool,
+    format: Option<&str>,
+    json: bool,
+) {
+    let mut patterns = args.regexp.clone();
+    for path in &args.pattern_file {
+        match fs::read_to_string(path) {
+            Ok(text) => patterns.extend(text.lines().map(str::to_string)),
+            Err(e) => {
+                eprintln!("{} {}: {}", "Error:".red().bold(), path, e);
+                std::process::exit(2);
+            }
+        }
+    }
+    match pattern {
+        Some(pattern) if patterns.is_empty() => patterns.push(pattern),
+        Some(path) => paths.insert(0, path),
+        None => {}
+    }
+    let regexes: Vec<Regex> = patterns
+        .iter()
+        .map(|pattern| {
+            match RegexBuilder::new(pattern)
+                .multiline(args.multiline_file)
+                .build()
+            {
+                Ok(r) => r,
+                Err(e) => {
+                    match json {
+                        true => print_json_error(pattern, &e),
+                        false => print_pattern_error(pattern, &e),
+                    }
+                    // As in grep, a bad pattern is an error rather than a miss
+                    std::process::exit(2);
+                }
+            }
+        })
+        .collect();
+    let format = format.map(|template| format_template(template, &regexes));
+    let set = RegexSet::from(regexes);
+
+    // JSON records hold only matches, so context lines are left out
+    let context = |lines: Option<usize>| match json {
+        true => 0,
+        false => lines.or(args.context).unwrap_or(0),
+    };
+    let mut grep = Grep {
+        set: &set,
+        labels: (patterns.len() > 1).then_some(&patterns[..]),
+        only_matching: only_matching || args.only_matching,
+        line_number: args.line_number,
+        color_groups: args.color_groups,
+        format: format.as_ref(),
+        quiet: args.quiet,
+        count: args.count,
+        files_with_matches: args.files_with_matches,
+        terminator: if args.null { '\0' } else { '\n' },
+        before: context(args.before_context),
+        after: context(args.after_context),
+        json,
+        matched: false,
+    };
+
+    if paths.is_empty() {
+        paths.push("-".to_string());
+    }
+    let mut failed = false;
+    for path in &paths {
+        let (name, reader): (&str, Box<dyn BufRead>) = if path == "-" {
+            ("(standard input)", Box::new(io::stdin().lock()))
+        } else {
+            match File::open(path) {
+                Ok(file) => (path, Box::new(BufReader::new(file))),
+                Err(e) => {
+                    eprintln!("{} {}: {}", "Error:".red().bold(), path, e);
+                    failed = true;
+                    continue;
+                }
+            }
+        };
+        let scanned = match args.multiline_file {
+            true => grep.scan_whole(name, reader),
+            false => grep.scan(name, reader),
+        };
+        if let Err(e) = scanned {
+            eprintln!("{} {}: {}", "Error:".red().bold(), path, e);
+            failed = true;
+        }
+    }
+
+    std::process::exit(match (failed, grep.matched) {
+        (true, _) => 2,
+        (false, true) => 0,
+        (false, false) => 1,
+    });
+}
+
+/// Searches input line by line, printing matching lines with context
+struct Grep<'a> {
+    set: &'a RegexSet,
+    /// The patterns, to label output with, when there is more than one
+    labels: Option<&'a [String]>,
+    only_matching: bool,
+    line_number: bool,
+    color_groups: bool,
+    /// Template to lay out each match with instead of highlighting it
+    format: Option<&'a Replacement>,
+    /// Print nothing and exit at the first match
+    quiet: bool,
+    /// Print the number of matches in each file instead of the matches
+    count: bool,
+    /// Print the name of each file with a match instead of the matches
+    files_with_matches: bool,
+    /// Ends each output record
+    terminator: char,
+    /// Lines of context to print before each matching line
+    before: usize,
+    /// Lines of context to print after each matching line
+    after: usize,
+    json: bool,
+    /// Whether any line has matched so far
+    matched: bool,
+}
+
+impl Grep<'_> {
+    fn scan(&mut self, name: &str, mut reader: impl BufRead) -> io::Result<()> {
+        let mut buf = Vec::new();
+        // Recent lines that may turn out to be context before a match
+        let mut before: VecDeque<(usize, String)> = VecDeque::with_capacity(self.before);
+        // Context lines still to print after the last match
+        let mut after = 0;
+        // The last line printed, to put `--` between separate groups of lines
+        let mut last_printed: Option<usize> = None;
+        let mut count = 0;
+        for number in 1.. {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            let line = String::from_utf8_lossy(&buf);
+            let line = line.trim_end_matches(['\n', '\r']);
+            let matches = self.set.find_all(line);
+
+            if matches.is_empty() {
+                if after > 0 {
+                    after -= 1;
+                    self.print_line(name, number, '-', line);
+                    last_printed = Some(number);
+                } else if self.before > 0 {
+                    if before.len() == self.before {
+                        before.pop_front();
+                    }
+                    before.push_back((number, line.to_string()));
+                }
+                continue;
+            }
+            self.matched = true;
+            if self.quiet {
+                std::process::exit(0);
+            }
+            if self.files_with_matches {
+                self.emit(name.magenta());
+                return Ok(());
+            }
+            if self.count {
+                count += match self.only_matching {
+                    true => matches.iter().filter(|(_, m)| m.start < m.end).count(),
+                    false => 1,
+                };
+                continue;
+            }
+
+            if self.json {
+                for (i, m) in &matches {
+                    let mut record = match_json(&self.set.regexes()[*i], m, line);
+                    record["file"] = json!(name);
+                    record["line"] = json!(number);
+                    if let Some(labels) = self.labels {
+                        record["pattern"] = json!(labels[*i]);
+                    }
+                    self.emit(record);
+                }
+                continue;
+            }
+
+            let first = before.front().map_or(number, |(n, _)| *n);
+            if (self.before > 0 || self.after > 0) && last_printed.is_some_and(|n| n + 1 < first) {
+                self.emit("--".cyan());
+            }
+            for (n, text) in before.drain(..) {
+                self.print_line(name, n, '-', &text);
+            }
+            if self.only_matching {
+                // Each match on its own line; empty matches have nothing to show
+                for (i, m) in matches.iter().filter(|(_, m)| m.start < m.end) {
+                    let text = self.match_text(line, *i, m);
+                    let label = self.label([*i]);
+                    self.print_line(name, number, ':', &format!("{}{}", label, text));
+                }
+            } else {
+                let mut indices: Vec<usize> = matches.iter().map(|(i, _)| *i).collect();
+                indices.sort_unstable();
+                indices.dedup();
+                let spans: Vec<HighlightSpan> = matches
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(k, (i, m))| self.set.regexes()[*i].highlight_match(m, k))
+                    .collect();
+                let text = highlight(line, &spans, self.color_groups);
+                let label = self.label(indices);
+                self.print_line(name, number, ':', &format!("{}{}", label, text));
+            }
+            last_printed = Some(number);
+            after = self.after;
+        }
+
+        if self.count {
+            self.print_count(name, count);
+        }
+        Ok(())
+    }
+
+    /// Search all of `reader` as one haystack, printing each match with the
+    /// number of the line it starts on
+    ///
+    /// Unlike [`Grep::scan`], a match may span any number of lines. Each
+    /// pattern runs a spanning [`StreamMatcher`], which holds only the text a
+    /// match could still use rather than the whole input.
+    fn scan_whole(&mut self, name: &str, mut reader: impl BufRead) -> io::Result<()> {
+        let set = self.set;
+        let mut matchers: Vec<_> = set
+            .regexes()
+            .iter()
+            .map(|regex| StreamMatcher::new(regex).spanning(true))
+            .collect();
+        let mut merged = Merged::default();
+        // Bytes read but not yet decoded, at most an incomplete character
+        let mut partial = Vec::new();
+        let mut count = 0;
+        loop {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() {
+                break;
+            }
+            partial.extend_from_slice(buf);
+            let read = buf.len();
+            reader.consume(read);
+
+            let text = decode_lossy(&mut partial);
+            for (i, matcher) in matchers.iter_mut().enumerate() {
+                let found = matcher.push(&text).map_err(io::Error::other)?;
+                merged.candidates.extend(found.into_iter().map(|m| (i, m)));
+            }
+            let settled = matchers.iter().map(StreamMatcher::settled).min();
+            for (i, m) in merged.take(settled.unwrap_or(usize::MAX)) {
+                if self.report_whole(name, i, &m, &mut count) {
+                    return Ok(());
+                }
+            }
+        }
+
+        let rest = String::from_utf8_lossy(&partial);
+        for (i, mut matcher) in matchers.into_iter().enumerate() {
+            let mut found = matcher.push(&rest).map_err(io::Error::other)?;
+            found.extend(matcher.finish().map_err(io::Error::other)?);
+            merged.candidates.extend(found.into_iter().map(|m| (i, m)));
+        }
+        for (i, m) in merged.take(usize::MAX) {
+            if self.report_whole(name, i, &m, &mut count) {
+                return Ok(());
+            }
+        }
+
+        if self.count {
+            self.print_count(name, count);
+        }
+        Ok(())
+    }
+
+    /// Print one match found by [`Grep::scan_whole`], returning whether the
+    /// file needs no further searching
+    fn report_whole(&mut self, name: &str, i: usize, m: &StreamMatch, count: &mut usize) -> bool {
+        self.matched = true;
+        if self.quiet {
+            std::process::exit(0);
+        }
+        if self.files_with_matches {
+            self.emit(name.magenta());
+            return true;
+        }
+        if m.start() == m.end() {
+            return false;
+        }
+
+        *count += 1;
+        if self.count {
+            return false;
+        }
+        let text = &m.line;
+        let number = 1 + m.line_number + text[..m.match_result.start].matches('\n').count();
+        if self.json {
+            let mut record = match_json(&self.set.regexes()[i], &m.match_result, text);
+            offset_json(&mut record, m.line_offset);
+            record["file"] = json!(name);
+            record["line"] = json!(number);
+            if let Some(labels) = self.labels {
+                record["pattern"] = json!(labels[i]);
+            }
+            self.emit(record);
+        } else {
+            let matched = self.match_text(text, i, &m.match_result);
+            let label = self.label([i]);
+            self.print_line(name, number, ':', &format!("{}{}", label, matched));
+        }
+        false
+    }
+
+    fn print_count(&self, name: &str, count: usize) {
+        match self.json {
+            true => self.emit(json!({ "file": name, "count": count })),
+            false => self.emit(format_args!("{}: {}", name.magenta(), count)),
+        }
+    }
+
+    /// A match as printed, laid out by the `--format` template if there is
+    /// one and highlighted otherwise
+    fn match_text(&self, text: &str, pattern: usize, m: &Match) -> String {
+        match self.format {
+            Some(template) => expand_format(template, text, m),
+            None => self.set.regexes()[pattern]
+                .highlight_match(m, 0)
+                .iter()
+                .map(|span| paint(text, span, self.color_groups))
+                .collect(),
+        }
+    }
+
+    /// Print one output record
+    fn emit(&self, record: impl std::fmt::Display) {
+        print!("{}{}", record, self.terminator);
+    }
+
+    /// `[pattern] ` for each of the given patterns, if output is labelled
+    fn label(&self, indices: impl IntoIterator<Item = usize>) -> String {
+        let Some(labels) = self.labels else {
+            return String::new();
+        };
+        indices
+            .into_iter()
+            .map(|i| format!("{} ", format!("[{}]", labels[i]).cyan()))
+            .collect()
+    }
+
+    /// Print a line as `file: text`, or `file:number: text` with line
+    /// numbers; `sep` is `:` for matching lines and `-` for context
+    fn print_line(&self, name: &str, number: usize, sep: char, text: &str) {
+        if self.line_number {
+            let number = number.to_string().green();
+            self.emit(format_args!(
+                "{}{}{}{} {}",
+                name.magenta(),
+                sep,
+                number,
+                sep,
+                text
+            ));
+        } else {
+            self.emit(format_args!("{}{} {}", name.magenta(), sep, text));
+        }
+    }
+}
+
+/// Matches from each pattern's stream matcher, merged the way
+/// [`RegexSet::find_all`] merges matches
+#[derive(Default)]
+struct Merged {
+    /// Matches not yet known to come before those still to be found
+    candidates: Vec<(usize, StreamMatch)>,
+    /// Start and end of the last match taken
+    last: Option<(usize, usize)>,
+}
+
+impl Merged {
+    /// The matches starting before `settled` in order, without those that
+    /// overlap a match before them
+    fn take(&mut self, settled: usize) -> Vec<(usize, StreamMatch)> {
+        self.candidates
+            .sort_by_key(|(i, m)| (m.start(), std::cmp::Reverse(m.end()), *i));
+        let ready = self
+            .candidates
+            .partition_point(|(_, m)| m.start() < settled);
+        let mut taken = Vec::new();
+        for (i, m) in self.candidates.drain(..ready) {
+            let keep = match self.last {
+                None => true,
+                // An empty match may follow a match ending where it starts,
+                // but not another empty match at the same place
+                Some((start, end)) => m.start() > end || m.start() == end && start < end,
+            };
+            if keep {
+                self.last = Some((m.start(), m.end()));
+                taken.push((i, m));
+            }
+        }
+        taken
+    }
+}
+
+/// Decode the whole characters at the start of `bytes`, replacing invalid
+/// sequences like [`String::from_utf8_lossy`], and remove them from `bytes`
+fn decode_lossy(bytes: &mut Vec<u8>) -> String {
+    let mut text = String::new();
+    let mut rest = &bytes[..];
+    while let Err(err) = std::str::from_utf8(rest) {
+        let (valid, after) = rest.split_at(err.valid_up_to());
+        text.push_str(&String::from_utf8_lossy(valid));
+        match err.error_len() {
+            Some(len) => {
+                text.push(char::REPLACEMENT_CHARACTER);
+                rest = &after[len..];
+            }
+            // An incomplete character, which the next read may finish
+            None => {
+                rest = after;
+                break;
+            }
+        }
+    }
+    if let Ok(valid) = std::str::from_utf8(rest) {
+        text.push_str(valid);
+        rest = &[];
+    }
+    let used = bytes.len() - rest.len();
+    bytes.drain(..used);
+    text
+}
+
+/// Move the offsets in a [`match_json`] record `by` bytes later
+fn offset_json(record: &mut Value, by: usize) {
+    let shift = |span: &mut Value| {
+        for key in ["start", "end"] {
+            if let Some(pos) = span.get(key).and_then(Value::as_u64) {
+                span[key] = json!(pos as usize + by);
+            }
+        }
+    };
+    if let Some(groups) = record["groups"].as_array_mut() {
+        groups.iter_mut().for_each(shift);
+    }
+    shift(record);
+}
+
+/// `text` with the given pieces of its matches highlighted
+fn highlight(text: &str, spans: &[HighlightSpan], groups: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last_end = 0;
+    for span in spans {
+        result.push_str(&text[last_end..span.start]);
+        result.push_str(&paint(text, span, groups));
+        last_end = span.end;
+    }
+    result.push_str(&text[last_end..]);
+    result
+}
+
+/// One piece of a match, in the color of its capture group if `groups` is
+/// set and green otherwise
+fn paint(text: &str, span: &HighlightSpan, groups: bool) -> String {
+    let piece = &text[span.start..span.end];
+    let colored = match span.group {
+        Some(n) if groups => piece.color(GROUP_COLORS[(n as usize - 1) % GROUP_COLORS.len()]),
+        _ => piece.green(),
+    };
+    colored.bold().to_string()
+}
+
+fn cmd_replace(
+    pattern: &str,
+    template: &str,
+    files: &[String],
+    in_place: Option<&str>,
+    count: Option<usize>,
+) {
+    let regex = match Regex::new(pattern) {
+        Ok(r) => r,
+        Err(e) => pattern_error(pattern, e),
+    };
+    let replacement = match Replacement::parse(template).and_then(|replacement| {
+        replacement
+            .validate(regex.group_registry())
+            .map(|()| replacement)
+    }) {
+        Ok(replacement) => replacement,
+        Err(e) => {
+            eprintln!("{} {}", "Error:".red().bold(), e);
+            std::process::exit(1);
+        }
+    };
+    if in_place.is_some() && (files.is_empty() || files.iter().any(|path| path == "-")) {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--in-place needs files to edit, and cannot edit stdin",
+            )
+            .exit();
+    }
+    // A count of 0 replaces every match, as in `Regex::replacen`
+    let limit = count.filter(|&n| n > 0);
+
+    let stdin = ["-".to_string()];
+    let files = if files.is_empty() { &stdin[..] } else { files };
+    let mut failed = false;
+    for path in files {
+        let result = match in_place {
+            Some(suffix) => replace_in_place(&regex, &replacement, path, suffix, limit),
+            None => replace_to_stdout(&regex, &replacement, path, limit),
+        };
+        if let Err(e) = result {
+            eprintln!("{} {}: {}", "Error:".red().bold(), path, e);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// Print a file, or stdin for `-`, with its matches replaced
+fn replace_to_stdout(
+    regex: &Regex,
+    replacement: &Replacement,
+    path: &str,
+    limit: Option<usize>,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    if path == "-" {
+        replace_lines(regex, replacement, io::stdin().lock(), &mut out, limit)?;
+    } else {
+        let reader = BufReader::new(File::open(path)?);
+        replace_lines(regex, replacement, reader, &mut out, limit)?;
+    }
+    out.flush()
+}
+
+/// Rewrite a file with its matches replaced, first saving the original to
+/// the path with `suffix` appended unless `suffix` is empty
+fn replace_in_place(
+    regex: &Regex,
+    replacement: &Replacement,
+    path: &str,
+    suffix: &str,
+    limit: Option<usize>,
+) -> io::Result<()> {
+    let original = String::from_utf8(fs::read(path)?).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "not UTF-8 text (invalid byte at offset {}), left unchanged",
+                e.utf8_error().valid_up_to()
+            ),
+        )
+    })?;
+    let mut out = Vec::with_capacity(original.len());
+    replace_lines(regex, replacement, original.as_bytes(), &mut out, limit)?;
+    if !suffix.is_empty() {
+        fs::write(format!("{}{}", path, suffix), &original)?;
+    }
+    write_atomically(Path::new(path), &out)
+}
+
+/// Replace the contents of `path` by writing a temporary file next to it
+/// and renaming that over it, so an interrupted write never leaves `path`
+/// truncated
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file"))?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(format!(".ogex-{}.tmp", std::process::id()));
+    let temp = path.with_file_name(temp_name);
+
+    let result = (|| {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)?;
+        file.write_all(contents)?;
+        file.set_permissions(fs::metadata(path)?.permissions())?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Stream `reader` to `writer` a line at a time, replacing at most `limit`
+/// matches in total (all of them if `None`)
+///
+/// As in sed, line breaks are not part of the text searched, so `$` matches
+/// at the end of each line.
+fn replace_lines(
+    regex: &Regex,
+    replacement: &Replacement,
+    mut reader: impl BufRead,
+    writer: &mut impl Write,
+    mut limit: Option<usize>,
+) -> io::Result<()> {
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        let text = line.trim_end_matches(['\n', '\r']);
+        match limit {
+            Some(0) => writer.write_all(text.as_bytes())?,
+            Some(ref mut remaining) => {
+                *remaining -= regex.replacen_to(text, *remaining, replacement, writer)?;
+            }
+            None => {
+                regex.replacen_to(text, 0, replacement, writer)?;
+            }
+        }
+        writer.write_all(&line.as_bytes()[text.len()..])?;
+        line.clear();
+    }
+    Ok(())
+}
+
+const REPL_HELP: &str = "\
+Enter text to test it against the pattern. Commands:
+  :p, :pattern <pattern>   Replace the pattern
+  :h, :help                Show this help
+  :q, :quit                Exit (or press Ctrl-D)";
+
+/// Read lines from stdin, testing each against the current pattern, until
+/// `:quit` or end of input
+fn cmd_repl(pattern: Option<String>) {
+    println!("{}", "ogex repl - type :help for commands".bold());
+    let mut regex = pattern.and_then(|pattern| repl_compile(&pattern));
+    let mut line = String::new();
+    loop {
+        let prompt = if regex.is_some() {
+            "input> "
+        } else {
+            "pattern> "
+        };
+        print!("{}", prompt.cyan());
+        line.clear();
+        // A failed flush only loses the prompt
+        let _ = io::stdout().flush();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{} {}", "Error:".red().bold(), e);
+                std::process::exit(1);
+            }
+        }
+        let input = line.trim_end_matches(['\n', '\r']);
+
+        let (command, argument) = match input.strip_prefix(':') {
+            Some(command) => command.split_once(' ').unwrap_or((command, "")),
+            None => ("", input),
+        };
+        match (command, &regex) {
+            ("q" | "quit", _) => break,
+            ("h" | "help", _) => println!("{}", REPL_HELP),
+            ("p" | "pattern", _) if argument.is_empty() => {
+                eprintln!("{} usage: :pattern <pattern>", "Error:".red().bold());
+            }
+            ("p" | "pattern", _) => regex = repl_compile(argument).or(regex),
+            ("", None) => regex = repl_compile(argument),
+            ("", Some(regex)) => repl_show(regex, argument),
+            _ => eprintln!(
+                "{} unknown command `{}`, see :help",
+                "Error:".red().bold(),
+                input
+            ),
+        }
+    }
+}
+
+/// Compile a pattern typed into the REPL, reporting errors without exiting
+fn repl_compile(pattern: &str) -> Option<Regex> {
+    match Regex::new(pattern) {
+        Ok(regex) => {
+            println!("  Pattern: {}", pattern.cyan());
+            Some(regex)
+        }
+        Err(err) => {
+            print_pattern_error(pattern, &err);
+            None
+        }
+    }
+}
+
+/// Show an input with its matches highlighted, then each match and the
+/// values of its groups
+fn repl_show(regex: &Regex, input: &str) {
+    let matches = regex.find_all(input);
+    if matches.is_empty() {
+        println!("  {}", "✗ No match".red());
+        return;
+    }
+    println!("  {}", highlight(input, &regex.highlight(input), true));
+    let names: Vec<Option<&str>> = regex.capture_names().collect();
+    for (i, m) in matches.iter().enumerate() {
+        println!(
+            "  [{}] {}..{} = {}",
+            i + 1,
+            m.start,
+            m.end,
+            m.as_str(input).green()
+        );
+        for (n, name) in names.iter().enumerate().skip(1) {
+            let label = match name {
+                Some(name) => format!("{} ({})", n, name),
+                None => n.to_string(),
+            };
+            let label = label.color(GROUP_COLORS[(n - 1) % GROUP_COLORS.len()]);
+            match m.groups.get(n).copied().flatten() {
+                Some((start, end)) => println!("      {}: {}", label, &input[start..end]),
+                None => println!("      {}: {}", label, "(did not participate)".dimmed()),
+            }
+        }
+    }
+}
+
+fn cmd_dot(pattern: &str, format: &str) {
+    let regex = match Regex::new(pattern) {
+        Ok(r) => r,
+        Err(e) => pattern_error(pattern, e),
+    };
+    if format == "json" {
+        println!("{}", regex.nfa().to_json());
+        return;
+    }
+    let dot = regex.nfa().to_dot();
+    if format == "dot" {
+        print!("{}", dot);
+        return;
+    }
+
+    // Pipe the graph through Graphviz, which writes the rendering to our stdout
+    let rendered = Command::new("dot")
+        .arg(format!("-T{}", format))
+        .stdin(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(dot.as_bytes())?;
+            }
+            child.wait()
+        });
+    match rendered {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            eprintln!("{} dot exited with {}", "Error:".red().bold(), status);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!(
+                "{} could not run Graphviz `dot`: {}",
+                "Error:".red().bold(),
+                e
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print `count` random strings matched by `pattern`; exits with 1 if no
+/// string satisfies its assertions
+fn cmd_generate(
+    pattern: &str,
+    count: usize,
+    max_repeat: u32,
+    seed: Option<u64>,
+    weighting: Weighting,
+) {
+    let mut generator = match Generator::new(pattern) {
+        Ok(g) => g.max_repeat(max_repeat).weighting(weighting),
+        Err(e) => pattern_error(pattern, e),
+    };
+    if let Some(seed) = seed {
+        generator = generator.seed(seed);
+    }
+
+    for _ in 0..count {
+        match generator.generate() {
+            Some(sample) => println!("{}", sample),
+            None => {
+                eprintln!(
+                    "{} no string satisfying the pattern's assertions was found",
+                    "Error:".red().bold()
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+fn cmd_equiv(first: &str, second: &str) {
+    let language = |pattern: &str| {
+        let regex = Regex::new(pattern).unwrap_or_else(|e| {
+            print_pattern_error(pattern, &e);
+            std::process::exit(2);
+        });
+        Language::new(&regex).unwrap_or_else(|e| {
+            eprintln!("{} {}: {}", "Error:".red().bold(), pattern, e);
+            std::process::exit(2);
+        })
+    };
+    let (a, b) = (language(first), language(second));
+
+    match a.counterexample(&b) {
+        Ok(None) => println!("{}", "✓ Equivalent".green().bold()),
+        Ok(Some(example)) => {
+            let which = if a.is_match(&example) {
+                "first"
+            } else {
+                "second"
+            };
+            println!("{}", "✗ Not equivalent".red().bold());
+            println!("  {:?} is matched only by the {} pattern", example, which);
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{} {}", "Error:".red().bold(), e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Print the steps of a search as they happen, then the match if any;
+/// exits with 1 if there is none
+fn cmd_trace(pattern: &str, input: &str) {
+    let text = input.to_string();
+    let regex = match RegexBuilder::new(pattern)
+        .trace_sink(move |event| print_trace_event(&text, event))
+        .build()
+    {
+        Ok(r) => r,
+        Err(e) => pattern_error(pattern, e),
+    };
+
+    let found = regex.try_find(input);
+
+    println!();
+    match found {
+        Ok(Some(m)) => println!(
+            "{} {}..{} = {}",
+            "Match:".bold(),
+            m.start,
+            m.end,
+            m.as_str(input).green()
+        ),
+        Ok(None) => {
+            println!("{}", "No match".red());
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{} {}", "Error:".red().bold(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Print one step of a traced search of `input`
+fn print_trace_event(input: &str, event: TraceEvent) {
+    let at = |pos: usize| format!("  {:>4}  ", pos).dimmed();
+    match event {
+        TraceEvent::Attempt { start } => {
+            println!("{}", format!("Attempt at {}", start).bold());
+        }
+        TraceEvent::States { pos, states } => {
+            let states: Vec<String> = states.iter().map(ToString::to_string).collect();
+            let next = input.get(pos..).and_then(|rest| rest.chars().next());
+            let next = next.map_or("end of input".to_string(), |c| format!("{:?}", c));
+            println!(
+                "{}states {{{}}} before {}",
+                at(pos),
+                states.join(", "),
+                next
+            );
+        }
+        // The states line lists every state entered
+        TraceEvent::Enter { .. } => {}
+        TraceEvent::Step {
+            pos,
+            from,
+            to,
+            label,
+        } => println!("{}{} -{}-> {}", at(pos), from, label.green(), to),
+        TraceEvent::Backref {
+            pos,
+            group,
+            matched,
+        } => match matched {
+            true => println!(
+                "{}backreference to group {} {}",
+                at(pos),
+                group,
+                "matches".green()
+            ),
+            false => println!(
+                "{}backreference to group {} {}",
+                at(pos),
+                group,
+                "fails".red()
+            ),
+        },
+        TraceEvent::GroupStart { group, pos } => {
+            println!("{}{}", at(pos), format!("group {} opens", group).cyan());
+        }
+        TraceEvent::GroupEnd { group, pos } => {
+            println!("{}{}", at(pos), format!("group {} closes", group).cyan());
+        }
+        TraceEvent::Assertion { pos, label, passed } => match passed {
+            true => println!("{}{} {}", at(pos), label, "holds".green()),
+            false => println!("{}{} {}", at(pos), label, "fails".red()),
+        },
+        TraceEvent::Accept { pos } => {
+            println!("{}{}", at(pos), "accept: a match can end here".green());
+        }
+        TraceEvent::Dead { pos } => {
+            println!("{}{}", at(pos), "no state can continue".red());
+        }
+    }
+}
+
 fn cmd_explain(pattern: &str) {
     match explain(pattern) {
         Ok(result) => {
@@ -292,3 +1759,9 @@ fn cmd_explain(pattern: &str) {
         }
     }
 }
+
+/// Print a completion script for `shell`, generated from the command
+/// definitions so that subcommands, options and their values stay in sync
+fn cmd_completions(shell: Shell) {
+    clap_complete::generate(shell, &mut Cli::command(), "ogex", &mut io::stdout());
+}
@@ -0,0 +1,495 @@
+//! Integration tests for the ogex command-line tool
+//!
+//! These tests run the built `ogex` binary on files in a scratch directory
+//! and check its output and exit status.
+//!
+//! Test categories:
+//! - Grep mode: -o/-n/-A/-B/-C, --json, --color, -e/-f
+//! - Quiet and count modes, and exit codes 0/1/2
+//! - --multiline-file, -l/-0 and --format
+//! - Subcommands: replace, convert, trace, generate and completions
+
+use ogex::Regex;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// =============================================================================
+// Helper Functions
+// =============================================================================
+
+/// A scratch directory for one test, removed when dropped
+struct Scratch {
+    dir: PathBuf,
+}
+
+impl Scratch {
+    /// Create a scratch directory holding `files`, as (name, contents) pairs
+    fn new(name: &str, files: &[(&str, &[u8])]) -> Self {
+        let dir = std::env::temp_dir().join(format!("ogex-cli-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("Failed to create scratch directory");
+        for (file, contents) in files {
+            fs::write(dir.join(file), contents).expect("Failed to write scratch file");
+        }
+        Scratch { dir }
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.dir.join(file)
+    }
+
+    fn read(&self, file: &str) -> String {
+        fs::read_to_string(self.path(file)).expect("Failed to read scratch file")
+    }
+
+    /// Run `ogex` in this directory
+    fn run(&self, args: &[&str]) -> Output {
+        run_in(&self.dir, args, None)
+    }
+
+    /// Run `ogex` in this directory with `stdin` as its input
+    fn run_with_stdin(&self, args: &[&str], stdin: &str) -> Output {
+        run_in(&self.dir, args, Some(stdin))
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// What a run of `ogex` printed, and its exit code
+struct Output {
+    code: i32,
+    stdout: String,
+    stderr: String,
+}
+
+fn run_in(dir: &Path, args: &[&str], stdin: Option<&str>) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ogex"))
+        .args(args)
+        .current_dir(dir)
+        // Keep `--color auto` from depending on the environment
+        .env_remove("CLICOLOR_FORCE")
+        .env_remove("NO_COLOR")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run ogex");
+    let mut input = child.stdin.take().expect("stdin is piped");
+    if let Some(stdin) = stdin {
+        input
+            .write_all(stdin.as_bytes())
+            .expect("Failed to write stdin");
+    }
+    drop(input);
+    let output = child.wait_with_output().expect("Failed to wait for ogex");
+    Output {
+        code: output.status.code().expect("ogex was killed by a signal"),
+        stdout: String::from_utf8(output.stdout).expect("stdout is UTF-8"),
+        stderr: String::from_utf8(output.stderr).expect("stderr is UTF-8"),
+    }
+}
+
+const LINES: &[u8] = b"one\nfoo two\nthree\nfour\nfoo five\nsix\n";
+
+fn lines() -> &'static [(&'static str, &'static [u8])] {
+    &[("a.txt", LINES), ("b.txt", b"bar\nnone\n")]
+}
+
+// =============================================================================
+// Grep Mode
+// =============================================================================
+
+#[test]
+fn test_grep_prints_matching_lines() {
+    let s = Scratch::new("grep-lines", lines());
+    let out = s.run(&["match", "--files", "foo", "a.txt", "b.txt"]);
+    assert_eq!(out.code, 0);
+    assert_eq!(out.stdout, "a.txt: foo two\na.txt: foo five\n");
+}
+
+#[test]
+fn test_grep_reads_stdin() {
+    let s = Scratch::new("grep-stdin", &[]);
+    let out = s.run_with_stdin(&["match", "--files", "foo"], "foo\nbar\n");
+    assert_eq!(out.code, 0);
+    assert_eq!(out.stdout, "(standard input): foo\n");
+}
+
+#[test]
+fn test_grep_only_matching() {
+    let s = Scratch::new("grep-only", lines());
+    let out = s.run(&["match", "--files", "-o", "fo+", "a.txt"]);
+    assert_eq!(out.stdout, "a.txt: foo\na.txt: fo\na.txt: foo\n");
+
+    // find always prints each match
+    let out = s.run(&["find", "--files", "fo+", "a.txt"]);
+    assert_eq!(out.stdout, "a.txt: foo\na.txt: fo\na.txt: foo\n");
+}
+
+#[test]
+fn test_grep_line_numbers() {
+    let s = Scratch::new("grep-numbers", lines());
+    let out = s.run(&["match", "--files", "-n", "foo", "a.txt"]);
+    assert_eq!(out.stdout, "a.txt:2: foo two\na.txt:5: foo five\n");
+}
+
+#[test]
+fn test_grep_context() {
+    let s = Scratch::new("grep-context", lines());
+    let out = s.run(&["match", "--files", "-A", "1", "two", "a.txt"]);
+    assert_eq!(out.stdout, "a.txt: foo two\na.txt- three\n");
+
+    let out = s.run(&["match", "--files", "-B", "1", "two", "a.txt"]);
+    assert_eq!(out.stdout, "a.txt- one\na.txt: foo two\n");
+
+    let out = s.run(&["match", "--files", "-C", "1", "-n", "two", "a.txt"]);
+    assert_eq!(
+        out.stdout,
+        "a.txt-1- one\na.txt:2: foo two\na.txt-3- three\n"
+    );
+}
+
+#[test]
+fn test_grep_json() {
+    let s = Scratch::new("grep-json", lines());
+    let out = s.run(&["find", "--files", "--json", "(f:f)oo", "a.txt"]);
+    assert_eq!(out.code, 0);
+    let records: Vec<serde_json::Value> = out
+        .stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each line is a JSON record"))
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["file"], "a.txt");
+    assert_eq!(records[0]["line"], 2);
+    assert_eq!(records[0]["text"], "foo");
+    assert_eq!(records[1]["line"], 5);
+}
+
+#[test]
+fn test_grep_color() {
+    let s = Scratch::new("grep-color", lines());
+    let out = s.run(&["match", "--files", "--color", "always", "two", "a.txt"]);
+    assert!(
+        out.stdout.contains("\x1b["),
+        "expected escapes: {:?}",
+        out.stdout
+    );
+    assert!(out.stdout.contains("two"));
+
+    let out = s.run(&["match", "--files", "--color", "never", "two", "a.txt"]);
+    assert_eq!(out.stdout, "a.txt: foo two\n");
+
+    // Output to a pipe is not colored by default
+    let out = s.run(&["match", "--files", "two", "a.txt"]);
+    assert!(!out.stdout.contains("\x1b["));
+}
+
+#[test]
+fn test_grep_multiple_patterns() {
+    let s = Scratch::new("grep-patterns", lines());
+    let out = s.run(&[
+        "match", "--files", "-e", "bar", "-e", "two", "a.txt", "b.txt",
+    ]);
+    assert_eq!(out.code, 0);
+    assert_eq!(out.stdout, "a.txt: [two] foo two\nb.txt: [bar] bar\n");
+}
+
+#[test]
+fn test_grep_pattern_file() {
+    let mut files = lines().to_vec();
+    files.push(("patterns.txt", b"foo\ntwo\n"));
+    let s = Scratch::new("grep-pattern-file", &files);
+    let out = s.run(&["match", "--files", "-f", "patterns.txt", "a.txt"]);
+    assert_eq!(out.code, 0);
+    assert_eq!(
+        out.stdout,
+        "a.txt: [foo] [two] foo two\na.txt: [foo] foo five\n"
+    );
+
+    let out = s.run(&["match", "--files", "-f", "missing.txt", "a.txt"]);
+    assert_eq!(out.code, 2);
+}
+
+// =============================================================================
+// Quiet, Count and Exit Codes
+// =============================================================================
+
+#[test]
+fn test_grep_quiet() {
+    let s = Scratch::new("grep-quiet", lines());
+    let out = s.run(&["match", "--files", "-q", "foo", "a.txt"]);
+    assert_eq!(out.code, 0);
+    assert_eq!(out.stdout, "");
+
+    let out = s.run(&["match", "--files", "-q", "zzz", "a.txt"]);
+    assert_eq!(out.code, 1);
+    assert_eq!(out.stdout, "");
+}
+
+#[test]
+fn test_grep_count() {
+    let s = Scratch::new("grep-count", lines());
+    let out = s.run(&["match", "--files", "-c", "foo", "a.txt", "b.txt"]);
+    assert_eq!(out.code, 0);
+    assert_eq!(out.stdout, "a.txt: 2\nb.txt: 0\n");
+
+    // find counts matches rather than lines
+    let out = s.run(&["find", "--files", "-c", "o", "a.txt"]);
+    assert_eq!(out.stdout, "a.txt: 7\n");
+}
+
+#[test]
+fn test_grep_exit_codes() {
+    let s = Scratch::new("grep-exit", lines());
+    assert_eq!(s.run(&["match", "--files", "foo", "a.txt"]).code, 0);
+    assert_eq!(s.run(&["match", "--files", "zzz", "a.txt"]).code, 1);
+
+    let out = s.run(&["match", "--files", "foo", "missing.txt"]);
+    assert_eq!(out.code, 2);
+    assert!(out.stderr.contains("missing.txt"));
+
+    // A file that cannot be read is an error even if another file matched
+    assert_eq!(
+        s.run(&["match", "--files", "foo", "a.txt", "missing.txt"])
+            .code,
+        2
+    );
+
+    let out = s.run(&["match", "--files", "(", "a.txt"]);
+    assert_eq!(out.code, 2);
+    assert!(out.stderr.contains("expected `)`"));
+}
+
+// =============================================================================
+// Multiline Files, File Lists and Formats
+// =============================================================================
+
+#[test]
+fn test_grep_multiline_file() {
+    let s = Scratch::new("grep-multiline", lines());
+    let out = s.run(&[
+        "find",
+        "--files",
+        "--multiline-file",
+        "-n",
+        r"two\sthree",
+        "a.txt",
+    ]);
+    assert_eq!(out.code, 0);
+    assert_eq!(out.stdout, "a.txt:2: two\nthree\n");
+
+    // ^ matches at the start of every line
+    let out = s.run(&[
+        "find",
+        "--files",
+        "--multiline-file",
+        "-n",
+        r"^f\w+",
+        "a.txt",
+    ]);
+    assert_eq!(out.stdout, "a.txt:2: foo\na.txt:4: four\na.txt:5: foo\n");
+}
+
+#[test]
+fn test_grep_files_with_matches_and_null() {
+    let s = Scratch::new("grep-null", lines());
+    let out = s.run(&["match", "--files", "-l", "o", "a.txt", "b.txt"]);
+    assert_eq!(out.stdout, "a.txt\nb.txt\n");
+
+    let out = s.run(&["match", "--files", "-l", "-0", "foo", "a.txt", "b.txt"]);
+    assert_eq!(out.stdout, "a.txt\0");
+
+    let out = s.run(&["find", "--files", "-0", "foo", "a.txt"]);
+    assert_eq!(out.stdout, "a.txt: foo\0a.txt: foo\0");
+}
+
+#[test]
+fn test_find_format() {
+    let s = Scratch::new("find-format", lines());
+    let out = s.run(&["find", "--format", "[{w}]{{{0}}}", r"(w:\w)o", "foo"]);
+    assert_eq!(out.code, 0);
+    assert_eq!(out.stdout, "[f]{fo}\n");
+
+    let out = s.run(&[
+        "find", "--files", "--format", "{w}={0}", r"(w:\w)o", "a.txt",
+    ]);
+    assert_eq!(
+        out.stdout,
+        "a.txt: f=fo\na.txt: w=wo\na.txt: f=fo\na.txt: f=fo\n"
+    );
+}
+
+// =============================================================================
+// Subcommands
+// =============================================================================
+
+#[test]
+fn test_replace_stdout() {
+    let s = Scratch::new("replace-stdout", lines());
+    let out = s.run_with_stdin(&["replace", "foo", "X", "-n", "2"], "foo foo\nfoo\n");
+    assert_eq!(out.code, 0);
+    assert_eq!(out.stdout, "X X\nfoo\n");
+
+    let out = s.run(&["replace", r"(\w+) (\w+)", r"\2 \1", "a.txt"]);
+    assert_eq!(out.stdout, "one\ntwo foo\nthree\nfour\nfive foo\nsix\n");
+    assert_eq!(s.read("a.txt").as_bytes(), LINES);
+}
+
+#[test]
+fn test_replace_in_place() {
+    let s = Scratch::new("replace-in-place", lines());
+    let out = s.run(&["replace", "--in-place", "foo", "bar", "a.txt"]);
+    assert_eq!(out.code, 0);
+    assert_eq!(out.stdout, "");
+    assert_eq!(
+        s.read("a.txt"),
+        "one\nbar two\nthree\nfour\nbar five\nsix\n"
+    );
+    assert!(!s.path("a.txt.bak").exists());
+
+    let out = s.run(&["replace", "--in-place=.bak", "bar", "baz", "a.txt"]);
+    assert_eq!(out.code, 0);
+    assert_eq!(
+        s.read("a.txt"),
+        "one\nbaz two\nthree\nfour\nbaz five\nsix\n"
+    );
+    assert_eq!(
+        s.read("a.txt.bak"),
+        "one\nbar two\nthree\nfour\nbar five\nsix\n"
+    );
+
+    // No temporary files are left behind
+    let mut names: Vec<_> = fs::read_dir(&s.dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["a.txt", "a.txt.bak", "b.txt"]);
+}
+
+#[test]
+fn test_replace_in_place_rejects_non_utf8() {
+    let s = Scratch::new("replace-non-utf8", &[("bin.dat", b"foo \xff\n")]);
+    let out = s.run(&["replace", "--in-place", "foo", "bar", "bin.dat"]);
+    assert_eq!(out.code, 1);
+    assert!(out.stderr.contains("not UTF-8"), "stderr: {}", out.stderr);
+    assert_eq!(fs::read(s.path("bin.dat")).unwrap(), b"foo \xff\n");
+}
+
+#[test]
+fn test_convert_dialect() {
+    let s = Scratch::new("convert-dialect", &[]);
+    let out = s.run(&["convert", "--dialect", "python", r"(x:a)\g{x}"]);
+    assert_eq!(out.code, 0);
+    assert!(
+        out.stdout.contains("(?P<x>a)(?P=x)"),
+        "stdout: {}",
+        out.stdout
+    );
+
+    let out = s.run(&["convert", "--dialect", "js", "(x:a)"]);
+    assert!(out.stdout.contains("(?<x>a)"), "stdout: {}", out.stdout);
+
+    let out = s.run(&[
+        "convert",
+        "--from",
+        "legacy",
+        "--dialect",
+        "python",
+        r"(?<x>a)\k<x>",
+    ]);
+    assert!(
+        out.stdout.contains("(?P<x>a)(?P=x)"),
+        "stdout: {}",
+        out.stdout
+    );
+
+    let out = s.run(&["convert", "--dialect", "posix", "a(>:b)"]);
+    assert_eq!(out.code, 1);
+    assert!(out.stderr.contains("lookahead"), "stderr: {}", out.stderr);
+}
+
+#[test]
+fn test_convert_check() {
+    let s = Scratch::new("convert-check", &[]);
+    let out = s.run(&["convert", "--check", "(x:a)"]);
+    assert_eq!(out.code, 0);
+    assert_eq!(out.stdout, "");
+
+    assert_eq!(s.run(&["convert", "--check", "("]).code, 1);
+    assert_eq!(
+        s.run(&["convert", "--check", "--dialect", "posix", "(x:a)"])
+            .code,
+        0
+    );
+    assert_eq!(
+        s.run(&["convert", "--check", "--dialect", "posix", "a(>:b)"])
+            .code,
+        1
+    );
+}
+
+#[test]
+fn test_trace() {
+    let s = Scratch::new("trace", &[]);
+    let out = s.run(&["trace", "ab", "xab"]);
+    assert_eq!(out.code, 0);
+    assert!(
+        out.stdout.starts_with("Attempt at 0\n"),
+        "stdout: {}",
+        out.stdout
+    );
+    assert!(out.stdout.contains("Attempt at 1\n"));
+    assert!(
+        out.stdout.ends_with("Match: 1..3 = ab\n"),
+        "stdout: {}",
+        out.stdout
+    );
+
+    let out = s.run(&["trace", "ab", "zz"]);
+    assert_eq!(out.code, 1);
+    assert!(out.stdout.ends_with("No match\n"), "stdout: {}", out.stdout);
+}
+
+#[test]
+fn test_generate() {
+    let s = Scratch::new("generate", &[]);
+    let out = s.run(&["generate", "-n", "5", "--seed", "7", "[ab]{4}(x|yz)"]);
+    assert_eq!(out.code, 0);
+    let samples: Vec<&str> = out.stdout.lines().collect();
+    assert_eq!(samples.len(), 5);
+    let regex = Regex::new("^[ab]{4}(x|yz)$").unwrap();
+    for sample in &samples {
+        assert!(regex.is_match(sample), "{:?} does not match", sample);
+    }
+
+    // The same seed gives the same samples
+    let again = s.run(&["generate", "-n", "5", "--seed", "7", "[ab]{4}(x|yz)"]);
+    assert_eq!(again.stdout, out.stdout);
+
+    assert_eq!(s.run(&["generate", "("]).code, 1);
+}
+
+#[test]
+fn test_completions() {
+    let s = Scratch::new("completions", &[]);
+    for shell in ["bash", "zsh", "fish"] {
+        let out = s.run(&["completions", shell]);
+        assert_eq!(out.code, 0, "{} completions failed", shell);
+        assert!(
+            out.stdout.contains("ogex"),
+            "{} completions: {}",
+            shell,
+            out.stdout
+        );
+    }
+
+    assert_eq!(s.run(&["completions", "nosh"]).code, 2);
+}
@@ -0,0 +1,253 @@
+//! Macros checking Ogex patterns at compile time
+//!
+//! [`ogex!`](macro@ogex) compiles a pattern once, on first use, after
+//! checking it when the crate is built:
+//!
+//! ```
+//! use ogex_derive::ogex;
+//!
+//! let input = "port 8080";
+//! let m = ogex!(r"(port:\d+)").find(input).unwrap();
+//! assert_eq!(m.named_group_str(input, "port"), Some("8080"));
+//! ```
+//!
+//! `#[derive(OgexCaptures)]` implements
+//! [`ogex::extract::FromCaptures`](https://docs.rs/ogex/latest/ogex/extract/trait.FromCaptures.html)
+//! for a struct. The pattern is given with `#[ogex(pattern = "...")]` and is
+//! compiled when the macro runs, so an invalid pattern or a field without a
+//! group is a compile error rather than a panic at run time.
+//!
+//! A named field takes the group of the same name, and a tuple field the
+//! group numbered one more than its position. `#[ogex(group = "name")]` or
+//! `#[ogex(group = 2)]` on a field picks a different group. Each field's
+//! type is parsed from the group's text with `FromStr`; an `Option` field is
+//! `None` when its group did not take part in the match.
+//!
+//! ```
+//! use ogex::extract::FromCaptures;
+//! use ogex_derive::OgexCaptures;
+//!
+//! #[derive(OgexCaptures)]
+//! #[ogex(pattern = r"(ip:\d+(@?:\.\d+){3}) (@?:- (user:\w+) )?(status:\d{3})")]
+//! struct LogLine {
+//!     ip: String,
+//!     user: Option<String>,
+//!     #[ogex(group = "status")]
+//!     code: u16,
+//! }
+//!
+//! let line = LogLine::find("10.0.0.1 - admin 404").unwrap().unwrap();
+//! assert_eq!(line.ip, "10.0.0.1");
+//! assert_eq!(line.user.as_deref(), Some("admin"));
+//! assert_eq!(line.code, 404);
+//! ```
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Data, DeriveInput, Error, Fields, GenericArgument, Lit, LitInt, LitStr,
+    PathArguments, Type, parse_macro_input,
+};
+
+/// Compile a pattern on first use, after checking it at compile time
+///
+/// Takes a string literal and evaluates to a `&'static ogex::Regex`, built
+/// the first time the expression runs and shared from then on. A pattern
+/// that does not compile is a compile error pointing at the literal.
+#[proc_macro]
+pub fn ogex(input: TokenStream) -> TokenStream {
+    let pattern = parse_macro_input!(input as LitStr);
+    expand_regex(&pattern)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_regex(pattern: &LitStr) -> syn::Result<TokenStream2> {
+    check_pattern(pattern)?;
+    Ok(quote! {{
+        static REGEX: ::std::sync::OnceLock<::ogex::Regex> = ::std::sync::OnceLock::new();
+        REGEX.get_or_init(|| {
+            ::ogex::Regex::new(#pattern).expect("the pattern was checked when ogex! expanded")
+        })
+    }})
+}
+
+/// Compile the pattern in a string literal, failing at the literal
+fn check_pattern(pattern: &LitStr) -> syn::Result<ogex::Regex> {
+    ogex::Regex::new(&pattern.value())
+        .map_err(|err| Error::new(pattern.span(), format!("invalid pattern: {}", err)))
+}
+
+/// Implement `ogex::extract::FromCaptures` for a struct
+#[proc_macro_derive(OgexCaptures, attributes(ogex))]
+pub fn derive_ogex_captures(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Which group a field is read from
+enum GroupRef {
+    Index(u32, Span),
+    Name(String, Span),
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(
+            input.ident.span(),
+            "OgexCaptures can only be derived for structs",
+        ));
+    };
+    let pattern = pattern_attr(&input.attrs)?.ok_or_else(|| {
+        Error::new(
+            input.ident.span(),
+            "missing #[ogex(pattern = \"...\")] attribute",
+        )
+    })?;
+    let regex = check_pattern(&pattern)?;
+
+    let mut values = Vec::new();
+    for (position, field) in data.fields.iter().enumerate() {
+        let name = match &field.ident {
+            Some(ident) => ident.to_string().trim_start_matches("r#").to_string(),
+            None => position.to_string(),
+        };
+        let group = match group_attr(&field.attrs)? {
+            Some(group) => group,
+            None => match &field.ident {
+                Some(ident) => GroupRef::Name(name.clone(), ident.span()),
+                None => GroupRef::Index(position as u32 + 1, field.ty.span()),
+            },
+        };
+        check_group(&regex, &group)?;
+
+        let group = match group {
+            GroupRef::Index(index, _) => quote!(::ogex::extract::Group::Index(#index)),
+            GroupRef::Name(name, _) => quote!(::ogex::extract::Group::Name(#name)),
+        };
+        let parse = if is_option(&field.ty) {
+            quote!(optional_field)
+        } else {
+            quote!(field)
+        };
+        let value = quote!(::ogex::extract::#parse(input, m, #group, #name)?);
+        values.push(match &field.ident {
+            Some(ident) => quote!(#ident: #value),
+            None => value,
+        });
+    }
+    let construct = match &data.fields {
+        Fields::Named(_) => quote!(Self { #(#values),* }),
+        Fields::Unnamed(_) => quote!(Self(#(#values),*)),
+        Fields::Unit => quote!(Self),
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::ogex::extract::FromCaptures for #ident #ty_generics #where_clause {
+            const PATTERN: &'static str = #pattern;
+
+            fn regex() -> &'static ::ogex::Regex {
+                static REGEX: ::std::sync::OnceLock<::ogex::Regex> = ::std::sync::OnceLock::new();
+                REGEX.get_or_init(|| {
+                    ::ogex::Regex::new(#pattern)
+                        .expect("the pattern was checked when OgexCaptures was derived")
+                })
+            }
+
+            fn from_match(
+                input: &str,
+                m: &::ogex::Match,
+            ) -> ::core::result::Result<Self, ::ogex::extract::ExtractError> {
+                ::core::result::Result::Ok(#construct)
+            }
+        }
+    })
+}
+
+/// The `pattern` given on the struct, if there is one
+fn pattern_attr(attrs: &[Attribute]) -> syn::Result<Option<LitStr>> {
+    let mut pattern = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("ogex")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("pattern") {
+                pattern = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `pattern`"))
+            }
+        })?;
+    }
+    Ok(pattern)
+}
+
+/// The `group` given on a field, if there is one
+fn group_attr(attrs: &[Attribute]) -> syn::Result<Option<GroupRef>> {
+    let mut group = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("ogex")) {
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("group") {
+                return Err(meta.error("expected `group`"));
+            }
+            group = Some(match meta.value()?.parse()? {
+                Lit::Str(name) => GroupRef::Name(name.value(), name.span()),
+                Lit::Int(index) => GroupRef::Index(group_index(&index)?, index.span()),
+                other => {
+                    return Err(Error::new(other.span(), "expected a group name or number"));
+                }
+            });
+            Ok(())
+        })?;
+    }
+    Ok(group)
+}
+
+fn group_index(index: &LitInt) -> syn::Result<u32> {
+    match index.base10_parse()? {
+        0 => Err(Error::new(
+            index.span(),
+            "group 0 is the whole match; use a numbered group from 1",
+        )),
+        index => Ok(index),
+    }
+}
+
+/// Fail if the pattern has no such group
+fn check_group(regex: &ogex::Regex, group: &GroupRef) -> syn::Result<()> {
+    match group {
+        GroupRef::Index(index, span) if *index as usize > regex.group_count() => Err(Error::new(
+            *span,
+            format!(
+                "the pattern has no group {}; it has {}",
+                index,
+                regex.group_count()
+            ),
+        )),
+        GroupRef::Name(name, span) if !regex.capture_names().any(|n| n == Some(name)) => Err(
+            Error::new(*span, format!("the pattern has no group named `{}`", name)),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Whether a field's type is written as `Option<T>`
+fn is_option(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    path.qself.is_none()
+        && path.path.segments.last().is_some_and(|segment| {
+            segment.ident == "Option"
+                && matches!(
+                    &segment.arguments,
+                    PathArguments::AngleBracketed(args)
+                        if args.args.len() == 1
+                            && matches!(args.args[0], GenericArgument::Type(_))
+                )
+        })
+}
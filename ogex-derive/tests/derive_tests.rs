@@ -0,0 +1,63 @@
+//! Tests for `#[derive(OgexCaptures)]`
+
+use ogex::extract::{ExtractError, FromCaptures};
+use ogex_derive::OgexCaptures;
+
+#[derive(Debug, PartialEq, OgexCaptures)]
+#[ogex(pattern = r"(key:\w+)=(value:\d+)")]
+struct Setting {
+    key: String,
+    value: i64,
+}
+
+#[derive(Debug, PartialEq, OgexCaptures)]
+#[ogex(pattern = r"(\d+)x(\d+)(@?:@(\d+))?")]
+struct Size(u32, u32, #[ogex(group = 3)] Option<u32>);
+
+#[derive(Debug, PartialEq, OgexCaptures)]
+#[ogex(pattern = r"v(major:\d+)\.(minor:\d+)")]
+struct Version {
+    #[ogex(group = "major")]
+    r#major: u8,
+    #[ogex(group = 2)]
+    second: u8,
+}
+
+#[test]
+fn test_named_fields_read_named_groups() {
+    assert_eq!(
+        Setting::find("set depth=-1 width=80").unwrap(),
+        Some(Setting {
+            key: "width".to_string(),
+            value: 80
+        })
+    );
+    assert_eq!(Setting::find("nothing here").unwrap(), None);
+    assert_eq!(Setting::PATTERN, r"(key:\w+)=(value:\d+)");
+}
+
+#[test]
+fn test_tuple_fields_read_numbered_groups() {
+    assert_eq!(
+        Size::find_all("640x480 1920x1080@60").unwrap(),
+        vec![Size(640, 480, None), Size(1920, 1080, Some(60))]
+    );
+}
+
+#[test]
+fn test_group_attribute_overrides_the_default() {
+    assert_eq!(
+        Version::find("release v1.12").unwrap(),
+        Some(Version {
+            major: 1,
+            second: 12
+        })
+    );
+}
+
+#[test]
+fn test_parse_failures_name_the_field() {
+    let err = Version::find("v300.1").unwrap_err();
+    assert!(matches!(err, ExtractError::Parse { field: "major", .. }));
+    assert!(Size::find("1x99999999999").is_err());
+}
@@ -0,0 +1,31 @@
+//! Tests for `ogex!`
+
+use ogex_derive::ogex;
+
+fn year(input: &str) -> Option<&str> {
+    let regex = ogex!(r"(year:\d{4})-\d{2}");
+    regex
+        .find(input)
+        .and_then(|m| m.named_group_str(input, "year"))
+}
+
+#[test]
+fn test_macro_matches_like_regex_new() {
+    assert_eq!(year("due 2024-06"), Some("2024"));
+    assert_eq!(year("due soon"), None);
+    assert_eq!(ogex!("a+").as_str(), "a+");
+}
+
+#[test]
+fn test_each_call_site_compiles_once() {
+    let first: *const ogex::Regex = ogex!(r"\w+");
+    let again: *const ogex::Regex = ogex!(r"\w+");
+    // The same expression shares its regex across calls, while another
+    // call site has a regex of its own
+    let mut seen = Vec::new();
+    for _ in 0..2 {
+        seen.push(ogex!(r"\d") as *const ogex::Regex);
+    }
+    assert_eq!(seen[0], seen[1]);
+    assert_ne!(first, again);
+}
@@ -2,229 +2,724 @@
 //!
 //! This module provides Python bindings for the Ogex regex engine,
 //! offering a `re`-compatible API with Ogex's unified syntax.
+use std::sync::Arc;
+
 use ::ogex::Match;
 use ::ogex::Regex;
-use ::ogex::Replacement;
+use ::ogex::RegexBuilder;
+use ::ogex::{Replacement, ReplacementPart};
 use pyo3::prelude::*;
-use pyo3::types::PyList;
-use std::collections::HashMap as StdHashMap;
+use pyo3::types::{PyBytes, PyDict, PyList, PyString};
+
+use exceptions::error;
+
+mod exceptions {
+    // Named like `re.error`
+    #![allow(non_camel_case_types)]
+
+    pyo3::create_exception!(
+        ogex,
+        error,
+        pyo3::exceptions::PyValueError,
+        "Raised for an invalid pattern or template, like `re.error`"
+    );
+}
+
+/// An `ogex.error` with `re.error`'s `msg`, `pattern` and `pos` attributes
+///
+/// `message` is the full text shown by `str()`, while `msg` is the bare
+/// description. `pos` is a character index into `pattern`.
+fn new_error(message: String, msg: &str, pattern: Option<&Text>, pos: Option<usize>) -> PyErr {
+    let err = error::new_err(message);
+    Python::attach(|py| {
+        let value = err.value(py);
+        let pattern = pattern.map(|text| text.slice(py, 0, text.as_bytes().len()));
+        let attrs = value
+            .setattr("msg", msg)
+            .and_then(|()| value.setattr("pattern", pattern))
+            .and_then(|()| value.setattr("pos", pos));
+        match attrs {
+            Ok(()) => err.clone_ref(py),
+            Err(e) => e,
+        }
+    })
+}
+
+// Flag values match the `re` module's, so `re.IGNORECASE` and friends work
+const IGNORECASE: u32 = 2;
+const MULTILINE: u32 = 8;
+const DOTALL: u32 = 16;
+/// Accepted for compatibility and ignored: unlike in `re`, `\w`, `\d` and `\s`
+/// only match ASCII characters
+const UNICODE: u32 = 32;
+const VERBOSE: u32 = 64;
+
+/// A builder for `pattern` with the options selected by `re`-style `flags`
+fn builder(pattern: &str, flags: u32) -> PyResult<RegexBuilder> {
+    let unsupported = flags & !(IGNORECASE | MULTILINE | DOTALL | UNICODE | VERBOSE);
+    if unsupported != 0 {
+        return Err(pyo3::exceptions::PyValueError::new_err(format!(
+            "unsupported flags: {:#x}",
+            unsupported
+        )));
+    }
+    Ok(RegexBuilder::new(pattern)
+        .case_insensitive(flags & IGNORECASE != 0)
+        .multiline(flags & MULTILINE != 0)
+        .dotall(flags & DOTALL != 0)
+        .extended(flags & VERBOSE != 0))
+}
+
+/// A pattern, template or subject: a `str`, or `bytes` whose every byte is
+/// matched as the Latin-1 character with the same value
+enum Text {
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+impl Text {
+    fn extract(obj: &Bound<'_, PyAny>) -> PyResult<Self> {
+        match obj.cast::<PyBytes>() {
+            Ok(bytes) => Ok(Text::Bytes(bytes.as_bytes().to_vec())),
+            Err(_) => Ok(Text::Str(obj.extract()?)),
+        }
+    }
+
+    fn is_bytes(&self) -> bool {
+        matches!(self, Text::Bytes(_))
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Text::Str(s) => s.as_bytes(),
+            Text::Bytes(b) => b,
+        }
+    }
+
+    /// The text as a string, reading each byte of `bytes` as Latin-1
+    fn to_latin1(&self) -> String {
+        match self {
+            Text::Str(s) => s.clone(),
+            Text::Bytes(b) => b.iter().map(|&byte| char::from(byte)).collect(),
+        }
+    }
+
+    /// Encode text produced from [`Text::to_latin1`] strings back into the
+    /// same kind of text, appending it to `dst`
+    fn push_latin1(&self, dst: &mut Vec<u8>, text: &str) {
+        match self {
+            Text::Str(_) => dst.extend_from_slice(text.as_bytes()),
+            // Every character came from a byte, so it fits in one
+            Text::Bytes(_) => dst.extend(text.chars().map(|c| c as u8)),
+        }
+    }
+
+    /// A Python object of the same kind holding `bytes[start..end]`
+    fn slice<'py>(&self, py: Python<'py>, start: usize, end: usize) -> Bound<'py, PyAny> {
+        match self {
+            Text::Str(s) => PyString::new(py, &s[start..end]).into_any(),
+            Text::Bytes(b) => PyBytes::new(py, &b[start..end]).into_any(),
+        }
+    }
+
+    /// A Python object of the same kind holding `bytes`, which for a `str`
+    /// is made of whole characters
+    fn new_like<'py>(&self, py: Python<'py>, bytes: Vec<u8>) -> Bound<'py, PyAny> {
+        match self {
+            Text::Str(_) => {
+                let text = String::from_utf8(bytes).expect("pieces split at character boundaries");
+                PyString::new(py, &text).into_any()
+            }
+            Text::Bytes(_) => PyBytes::new(py, &bytes).into_any(),
+        }
+    }
+
+    /// Byte offset of the Python index `index`, which counts characters in
+    /// a `str` and bytes in `bytes`, clamped to the end of the text
+    fn offset(&self, index: usize) -> usize {
+        match self {
+            Text::Str(s) => s.char_indices().nth(index).map_or(s.len(), |(i, _)| i),
+            Text::Bytes(b) => index.min(b.len()),
+        }
+    }
+
+    /// Python index of the byte offset `offset`, the inverse of
+    /// [`Text::offset`]
+    fn index(&self, offset: usize) -> usize {
+        match self {
+            Text::Str(s) => s[..offset].chars().count(),
+            Text::Bytes(_) => offset,
+        }
+    }
+
+    /// Position after the character or byte at `pos`
+    fn next_boundary(&self, pos: usize) -> usize {
+        match self {
+            Text::Str(s) => pos + s[pos..].chars().next().map_or(1, char::len_utf8),
+            Text::Bytes(_) => pos + 1,
+        }
+    }
+}
+
+/// Append the expansion of `replacement` for the match `m` in `input`
+fn expand_into(dst: &mut Vec<u8>, replacement: &Replacement, input: &Text, m: &Match) {
+    for part in replacement.parts() {
+        let span = match part {
+            ReplacementPart::Literal(text) => {
+                input.push_latin1(dst, text);
+                continue;
+            }
+            ReplacementPart::BackrefNumber(0) | ReplacementPart::EntireMatch => {
+                Some((m.start, m.end))
+            }
+            ReplacementPart::BackrefNumber(n) => m.group(*n),
+            ReplacementPart::BackrefName(name) => m.named_group(name),
+        };
+        // Groups that did not participate expand to nothing
+        if let Some((start, end)) = span {
+            dst.extend_from_slice(&input.as_bytes()[start..end]);
+        }
+    }
+}
 
 /// A compiled regex pattern
 #[pyclass(name = "Regex")]
 pub struct PyRegex {
     inner: Regex,
+    /// The pattern as written
+    source: Text,
+    /// Group names indexed by group number, shared with every match
+    group_names: Arc<[Option<String>]>,
+}
+
+impl PyRegex {
+    /// Read a subject or template, which must be the same kind as the pattern
+    fn text(&self, obj: &Bound<'_, PyAny>) -> PyResult<Text> {
+        let text = Text::extract(obj)?;
+        match (self.source.is_bytes(), text.is_bytes()) {
+            (false, true) => Err(pyo3::exceptions::PyTypeError::new_err(
+                "cannot use a string pattern on a bytes-like object",
+            )),
+            (true, false) => Err(pyo3::exceptions::PyTypeError::new_err(
+                "cannot use a bytes pattern on a string-like object",
+            )),
+            _ => Ok(text),
+        }
+    }
+
+    /// Where a search limited by `pos` and `endpos` starts and where the
+    /// text is cut off
+    ///
+    /// As in `re`, the text is cut off at `endpos` so `$` can match there,
+    /// while `^` still only matches at the real start of the string.
+    fn region(text: &Text, pos: usize, endpos: Option<usize>) -> (usize, usize) {
+        let end = endpos.map_or(text.as_bytes().len(), |end| text.offset(end));
+        (text.offset(pos), end)
+    }
+
+    /// Find the first match in `text[..end]` at or after `pos`
+    fn find_at(&self, text: &Text, pos: usize, end: usize) -> Option<Match> {
+        match text {
+            Text::Str(s) => self.inner.find_at(&s[..end], pos),
+            Text::Bytes(b) => self.inner.find_bytes_at(&b[..end], pos).ok().flatten(),
+        }
+    }
+
+    /// Find the next match in `text[..end]` at or after `*pos`, and move
+    /// `*pos` to where the search after it starts
+    fn next_match(&self, text: &Text, pos: &mut usize, end: usize) -> Option<Match> {
+        let Some(m) = self.find_at(text, *pos, end) else {
+            // Nothing further to find, don't rescan on the next call
+            *pos = end + 1;
+            return None;
+        };
+        // Step past empty matches, as `re.finditer` does
+        *pos = if m.end == m.start {
+            text.next_boundary(m.end)
+        } else {
+            m.end
+        };
+        Some(m)
+    }
+
+    fn to_match(&self, m: Match, input: &Arc<Text>) -> PyMatch {
+        PyMatch {
+            inner: m,
+            group_names: Arc::clone(&self.group_names),
+            input: Arc::clone(input),
+        }
+    }
+
+    /// Parse a replacement template, checking the groups it refers to
+    fn template(&self, template: &Text) -> PyResult<Replacement> {
+        Replacement::parse(&template.to_latin1())
+            .and_then(|replacement| {
+                replacement.validate(self.inner.group_registry())?;
+                Ok(replacement)
+            })
+            .map_err(|e| new_error(e.to_string(), &e.to_string(), Some(template), None))
+    }
 }
 
 #[pymethods]
 impl PyRegex {
-    /// Compile a regex pattern
+    /// Compile a regex pattern, given as `str` or `bytes`
     #[new]
-    fn new(pattern: &str) -> PyResult<Self> {
-        let regex = Regex::new(pattern)
-            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
-        Ok(PyRegex { inner: regex })
+    #[pyo3(signature = (pattern, flags=0))]
+    fn new(pattern: &Bound<'_, PyAny>, flags: u32) -> PyResult<Self> {
+        let source = Text::extract(pattern)?;
+        let pattern = source.to_latin1();
+        let regex = builder(&pattern, flags)?.build().map_err(|e| {
+            let message = match e.spanned() {
+                Some(spanned) => spanned.render(&pattern),
+                None => e.to_string(),
+            };
+            let pos = e.span().map(|span| span.start);
+            new_error(message, &e.message(), Some(&source), pos)
+        })?;
+        let group_names = regex
+            .capture_names()
+            .map(|name| name.map(str::to_string))
+            .collect();
+        Ok(PyRegex {
+            inner: regex,
+            source,
+            group_names,
+        })
+    }
+
+    /// The pattern the regex was compiled from
+    #[getter]
+    fn pattern<'py>(&self, py: Python<'py>) -> Bound<'py, PyAny> {
+        self.source.slice(py, 0, self.source.as_bytes().len())
+    }
+
+    /// Number of capture groups in the pattern
+    #[getter]
+    fn groups(&self) -> usize {
+        self.inner.group_count()
     }
 
-    /// Check if the pattern matches at the beginning of the string
-    fn match_(&self, string: &str) -> Option<PyMatch> {
-        // Check if match is at position 0
-        if let Some(m) = self.inner.find(string)
-            && m.start == 0
+    /// Dict mapping each group name to its number
+    #[getter]
+    fn groupindex<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for (n, name) in self.group_names.iter().enumerate() {
+            if let Some(name) = name {
+                dict.set_item(name, n)?;
+            }
+        }
+        Ok(dict)
+    }
+
+    /// Check if the pattern matches at the beginning of the string, or at
+    /// `pos`
+    #[pyo3(signature = (string, pos=0, endpos=None))]
+    fn match_(
+        &self,
+        string: &Bound<'_, PyAny>,
+        pos: usize,
+        endpos: Option<usize>,
+    ) -> PyResult<Option<PyMatch>> {
+        let text = self.text(string)?;
+        let (pos, end) = Self::region(&text, pos, endpos);
+        // Check if match is at the start position
+        if let Some(m) = self.find_at(&text, pos, end)
+            && m.start == pos
         {
-            return Some(PyMatch::new(m, string.to_string()));
+            return Ok(Some(self.to_match(m, &Arc::new(text))));
         }
-        None
+        Ok(None)
     }
 
-    /// Search for a match anywhere in the string
-    fn search(&self, string: &str) -> Option<PyMatch> {
-        self.inner
-            .find(string)
-            .map(|m| PyMatch::new(m, string.to_string()))
+    /// Search for a match anywhere in the string, or between `pos` and
+    /// `endpos`
+    #[pyo3(signature = (string, pos=0, endpos=None))]
+    fn search(
+        &self,
+        string: &Bound<'_, PyAny>,
+        pos: usize,
+        endpos: Option<usize>,
+    ) -> PyResult<Option<PyMatch>> {
+        let text = self.text(string)?;
+        let (pos, end) = Self::region(&text, pos, endpos);
+        Ok(self
+            .find_at(&text, pos, end)
+            .map(|m| self.to_match(m, &Arc::new(text))))
     }
 
     /// Check if the pattern matches the string
-    fn is_match(&self, string: &str) -> bool {
-        self.inner.is_match(string)
+    fn is_match(&self, string: &Bound<'_, PyAny>) -> PyResult<bool> {
+        let text = self.text(string)?;
+        Ok(self.find_at(&text, 0, text.as_bytes().len()).is_some())
     }
 
     /// Find all non-overlapping matches
-    fn findall<'py>(&self, py: Python<'py>, string: &str) -> PyResult<Bound<'py, PyList>> {
-        let matches: Vec<_> = self.inner.find_all(string);
-
+    #[pyo3(signature = (string, pos=0, endpos=None))]
+    fn findall<'py>(
+        &self,
+        py: Python<'py>,
+        string: &Bound<'_, PyAny>,
+        pos: usize,
+        endpos: Option<usize>,
+    ) -> PyResult<Bound<'py, PyList>> {
+        let text = Arc::new(self.text(string)?);
+        let (mut pos, end) = Self::region(&text, pos, endpos);
         let list = PyList::empty(py);
-        for m in matches {
-            let py_match = PyMatch::new(m, string.to_string());
-            list.append(py_match)?;
+        while let Some(m) = self.next_match(&text, &mut pos, end) {
+            list.append(self.to_match(m, &text))?;
         }
         Ok(list)
     }
 
-    /// Replace matches with a replacement string
+    /// Iterate lazily over all non-overlapping matches
+    #[pyo3(signature = (string, pos=0, endpos=None))]
+    fn finditer(
+        slf: &Bound<'_, Self>,
+        string: &Bound<'_, PyAny>,
+        pos: usize,
+        endpos: Option<usize>,
+    ) -> PyResult<PyMatchIterator> {
+        let text = slf.borrow().text(string)?;
+        let (pos, end) = Self::region(&text, pos, endpos);
+        Ok(PyMatchIterator {
+            regex: slf.clone().unbind(),
+            input: Arc::new(text),
+            pos,
+            end,
+        })
+    }
+
+    /// Replace matches with a replacement string, or with what a function
+    /// returns when called with each match
     #[pyo3(signature = (repl, string, count=None))]
-    fn sub(&self, repl: &str, string: &str, count: Option<usize>) -> PyResult<String> {
-        let replacement = Replacement::parse(repl)
-            .map_err(|e| pyo3::exceptions::PyValueError::new_err(e.to_string()))?;
+    fn sub<'py>(
+        &self,
+        py: Python<'py>,
+        repl: &Bound<'py, PyAny>,
+        string: &Bound<'py, PyAny>,
+        count: Option<usize>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let text = Arc::new(self.text(string)?);
+        let template = match repl.is_callable() {
+            true => None,
+            false => Some(self.template(&self.text(repl)?)?),
+        };
+        // A count of 0 (or none) replaces every match, as in `re.sub`
+        let limit = match count {
+            Some(0) | None => usize::MAX,
+            Some(n) => n,
+        };
 
-        let max_replacements = count.unwrap_or(usize::MAX);
-        let mut result = String::new();
+        let input = text.as_bytes();
+        let mut result = Vec::with_capacity(input.len());
         let mut last_end = 0;
-
-        for (i, m) in self.inner.find_all(string).into_iter().enumerate() {
-            if i >= max_replacements {
+        let mut pos = 0;
+        for _ in 0..limit {
+            let Some(m) = self.next_match(&text, &mut pos, input.len()) else {
                 break;
-            }
-
-            // Add text before match
-            result.push_str(&string[last_end..m.start]);
-
-            // Get groups as pairs in order
-            let mut group_pairs = vec![(0usize, 0usize); m.groups.len()];
-            for (idx, opt) in m.groups.iter().enumerate() {
-                if let Some((s, e)) = opt {
-                    // idx 0 is unused (groups are 1-indexed)
-                    if idx > 0 && idx < group_pairs.len() {
-                        group_pairs[idx - 1] = (*s, *e);
-                    }
+            };
+            result.extend_from_slice(&input[last_end..m.start]);
+            last_end = m.end;
+            match &template {
+                Some(template) => expand_into(&mut result, template, &text, &m),
+                None => {
+                    let replacement = self.text(&repl.call1((self.to_match(m, &text),))?)?;
+                    result.extend_from_slice(replacement.as_bytes());
                 }
             }
-
-            // Named groups mapping (empty for now)
-            let named = StdHashMap::new();
-
-            // Apply replacement
-            let replaced =
-                replacement.apply_with_names(string, m.start, m.end, &group_pairs, &named);
-            result.push_str(&replaced);
-
-            last_end = m.end;
         }
+        result.extend_from_slice(&input[last_end..]);
+        Ok(text.new_like(py, result))
+    }
+}
+
+/// An iterator over the matches in a string, returned by `finditer`
+#[pyclass(name = "MatchIterator")]
+pub struct PyMatchIterator {
+    regex: Py<PyRegex>,
+    input: Arc<Text>,
+    /// Where the next search starts, past the end once exhausted
+    pos: usize,
+    /// Where the searched text is cut off
+    end: usize,
+}
 
-        // Add remaining text
-        result.push_str(&string[last_end..]);
+#[pymethods]
+impl PyMatchIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
 
-        Ok(result)
+    fn __next__(&mut self, py: Python<'_>) -> Option<PyMatch> {
+        let regex = self.regex.borrow(py);
+        let m = regex.next_match(&self.input, &mut self.pos, self.end)?;
+        Some(regex.to_match(m, &self.input))
     }
 }
 
 /// A match result
 #[pyclass(name = "Match")]
 pub struct PyMatch {
-    start: usize,
-    end: usize,
-    groups: StdHashMap<u32, (usize, usize)>,
-    input: String,
+    inner: Match,
+    group_names: Arc<[Option<String>]>,
+    input: Arc<Text>,
 }
 
 impl PyMatch {
-    fn new(m: Match, input: String) -> Self {
-        // Convert Vec<Option<(usize, usize)>> to HashMap<u32, (usize, usize)>
-        let mut groups = StdHashMap::new();
-        for (idx, opt) in m.groups.iter().enumerate() {
-            if let Some((s, e)) = opt {
-                // idx 0 is unused (groups are 1-indexed), so store as (idx + 1)
-                groups.insert((idx + 1) as u32, (*s, *e));
-            }
-        }
-        PyMatch {
-            start: m.start,
-            end: m.end,
-            groups,
-            input,
+    /// Number of the group `group` refers to, by index or by name
+    fn group_index(&self, group: &Bound<'_, PyAny>) -> PyResult<usize> {
+        let index = if let Ok(name) = group.extract::<String>() {
+            self.group_names
+                .iter()
+                .position(|n| n.as_deref() == Some(name.as_str()))
+        } else {
+            group
+                .extract::<usize>()
+                .ok()
+                .filter(|&n| n < self.group_names.len())
+        };
+        index.ok_or_else(|| pyo3::exceptions::PyIndexError::new_err("no such group"))
+    }
+
+    /// Span of group `n`, with group 0 being the entire match
+    fn group_span(&self, n: usize) -> Option<(usize, usize)> {
+        if n == 0 {
+            Some((self.inner.start, self.inner.end))
+        } else {
+            self.inner.groups.get(n).copied().flatten()
         }
     }
+
+    /// Text of group `n`, or `None` if it did not participate in the match
+    fn group_text<'py>(&self, py: Python<'py>, n: usize) -> Option<Bound<'py, PyAny>> {
+        self.group_span(n).map(|(s, e)| self.input.slice(py, s, e))
+    }
 }
 
 #[pymethods]
 impl PyMatch {
     /// The matched text
     #[getter]
-    fn group0(&self) -> &str {
-        &self.input[self.start..self.end]
+    fn group0<'py>(&self, py: Python<'py>) -> Bound<'py, PyAny> {
+        self.input.slice(py, self.inner.start, self.inner.end)
     }
 
-    /// Get a group by index
-    fn group(&self, n: u32) -> Option<&str> {
-        self.groups.get(&n).map(|(s, e)| &self.input[*s..*e])
+    /// Get a group by index or name, or `None` if it did not participate in
+    /// the match
+    fn group<'py>(
+        &self,
+        py: Python<'py>,
+        group: &Bound<'py, PyAny>,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let n = self.group_index(group)?;
+        Ok(self.group_text(py, n))
     }
 
-    /// Start position of the match
+    /// All named groups as a dict, with `default` for groups that did not
+    /// participate
+    #[pyo3(signature = (default=None))]
+    fn groupdict<'py>(
+        &self,
+        py: Python<'py>,
+        default: Option<Bound<'py, PyAny>>,
+    ) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for (n, name) in self.group_names.iter().enumerate() {
+            let Some(name) = name else { continue };
+            match self.group_text(py, n) {
+                Some(text) => dict.set_item(name, text)?,
+                None => dict.set_item(name, &default)?,
+            }
+        }
+        Ok(dict)
+    }
+
+    /// Expand a template such as `\1` or `\g<name>` using this match's groups
+    fn expand<'py>(
+        &self,
+        py: Python<'py>,
+        template: &Bound<'py, PyAny>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let template = Text::extract(template)?;
+        if template.is_bytes() != self.input.is_bytes() {
+            return Err(pyo3::exceptions::PyTypeError::new_err(
+                "template must be the same type as the string",
+            ));
+        }
+        let replacement = Replacement::parse(&template.to_latin1())
+            .map_err(|e| new_error(e.to_string(), &e.to_string(), Some(&template), None))?;
+        let mut result = Vec::new();
+        expand_into(&mut result, &replacement, &self.input, &self.inner);
+        Ok(self.input.new_like(py, result))
+    }
+
+    /// Same as `group`, for `m[group]`
+    fn __getitem__<'py>(
+        &self,
+        py: Python<'py>,
+        group: &Bound<'py, PyAny>,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        self.group(py, group)
+    }
+
+    /// Start position of a group, or -1 if it did not participate in the match
+    #[pyo3(signature = (group=None))]
+    fn start(&self, group: Option<&Bound<'_, PyAny>>) -> PyResult<isize> {
+        Ok(self.span(group)?.0)
+    }
+
+    /// End position of a group, or -1 if it did not participate in the match
+    #[pyo3(signature = (group=None))]
+    fn end(&self, group: Option<&Bound<'_, PyAny>>) -> PyResult<isize> {
+        Ok(self.span(group)?.1)
+    }
+
+    /// `(start, end)` of a group, or `(-1, -1)` if it did not participate in
+    /// the match
+    #[pyo3(signature = (group=None))]
+    fn span(&self, group: Option<&Bound<'_, PyAny>>) -> PyResult<(isize, isize)> {
+        let n = match group {
+            Some(group) => self.group_index(group)?,
+            None => 0,
+        };
+        Ok(self.group_span(n).map_or((-1, -1), |(s, e)| {
+            (self.input.index(s) as isize, self.input.index(e) as isize)
+        }))
+    }
+
+    /// Number of the last group to close, or `None` if no group participated
     #[getter]
-    fn start(&self) -> usize {
-        self.start
+    fn lastindex(&self) -> Option<usize> {
+        // Of groups ending at the same place, the outermost closes last
+        (1..self.inner.groups.len())
+            .filter_map(|n| self.group_span(n).map(|(_, e)| (e, std::cmp::Reverse(n))))
+            .max()
+            .map(|(_, std::cmp::Reverse(n))| n)
     }
 
-    /// End position of the match
+    /// Name of the last group to close, or `None` if it has no name
     #[getter]
-    fn end(&self) -> usize {
-        self.end
+    fn lastgroup(&self) -> Option<&str> {
+        self.group_names.get(self.lastindex()?)?.as_deref()
     }
 
     /// The matched text
     #[getter]
-    fn text(&self) -> &str {
-        &self.input[self.start..self.end]
+    fn text<'py>(&self, py: Python<'py>) -> Bound<'py, PyAny> {
+        self.group0(py)
     }
 
-    /// All captured groups as a list
+    /// All captured groups as a list, with `None` for groups that did not participate
     #[getter]
-    fn groups(&self) -> Vec<Option<String>> {
-        let max_group = self.groups.keys().max().copied().unwrap_or(0);
-        let mut result = Vec::with_capacity(max_group as usize);
-        for i in 1..=max_group {
-            if let Some(&(s, e)) = self.groups.get(&i) {
-                result.push(Some(self.input[s..e].to_string()));
-            } else {
-                result.push(None);
-            }
-        }
-        result
+    fn groups<'py>(&self, py: Python<'py>) -> Vec<Option<Bound<'py, PyAny>>> {
+        (1..self.inner.groups.len())
+            .map(|n| self.group_text(py, n))
+            .collect()
     }
 }
 
 /// Compile a regex pattern
-#[pyfunction]
-fn compile(pattern: &str) -> PyResult<PyRegex> {
-    PyRegex::new(pattern)
+#[pyfunction(signature = (pattern, flags=0))]
+fn compile(pattern: &Bound<'_, PyAny>, flags: u32) -> PyResult<PyRegex> {
+    PyRegex::new(pattern, flags)
 }
 
 /// Search for a match
-#[pyfunction]
-fn search(pattern: &str, string: &str) -> PyResult<Option<PyMatch>> {
-    let regex = PyRegex::new(pattern)?;
-    Ok(regex.search(string))
+#[pyfunction(signature = (pattern, string, flags=0))]
+fn search(
+    pattern: &Bound<'_, PyAny>,
+    string: &Bound<'_, PyAny>,
+    flags: u32,
+) -> PyResult<Option<PyMatch>> {
+    let regex = PyRegex::new(pattern, flags)?;
+    regex.search(string, 0, None)
 }
 
 /// Check if pattern matches at start
-#[pyfunction]
-fn match_(pattern: &str, string: &str) -> PyResult<Option<PyMatch>> {
-    let regex = PyRegex::new(pattern)?;
-    Ok(regex.match_(string))
+#[pyfunction(signature = (pattern, string, flags=0))]
+fn match_(
+    pattern: &Bound<'_, PyAny>,
+    string: &Bound<'_, PyAny>,
+    flags: u32,
+) -> PyResult<Option<PyMatch>> {
+    let regex = PyRegex::new(pattern, flags)?;
+    regex.match_(string, 0, None)
 }
 
 /// Find all matches
-#[pyfunction]
-fn findall<'py>(py: Python<'py>, pattern: &str, string: &str) -> PyResult<Bound<'py, PyList>> {
-    let regex = PyRegex::new(pattern)?;
-    regex.findall(py, string)
+#[pyfunction(signature = (pattern, string, flags=0))]
+fn findall<'py>(
+    py: Python<'py>,
+    pattern: &Bound<'_, PyAny>,
+    string: &Bound<'_, PyAny>,
+    flags: u32,
+) -> PyResult<Bound<'py, PyList>> {
+    let regex = PyRegex::new(pattern, flags)?;
+    regex.findall(py, string, 0, None)
+}
+
+/// Iterate over all matches
+#[pyfunction(signature = (pattern, string, flags=0))]
+fn finditer(
+    py: Python<'_>,
+    pattern: &Bound<'_, PyAny>,
+    string: &Bound<'_, PyAny>,
+    flags: u32,
+) -> PyResult<PyMatchIterator> {
+    let regex = Bound::new(py, PyRegex::new(pattern, flags)?)?;
+    PyRegex::finditer(&regex, string, 0, None)
 }
 
 /// Substitute matches
-#[pyfunction(signature = (pattern, repl, string, count=None))]
-fn sub(pattern: &str, repl: &str, string: &str, count: Option<usize>) -> PyResult<String> {
-    let regex = PyRegex::new(pattern)?;
-    regex.sub(repl, string, count)
+#[pyfunction(signature = (pattern, repl, string, count=None, flags=0))]
+fn sub<'py>(
+    py: Python<'py>,
+    pattern: &Bound<'py, PyAny>,
+    repl: &Bound<'py, PyAny>,
+    string: &Bound<'py, PyAny>,
+    count: Option<usize>,
+    flags: u32,
+) -> PyResult<Bound<'py, PyAny>> {
+    let regex = PyRegex::new(pattern, flags)?;
+    regex.sub(py, repl, string, count)
 }
 
+/// Escape the characters of `string` that have a meaning in a pattern
+#[pyfunction]
+fn escape<'py>(py: Python<'py>, string: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PyAny>> {
+    let text = Text::extract(string)?;
+    let mut result = Vec::new();
+    text.push_latin1(&mut result, &::ogex::escape(&text.to_latin1()));
+    Ok(text.new_like(py, result))
+}
 /// Ogex Python module
 #[pymodule(name = "ogex")]
 fn ogex(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    for (name, value) in [
+        ("IGNORECASE", IGNORECASE),
+        ("I", IGNORECASE),
+        ("MULTILINE", MULTILINE),
+        ("M", MULTILINE),
+        ("DOTALL", DOTALL),
+        ("S", DOTALL),
+        ("UNICODE", UNICODE),
+        ("U", UNICODE),
+        ("VERBOSE", VERBOSE),
+        ("X", VERBOSE),
+    ] {
+        m.add(name, value)?;
+    }
+    m.add("error", m.py().get_type::<error>())?;
     m.add_class::<PyRegex>()?;
     m.add_class::<PyMatch>()?;
+    m.add_class::<PyMatchIterator>()?;
     m.add_function(wrap_pyfunction!(compile, m)?)?;
     m.add_function(wrap_pyfunction!(search, m)?)?;
     m.add_function(wrap_pyfunction!(match_, m)?)?;
     m.add_function(wrap_pyfunction!(findall, m)?)?;
+    m.add_function(wrap_pyfunction!(finditer, m)?)?;
     m.add_function(wrap_pyfunction!(sub, m)?)?;
+    m.add_function(wrap_pyfunction!(escape, m)?)?;
     Ok(())
 }
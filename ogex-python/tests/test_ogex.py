@@ -22,11 +22,66 @@ class TestCompile:
         r = ogex.compile("(name:hello)")
         assert r is not None
     
+    def test_pattern_attributes(self):
+        """Test pattern, groups and groupindex"""
+        import ogex
+        r = ogex.compile(r"(year:\d{4})-(\d{2})-(day:\d{2})")
+        assert r.pattern == r"(year:\d{4})-(\d{2})-(day:\d{2})"
+        assert r.groups == 3
+        assert r.groupindex == {"year": 1, "day": 3}
+    
     def test_compile_invalid(self):
         """Test compiling an invalid pattern raises error"""
         import ogex
         with pytest.raises(ValueError):
             ogex.compile("(unclosed")
+    
+    def test_error_attributes(self):
+        """Test ogex.error carries msg, pattern and pos like re.error"""
+        import ogex
+        try:
+            ogex.compile("ab(c")
+        except ogex.error as e:
+            assert e.msg == "expected `)`, found EOF"
+            assert e.pattern == "ab(c"
+            assert e.pos == 4
+        else:
+            raise AssertionError("expected ogex.error")
+        assert issubclass(ogex.error, ValueError)
+    
+    def test_template_error(self):
+        """Test an invalid template raises ogex.error"""
+        import ogex
+        with pytest.raises(ogex.error):
+            ogex.sub("(a)", r"\2", "a")
+
+
+class TestFlags:
+    """Test re-style flags"""
+    
+    def test_ignorecase_multiline_dotall(self):
+        """Test flags from ogex and from re are both accepted"""
+        import re
+        import ogex
+        assert ogex.search("^b.c$", "a\nB\nc", ogex.I | ogex.M | ogex.S)
+        assert ogex.compile("^b.c$", flags=re.IGNORECASE | re.MULTILINE | re.DOTALL)
+        assert ogex.search("^b.c$", "a\nB\nc") is None
+    
+    def test_verbose(self):
+        """Test VERBOSE ignores whitespace and comments"""
+        import ogex
+        r = ogex.compile(r"""
+            (year: \d{4})  # year
+            - (month: \d{2})
+        """, ogex.VERBOSE)
+        assert r.search("on 2024-05").group("month") == "05"
+    
+    def test_unsupported_flag(self):
+        """Test unknown flags are rejected"""
+        import re
+        import ogex
+        with pytest.raises(ValueError):
+            ogex.compile("a", re.ASCII)
 
 
 class TestMatch:
@@ -81,6 +136,46 @@ class TestSearch:
         assert m.text() == "world"
 
 
+class TestRegion:
+    """Test pos and endpos arguments"""
+    
+    def test_search_region(self):
+        """Test search only looks between pos and endpos"""
+        import ogex
+        r = ogex.compile(r"\d+")
+        assert r.search("12 34 56", 2).group(0) == "34"
+        assert r.search("12 34 56", 2, 4).group(0) == "3"
+        assert r.search("12 34 56", 3, 3) is None
+    
+    def test_anchors_at_region_boundaries(self):
+        """Test ^ keeps its meaning at pos while $ matches at endpos"""
+        import ogex
+        assert ogex.compile("^b").search("ab", 1) is None
+        assert ogex.compile("a$").search("ab", 0, 1).span() == (0, 1)
+    
+    def test_match_at_pos(self):
+        """Test match anchors at pos"""
+        import ogex
+        r = ogex.compile("b+")
+        assert r.match_("abb", 1).span() == (1, 3)
+        assert r.match_("abb") is None
+    
+    def test_finditer_region(self):
+        """Test finditer and findall honour pos and endpos"""
+        import ogex
+        r = ogex.compile(r"\d")
+        assert [m.span() for m in r.finditer("1234", 1, 3)] == [(1, 2), (2, 3)]
+        assert len(r.findall("1234", 2)) == 2
+    
+    def test_non_ascii_region(self):
+        """Test pos and endpos count characters rather than UTF-8 bytes"""
+        import ogex
+        r = ogex.compile("é")
+        assert r.search("aéé", 2).span() == (2, 3)
+        assert r.search("aéé", 0, 1) is None
+        assert [m.span() for m in r.finditer("éaé", 1)] == [(2, 3)]
+
+
 class TestFindAll:
     """Test findall functionality"""
     
@@ -105,6 +200,25 @@ class TestFindAll:
         assert len(matches) == 3
 
 
+class TestFindIter:
+    """Test finditer functionality"""
+    
+    def test_finditer_lazy(self):
+        """Test finditer yields matches one at a time"""
+        import ogex
+        r = ogex.compile(r"(d:\d+)")
+        it = r.finditer("a1 b22 c333")
+        assert next(it).text == "1"
+        assert [m.group(1) for m in it] == ["22", "333"]
+        assert list(it) == []
+    
+    def test_finditer_empty_matches(self):
+        """Test finditer advances past empty matches like re.finditer"""
+        import ogex
+        spans = [m.span() for m in ogex.finditer("x*", "axb")]
+        assert spans == [(0, 0), (1, 2), (2, 2), (3, 3)]
+
+
 class TestSub:
     """Test substitution functionality"""
     
@@ -136,6 +250,14 @@ class TestSub:
         result = r.sub("X", "banana", count=1)
         assert result == "bXnana"
     
+    def test_sub_callable(self):
+        """Test substitution with a function of the match"""
+        import ogex
+        r = ogex.compile(r"(n:\d+)")
+        result = r.sub(lambda m: str(int(m.group("n")) * 2), "a1 b22 c3", count=2)
+        assert result == "a2 b44 c3"
+        assert ogex.sub("x*", lambda m: "-", "ab") == "-a-b-"
+    
     def test_sub_function(self):
         """Test module-level sub function"""
         import ogex
@@ -143,6 +265,18 @@ class TestSub:
         assert result == "bXnXnX"
 
 
+class TestEscape:
+    """Test escape()"""
+    
+    def test_escape_matches_literally(self):
+        """Test escaped user input is matched as plain text"""
+        import ogex
+        text = "(user:1) costs $5.00 [+tax]?"
+        assert ogex.escape("(a:b)") == r"\(a\:b\)"
+        assert ogex.search(ogex.escape(text), "x " + text).group(0) == text
+        assert ogex.compile(ogex.escape("a b"), ogex.VERBOSE).search("a b")
+
+
 class TestNamedGroups:
     """Test named group functionality"""
     
@@ -160,6 +294,23 @@ class TestNamedGroups:
         r = ogex.compile(r"(word:\w+) is \g{word}")
         m = r.search("test is test")
         assert m is not None
+    
+    def test_group_by_name(self):
+        """Test group() accepts group names"""
+        import ogex
+        m = ogex.search(r"(year:\d{4})-(month:\d{2})", "on 2024-05")
+        assert m.group("year") == "2024"
+        assert m.group("month") == "05"
+        assert m.group(0) == "2024-05"
+        with pytest.raises(IndexError):
+            m.group("day")
+    
+    def test_groupdict(self):
+        """Test groupdict() with and without a default"""
+        import ogex
+        m = ogex.search(r"(a:x)|(b:y)", "y")
+        assert m.groupdict() == {"a": None, "b": "y"}
+        assert m.groupdict("") == {"a": "", "b": "y"}
 
 
 class TestRelativeBackref:
@@ -211,6 +362,49 @@ class TestMatchObject:
         assert m.group(1) == "a"
         assert m.group(2) == "b"
         assert m.group(3) == "c"
+    
+    def test_match_span(self):
+        """Test span() for the match and its groups"""
+        import ogex
+        m = ogex.search(r"(a:x)|(b:y)", "zy")
+        assert m.span() == (1, 2)
+        assert m.span("b") == (1, 2)
+        assert m.span(1) == (-1, -1)
+        assert m.start(2) == 1
+    
+    def test_non_ascii_positions(self):
+        """Test positions count characters rather than UTF-8 bytes"""
+        import ogex
+        m = ogex.search(r"(b+)", "éébb")
+        assert m.span() == (2, 4)
+        assert m.start(1) == 2
+        assert m.end(1) == 4
+    
+    def test_match_expand(self):
+        """Test expand() with numbered and named references"""
+        import ogex
+        m = ogex.search(r"(key:\w+)=(\w+)", "a=b")
+        assert m.expand(r"\2=\g<key>") == "b=a"
+        assert m.expand(r"[\g{key}]") == "[a]"
+    
+    def test_match_getitem(self):
+        """Test m[group] subscripts"""
+        import ogex
+        m = ogex.search(r"(key:\w+)=(\w+)", "a=b")
+        assert m[0] == "a=b"
+        assert m["key"] == "a"
+        assert m[2] == "b"
+    
+    def test_lastindex_and_lastgroup(self):
+        """Test lastindex and lastgroup"""
+        import ogex
+        m = ogex.search(r"(a)(outer:b(c))", "abc")
+        assert m.lastindex == 2
+        assert m.lastgroup == "outer"
+        m = ogex.search(r"(a)(x)?", "a")
+        assert m.lastindex == 1
+        assert m.lastgroup is None
+        assert ogex.search("a", "a").lastindex is None
 
 
 class TestIsMatch:
@@ -269,5 +463,44 @@ class TestSpecialSyntax:
         assert m.text() == "helloworld"
 
 
+
+class TestBytes:
+    """Test bytes patterns and input"""
+
+    def test_bytes_search(self):
+        """Test a bytes pattern finds matches in bytes"""
+        import ogex
+        m = ogex.compile(rb"(word:\w+)=(\d+)").search(b"\xff\xfe key=42")
+        assert m is not None
+        assert m.group(0) == b"key=42"
+        assert m.group("word") == b"key"
+        assert m.groups == [b"key", b"42"]
+        assert m.span() == (3, 9)
+
+    def test_bytes_findall_and_pattern(self):
+        """Test findall and the pattern getter keep the bytes type"""
+        import ogex
+        r = ogex.compile(rb"\d+")
+        assert r.pattern == rb"\d+"
+        assert [m.group(0) for m in r.findall(b"1 \x80 22")] == [b"1", b"22"]
+
+    def test_bytes_sub(self):
+        """Test sub with a bytes template and with a callable"""
+        import ogex
+        r = ogex.compile(rb"(\d)")
+        assert r.sub(rb"<\1>", b"a1\xffb2") == b"a<1>\xffb<2>"
+        assert r.sub(lambda m: m.group(0) * 2, b"x3") == b"x33"
+
+    def test_mixed_types_raise(self):
+        """Test mixing str and bytes raises TypeError"""
+        import ogex
+        with pytest.raises(TypeError):
+            ogex.compile("a").search(b"a")
+        with pytest.raises(TypeError):
+            ogex.compile(b"a").search("a")
+        with pytest.raises(TypeError):
+            ogex.compile(b"a").sub("b", b"a")
+
+
 if __name__ == "__main__":
     pytest.main([__file__, "-v"])
@@ -0,0 +1,455 @@
+//! Set operations on the strings patterns match
+//!
+//! A [`Language`] is the set of strings a pattern matches in full, as if it
+//! were anchored at both ends. Languages are compiled to deterministic
+//! automata, so they can be intersected, complemented and compared, which
+//! answers questions a search cannot: whether two patterns accept exactly the
+//! same strings, whether one accepts a subset of the other, or whether a
+//! pattern can match anything at all.
+//!
+//! Anchors and word boundaries are supported. Backreferences and lookarounds
+//! are not, since the strings they accept need not form a regular language.
+//!
+//! ```
+//! use ogex::Regex;
+//! use ogex::algebra::Language;
+//!
+//! let short = Language::new(&Regex::new("a+|b").unwrap()).unwrap();
+//! let long = Language::new(&Regex::new("b|aa*").unwrap()).unwrap();
+//! assert!(short.equivalent(&long).unwrap());
+//!
+//! let digits = Language::new(&Regex::new(r"\d{2,}").unwrap()).unwrap();
+//! let odd = Language::new(&Regex::new(r"\d*[13579]").unwrap()).unwrap();
+//! let both = digits.intersection(&odd).unwrap();
+//! assert_eq!(both.example().as_deref(), Some("01"));
+//! assert!(digits.intersection(&short).unwrap().is_empty());
+//! ```
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::collections::HashMap;
+use crate::engine::Regex;
+use crate::nfa::{Nfa, StateId, Transition};
+
+/// Most states an automaton may have before an operation gives up
+const STATE_LIMIT: usize = 10_000;
+
+/// Why a language could not be built
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlgebraError {
+    /// The pattern uses a feature whose strings are not a regular language,
+    /// such as `"backreferences"`
+    Unsupported(&'static str),
+    /// The automaton grew past the state limit
+    TooManyStates {
+        /// The limit that was reached
+        limit: usize,
+    },
+}
+
+impl fmt::Display for AlgebraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlgebraError::Unsupported(feature) => {
+                write!(f, "patterns with {} are not supported", feature)
+            }
+            AlgebraError::TooManyStates { limit } => {
+                write!(f, "the automaton needs more than {} states", limit)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AlgebraError {}
+
+/// Check whether two patterns match exactly the same strings
+pub fn equivalent(a: &Regex, b: &Regex) -> Result<bool, AlgebraError> {
+    Language::new(a)?.equivalent(&Language::new(b)?)
+}
+
+/// The set of strings a pattern matches in full
+#[derive(Debug, Clone)]
+pub struct Language {
+    /// The first character of each class of characters the automaton does
+    /// not tell apart, in order
+    classes: Vec<char>,
+    /// The next state for each state and class; state 0 is the start
+    next: Vec<Vec<usize>>,
+    accepting: Vec<bool>,
+}
+
+impl Language {
+    /// Build the language of a pattern
+    pub fn new(regex: &Regex) -> Result<Self, AlgebraError> {
+        Subsets::new(regex.nfa())?.build()
+    }
+
+    /// Check if `input` is in the language, that is, whether the pattern
+    /// matches all of it
+    pub fn is_match(&self, input: &str) -> bool {
+        let end = input
+            .chars()
+            .fold(0, |state, c| self.next[state][self.class_of(c)]);
+        self.accepting[end]
+    }
+
+    /// Whether the language has no strings at all
+    pub fn is_empty(&self) -> bool {
+        self.example().is_none()
+    }
+
+    /// One of the shortest strings in the language, if it has any
+    pub fn example(&self) -> Option<String> {
+        let mut parent: Vec<Option<(usize, usize)>> = vec![None; self.next.len()];
+        let mut seen = vec![false; self.next.len()];
+        seen[0] = true;
+        let mut queue = VecDeque::from([0]);
+        while let Some(state) = queue.pop_front() {
+            if self.accepting[state] {
+                let mut text = Vec::new();
+                let mut at = state;
+                while let Some((from, class)) = parent[at] {
+                    text.push(self.sample(class));
+                    at = from;
+                }
+                return Some(text.into_iter().rev().collect());
+            }
+            for (class, &target) in self.next[state].iter().enumerate() {
+                if !seen[target] {
+                    seen[target] = true;
+                    parent[target] = Some((state, class));
+                    queue.push_back(target);
+                }
+            }
+        }
+        None
+    }
+
+    /// The strings not in the language
+    pub fn complement(&self) -> Language {
+        Language {
+            classes: self.classes.clone(),
+            next: self.next.clone(),
+            accepting: self.accepting.iter().map(|accepting| !accepting).collect(),
+        }
+    }
+
+    /// The strings in both languages
+    pub fn intersection(&self, other: &Language) -> Result<Language, AlgebraError> {
+        self.product(other, |a, b| a && b)
+    }
+
+    /// The strings in either language
+    pub fn union(&self, other: &Language) -> Result<Language, AlgebraError> {
+        self.product(other, |a, b| a || b)
+    }
+
+    /// The strings in this language but not in `other`
+    pub fn difference(&self, other: &Language) -> Result<Language, AlgebraError> {
+        self.product(other, |a, b| a && !b)
+    }
+
+    /// Whether every string in this language is also in `other`
+    pub fn is_subset(&self, other: &Language) -> Result<bool, AlgebraError> {
+        Ok(self.difference(other)?.is_empty())
+    }
+
+    /// Whether both languages have exactly the same strings
+    pub fn equivalent(&self, other: &Language) -> Result<bool, AlgebraError> {
+        Ok(self.counterexample(other)?.is_none())
+    }
+
+    /// One of the shortest strings in exactly one of the languages, or
+    /// `None` if they are equivalent
+    pub fn counterexample(&self, other: &Language) -> Result<Option<String>, AlgebraError> {
+        Ok(self.product(other, |a, b| a != b)?.example())
+    }
+
+    /// Run both automata side by side, accepting where `accept` says to
+    fn product(
+        &self,
+        other: &Language,
+        accept: impl Fn(bool, bool) -> bool,
+    ) -> Result<Language, AlgebraError> {
+        let mut classes = self.classes.clone();
+        classes.extend_from_slice(&other.classes);
+        classes.sort_unstable();
+        classes.dedup();
+        let ours: Vec<_> = classes.iter().map(|&c| self.class_of(c)).collect();
+        let theirs: Vec<_> = classes.iter().map(|&c| other.class_of(c)).collect();
+
+        let mut ids = HashMap::new();
+        let mut pairs = vec![(0, 0)];
+        ids.insert((0, 0), 0);
+        let mut next = Vec::new();
+        while let Some(&(a, b)) = pairs.get(next.len()) {
+            let mut row = Vec::with_capacity(classes.len());
+            for class in 0..classes.len() {
+                let pair = (self.next[a][ours[class]], other.next[b][theirs[class]]);
+                let id = match ids.get(&pair) {
+                    Some(&id) => id,
+                    None => {
+                        if pairs.len() == STATE_LIMIT {
+                            return Err(AlgebraError::TooManyStates { limit: STATE_LIMIT });
+                        }
+                        ids.insert(pair, pairs.len());
+                        pairs.push(pair);
+                        pairs.len() - 1
+                    }
+                };
+                row.push(id);
+            }
+            next.push(row);
+        }
+        let accepting = pairs
+            .iter()
+            .map(|&(a, b)| accept(self.accepting[a], other.accepting[b]))
+            .collect();
+        Ok(Language {
+            classes,
+            next,
+            accepting,
+        })
+    }
+
+    fn class_of(&self, c: char) -> usize {
+        self.classes.partition_point(|&start| start <= c) - 1
+    }
+
+    /// A character of a class, readable if the class has one
+    fn sample(&self, class: usize) -> char {
+        let start = self.classes[class];
+        let end = self.classes.get(class + 1).copied();
+        let within = |c: &char| *c >= start && end.is_none_or(|end| *c < end);
+        ('a'..='z')
+            .chain('0'..='9')
+            .chain(' '..='~')
+            .find(within)
+            .unwrap_or(start)
+    }
+}
+
+/// What the assertions at a position depend on besides the next character
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Context {
+    /// Whether `^` holds
+    line_start: bool,
+    /// Whether the previous character is a word character
+    after_word: bool,
+}
+
+/// The subset construction of a pattern's automaton
+struct Subsets<'a> {
+    nfa: &'a Nfa,
+    classes: Vec<char>,
+}
+
+impl<'a> Subsets<'a> {
+    fn new(nfa: &'a Nfa) -> Result<Self, AlgebraError> {
+        let mut classes: Vec<char> = ('\0'..='\u{100}').collect();
+        for state in &nfa.states {
+            for (transition, _) in &state.transitions {
+                match transition {
+                    Transition::Backref(_) | Transition::BackrefRelative(_) => {
+                        return Err(AlgebraError::Unsupported("backreferences"));
+                    }
+                    Transition::Lookahead(_)
+                    | Transition::NegativeLookahead(_)
+                    | Transition::Lookbehind(_)
+                    | Transition::NegativeLookbehind(_) => {
+                        return Err(AlgebraError::Unsupported("lookarounds"));
+                    }
+                    // Characters past Latin-1 only differ where a literal
+                    // names them
+                    Transition::Char(c) if *c >= '\u{100}' => {
+                        classes.push(*c);
+                        classes.extend(
+                            char::from_u32(*c as u32 + 1)
+                                .or_else(|| (*c == '\u{d7ff}').then_some('\u{e000}')),
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+        classes.sort_unstable();
+        classes.dedup();
+        Ok(Subsets { nfa, classes })
+    }
+
+    fn build(self) -> Result<Language, AlgebraError> {
+        let start = (
+            vec![self.nfa.start],
+            Context {
+                line_start: true,
+                after_word: false,
+            },
+        );
+        let mut ids = HashMap::new();
+        ids.insert(start.clone(), 0);
+        let mut sets = vec![start];
+        let mut next = Vec::new();
+        while let Some((states, context)) = sets.get(next.len()).cloned() {
+            let mut row = Vec::with_capacity(self.classes.len());
+            for &c in &self.classes {
+                let mut targets = Vec::new();
+                for state in self.close(&states, context, Some(c)) {
+                    for (transition, target) in &self.nfa.states[state].transitions {
+                        if self.nfa.matches_char(transition, c) {
+                            targets.push(*target);
+                        }
+                    }
+                }
+                targets.sort_unstable();
+                targets.dedup();
+                let set = (
+                    targets,
+                    Context {
+                        line_start: self.nfa.mode_flags.multiline && c == '\n',
+                        after_word: is_word(c),
+                    },
+                );
+                let id = match ids.get(&set) {
+                    Some(&id) => id,
+                    None => {
+                        if sets.len() == STATE_LIMIT {
+                            return Err(AlgebraError::TooManyStates { limit: STATE_LIMIT });
+                        }
+                        ids.insert(set.clone(), sets.len());
+                        sets.push(set);
+                        sets.len() - 1
+                    }
+                };
+                row.push(id);
+            }
+            next.push(row);
+        }
+        let accepting = sets
+            .iter()
+            .map(|(states, context)| {
+                self.close(states, *context, None)
+                    .contains(&self.nfa.accept)
+            })
+            .collect();
+        Ok(Language {
+            classes: self.classes,
+            next,
+            accepting,
+        })
+    }
+
+    /// The states reachable from `states` without consuming a character,
+    /// before `next` or at the end of the input
+    fn close(&self, states: &[StateId], context: Context, next: Option<char>) -> Vec<StateId> {
+        let multiline = self.nfa.mode_flags.multiline;
+        let before_word = next.is_some_and(is_word);
+        let mut reached = vec![false; self.nfa.states.len()];
+        let mut pending = states.to_vec();
+        for &state in states {
+            reached[state] = true;
+        }
+        while let Some(state) = pending.pop() {
+            for (transition, target) in &self.nfa.states[state].transitions {
+                let follow = match transition {
+                    Transition::Epsilon | Transition::GroupStart(_) | Transition::GroupEnd(_) => {
+                        true
+                    }
+                    Transition::StartAnchor => context.line_start,
+                    Transition::EndAnchor => next.is_none() || (multiline && next == Some('\n')),
+                    Transition::WordBoundary => context.after_word != before_word,
+                    Transition::NonWordBoundary => context.after_word == before_word,
+                    _ => false,
+                };
+                if follow && !reached[*target] {
+                    reached[*target] = true;
+                    pending.push(*target);
+                }
+            }
+        }
+        (0..reached.len()).filter(|&state| reached[state]).collect()
+    }
+}
+
+/// Whether `\b` counts `c` as a word character, as the matcher does
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn language(pattern: &str) -> Language {
+        Language::new(&Regex::new(pattern).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_membership_is_a_full_match() {
+        let lang = language(r"(key:\w+)=\d*");
+        assert!(lang.is_match("a=1"));
+        assert!(lang.is_match("abc="));
+        assert!(!lang.is_match("a=1 "));
+        assert!(!lang.is_match("=1"));
+        assert!(language("(@i:abc)").is_match("AbC"));
+        assert!(language("é+").is_match("éé"));
+        assert!(!language(".").is_match("\n"));
+    }
+
+    #[test]
+    fn test_equivalence() {
+        assert!(
+            equivalent(
+                &Regex::new("(a|b)*").unwrap(),
+                &Regex::new("[ab]*").unwrap()
+            )
+            .unwrap()
+        );
+        assert!(equivalent(&Regex::new("a{2,3}").unwrap(), &Regex::new("aaa?").unwrap()).unwrap());
+        let (a, b) = (language("a*"), language("a+"));
+        assert!(!a.equivalent(&b).unwrap());
+        assert_eq!(a.counterexample(&b).unwrap().as_deref(), Some(""));
+        assert!(b.is_subset(&a).unwrap());
+        assert!(!a.is_subset(&b).unwrap());
+    }
+
+    #[test]
+    fn test_complement_and_emptiness() {
+        let lang = language("[a-z]+");
+        let others = lang.complement();
+        assert!(others.is_match(""));
+        assert!(others.is_match("a1"));
+        assert!(!others.is_match("abc"));
+        assert!(lang.intersection(&others).unwrap().is_empty());
+        assert!(lang.union(&others).unwrap().complement().is_empty());
+        assert!(language("a^b").is_empty());
+        assert!(!language("a$").is_empty());
+    }
+
+    #[test]
+    fn test_assertions() {
+        assert!(language(r"a\b").is_match("a"));
+        assert!(language(r"a\bb").is_empty());
+        assert!(language(r"a\B").is_empty());
+        let lines = language("(@m:a$\n^b)");
+        assert!(lines.is_match("a\nb"));
+        assert!(language("a$\n^b").is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_patterns() {
+        let unsupported = |pattern: &str| Language::new(&Regex::new(pattern).unwrap()).unwrap_err();
+        assert_eq!(
+            unsupported(r"(a)\1"),
+            AlgebraError::Unsupported("backreferences")
+        );
+        assert_eq!(
+            unsupported(r"a(@>:b)"),
+            AlgebraError::Unsupported("lookarounds")
+        );
+    }
+}
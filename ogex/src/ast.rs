@@ -9,7 +9,12 @@
 //! - Anchors (^, $)
 //! - Backreferences
 
-use std::fmt;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
 
 /// An expression in the AST
 #[derive(Debug, Clone, PartialEq)]
@@ -131,7 +136,8 @@ impl CharacterClass {
                     'W' => !(ch.is_ascii_alphanumeric() || ch == '_'),
                     's' => ch.is_ascii_whitespace(),
                     'S' => !ch.is_ascii_whitespace(),
-                    _ => false,
+                    // Any other escape stands for the character itself
+                    _ => ch == *sh,
                 },
             });
 
@@ -164,7 +170,7 @@ impl CharacterClass {
                     'W' => !(c.is_ascii_alphanumeric() || c == '_'),
                     's' => c.is_ascii_whitespace(),
                     'S' => !c.is_ascii_whitespace(),
-                    _ => false,
+                    _ => c == *sh,
                 },
             });
             if self.negated { !matched } else { matched }
@@ -315,6 +321,40 @@ impl Expr {
         Expr::NamedBackreference(name.into())
     }
 
+    /// The expressions directly inside this one, in pattern order
+    pub fn children(&self) -> &[Expr] {
+        match self {
+            Expr::Sequence(exprs) | Expr::Alternation(exprs) => exprs,
+            Expr::Quantified { expr, .. }
+            | Expr::Group(expr)
+            | Expr::NonCapturingGroup(expr)
+            | Expr::NamedGroup { pattern: expr, .. }
+            | Expr::ModeFlagsGroup { pattern: expr, .. }
+            | Expr::Lookahead(expr)
+            | Expr::NegativeLookahead(expr)
+            | Expr::Lookbehind(expr)
+            | Expr::NegativeLookbehind(expr)
+            | Expr::AtomicGroup(expr)
+            | Expr::ConditionalGroup(expr) => core::slice::from_ref(&**expr),
+            _ => &[],
+        }
+    }
+
+    /// The backreferences in this expression, in the order of a walk of the
+    /// tree
+    pub fn backreferences(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Backreference(_)
+            | Expr::RelativeBackreference(_)
+            | Expr::NamedBackreference(_) => vec![self],
+            _ => self
+                .children()
+                .iter()
+                .flat_map(Expr::backreferences)
+                .collect(),
+        }
+    }
+
     /// Convert the AST back to a string (for debugging/transpilation)
     pub fn to_regex_string(&self) -> String {
         match self {
@@ -552,7 +592,7 @@ impl CharacterClass {
 impl Quantifier {
     /// Convert quantifier to regex string
     #[allow(clippy::wrong_self_convention)]
-    fn to_regex_string(&self, greedy: bool) -> String {
+    pub(crate) fn to_regex_string(&self, greedy: bool) -> String {
         let suffix = if greedy {
             "".to_string()
         } else {
@@ -639,6 +679,18 @@ mod tests {
         assert_eq!(expr.to_regex_string(), "[a-z0-9]");
     }
 
+    #[test]
+    fn test_character_class_escapes() {
+        let class = CharacterClass {
+            negated: false,
+            items: vec![ClassItem::Shorthand('d'), ClassItem::Shorthand('.')],
+        };
+        let lookup = class.to_lookup_table();
+        assert!(class.matches('7', &lookup));
+        assert!(class.matches('.', &lookup));
+        assert!(!class.matches('x', &lookup));
+    }
+
     #[test]
     fn test_quantifier_zero_or_more() {
         let expr = Expr::quantified(Expr::literal('a'), Quantifier::ZeroOrMore, true);
@@ -0,0 +1,297 @@
+//! Reusing compiled regexes across requests
+//!
+//! Applications that receive patterns at run time, from a reloaded config
+//! file or a search box, tend to see the same few patterns again and again.
+//! A [`RegexCache`] keeps the regexes compiled most recently, up to a fixed
+//! number, so asking for a pattern it has seen costs a lookup rather than a
+//! compile:
+//!
+//! ```
+//! use ogex::{RegexBuilder, RegexCache};
+//!
+//! let mut cache = RegexCache::new(2);
+//! let words = cache.get(r"\w+").unwrap();
+//! assert!(words.is_match("hello"));
+//! // The same pattern with other options is another regex
+//! let upper = cache
+//!     .get_with(&RegexBuilder::new("abc").case_insensitive(true))
+//!     .unwrap();
+//! assert!(upper.is_match("ABC"));
+//!
+//! // A third pattern pushes out the one used least recently
+//! cache.get(r"\d+").unwrap();
+//! assert!(!cache.contains(&RegexBuilder::new(r"\w+")));
+//! assert_eq!(cache.len(), 2);
+//! ```
+//!
+//! Regexes are handed out as `Arc<Regex>`, so they stay usable after the
+//! cache drops them. With the `sync` feature, [`SyncRegexCache`] does the
+//! same behind a lock, for caches shared between threads.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+
+use crate::collections::HashMap;
+use crate::engine::{Limits, ModeFlags, Regex, RegexBuilder};
+use crate::error::RegexError;
+use crate::groups::GroupNumbering;
+
+/// Everything that decides what a [`RegexBuilder`] compiles
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey {
+    pub(crate) pattern: String,
+    pub(crate) numbering: GroupNumbering,
+    pub(crate) flags: ModeFlags,
+    pub(crate) limits: Limits,
+    pub(crate) backend: crate::vm::Backend,
+}
+
+struct Entry {
+    regex: Arc<Regex>,
+    /// When the entry was last used, as a key of `RegexCache::recent`
+    used: u64,
+}
+
+/// A bounded cache of compiled regexes that drops the least recently used
+///
+/// Entries are keyed on the pattern and every builder option. Builders
+/// with a [trace sink](RegexBuilder::trace_sink) or a
+/// [library](RegexBuilder::library) cannot be compared, so they are
+/// compiled every time and never cached. Patterns that fail to compile are
+/// not cached either.
+pub struct RegexCache {
+    capacity: usize,
+    entries: HashMap<CacheKey, Entry>,
+    /// Keys by when they were last used, oldest first
+    recent: BTreeMap<u64, CacheKey>,
+    clock: u64,
+}
+
+impl RegexCache {
+    /// Create a cache holding at most `capacity` regexes
+    ///
+    /// A capacity of zero caches nothing.
+    pub fn new(capacity: usize) -> Self {
+        RegexCache {
+            capacity,
+            entries: HashMap::default(),
+            recent: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// The compiled `pattern` with default options
+    pub fn get(&mut self, pattern: &str) -> Result<Arc<Regex>, RegexError> {
+        self.get_with(&RegexBuilder::new(pattern))
+    }
+
+    /// The regex `builder` compiles, compiling it if it is not cached
+    pub fn get_with(&mut self, builder: &RegexBuilder) -> Result<Arc<Regex>, RegexError> {
+        let Some(key) = builder.cache_key() else {
+            return builder.build().map(Arc::new);
+        };
+        if let Some(regex) = self.lookup(&key) {
+            return Ok(regex);
+        }
+        let regex = Arc::new(builder.build()?);
+        self.insert(key, Arc::clone(&regex));
+        Ok(regex)
+    }
+
+    /// Whether the regex `builder` compiles is cached, without marking it
+    /// as used
+    pub fn contains(&self, builder: &RegexBuilder) -> bool {
+        builder
+            .cache_key()
+            .is_some_and(|key| self.entries.contains_key(&key))
+    }
+
+    /// Number of regexes in the cache
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache holds no regexes
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The most regexes the cache holds
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Drop every cached regex
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recent.clear();
+    }
+
+    /// The cached regex for `key`, marked as just used
+    fn lookup(&mut self, key: &CacheKey) -> Option<Arc<Regex>> {
+        let entry = self.entries.get_mut(key)?;
+        self.clock += 1;
+        let key = self
+            .recent
+            .remove(&entry.used)
+            .expect("every entry is in the recency list");
+        entry.used = self.clock;
+        self.recent.insert(self.clock, key);
+        Some(Arc::clone(&entry.regex))
+    }
+
+    /// Cache `regex`, dropping the least recently used entries to make room
+    fn insert(&mut self, key: CacheKey, regex: Arc<Regex>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some(old) = self.entries.remove(&key) {
+            // Another thread compiled the same pattern in the meantime
+            self.recent.remove(&old.used);
+        }
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.recent.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        self.clock += 1;
+        self.recent.insert(self.clock, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                regex,
+                used: self.clock,
+            },
+        );
+    }
+}
+
+/// A [`RegexCache`] that threads can share
+///
+/// Lookups take a lock, but compiling a pattern that is not cached does
+/// not hold it, so a slow pattern only holds up the threads that asked for
+/// it. Two threads asking for the same new pattern at once may both
+/// compile it; the cache keeps one.
+#[cfg(feature = "sync")]
+pub struct SyncRegexCache {
+    inner: std::sync::Mutex<RegexCache>,
+}
+
+#[cfg(feature = "sync")]
+impl SyncRegexCache {
+    /// Create a cache holding at most `capacity` regexes
+    pub fn new(capacity: usize) -> Self {
+        SyncRegexCache {
+            inner: std::sync::Mutex::new(RegexCache::new(capacity)),
+        }
+    }
+
+    /// The compiled `pattern` with default options
+    pub fn get(&self, pattern: &str) -> Result<Arc<Regex>, RegexError> {
+        self.get_with(&RegexBuilder::new(pattern))
+    }
+
+    /// The regex `builder` compiles, compiling it if it is not cached
+    pub fn get_with(&self, builder: &RegexBuilder) -> Result<Arc<Regex>, RegexError> {
+        let Some(key) = builder.cache_key() else {
+            return builder.build().map(Arc::new);
+        };
+        if let Some(regex) = self.lock().lookup(&key) {
+            return Ok(regex);
+        }
+        let regex = Arc::new(builder.build()?);
+        self.lock().insert(key, Arc::clone(&regex));
+        Ok(regex)
+    }
+
+    /// Whether the regex `builder` compiles is cached
+    pub fn contains(&self, builder: &RegexBuilder) -> bool {
+        self.lock().contains(builder)
+    }
+
+    /// Number of regexes in the cache
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Whether the cache holds no regexes
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Drop every cached regex
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, RegexCache> {
+        // The cache is consistent between calls, so a panic elsewhere while
+        // it was locked leaves nothing to repair
+        self.inner
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_least_recently_used_is_dropped() {
+        let mut cache = RegexCache::new(2);
+        let a = cache.get("a").unwrap();
+        cache.get("b").unwrap();
+        // Using `a` again makes `b` the oldest
+        assert!(Arc::ptr_eq(&a, &cache.get("a").unwrap()));
+        cache.get("c").unwrap();
+        assert!(cache.contains(&RegexBuilder::new("a")));
+        assert!(!cache.contains(&RegexBuilder::new("b")));
+        assert!(cache.contains(&RegexBuilder::new("c")));
+        assert_eq!(cache.len(), 2);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(!Arc::ptr_eq(&a, &cache.get("a").unwrap()));
+    }
+
+    #[test]
+    fn test_options_are_part_of_the_key() {
+        let mut cache = RegexCache::new(8);
+        let plain = cache.get("^x$").unwrap();
+        let multiline = cache
+            .get_with(&RegexBuilder::new("^x$").multiline(true))
+            .unwrap();
+        assert!(!Arc::ptr_eq(&plain, &multiline));
+        assert!(multiline.is_match("a\nx"));
+        assert!(!plain.is_match("a\nx"));
+        assert_eq!(cache.len(), 2);
+
+        // Errors and builders with a library are not cached
+        assert!(cache.get("(x").is_err());
+        let library = crate::PatternLibrary::new();
+        cache
+            .get_with(&RegexBuilder::new("y").library(&library))
+            .unwrap();
+        assert_eq!(cache.len(), 2);
+        assert!(RegexCache::new(0).get("z").is_ok());
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_shared_between_threads() {
+        let cache = SyncRegexCache::new(4);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for pattern in ["a+", "b+", "c+"] {
+                        assert!(cache.get(pattern).unwrap().is_match("aabbcc"));
+                    }
+                });
+            }
+        });
+        assert_eq!(cache.len(), 3);
+    }
+}
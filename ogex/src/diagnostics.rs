@@ -0,0 +1,328 @@
+//! Static validation of patterns
+//!
+//! [`validate`] runs every compile-time check on a pattern — lexing,
+//! parsing, group names, backreferences, quantifiers and a ReDoS heuristic —
+//! and reports all the problems it finds instead of stopping at the first.
+//! No engine is built, which makes it cheap enough to run on every keystroke
+//! in an editor.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
+
+use crate::ast::{Expr, Quantifier};
+use crate::error::{ErrorCode, RegexError, Span};
+use crate::groups::{GroupCollector, GroupRegistry};
+use crate::parser::Parser;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    /// The pattern does not compile
+    Error,
+    /// The pattern compiles but probably does not do what was intended
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+/// A problem found in a pattern
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diagnostic {
+    /// Whether this stops the pattern from compiling
+    pub severity: Severity,
+    /// Stable code identifying the problem
+    pub code: ErrorCode,
+    /// Description of the problem
+    pub message: String,
+    /// Location in the pattern, if known
+    pub span: Option<Span>,
+    /// What the user probably meant to write, if there is a likely fix
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    fn warning(code: ErrorCode, message: String, span: Option<Span>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            code,
+            message,
+            span,
+            suggestion: None,
+        }
+    }
+
+    /// Whether this diagnostic stops the pattern from compiling
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl From<&RegexError> for Diagnostic {
+    fn from(err: &RegexError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: err.code(),
+            message: err.message(),
+            span: err.span(),
+            suggestion: err.suggestion().map(str::to_string),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)
+    }
+}
+
+/// Check a pattern and report every problem found, errors first
+///
+/// A pattern with no error diagnostics compiles with [`Regex::new`](crate::Regex::new).
+/// Lexer and parser errors stop the analysis, since later checks need the
+/// syntax tree.
+pub fn validate(pattern: &str) -> Vec<Diagnostic> {
+    let mut parser = Parser::new(pattern);
+    let ast = match parser.parse() {
+        Ok(ast) => ast,
+        Err(err) => return vec![Diagnostic::from(&err)],
+    };
+
+    let mut registry = GroupRegistry::new();
+    let group_errors = GroupCollector::collect_all(&ast, &mut registry);
+    for (index, span) in (1..).zip(parser.group_spans()) {
+        registry.set_span(index, *span);
+    }
+
+    let mut checker = Checker {
+        registry: &registry,
+        quantifier_spans: parser.quantifier_spans().iter(),
+        backref_spans: parser.backref_spans().iter(),
+        diagnostics: Vec::new(),
+    };
+    for (index, err) in group_errors {
+        let span = registry.get_by_index(index).and_then(|group| group.span);
+        checker.diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            code: ErrorCode::DuplicateGroupName,
+            message: err.to_string(),
+            span,
+            suggestion: None,
+        });
+    }
+    checker.visit(&ast);
+
+    let mut diagnostics = checker.diagnostics;
+    diagnostics.sort_by_key(|d| (!d.is_error(), d.span.map_or(usize::MAX, |span| span.start)));
+    diagnostics
+}
+
+/// Walks the AST collecting diagnostics
+struct Checker<'a> {
+    registry: &'a GroupRegistry,
+    /// Spans of quantified expressions in post-order, as recorded by the parser
+    quantifier_spans: core::slice::Iter<'a, Span>,
+    /// Spans of backreferences in the order the walk reaches them
+    backref_spans: core::slice::Iter<'a, Span>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker<'_> {
+    /// Check an expression and everything inside it, returning whether it
+    /// contains an unbounded quantifier
+    fn visit(&mut self, expr: &Expr) -> bool {
+        let mut unbounded = false;
+        for child in expr.children() {
+            unbounded |= self.visit(child);
+        }
+
+        match expr {
+            Expr::Quantified {
+                expr: inner,
+                quantifier,
+                ..
+            } => {
+                let span = self.quantifier_spans.next().copied();
+                self.check_quantifier(inner, quantifier, unbounded, span);
+                unbounded
+                    || matches!(
+                        quantifier,
+                        Quantifier::ZeroOrMore | Quantifier::OneOrMore | Quantifier::AtLeast(_)
+                    )
+            }
+            Expr::Backreference(n) => {
+                let span = self.backref_spans.next().copied();
+                if self.registry.validate_backref_number(*n).is_err() {
+                    self.diagnostics.push(Diagnostic::warning(
+                        ErrorCode::BackreferenceOutOfRange,
+                        format!(
+                            "backreference \\{} never matches: the pattern has {} group(s)",
+                            n,
+                            self.registry.group_count()
+                        ),
+                        span,
+                    ));
+                }
+                false
+            }
+            Expr::NamedBackreference(name) => {
+                let span = self.backref_spans.next().copied();
+                if !self.registry.has_name(name) {
+                    self.diagnostics.push(Diagnostic {
+                        severity: Severity::Error,
+                        code: ErrorCode::UndefinedBackreference,
+                        message: format!("undefined backreference '{}'", name),
+                        span,
+                        suggestion: crate::suggest::backreference(name, self.registry),
+                    });
+                }
+                false
+            }
+            Expr::RelativeBackreference(_) => {
+                self.backref_spans.next();
+                false
+            }
+            _ => unbounded,
+        }
+    }
+
+    fn check_quantifier(
+        &mut self,
+        inner: &Expr,
+        quantifier: &Quantifier,
+        inner_unbounded: bool,
+        span: Option<Span>,
+    ) {
+        if matches!(
+            quantifier,
+            Quantifier::Exactly(0) | Quantifier::Between(0, 0)
+        ) {
+            self.diagnostics.push(Diagnostic::warning(
+                ErrorCode::EmptyRepetition,
+                "quantifier repeats zero times, so the expression never matches anything"
+                    .to_string(),
+                span,
+            ));
+        }
+
+        if matches!(
+            inner,
+            Expr::StartAnchor
+                | Expr::EndAnchor
+                | Expr::WordBoundary
+                | Expr::NonWordBoundary
+                | Expr::Lookahead(_)
+                | Expr::NegativeLookahead(_)
+                | Expr::Lookbehind(_)
+                | Expr::NegativeLookbehind(_)
+        ) {
+            self.diagnostics.push(Diagnostic::warning(
+                ErrorCode::RepeatedAssertion,
+                "quantifier on an assertion has no effect".to_string(),
+                span,
+            ));
+        }
+
+        let unbounded = matches!(
+            quantifier,
+            Quantifier::ZeroOrMore | Quantifier::OneOrMore | Quantifier::AtLeast(_)
+        );
+        if unbounded && inner_unbounded {
+            self.diagnostics.push(Diagnostic::warning(
+                ErrorCode::NestedQuantifier,
+                "nested unbounded quantifiers can backtrack exponentially in other regex engines"
+                    .to_string(),
+                span,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(pattern: &str) -> Vec<ErrorCode> {
+        validate(pattern).into_iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_valid_pattern_has_no_diagnostics() {
+        assert!(validate(r"(year:\d{4})-(\d+)\g{year}").is_empty());
+    }
+
+    #[test]
+    fn test_syntax_error_stops_analysis() {
+        let diagnostics = validate("(a+)+(b");
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+        assert_eq!(diagnostics[0].code, ErrorCode::UnexpectedToken);
+        assert_eq!(diagnostics[0].span, Some(Span::new(7, 7)));
+    }
+
+    #[test]
+    fn test_reports_every_group_error() {
+        let diagnostics = validate(r"(a:x)(a:y)\g{b}(a:z)");
+        assert_eq!(
+            diagnostics.iter().map(|d| d.code).collect::<Vec<_>>(),
+            vec![
+                ErrorCode::DuplicateGroupName,
+                ErrorCode::UndefinedBackreference,
+                ErrorCode::DuplicateGroupName,
+            ]
+        );
+        assert_eq!(diagnostics[0].span, Some(Span::new(5, 10)));
+        assert_eq!(diagnostics[1].span, Some(Span::new(10, 15)));
+    }
+
+    #[test]
+    fn test_warnings() {
+        assert_eq!(codes(r"(a)\2"), vec![ErrorCode::BackreferenceOutOfRange]);
+        assert_eq!(validate(r"(a)\g{-1}\2")[0].span, Some(Span::new(9, 11)));
+        assert_eq!(codes("a{0}b"), vec![ErrorCode::EmptyRepetition]);
+        assert_eq!(codes("^*a"), vec![ErrorCode::RepeatedAssertion]);
+
+        let diagnostics = validate("x(a+)*");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, ErrorCode::NestedQuantifier);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].span, Some(Span::new(1, 6)));
+
+        assert!(validate("(a{2})+").is_empty());
+    }
+
+    #[test]
+    fn test_errors_sort_before_warnings() {
+        let diagnostics = validate(r"(a+)+\g{b}");
+        assert!(diagnostics[0].is_error());
+        assert!(!diagnostics[1].is_error());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_diagnostic() {
+        let diagnostics = validate(r"(a:x)\g{b}");
+        let json = serde_json::to_value(&diagnostics[0]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "severity": "error",
+                "code": "E104",
+                "message": "undefined backreference 'b'",
+                "span": { "start": 5, "end": 10 },
+                "suggestion": "\\g{a}",
+            })
+        );
+    }
+}
@@ -0,0 +1,619 @@
+//! Conversion to and from other regex dialects
+//!
+//! [`transpile_to_dialect`] writes an Ogex pattern in the syntax of another
+//! engine, failing with [`TranspileError::Unsupported`] when the pattern uses
+//! a feature that engine lacks (RE2 has no backreferences, POSIX no lazy
+//! quantifiers, and so on) rather than emitting something that matches
+//! differently.
+//!
+//! [`transpile_from_legacy`] goes the other way, rewriting a pattern written
+//! in PCRE/Python syntax into Ogex syntax.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::str::FromStr;
+
+use crate::ast::{CharacterClass, ClassItem, Expr, Quantifier};
+use crate::error::{RegexError, Result};
+use crate::parser::parse;
+
+/// A regex engine whose syntax patterns can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dialect {
+    /// PCRE2, also understood by Perl, PHP and .NET
+    Pcre2,
+    /// JavaScript `RegExp`
+    Js,
+    /// Python's `re` module
+    Python,
+    /// RE2, also used by Go's `regexp` and Rust's `regex` crate
+    Re2,
+    /// POSIX extended regular expressions, as used by `grep -E` and `awk`
+    Posix,
+}
+
+impl Dialect {
+    /// Every dialect, in the order they are listed to users
+    pub const ALL: [Dialect; 5] = [
+        Dialect::Pcre2,
+        Dialect::Js,
+        Dialect::Python,
+        Dialect::Re2,
+        Dialect::Posix,
+    ];
+
+    /// The identifier [`Dialect::from_str`] accepts, such as `"re2"`
+    pub fn id(self) -> &'static str {
+        match self {
+            Dialect::Pcre2 => "pcre2",
+            Dialect::Js => "js",
+            Dialect::Python => "python",
+            Dialect::Re2 => "re2",
+            Dialect::Posix => "posix",
+        }
+    }
+
+    /// The human-readable name, such as `"RE2"`
+    pub fn name(self) -> &'static str {
+        match self {
+            Dialect::Pcre2 => "PCRE2",
+            Dialect::Js => "JavaScript",
+            Dialect::Python => "Python",
+            Dialect::Re2 => "RE2",
+            Dialect::Posix => "POSIX ERE",
+        }
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        Dialect::ALL
+            .into_iter()
+            .find(|dialect| dialect.id() == s)
+            .ok_or_else(|| format!("unsupported dialect '{}'", s))
+    }
+}
+
+/// Why a pattern could not be written in another dialect
+#[derive(Debug, Clone)]
+pub enum TranspileError {
+    /// The pattern does not parse
+    Pattern(RegexError),
+    /// The pattern uses a feature the dialect does not have
+    Unsupported {
+        /// The dialect being written
+        dialect: Dialect,
+        /// The feature it lacks, such as `"lookbehind"`
+        feature: &'static str,
+    },
+}
+
+impl From<RegexError> for TranspileError {
+    fn from(err: RegexError) -> Self {
+        TranspileError::Pattern(err)
+    }
+}
+
+impl fmt::Display for TranspileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranspileError::Pattern(err) => fmt::Display::fmt(err, f),
+            TranspileError::Unsupported { dialect, feature } => {
+                write!(f, "{} does not support {}", dialect, feature)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TranspileError {}
+
+/// Write an Ogex pattern in the syntax of another dialect
+///
+/// # Example
+/// ```
+/// use ogex::{Dialect, transpile_to_dialect};
+///
+/// let js = transpile_to_dialect(r"(year:\d{4})-\g{year}", Dialect::Js).unwrap();
+/// assert_eq!(js, r"(?<year>\d{4})-\k<year>");
+///
+/// let posix = transpile_to_dialect(r"\w+\.txt", Dialect::Posix).unwrap();
+/// assert_eq!(posix, r"[_[:alnum:]]+\.txt");
+///
+/// assert!(transpile_to_dialect(r"(a)\1", Dialect::Re2).is_err());
+/// ```
+pub fn transpile_to_dialect(
+    input: &str,
+    dialect: Dialect,
+) -> core::result::Result<String, TranspileError> {
+    let ast = parse(input)?;
+    let mut writer = Writer {
+        dialect,
+        out: String::new(),
+    };
+    writer.expr(&ast)?;
+    Ok(writer.out)
+}
+
+/// Writes an AST in the syntax of one dialect
+struct Writer {
+    dialect: Dialect,
+    out: String,
+}
+
+impl Writer {
+    fn unsupported(&self, feature: &'static str) -> TranspileError {
+        TranspileError::Unsupported {
+            dialect: self.dialect,
+            feature,
+        }
+    }
+
+    /// Fail with `feature` unless the dialect is one of `dialects`
+    fn require(
+        &self,
+        dialects: &[Dialect],
+        feature: &'static str,
+    ) -> core::result::Result<(), TranspileError> {
+        match dialects.contains(&self.dialect) {
+            true => Ok(()),
+            false => Err(self.unsupported(feature)),
+        }
+    }
+
+    /// Write `open`, then `inner`, then a closing parenthesis
+    fn wrap(&mut self, open: &str, inner: &Expr) -> core::result::Result<(), TranspileError> {
+        self.out.push_str(open);
+        self.expr(inner)?;
+        self.out.push(')');
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> core::result::Result<(), TranspileError> {
+        use Dialect::*;
+
+        match expr {
+            Expr::Empty => {}
+            Expr::Literal(c) => {
+                if "\\.^$|?*+()[]{}".contains(*c) {
+                    self.out.push('\\');
+                }
+                self.out.push(*c);
+            }
+            Expr::Any => self.out.push('.'),
+            Expr::Sequence(exprs) => {
+                for expr in exprs {
+                    self.expr(expr)?;
+                }
+            }
+            Expr::Alternation(exprs) => {
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push('|');
+                    }
+                    self.expr(expr)?;
+                }
+            }
+            Expr::CharacterClass(class) => self.class(class)?,
+            Expr::Quantified {
+                expr,
+                quantifier,
+                greedy,
+            } => self.quantified(expr, quantifier, *greedy)?,
+            Expr::Group(inner) => self.wrap("(", inner)?,
+            Expr::NonCapturingGroup(inner) => {
+                // POSIX has no non-capturing groups, so the group captures
+                // and later group numbers shift by one
+                let open = if self.dialect == Posix { "(" } else { "(?:" };
+                self.wrap(open, inner)?;
+            }
+            Expr::NamedGroup { name, pattern } => {
+                // POSIX groups cannot be named, but keep their number
+                let open = match self.dialect {
+                    Pcre2 | Js => format!("(?<{}>", name),
+                    Python | Re2 => format!("(?P<{}>", name),
+                    Posix => "(".to_string(),
+                };
+                self.wrap(&open, pattern)?;
+            }
+            Expr::StartAnchor => self.out.push('^'),
+            Expr::EndAnchor => self.out.push('$'),
+            Expr::Backreference(n) => {
+                self.require(&[Pcre2, Js, Python], "backreferences")?;
+                self.out.push_str(&format!("\\{}", n));
+            }
+            Expr::RelativeBackreference(n) => {
+                self.require(&[Pcre2], "relative backreferences")?;
+                self.out.push_str(&format!("\\g{{{}}}", n));
+            }
+            Expr::NamedBackreference(name) => {
+                self.require(&[Pcre2, Js, Python], "backreferences")?;
+                match self.dialect {
+                    Python => self.out.push_str(&format!("(?P={})", name)),
+                    _ => self.out.push_str(&format!("\\k<{}>", name)),
+                }
+            }
+            Expr::Shorthand(c) => match self.dialect {
+                Posix => self.out.push_str(posix_shorthand(*c, true)),
+                _ => {
+                    self.out.push('\\');
+                    self.out.push(*c);
+                }
+            },
+            Expr::WordBoundary | Expr::NonWordBoundary => {
+                self.require(&[Pcre2, Js, Python, Re2], "word boundaries")?;
+                let negated = matches!(expr, Expr::NonWordBoundary);
+                self.out.push_str(if negated { "\\B" } else { "\\b" });
+            }
+            Expr::Lookahead(inner) | Expr::NegativeLookahead(inner) => {
+                self.require(&[Pcre2, Js, Python], "lookahead")?;
+                let negated = matches!(expr, Expr::NegativeLookahead(_));
+                self.wrap(if negated { "(?!" } else { "(?=" }, inner)?;
+            }
+            Expr::Lookbehind(inner) | Expr::NegativeLookbehind(inner) => {
+                self.require(&[Pcre2, Js, Python], "lookbehind")?;
+                let negated = matches!(expr, Expr::NegativeLookbehind(_));
+                self.wrap(if negated { "(?<!" } else { "(?<=" }, inner)?;
+            }
+            Expr::AtomicGroup(inner) => {
+                self.require(&[Pcre2, Python], "atomic groups")?;
+                self.wrap("(?>", inner)?;
+            }
+            Expr::ConditionalGroup(_) => return Err(self.unsupported("conditional groups")),
+            Expr::ModeFlagsGroup { flags, pattern } => {
+                self.require(&[Pcre2, Python, Re2], "inline flags")?;
+                if self.dialect == Re2 && flags.contains('x') {
+                    return Err(self.unsupported("the x (extended) flag"));
+                }
+                self.wrap(&format!("(?{}:", flags), pattern)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn quantified(
+        &mut self,
+        expr: &Expr,
+        quantifier: &Quantifier,
+        greedy: bool,
+    ) -> core::result::Result<(), TranspileError> {
+        if !greedy && self.dialect == Dialect::Posix {
+            return Err(self.unsupported("lazy quantifiers"));
+        }
+        let needs_group = match expr {
+            Expr::Empty | Expr::Alternation(_) => true,
+            Expr::Sequence(exprs) => exprs.len() != 1,
+            _ => false,
+        };
+        if needs_group {
+            let open = if self.dialect == Dialect::Posix {
+                "("
+            } else {
+                "(?:"
+            };
+            self.wrap(open, expr)?;
+        } else {
+            self.expr(expr)?;
+        }
+        self.out.push_str(&quantifier.to_regex_string(greedy));
+        Ok(())
+    }
+
+    fn class(&mut self, class: &CharacterClass) -> core::result::Result<(), TranspileError> {
+        if self.dialect == Dialect::Posix {
+            return self.posix_class(class);
+        }
+        self.out.push('[');
+        if class.negated {
+            self.out.push('^');
+        }
+        for item in &class.items {
+            match item {
+                ClassItem::Char(c) => push_class_char(&mut self.out, *c),
+                ClassItem::Range(start, end) => {
+                    push_class_char(&mut self.out, *start);
+                    self.out.push('-');
+                    push_class_char(&mut self.out, *end);
+                }
+                ClassItem::Shorthand(c) if "dDwWsS".contains(*c) => {
+                    self.out.push('\\');
+                    self.out.push(*c);
+                }
+                ClassItem::Shorthand(c) => push_class_char(&mut self.out, *c),
+            }
+        }
+        self.out.push(']');
+        Ok(())
+    }
+
+    /// Write a POSIX bracket expression, which has no escapes: `]` must come
+    /// first, `-` last and `^` anywhere but first to be literal
+    fn posix_class(&mut self, class: &CharacterClass) -> core::result::Result<(), TranspileError> {
+        let (mut bracket, mut caret, mut dash) = (false, false, false);
+        let mut body = String::new();
+        for item in &class.items {
+            match *item {
+                ClassItem::Char(']') | ClassItem::Shorthand(']') => bracket = true,
+                ClassItem::Char('^') | ClassItem::Shorthand('^') => caret = true,
+                ClassItem::Char('-') | ClassItem::Shorthand('-') => dash = true,
+                ClassItem::Char(c) => body.push(c),
+                ClassItem::Range(start, end) => {
+                    if [start, end].iter().any(|c| matches!(c, ']' | '^' | '-')) {
+                        return Err(self.unsupported("ranges bounded by `]`, `^` or `-`"));
+                    }
+                    body.push(start);
+                    body.push('-');
+                    body.push(end);
+                }
+                ClassItem::Shorthand(c @ ('d' | 'w' | 's')) => {
+                    body.push_str(posix_shorthand(c, false));
+                }
+                ClassItem::Shorthand('D' | 'W' | 'S') => {
+                    return Err(self.unsupported("negated shorthands inside a class"));
+                }
+                ClassItem::Shorthand(c) => body.push(c),
+            }
+        }
+
+        if !class.negated && !bracket && !dash && body.is_empty() && caret {
+            // `[^]` would be an unterminated negated class
+            self.out.push_str("\\^");
+            return Ok(());
+        }
+        self.out.push('[');
+        if class.negated {
+            self.out.push('^');
+        }
+        if bracket {
+            self.out.push(']');
+        }
+        self.out.push_str(&body);
+        if caret {
+            self.out.push('^');
+        }
+        if dash {
+            self.out.push('-');
+        }
+        self.out.push(']');
+        Ok(())
+    }
+}
+
+/// A character inside a non-POSIX class, escaped if it is special there
+fn push_class_char(out: &mut String, c: char) {
+    if matches!(c, '\\' | ']' | '[' | '^' | '-') {
+        out.push('\\');
+    }
+    out.push(c);
+}
+
+/// The POSIX character class for a shorthand, as a whole bracket
+/// expression if `standalone` or as a class member otherwise
+fn posix_shorthand(c: char, standalone: bool) -> &'static str {
+    match (c, standalone) {
+        ('d', true) => "[[:digit:]]",
+        ('D', true) => "[^[:digit:]]",
+        ('w', true) => "[_[:alnum:]]",
+        ('W', true) => "[^_[:alnum:]]",
+        ('s', true) => "[[:space:]]",
+        ('S', true) => "[^[:space:]]",
+        ('d', false) => "[:digit:]",
+        ('w', false) => "_[:alnum:]",
+        ('s', false) => "[:space:]",
+        _ => unreachable!("not a shorthand: \\{}", c),
+    }
+}
+
+/// Rewrite a pattern in PCRE/Python syntax into Ogex syntax
+///
+/// Named groups, lookarounds, atomic and non-capturing groups, inline flag
+/// groups and named backreferences (`\k<name>`, `(?P=name)`) are rewritten;
+/// everything else is copied unchanged. The result is checked by parsing it.
+///
+/// # Example
+/// ```
+/// use ogex::transpile_from_legacy;
+///
+/// let ogex = transpile_from_legacy(r"(?P<word>\w+)(?=\s)\s(?P=word)").unwrap();
+/// assert_eq!(ogex, r"(word:\w+)(@>:\s)\s\g{word}");
+/// ```
+pub fn transpile_from_legacy(input: &str) -> Result<String> {
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+    let mut in_class = false;
+    while let Some(c) = rest.chars().next() {
+        if c == '\\' {
+            if !in_class && let Some((name, len)) = legacy_named_backref(rest) {
+                out.push_str(&format!("\\g{{{}}}", name));
+                rest = &rest[len..];
+                continue;
+            }
+            // Copy the escape whole, so an escaped bracket or parenthesis
+            // is not taken for syntax
+            let len = rest[1..]
+                .chars()
+                .next()
+                .map_or(1, |next| 1 + next.len_utf8());
+            out.push_str(&rest[..len]);
+            rest = &rest[len..];
+            continue;
+        }
+        if in_class {
+            in_class = c != ']';
+        } else if c == '[' {
+            in_class = true;
+        } else if rest.starts_with("(?")
+            && let Some((replacement, len)) = legacy_group(rest)
+        {
+            out.push_str(&replacement);
+            rest = &rest[len..];
+            continue;
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    parse(&out)?;
+    Ok(out)
+}
+
+/// The Ogex spelling of a legacy `(?...` group opener at the start of
+/// `text`, and how many bytes of `text` it replaces
+fn legacy_group(text: &str) -> Option<(String, usize)> {
+    const OPENERS: [(&str, &str); 6] = [
+        ("(?:", "(@?:"),
+        ("(?=", "(@>:"),
+        ("(?!", "(@>~:"),
+        ("(?<=", "(@<:"),
+        ("(?<!", "(@<~:"),
+        ("(?>", "(@*:"),
+    ];
+    if let Some((legacy, ogex)) = OPENERS.iter().find(|(legacy, _)| text.starts_with(legacy)) {
+        return Some((ogex.to_string(), legacy.len()));
+    }
+
+    for (open, close) in [("(?P<", '>'), ("(?<", '>'), ("(?'", '\'')] {
+        if let Some(name) = text
+            .strip_prefix(open)
+            .and_then(|rest| identifier(rest, close))
+        {
+            return Some((format!("({}:", name), open.len() + name.len() + 1));
+        }
+    }
+    if let Some(name) = text
+        .strip_prefix("(?P=")
+        .and_then(|rest| identifier(rest, ')'))
+    {
+        return Some((format!("\\g{{{}}}", name), "(?P=".len() + name.len() + 1));
+    }
+
+    let flags = text[2..]
+        .find(|c: char| !matches!(c, 'i' | 'm' | 's' | 'x'))
+        .filter(|&len| len > 0 && text[2 + len..].starts_with(':'))?;
+    Some((format!("(@{}:", &text[2..2 + flags]), 2 + flags + 1))
+}
+
+/// A `\k<name>`, `\k{name}` or `\k'name'` backreference at the start of
+/// `text`, and its length in bytes
+fn legacy_named_backref(text: &str) -> Option<(&str, usize)> {
+    let rest = text.strip_prefix("\\k")?;
+    let close = match rest.chars().next()? {
+        '<' => '>',
+        '{' => '}',
+        '\'' => '\'',
+        _ => return None,
+    };
+    let name = identifier(&rest[1..], close)?;
+    Some((name, "\\k".len() + name.len() + 2))
+}
+
+/// The identifier at the start of `text` if it is directly followed by `close`
+fn identifier(text: &str, close: char) -> Option<&str> {
+    let len = text.find(|c: char| !(c.is_alphanumeric() || c == '_'))?;
+    (len > 0 && text[len..].starts_with(close)).then(|| &text[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to(dialect: Dialect, pattern: &str) -> String {
+        transpile_to_dialect(pattern, dialect).unwrap()
+    }
+
+    fn unsupported(dialect: Dialect, pattern: &str) -> &'static str {
+        match transpile_to_dialect(pattern, dialect) {
+            Err(TranspileError::Unsupported { feature, .. }) => feature,
+            other => panic!("expected an unsupported feature, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_named_groups_and_backreferences() {
+        let pattern = r"(word:\w+) \g{word}";
+        assert_eq!(to(Dialect::Pcre2, pattern), r"(?<word>\w+) \k<word>");
+        assert_eq!(to(Dialect::Js, pattern), r"(?<word>\w+) \k<word>");
+        assert_eq!(to(Dialect::Python, pattern), r"(?P<word>\w+) (?P=word)");
+        assert_eq!(unsupported(Dialect::Re2, pattern), "backreferences");
+        assert_eq!(to(Dialect::Re2, r"(word:\w+)"), r"(?P<word>\w+)");
+        assert_eq!(to(Dialect::Posix, r"(word:\w+)"), "([_[:alnum:]]+)");
+    }
+
+    #[test]
+    fn test_literals_are_escaped() {
+        assert_eq!(to(Dialect::Pcre2, r"a\.b\(c\)"), r"a\.b\(c\)");
+        assert_eq!(to(Dialect::Posix, r"1\+1"), r"1\+1");
+    }
+
+    #[test]
+    fn test_lookarounds_and_atomic_groups() {
+        let pattern = "(@<:a)b(@>~:c)";
+        assert_eq!(to(Dialect::Pcre2, pattern), "(?<=a)b(?!c)");
+        assert_eq!(to(Dialect::Js, pattern), "(?<=a)b(?!c)");
+        assert_eq!(unsupported(Dialect::Re2, pattern), "lookbehind");
+        assert_eq!(to(Dialect::Python, "(@*:a+)b"), "(?>a+)b");
+        assert_eq!(unsupported(Dialect::Js, "(@*:a+)b"), "atomic groups");
+    }
+
+    #[test]
+    fn test_posix() {
+        assert_eq!(
+            to(Dialect::Posix, r"\d[\-a\]]+(@?:ab|c)*"),
+            "[[:digit:]][]a-]+(ab|c)*"
+        );
+        assert_eq!(to(Dialect::Posix, r"[^\^a]"), "[^a^]");
+        assert_eq!(to(Dialect::Posix, r"[\^]"), r"\^");
+        assert_eq!(unsupported(Dialect::Posix, "a+?"), "lazy quantifiers");
+        assert_eq!(unsupported(Dialect::Posix, r"\bx"), "word boundaries");
+    }
+
+    #[test]
+    fn test_inline_flags() {
+        assert_eq!(to(Dialect::Re2, "(@i:abc)"), "(?i:abc)");
+        assert_eq!(
+            unsupported(Dialect::Re2, "(@x:abc)"),
+            "the x (extended) flag"
+        );
+        assert_eq!(unsupported(Dialect::Js, "(@i:abc)"), "inline flags");
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(matches!(
+            transpile_to_dialect("(a", Dialect::Js),
+            Err(TranspileError::Pattern(_))
+        ));
+    }
+
+    #[test]
+    fn test_dialect_ids() {
+        for dialect in Dialect::ALL {
+            assert_eq!(dialect.id().parse::<Dialect>(), Ok(dialect));
+        }
+        assert!("perl".parse::<Dialect>().is_err());
+    }
+
+    #[test]
+    fn test_from_legacy() {
+        let legacy = r"(?<year>\d{4})-(?:\d\d)(?<=x)(?!y)(?>z)(?i:w)\k<year>";
+        assert_eq!(
+            transpile_from_legacy(legacy).unwrap(),
+            r"(year:\d{4})-(@?:\d\d)(@<:x)(@>~:y)(@*:z)(@i:w)\g{year}"
+        );
+    }
+
+    #[test]
+    fn test_from_legacy_leaves_escapes_alone() {
+        assert_eq!(transpile_from_legacy(r"\(?=b").unwrap(), r"\(?=b");
+        assert!(transpile_from_legacy("(?<name>a").is_err());
+    }
+}
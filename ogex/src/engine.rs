@@ -3,15 +3,31 @@
 //! This module provides the actual regex matching functionality,
 //! including NFA simulation and backreference handling.
 
-use crate::nfa::{Nfa, StateId, Transition};
-use std::collections::HashMap;
+use crate::collections::HashMap;
+use crate::error::RuntimeError;
+use crate::groups::{GroupCollector, GroupNumbering, GroupRegistry, GroupRegistryError};
+use crate::library::PatternLibrary;
+use crate::nfa::{Nfa, StateId, Transition, transition_label};
+use crate::pool::Pool;
+#[cfg(feature = "replace")]
+use crate::replace::{Replacement, ReplacementError, Replacer};
+use crate::trace::{SharedSink, TraceEvent, TraceSink};
+use crate::vm::{Backend, Haystack, Program};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::time::Duration;
+#[cfg(all(feature = "replace", feature = "std"))]
+use std::io::{self, BufRead, Write};
 
 /// Dense vector storage for capture groups (index-based for better cache locality)
 /// Index 0 is unused (groups are 1-indexed), so groups[n] gives group n's capture
 type GroupStorage = Vec<Option<(usize, usize)>>;
 
 /// Mode flags for regex matching
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct ModeFlags {
     /// Case insensitive matching (@i)
     pub case_insensitive: bool,
@@ -48,6 +64,68 @@ impl ModeFlags {
     }
 }
 
+/// How much work a single search may do before giving up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub(crate) struct Limits {
+    /// Most steps a search may take
+    pub(crate) steps: Option<usize>,
+    /// Longest input, in bytes, a search accepts
+    pub(crate) haystack_len: Option<usize>,
+    /// Longest a search may run; only enforced with the `std` feature
+    pub(crate) timeout: Option<Duration>,
+}
+
+/// When a search with a timeout gives up
+///
+/// Reading the clock on every step would slow searches down, so it is only
+/// read once every [`Deadline::INTERVAL`] steps.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Deadline {
+    #[cfg(feature = "std")]
+    at: Option<std::time::Instant>,
+    /// Steps taken since the clock was last read
+    #[cfg(feature = "std")]
+    pending: usize,
+    /// Whether the clock was found past the deadline
+    expired: bool,
+}
+
+impl Deadline {
+    #[cfg(feature = "std")]
+    const INTERVAL: usize = 4096;
+
+    /// A deadline `timeout` from now, or none
+    fn start(timeout: Option<Duration>) -> Self {
+        #[cfg(feature = "std")]
+        {
+            Deadline {
+                at: timeout.and_then(|timeout| std::time::Instant::now().checked_add(timeout)),
+                ..Default::default()
+            }
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            let _ = timeout;
+            Deadline::default()
+        }
+    }
+
+    /// Count `steps` more steps, and whether the deadline has passed
+    pub(crate) fn tick(&mut self, steps: usize) -> bool {
+        #[cfg(feature = "std")]
+        if let Some(at) = self.at {
+            self.pending = self.pending.saturating_add(steps);
+            if self.pending >= Self::INTERVAL {
+                self.pending = 0;
+                self.expired = std::time::Instant::now() >= at;
+            }
+        }
+        #[cfg(not(feature = "std"))]
+        let _ = steps;
+        self.expired
+    }
+}
+
 /// A match result
 #[derive(Debug, Clone, PartialEq)]
 pub struct Match {
@@ -93,19 +171,525 @@ impl Match {
         self.named_group(name)
             .map(|(start, end)| &input[start..end])
     }
+
+    /// Expand a replacement template using this match's groups
+    ///
+    /// The template uses the same syntax as [`Regex::replace`]; numbered and
+    /// named references are resolved against this match and the result is
+    /// appended to `dst`. References to groups that did not participate
+    /// expand to empty text.
+    #[cfg(feature = "replace")]
+    pub fn expand(
+        &self,
+        input: &str,
+        template: &str,
+        dst: &mut String,
+    ) -> Result<(), ReplacementError> {
+        let replacement = Replacement::parse(template)?;
+        replacement.append_match(dst, input, self)
+    }
+
+    /// Copy the text of the match and of each group out of `input`, the
+    /// text the match was found in
+    ///
+    /// ```
+    /// use ogex::{OwnedMatch, Regex};
+    ///
+    /// let regex = Regex::new(r"(key:\w+)\=(\d+)").unwrap();
+    /// let owned: OwnedMatch = {
+    ///     let line = String::from("set depth=42");
+    ///     regex.find(&line).unwrap().into_owned(&line)
+    /// };
+    /// let worker = std::thread::spawn(move || owned);
+    /// let owned = worker.join().unwrap();
+    /// assert_eq!(owned.as_str(), "depth=42");
+    /// assert_eq!(owned.named_group_str("key"), Some("depth"));
+    /// assert_eq!(owned.group(2), Some((10, 12)));
+    /// ```
+    pub fn into_owned(self, input: &str) -> OwnedMatch {
+        let owned = |(start, end): (usize, usize)| ((start, end), input[start..end].to_string());
+        OwnedMatch {
+            start: self.start,
+            end: self.end,
+            text: self.as_str(input).to_string(),
+            groups: self
+                .groups
+                .into_iter()
+                .map(|span| span.map(owned))
+                .collect(),
+            named_groups: self
+                .named_groups
+                .into_iter()
+                .map(|(name, span)| (name, owned(span)))
+                .collect(),
+        }
+    }
+}
+
+/// A group's span and text
+type OwnedGroup = ((usize, usize), String);
+
+/// A match that keeps its own copy of the text it matched
+///
+/// Made with [`Match::into_owned`]. Positions still refer to the input the
+/// match was found in, but the text of the match and its groups is stored
+/// in the match, so it can outlive that input or be sent to another thread.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedMatch {
+    /// The start position of the match
+    pub start: usize,
+    /// The end position of the match (exclusive)
+    pub end: usize,
+    text: String,
+    /// Captured groups, indexed like [`Match::groups`]
+    groups: Vec<Option<OwnedGroup>>,
+    named_groups: HashMap<String, OwnedGroup>,
+}
+
+impl OwnedMatch {
+    /// Get the matched text
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Get a capture group by index (1-based)
+    pub fn group(&self, n: u32) -> Option<(usize, usize)> {
+        self.owned_group(n).map(|(span, _)| *span)
+    }
+
+    /// Get a named capture group
+    pub fn named_group(&self, name: &str) -> Option<(usize, usize)> {
+        self.named_groups.get(name).map(|(span, _)| *span)
+    }
+
+    /// Get the text of a capture group
+    pub fn group_str(&self, n: u32) -> Option<&str> {
+        self.owned_group(n).map(|(_, text)| text.as_str())
+    }
+
+    /// Get the text of a named capture group
+    pub fn named_group_str(&self, name: &str) -> Option<&str> {
+        self.named_groups.get(name).map(|(_, text)| text.as_str())
+    }
+
+    fn owned_group(&self, n: u32) -> Option<&OwnedGroup> {
+        self.groups.get(n as usize)?.as_ref()
+    }
+}
+
+/// Compile-time options for building a [`Regex`]
+///
+/// # Example
+/// ```
+/// use ogex::{GroupNumbering, RegexBuilder};
+///
+/// // With PCRE numbering, \g{-1} refers to the named group
+/// let regex = RegexBuilder::new(r"(a)(n:b)\g{-1}")
+///     .group_numbering(GroupNumbering::Pcre)
+///     .build()
+///     .unwrap();
+/// assert!(regex.is_match("abb"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegexBuilder {
+    pattern: String,
+    numbering: GroupNumbering,
+    flags: ModeFlags,
+    limits: Limits,
+    trace: Option<SharedSink>,
+    library: Option<Arc<PatternLibrary>>,
+    backend: Backend,
+}
+
+impl RegexBuilder {
+    /// Create a builder for the given pattern with default options
+    pub fn new(pattern: &str) -> Self {
+        RegexBuilder {
+            pattern: pattern.to_string(),
+            numbering: GroupNumbering::default(),
+            flags: ModeFlags::default(),
+            limits: Limits::default(),
+            trace: None,
+            library: None,
+            backend: Backend::default(),
+        }
+    }
+
+    /// Set which groups relative backreferences count
+    pub fn group_numbering(mut self, numbering: GroupNumbering) -> Self {
+        self.numbering = numbering;
+        self
+    }
+
+    /// Match letters regardless of case, as with the inline `@i` flag
+    pub fn case_insensitive(mut self, yes: bool) -> Self {
+        self.flags.case_insensitive = yes;
+        self
+    }
+
+    /// Let `^` and `$` match at line boundaries, as with the inline `@m` flag
+    pub fn multiline(mut self, yes: bool) -> Self {
+        self.flags.multiline = yes;
+        self
+    }
+
+    /// Let `.` match newlines, as with the inline `@s` flag
+    pub fn dotall(mut self, yes: bool) -> Self {
+        self.flags.dotall = yes;
+        self
+    }
+
+    /// Ignore whitespace and `#` comments outside character classes, as with
+    /// the `@x` flag
+    pub fn extended(mut self, yes: bool) -> Self {
+        self.flags.extended = yes;
+        self
+    }
+
+    /// Give up a search after `steps` units of work
+    ///
+    /// A step is one NFA state visited at one input position. When the limit
+    /// is reached, [`Regex::try_find_at`] reports
+    /// [`RuntimeError::StepLimitExceeded`] and the infallible search methods
+    /// report no match.
+    pub fn step_limit(mut self, steps: usize) -> Self {
+        self.limits.steps = Some(steps);
+        self
+    }
+
+    /// Refuse to search inputs longer than `bytes`
+    ///
+    /// Searching a longer input reports [`RuntimeError::HaystackTooLarge`]
+    /// from [`Regex::try_find_at`] without looking at it, and the infallible
+    /// search methods report no match.
+    pub fn max_haystack_len(mut self, bytes: usize) -> Self {
+        self.limits.haystack_len = Some(bytes);
+        self
+    }
+
+    /// Give up a search that runs for longer than `timeout`
+    ///
+    /// The clock is only read every few thousand steps, so a search may run
+    /// a little over. When the time is up, [`Regex::try_find_at`] reports
+    /// [`RuntimeError::TimeoutExceeded`] and the infallible search methods
+    /// report no match.
+    ///
+    /// ```
+    /// use ogex::{RegexBuilder, RegexError, RuntimeError};
+    /// use std::time::Duration;
+    ///
+    /// let regex = RegexBuilder::new("a*b")
+    ///     .timeout(Duration::ZERO)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(matches!(
+    ///     regex.try_find(&"a".repeat(10_000)),
+    ///     Err(RegexError::Runtime(RuntimeError::TimeoutExceeded))
+    /// ));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.limits.timeout = Some(timeout);
+        self
+    }
+
+    /// Report every step of every search the regex runs to `sink`
+    ///
+    /// See the [`trace`](crate::trace) module for the events reported.
+    pub fn trace_sink(mut self, sink: impl TraceSink + Send + Sync + 'static) -> Self {
+        self.trace = Some(SharedSink(Arc::new(sink)));
+        self
+    }
+
+    /// Let the pattern use the fragments in `library` with `(@use:name)`
+    ///
+    /// See [`PatternLibrary`](crate::PatternLibrary).
+    pub fn library(mut self, library: &PatternLibrary) -> Self {
+        self.library = Some(Arc::new(library.clone()));
+        self
+    }
+
+    /// Choose the engine that runs the regex's searches
+    ///
+    /// Both find the same matches and count the same steps; see [`Backend`].
+    ///
+    /// ```
+    /// use ogex::{Backend, RegexBuilder};
+    ///
+    /// let regex = RegexBuilder::new(r"(\w+)@(\w+)")
+    ///     .backend(Backend::Vm)
+    ///     .build()
+    ///     .unwrap();
+    /// let m = regex.find("mail ada@home").unwrap();
+    /// assert_eq!((m.start, m.end), (5, 13));
+    /// assert_eq!(m.group(2), Some((9, 13)));
+    /// ```
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Compile the pattern with the configured options
+    pub fn build(&self) -> Result<Regex, crate::error::RegexError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("compile", pattern = %self.pattern).entered();
+        #[cfg(feature = "tracing")]
+        let started = std::time::Instant::now();
+        let mut parser = crate::parser::Parser::with_extended(&self.pattern, self.flags.extended);
+        if let Some(library) = &self.library {
+            parser = parser.library(library);
+        }
+        let ast = parser.parse()?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(elapsed_us = started.elapsed().as_micros() as u64, "parsed");
+        self.build_ast(&ast, Some(&parser))
+    }
+
+    /// What identifies the regex this builder compiles, or `None` when it
+    /// has a trace sink or library, which cannot be compared
+    pub(crate) fn cache_key(&self) -> Option<crate::cache::CacheKey> {
+        if self.trace.is_some() || self.library.is_some() {
+            return None;
+        }
+        Some(crate::cache::CacheKey {
+            pattern: self.pattern.clone(),
+            numbering: self.numbering,
+            flags: self.flags.clone(),
+            limits: self.limits,
+            backend: self.backend,
+        })
+    }
+
+    /// Compile a pattern that is already parsed, keeping the builder's
+    /// pattern as its source
+    ///
+    /// `parser` is the parser that produced `ast`, if any; errors and groups
+    /// then carry their spans in the pattern.
+    pub(crate) fn build_ast(
+        &self,
+        ast: &crate::ast::Expr,
+        parser: Option<&crate::parser::Parser<'_>>,
+    ) -> Result<Regex, crate::error::RegexError> {
+        #[cfg(feature = "tracing")]
+        let started = std::time::Instant::now();
+        let group_spans = parser.map_or(&[][..], |parser| parser.group_spans());
+        let mut registry = GroupRegistry::with_numbering(self.numbering);
+        if let Some((index, err)) = GroupCollector::collect_all(ast, &mut registry)
+            .into_iter()
+            .next()
+        {
+            return Err(match err {
+                GroupRegistryError::DuplicateGroupName(name) => {
+                    crate::error::ParseError::DuplicateGroupName {
+                        name,
+                        span: group_spans.get(index as usize - 1).copied(),
+                    }
+                    .into()
+                }
+                err => crate::error::RegexError::Compile(err.to_string()),
+            });
+        }
+        for (index, span) in (1..).zip(group_spans) {
+            registry.set_span(index, *span);
+        }
+        if let Some(parser) = parser {
+            for (index, fragment) in (1..).zip(parser.group_fragments()) {
+                if let Some(fragment) = fragment {
+                    registry.set_fragment(index, fragment.clone());
+                }
+            }
+        }
+        if let Err(GroupRegistryError::UndefinedBackreference(name)) =
+            GroupCollector::validate_backref_names(ast, &registry)
+        {
+            // The first reference to the name is the one reported
+            let span = parser.and_then(|parser| {
+                let position = ast.backreferences().iter().position(
+                    |backref| matches!(backref, crate::ast::Expr::NamedBackreference(n) if *n == name),
+                )?;
+                parser.backref_spans().get(position).copied()
+            });
+            return Err(crate::error::ParseError::UndefinedBackreference {
+                suggestion: crate::suggest::backreference(&name, &registry),
+                name,
+                span,
+            }
+            .into());
+        }
+        let mut nfa = Nfa::from_expr_with_numbering(ast, self.numbering);
+        nfa.mode_flags.merge(&self.flags);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            elapsed_us = started.elapsed().as_micros() as u64,
+            states = nfa.states.len(),
+            groups = registry.group_count(),
+            "built automaton"
+        );
+        let program = (self.backend == Backend::Vm).then(|| Program::new(&nfa));
+        Ok(Regex {
+            pattern: self.pattern.clone(),
+            nfa,
+            program,
+            registry,
+            limits: self.limits,
+            trace: self.trace.clone(),
+            library: self.library.clone(),
+            scratch: Pool::default(),
+        })
+    }
 }
 
 /// The regex engine
+///
+/// A `Regex` is `Send` and `Sync`: searching never changes the compiled
+/// pattern, and the memory a search works in is taken from a pool, so one
+/// regex can be shared between threads behind an `Arc` or in a `static`.
 pub struct Regex {
+    /// The source pattern, kept so samples can be drawn from its syntax tree
+    pattern: String,
     nfa: Nfa,
+    /// The NFA compiled to bytecode, when searches run on the VM
+    program: Option<Program>,
+    /// Capture groups of the pattern, used to validate replacements
+    registry: GroupRegistry,
+    /// How much work a single search may do
+    limits: Limits,
+    /// Receives the steps of every search, if set
+    trace: Option<SharedSink>,
+    /// The fragments the pattern may use, needed to parse it again
+    library: Option<Arc<PatternLibrary>>,
+    /// Memory for searches to reuse
+    scratch: Pool<Scratch>,
+}
+
+// Fails to compile if a field stops `Regex` being shared between threads
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Regex>();
+};
+
+/// Memory a search reuses from one start position to the next
+#[derive(Default)]
+struct Scratch {
+    memo: HashMap<(StateId, usize), Option<GroupStorage>>,
+    vm: crate::vm::Cache,
+}
+
+/// The input of a search
+#[derive(Clone, Copy)]
+enum Subject<'a> {
+    Text(&'a str),
+    /// A byte string, each byte matched as a Latin-1 character
+    Bytes(&'a [u8]),
 }
 
 impl Regex {
     /// Compile a regex pattern
     pub fn new(pattern: &str) -> Result<Self, crate::error::RegexError> {
-        let ast = crate::parser::parse(pattern)?;
-        let nfa = Nfa::from_expr(&ast);
-        Ok(Regex { nfa })
+        RegexBuilder::new(pattern).build()
+    }
+
+    /// Check a pattern without compiling it, reporting every error and
+    /// warning found
+    ///
+    /// See [`diagnostics::validate`](crate::diagnostics::validate).
+    pub fn validate(pattern: &str) -> Vec<crate::diagnostics::Diagnostic> {
+        crate::diagnostics::validate(pattern)
+    }
+
+    /// Encode the compiled program so it can be restored with
+    /// [`Regex::from_bytes`] without parsing the pattern again
+    pub fn to_bytes(&self) -> Vec<u8> {
+        crate::program::encode(
+            &self.pattern,
+            &self.nfa,
+            &self.registry,
+            &self.limits,
+            self.backend(),
+        )
+    }
+
+    /// Restore a regex from bytes written by [`Regex::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, crate::program::ProgramError> {
+        let (pattern, nfa, registry, limits, backend) = crate::program::decode(bytes)?;
+        let program = (backend == Backend::Vm).then(|| Program::new(&nfa));
+        Ok(Regex {
+            pattern,
+            nfa,
+            program,
+            registry,
+            limits,
+            trace: None,
+            library: None,
+            scratch: Pool::default(),
+        })
+    }
+
+    /// The engine that runs the regex's searches
+    pub fn backend(&self) -> Backend {
+        match self.program {
+            Some(_) => Backend::Vm,
+            None => Backend::Nfa,
+        }
+    }
+
+    /// The pattern this regex was compiled from
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// A generator of random strings this regex matches
+    ///
+    /// See [`Generator`](crate::Generator) for the options it takes.
+    pub fn generator(&self) -> Result<crate::Generator, crate::error::RegexError> {
+        crate::Generator::from_regex(self)
+    }
+
+    /// Draw one random string this regex matches, or `None` if no string
+    /// satisfying its assertions was found
+    ///
+    /// This parses the pattern on each call; use [`Regex::generator`] to
+    /// draw many samples.
+    pub fn generate(&self) -> Option<String> {
+        self.generator().ok()?.generate()
+    }
+
+    /// The library the pattern was compiled with, if any
+    pub(crate) fn library(&self) -> Option<&PatternLibrary> {
+        self.library.as_deref()
+    }
+
+    /// The capture groups of the pattern
+    pub fn group_registry(&self) -> &GroupRegistry {
+        &self.registry
+    }
+
+    /// The compiled NFA, for inspecting or visualizing how the pattern matches
+    pub fn nfa(&self) -> &Nfa {
+        &self.nfa
+    }
+
+    /// Names of the capture groups, indexed by group number
+    ///
+    /// The first item is always `None` for the entire match (group 0),
+    /// followed by one item per capture group, `None` for unnamed groups.
+    pub fn capture_names(&self) -> impl Iterator<Item = Option<&str>> {
+        core::iter::once(None).chain(
+            self.registry
+                .groups()
+                .iter()
+                .map(|group| group.name.as_deref()),
+        )
+    }
+
+    /// Number of capture groups in the pattern, not counting the entire match
+    pub fn group_count(&self) -> usize {
+        self.registry.group_count()
+    }
+
+    /// Index of the named group `name`, if the pattern defines it
+    pub fn group_index(&self, name: &str) -> Option<u32> {
+        self.registry.get_by_name(name)
     }
 
     /// Check if the pattern matches anywhere in the input
@@ -113,45 +697,949 @@ impl Regex {
         self.find(input).is_some()
     }
 
-    /// Find the first match in the input
-    pub fn find(&self, input: &str) -> Option<Match> {
-        // Try matching from each position
-        for start in 0..=input.len() {
-            if let Some(match_result) = self.match_from(input, start) {
-                return Some(match_result);
+    /// Check if the input matches, or could match once more characters are
+    /// appended to it
+    ///
+    /// This suits validating input as it is typed. Assertions after the end
+    /// of the input, such as `$` and lookaheads, are assumed to hold, and a
+    /// backreference is assumed to match whatever text follows it.
+    ///
+    /// ```
+    /// use ogex::Regex;
+    ///
+    /// let regex = Regex::new(r"^\d{3}-\d{4}$").unwrap();
+    /// assert!(regex.is_partial_match("555-12"));
+    /// assert!(!regex.is_partial_match("555-12a"));
+    /// ```
+    pub fn is_partial_match(&self, input: &str) -> bool {
+        crate::partial::is_partial_match(self, input)
+    }
+
+    /// Find the first match in the input
+    pub fn find(&self, input: &str) -> Option<Match> {
+        self.find_at(input, 0)
+    }
+
+    /// Find the first match that starts at or after `start`
+    ///
+    /// Unlike slicing the input, anchors and lookbehinds still see the text
+    /// before `start`.
+    pub fn find_at(&self, input: &str, start: usize) -> Option<Match> {
+        self.try_find_at(input, start).ok().flatten()
+    }
+
+    /// Find the first match, reporting a search that hit the step limit
+    pub fn try_find(&self, input: &str) -> Result<Option<Match>, crate::error::RegexError> {
+        self.try_find_at(input, 0)
+    }
+
+    /// Find the first match that starts at or after `start`, reporting a
+    /// search that hit the step limit or a `start` that is past the end of
+    /// the input or inside a character
+    ///
+    /// ```
+    /// use ogex::{RegexBuilder, RegexError, RuntimeError};
+    ///
+    /// let regex = RegexBuilder::new("(a*)*b").step_limit(100).build().unwrap();
+    /// assert!(matches!(
+    ///     regex.try_find_at(&"a".repeat(50), 0),
+    ///     Err(RegexError::Runtime(RuntimeError::StepLimitExceeded { steps: 100 }))
+    /// ));
+    /// ```
+    pub fn try_find_at(
+        &self,
+        input: &str,
+        start: usize,
+    ) -> Result<Option<Match>, crate::error::RegexError> {
+        if !input.is_char_boundary(start) {
+            return Err(RuntimeError::InvalidUtf8Offset { offset: start }.into());
+        }
+        let found = self.search_at(input, start)?;
+        Ok(found.map(|m| self.with_named_groups(m)))
+    }
+
+    /// Find the first match like [`Regex::try_find`], passing each step of
+    /// the search to `hook`
+    ///
+    /// See the [`trace`](crate::trace) module for the events reported.
+    pub fn trace(
+        &self,
+        input: &str,
+        hook: impl FnMut(TraceEvent),
+    ) -> Result<Option<Match>, crate::error::RegexError> {
+        let hook = RefCell::new(hook);
+        let emit = |event: TraceEvent| (hook.borrow_mut())(event);
+        let found = self.search_with(Subject::Text(input), input.len(), 0, Some(&emit))?;
+        Ok(found.map(|m| self.with_named_groups(m)))
+    }
+
+    /// Find the first match in a byte string that starts at or after `start`
+    ///
+    /// The input need not be UTF-8: each byte is matched as the character
+    /// with the same value, as in Latin-1, so `.` matches any single byte and
+    /// `\w` matches only ASCII word bytes. Match positions are byte offsets.
+    ///
+    /// ```
+    /// use ogex::Regex;
+    ///
+    /// let regex = Regex::new(r"(n:\d+)").unwrap();
+    /// let m = regex.find_bytes_at(b"\xff\xfe 42", 0).unwrap().unwrap();
+    /// assert_eq!((m.start, m.end), (3, 5));
+    /// ```
+    pub fn find_bytes_at(
+        &self,
+        input: &[u8],
+        start: usize,
+    ) -> Result<Option<Match>, crate::error::RegexError> {
+        if start > input.len() {
+            return Err(RuntimeError::InvalidUtf8Offset { offset: start }.into());
+        }
+        let found = self.search_with(Subject::Bytes(input), input.len(), start, self.sink())?;
+        Ok(found.map(|m| self.with_named_groups(m)))
+    }
+
+    /// Resolve named groups so they can be looked up by name
+    fn with_named_groups(&self, mut match_result: Match) -> Match {
+        for group in self.registry.groups() {
+            if let Some(name) = &group.name
+                && let Some(span) = match_result.group(group.index)
+            {
+                match_result.named_groups.insert(name.clone(), span);
+            }
+        }
+        match_result
+    }
+
+    /// Allocate storage for the capture group positions of this pattern
+    ///
+    /// The same locations can be filled again and again by
+    /// [`Regex::captures_read_at`].
+    pub fn capture_locations(&self) -> CaptureLocations {
+        CaptureLocations {
+            slots: vec![None; self.group_count() + 1],
+        }
+    }
+
+    /// Find the first match, storing its group positions in `locs`
+    ///
+    /// Returns the span of the entire match.
+    pub fn captures_read(
+        &self,
+        locs: &mut CaptureLocations,
+        input: &str,
+    ) -> Option<(usize, usize)> {
+        self.captures_read_at(locs, input, 0)
+    }
+
+    /// Find the first match starting at or after `start`, storing its group
+    /// positions in `locs`
+    ///
+    /// Unlike [`Regex::find_at`], no per-match name map is built, which makes
+    /// this suited to hot loops. On failure every location is cleared.
+    pub fn captures_read_at(
+        &self,
+        locs: &mut CaptureLocations,
+        input: &str,
+        start: usize,
+    ) -> Option<(usize, usize)> {
+        locs.slots.fill(None);
+        let match_result = self.search_at(input, start).ok().flatten()?;
+        for (index, slot) in locs.slots.iter_mut().enumerate().skip(1) {
+            *slot = match_result.group(index as u32);
+        }
+        locs.slots[0] = Some((match_result.start, match_result.end));
+        locs.slots[0]
+    }
+
+    /// Iterate over all non-overlapping matches
+    pub fn find_iter<'r, 'h>(&'r self, input: &'h str) -> Matches<'r, 'h> {
+        self.find_iter_at(input, 0)
+    }
+
+    /// Iterate over all non-overlapping matches that start at or after `start`
+    pub fn find_iter_at<'r, 'h>(&'r self, input: &'h str, start: usize) -> Matches<'r, 'h> {
+        Matches(self.try_find_iter_at(input, start))
+    }
+
+    /// Iterate over all non-overlapping matches, reporting a search that hit
+    /// the step limit
+    ///
+    /// [`Regex::find_iter`] stops at such a search as if there were no more
+    /// matches; this yields the error instead, and then stops.
+    ///
+    /// ```
+    /// use ogex::{RegexBuilder, RegexError, RuntimeError};
+    ///
+    /// let regex = RegexBuilder::new("(a|a)*b|x").step_limit(200).build().unwrap();
+    /// let input = format!("x {} x", "a".repeat(30));
+    /// let found: Vec<_> = regex.try_find_iter(&input).collect();
+    /// assert_eq!(found.len(), 2);
+    /// assert!(found[0].is_ok());
+    /// assert!(matches!(
+    ///     found[1],
+    ///     Err(RegexError::Runtime(RuntimeError::StepLimitExceeded { steps: 200 }))
+    /// ));
+    /// ```
+    pub fn try_find_iter<'r, 'h>(&'r self, input: &'h str) -> TryMatches<'r, 'h> {
+        self.try_find_iter_at(input, 0)
+    }
+
+    /// Iterate over all non-overlapping matches that start at or after
+    /// `start`, reporting a search that hit the step limit
+    pub fn try_find_iter_at<'r, 'h>(&'r self, input: &'h str, start: usize) -> TryMatches<'r, 'h> {
+        TryMatches {
+            regex: self,
+            input,
+            pos: Some(start),
+        }
+    }
+
+    /// Find all non-overlapping matches
+    pub fn find_all(&self, input: &str) -> Vec<Match> {
+        self.find_iter(input).collect()
+    }
+
+    /// Iterate over the pieces of the input between matches
+    ///
+    /// ```
+    /// use ogex::Regex;
+    ///
+    /// let regex = Regex::new(r";\s*").unwrap();
+    /// let fields: Vec<_> = regex.split("a; b;c;").collect();
+    /// assert_eq!(fields, vec!["a", "b", "c", ""]);
+    /// ```
+    pub fn split<'r, 'h>(&'r self, input: &'h str) -> Split<'r, 'h> {
+        Split {
+            matches: self.find_iter(input),
+            input,
+            last: 0,
+            finished: false,
+        }
+    }
+
+    /// Iterate over the pieces of the input between matches, reporting a
+    /// search that hit the step limit
+    ///
+    /// [`Regex::split`] treats such a search as the end of the matches and
+    /// yields the rest of the input as the last piece; this yields the error
+    /// instead, and then stops.
+    pub fn try_split<'r, 'h>(&'r self, input: &'h str) -> TrySplit<'r, 'h> {
+        TrySplit {
+            matches: self.try_find_iter(input),
+            input,
+            last: 0,
+            finished: false,
+        }
+    }
+
+    /// Iterate over the pieces of the input between matches, with the text
+    /// of each capture group after the piece before it, as Python's
+    /// `re.split` does
+    ///
+    /// Pieces are always `Some`; a group is `None` when it did not take
+    /// part in the match.
+    ///
+    /// ```
+    /// use ogex::Regex;
+    ///
+    /// let regex = Regex::new(r"\s*([\+\-])\s*|(;)").unwrap();
+    /// let parts: Vec<_> = regex.split_with_captures("1 + 2-3;").collect();
+    /// assert_eq!(
+    ///     parts,
+    ///     [
+    ///         Some("1"), Some("+"), None,
+    ///         Some("2"), Some("-"), None,
+    ///         Some("3"), None, Some(";"),
+    ///         Some(""),
+    ///     ]
+    /// );
+    /// ```
+    pub fn split_with_captures<'r, 'h>(&'r self, input: &'h str) -> SplitWithCaptures<'r, 'h> {
+        SplitWithCaptures {
+            split: self.split(input),
+            groups: self.group_count(),
+            pending: None,
+        }
+    }
+
+    /// Iterate over the pieces of the input, each ending with the match
+    /// that follows it, as [`str::split_inclusive`] does
+    ///
+    /// Text after the last match is the final piece. An input that ends
+    /// with a match has no empty piece after it, and an empty input has no
+    /// pieces at all.
+    ///
+    /// ```
+    /// use ogex::Regex;
+    ///
+    /// let regex = Regex::new(r"[\.\!\?]+\s*").unwrap();
+    /// let sentences: Vec<_> = regex.split_inclusive("Hi. Who? Me!").collect();
+    /// assert_eq!(sentences, ["Hi. ", "Who? ", "Me!"]);
+    /// ```
+    pub fn split_inclusive<'r, 'h>(&'r self, input: &'h str) -> SplitInclusive<'r, 'h> {
+        SplitInclusive {
+            matches: self.find_iter(input),
+            input,
+            last: 0,
+        }
+    }
+
+    /// Find the first match starting at or after `start`, without resolving names
+    fn search_at(&self, input: &str, start: usize) -> Result<Option<Match>, RuntimeError> {
+        self.search_with(Subject::Text(input), input.len(), start, self.sink())
+    }
+
+    /// The sink set with [`RegexBuilder::trace_sink`], if any
+    fn sink(&self) -> Option<&dyn TraceSink> {
+        self.trace.as_ref().map(|sink| &*sink.0 as &dyn TraceSink)
+    }
+
+    /// Try each position of `subject` from `start` to `len`, sharing one
+    /// step budget between them and reporting their steps to `sink`
+    fn search_with<'a>(
+        &'a self,
+        subject: Subject<'a>,
+        len: usize,
+        start: usize,
+        sink: Option<&'a dyn TraceSink>,
+    ) -> Result<Option<Match>, RuntimeError> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::trace_span!(
+            "search",
+            start,
+            steps = tracing::field::Empty,
+            matched = tracing::field::Empty,
+        )
+        .entered();
+        let haystack_len = match subject {
+            Subject::Text(input) => input.len(),
+            Subject::Bytes(input) => input.len(),
+        };
+        if let Some(limit) = self.limits.haystack_len
+            && haystack_len > limit
+        {
+            return Err(RuntimeError::HaystackTooLarge {
+                len: haystack_len,
+                limit,
+            });
+        }
+        let limit = self.limits.steps.unwrap_or(usize::MAX);
+        let mut steps = 0;
+        let mut deadline = Deadline::start(self.limits.timeout);
+        let mut scratch = self.scratch.get();
+        let Scratch { memo, vm } = &mut *scratch;
+        // Only the simulator reports its steps, so traced searches use it
+        let vm_input = self
+            .program
+            .as_ref()
+            .filter(|_| sink.is_none())
+            .map(|program| {
+                let haystack = match subject {
+                    Subject::Text(input) => Haystack::text(input),
+                    Subject::Bytes(input) => Haystack::bytes(input),
+                };
+                (program, haystack)
+            });
+        // Try matching from each position, which in text is each character
+        let positions = (start..=len).filter(|&pos| match subject {
+            Subject::Text(input) => input.is_char_boundary(pos),
+            Subject::Bytes(_) => true,
+        });
+        for pos in positions {
+            if let Some(sink) = sink {
+                sink.event(TraceEvent::Attempt { start: pos });
+            }
+            let budget = limit - steps;
+            let (found, taken) = match &vm_input {
+                Some((program, haystack)) => {
+                    let (found, taken) = program.run(haystack, pos, vm, budget, &mut deadline);
+                    let found = found.map(|(end, groups)| Match {
+                        start: pos,
+                        end,
+                        groups,
+                        named_groups: HashMap::new(),
+                    });
+                    (found, taken)
+                }
+                None => {
+                    let mut simulator = match subject {
+                        Subject::Text(input) => NfaSimulator::new(&self.nfa, input, pos),
+                        Subject::Bytes(input) => NfaSimulator::over_bytes(&self.nfa, input, pos),
+                    };
+                    simulator.trace = sink;
+                    simulator.budget = budget;
+                    simulator.deadline = deadline;
+                    let found = simulator.run_with(memo);
+                    deadline = simulator.deadline;
+                    (found, simulator.steps)
+                }
+            };
+            if taken > budget {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(steps = limit, "step limit exceeded");
+                return Err(RuntimeError::StepLimitExceeded { steps: limit });
+            }
+            steps += taken;
+            if found.is_some() {
+                #[cfg(feature = "tracing")]
+                span.record("steps", steps).record("matched", true);
+                return Ok(found);
+            }
+            // A run that stopped at the deadline has no match to report
+            if deadline.tick(1) {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(steps, "timeout exceeded");
+                return Err(RuntimeError::TimeoutExceeded);
+            }
+        }
+        #[cfg(feature = "tracing")]
+        span.record("steps", steps).record("matched", false);
+        Ok(None)
+    }
+
+    /// Find the first approximate match within `max_edits` insertions,
+    /// deletions and substitutions
+    ///
+    /// See [`FuzzyRegex`](crate::FuzzyRegex) for how matches are chosen.
+    pub fn find_fuzzy(&self, input: &str, max_edits: u32) -> Option<crate::FuzzyMatch> {
+        crate::FuzzyRegex::new(self, max_edits).find(input)
+    }
+
+    /// Every match split into pieces at its group boundaries, for rendering
+    /// in color
+    ///
+    /// See [`highlight`](crate::highlight) for how matches are split.
+    pub fn highlight(&self, input: &str) -> Vec<crate::HighlightSpan> {
+        self.find_iter(input)
+            .enumerate()
+            .flat_map(|(index, m)| crate::highlight::match_spans(self, &m, index))
+            .collect()
+    }
+
+    /// The pieces of a match that was found already, labelled as match
+    /// number `match_index`
+    pub fn highlight_match(&self, m: &Match, match_index: usize) -> Vec<crate::HighlightSpan> {
+        crate::highlight::match_spans(self, m, match_index)
+    }
+
+    /// Search text read from `reader`, yielding each match as it is found
+    ///
+    /// See [`FindStream`](crate::stream::FindStream) for how the input is
+    /// read and matched.
+    #[cfg(feature = "async")]
+    pub fn find_stream<R>(&self, reader: R) -> crate::stream::FindStream<'_, R>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        crate::stream::FindStream::new(self, reader)
+    }
+
+    /// Find a match that starts exactly at `pos`, without trying later
+    /// positions
+    pub(crate) fn match_at(&self, input: &str, pos: usize) -> Option<Match> {
+        let found = self.search_with(Subject::Text(input), pos, pos, self.sink());
+        found.ok().flatten().map(|m| self.with_named_groups(m))
+    }
+
+    /// Try to match the pattern at a specific position without trying other positions
+    /// Used for lookahead assertions - checks if pattern matches at current position
+    pub fn try_match_at(&self, input: &str, pos: usize) -> bool {
+        let mut scratch = self.scratch.get();
+        let Scratch { memo, vm } = &mut *scratch;
+        match &self.program {
+            Some(program) => {
+                let haystack = Haystack::text(input);
+                program
+                    .run(&haystack, pos, vm, usize::MAX, &mut Deadline::default())
+                    .0
+                    .is_some()
+            }
+            None => NfaSimulator::new(&self.nfa, input, pos)
+                .run_with(memo)
+                .is_some(),
+        }
+    }
+}
+
+#[cfg(feature = "replace")]
+impl Regex {
+    /// Replace the first match with a replacement
+    ///
+    /// String replacements are parsed with [`Replacement::parse`]; wrap text in
+    /// [`NoExpand`](crate::NoExpand) to insert it verbatim. The replacement is
+    /// validated against the pattern's groups before any text is produced, so
+    /// a reference to a group that does not exist is reported as an error. A
+    /// search that hits the step limit is reported as
+    /// [`ReplacementError::Runtime`].
+    pub fn replace<R: Replacer>(
+        &self,
+        input: &str,
+        replacer: R,
+    ) -> Result<String, ReplacementError> {
+        self.replace_matches(input, replacer, self.runtime_matches(input, 0).take(1))
+    }
+
+    /// Replace at most `limit` matches with a replacement (all of them if `limit` is 0)
+    ///
+    /// See [`Regex::replace`] for how the replacement is validated.
+    pub fn replacen<R: Replacer>(
+        &self,
+        input: &str,
+        limit: usize,
+        replacer: R,
+    ) -> Result<String, ReplacementError> {
+        self.replacen_at(input, 0, limit, replacer)
+    }
+
+    /// Replace at most `limit` matches that start at or after `start`
+    /// (all of them if `limit` is 0)
+    ///
+    /// Text before `start` is copied unchanged. See [`Regex::replace`] for how
+    /// the replacement is validated.
+    pub fn replacen_at<R: Replacer>(
+        &self,
+        input: &str,
+        start: usize,
+        limit: usize,
+        replacer: R,
+    ) -> Result<String, ReplacementError> {
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        self.replace_matches(
+            input,
+            replacer,
+            self.runtime_matches(input, start).take(limit),
+        )
+    }
+
+    /// Replace all non-overlapping matches with a replacement
+    ///
+    /// See [`Regex::replace`] for how the replacement is validated.
+    pub fn replace_all<R: Replacer>(
+        &self,
+        input: &str,
+        replacer: R,
+    ) -> Result<String, ReplacementError> {
+        self.replace_matches(input, replacer, self.runtime_matches(input, 0))
+    }
+
+    /// Replace every match, or the chosen named groups in every match, with
+    /// a mask, a token or a hash of the text
+    ///
+    /// See [`redact`](crate::redact) for the styles. Fails if the redaction
+    /// names a group the pattern does not have, or if a search hits the step
+    /// limit, rather than leaving the rest of the input unredacted.
+    pub fn redact(
+        &self,
+        input: &str,
+        redaction: &crate::Redaction,
+    ) -> Result<String, ReplacementError> {
+        crate::redact::redact(self, input, redaction)
+    }
+
+    /// Replace all matches, writing the result to `writer`
+    ///
+    /// This produces the same text as [`Regex::replace_all`] without building
+    /// the output in memory. An invalid replacement is reported as an
+    /// [`io::ErrorKind::InvalidInput`] error before anything is written, and a
+    /// search that hits the step limit as an [`io::ErrorKind::Other`] error
+    /// once the text before it is written.
+    #[cfg(feature = "std")]
+    pub fn replace_all_to<R: Replacer, W: Write>(
+        &self,
+        input: &str,
+        replacer: R,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let replacement = self.checked_replacement(replacer)?;
+        self.write_replaced(input, usize::MAX, &replacement, writer)?;
+        Ok(())
+    }
+
+    /// Replace at most `limit` matches (all of them if `limit` is 0), writing
+    /// the result to `writer` and returning how many were replaced
+    ///
+    /// See [`Regex::replace_all_to`] for how errors are reported.
+    #[cfg(feature = "std")]
+    pub fn replacen_to<R: Replacer, W: Write>(
+        &self,
+        input: &str,
+        limit: usize,
+        replacer: R,
+        writer: &mut W,
+    ) -> io::Result<usize> {
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        let replacement = self.checked_replacement(replacer)?;
+        self.write_replaced(input, limit, &replacement, writer)
+    }
+
+    /// Replace all matches in text read from `reader`, writing the result to `writer`
+    ///
+    /// The input is processed one line at a time (each line including its
+    /// terminator), so arbitrarily large inputs can be rewritten with bounded
+    /// memory. Matches therefore never span multiple lines.
+    #[cfg(feature = "std")]
+    pub fn replace_all_reader_to<R: Replacer, B: BufRead, W: Write>(
+        &self,
+        reader: &mut B,
+        replacer: R,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        let replacement = self.checked_replacement(replacer)?;
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            self.write_replaced(&line, usize::MAX, &replacement, writer)?;
+            line.clear();
+        }
+        Ok(())
+    }
+
+    /// Build and validate a replacement for the streaming APIs
+    #[cfg(feature = "std")]
+    fn checked_replacement<R: Replacer>(&self, replacer: R) -> io::Result<Replacement> {
+        let replacement = replacer
+            .to_replacement()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        replacement
+            .validate(&self.registry)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        Ok(replacement)
+    }
+
+    /// Write `input` with up to `limit` matches substituted by
+    /// `replacement`, returning how many were
+    #[cfg(feature = "std")]
+    fn write_replaced<W: Write>(
+        &self,
+        input: &str,
+        limit: usize,
+        replacement: &Replacement,
+        writer: &mut W,
+    ) -> io::Result<usize> {
+        let mut last_end = 0;
+        let mut count = 0;
+        for m in self.runtime_matches(input, 0).take(limit) {
+            let m = m.map_err(io::Error::other)?;
+            writer.write_all(&input.as_bytes()[last_end..m.start])?;
+            for piece in replacement.pieces(input, &m) {
+                let piece = piece.map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                writer.write_all(piece.as_bytes())?;
+            }
+            last_end = m.end;
+            count += 1;
+        }
+        writer.write_all(&input.as_bytes()[last_end..])?;
+        Ok(count)
+    }
+
+    /// The matches from `start` on, as searched for by [`Regex::try_find_iter_at`]
+    pub(crate) fn runtime_matches<'r, 'h>(
+        &'r self,
+        input: &'h str,
+        start: usize,
+    ) -> impl Iterator<Item = Result<Match, RuntimeError>> + use<'r, 'h> {
+        let mut matches = self.try_find_iter_at(input, start);
+        core::iter::from_fn(move || matches.next_match())
+    }
+
+    /// Validate a replacement and substitute it for each of the given matches
+    fn replace_matches<R: Replacer>(
+        &self,
+        input: &str,
+        replacer: R,
+        matches: impl IntoIterator<Item = Result<Match, RuntimeError>>,
+    ) -> Result<String, ReplacementError> {
+        let replacement = replacer.to_replacement()?;
+        replacement.validate(&self.registry)?;
+
+        let mut result = String::with_capacity(input.len());
+        let mut last_end = 0;
+        for m in matches {
+            let m = m.map_err(ReplacementError::Runtime)?;
+            result.push_str(&input[last_end..m.start]);
+            replacement.append_match(&mut result, input, &m)?;
+            last_end = m.end;
+        }
+        result.push_str(&input[last_end..]);
+        Ok(result)
+    }
+}
+
+/// Reusable storage for the capture group positions of a match
+///
+/// Created by [`Regex::capture_locations`] and filled by
+/// [`Regex::captures_read_at`]. Location 0 is the entire match.
+#[derive(Debug, Clone)]
+pub struct CaptureLocations {
+    slots: GroupStorage,
+}
+
+impl CaptureLocations {
+    /// Get the position of group `index`, or `None` if it did not participate
+    pub fn get(&self, index: usize) -> Option<(usize, usize)> {
+        self.slots.get(index).copied().flatten()
+    }
+
+    /// Number of capture groups, not counting the entire match
+    pub fn group_count(&self) -> usize {
+        self.slots.len() - 1
+    }
+}
+
+/// An iterator over the non-overlapping matches in an input
+///
+/// Created by [`Regex::find_iter`] and [`Regex::find_iter_at`].
+pub struct Matches<'r, 'h>(TryMatches<'r, 'h>);
+
+impl Iterator for Matches<'_, '_> {
+    type Item = Match;
+
+    fn next(&mut self) -> Option<Match> {
+        self.0.next_match()?.ok()
+    }
+}
+
+/// An iterator over the non-overlapping matches in an input that reports a
+/// search that hit the step limit
+///
+/// Created by [`Regex::try_find_iter`] and [`Regex::try_find_iter_at`].
+pub struct TryMatches<'r, 'h> {
+    regex: &'r Regex,
+    input: &'h str,
+    /// Where the next search starts, or `None` once there is nothing left
+    pos: Option<usize>,
+}
+
+impl TryMatches<'_, '_> {
+    fn next_match(&mut self) -> Option<Result<Match, RuntimeError>> {
+        let pos = self.pos?;
+        if !self.input.is_char_boundary(pos) {
+            self.pos = None;
+            return Some(Err(RuntimeError::InvalidUtf8Offset { offset: pos }));
+        }
+        let match_result = match self.regex.search_at(self.input, pos) {
+            Ok(Some(match_result)) => self.regex.with_named_groups(match_result),
+            // Nothing further to find, don't rescan on the next call
+            Ok(None) => {
+                self.pos = None;
+                return None;
+            }
+            Err(e) => {
+                self.pos = None;
+                return Some(Err(e));
+            }
+        };
+        // Step past empty matches so the search always makes progress, and
+        // stop after an empty match at the end of the input
+        self.pos = if match_result.end == match_result.start {
+            Some(next_char_boundary(self.input, match_result.end))
+                .filter(|&next| next <= self.input.len())
+        } else {
+            Some(match_result.end)
+        };
+        Some(Ok(match_result))
+    }
+}
+
+impl Iterator for TryMatches<'_, '_> {
+    type Item = Result<Match, crate::error::RegexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_match()?.map_err(Into::into))
+    }
+}
+
+/// Escape every character in `text` that has a meaning in a pattern, so the
+/// result matches `text` literally
+///
+/// Besides the usual metacharacters this covers `:` and `@`, which start
+/// named groups and flag groups after `(`, and whitespace and `#`, which are
+/// ignored in extended mode.
+///
+/// ```
+/// use ogex::{Regex, escape};
+///
+/// assert_eq!(escape("(a:1)"), r"\(a\:1\)");
+/// assert!(Regex::new(&escape("1+1=2?")).unwrap().is_match("1+1=2?"));
+/// ```
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_whitespace()
+            || matches!(
+                c,
+                '\\' | '.'
+                    | '^'
+                    | '$'
+                    | '|'
+                    | '?'
+                    | '*'
+                    | '+'
+                    | '('
+                    | ')'
+                    | '['
+                    | ']'
+                    | '{'
+                    | '}'
+                    | ','
+                    | '-'
+                    | ':'
+                    | '@'
+                    | '#'
+            )
+        {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Position of the character after the one at `pos`, or one past the end
+/// of the input
+pub(crate) fn next_char_boundary(input: &str, pos: usize) -> usize {
+    pos + input[pos..].chars().next().map_or(1, char::len_utf8)
+}
+
+/// An iterator over the pieces of an input between matches
+///
+/// Created by [`Regex::split`].
+pub struct Split<'r, 'h> {
+    matches: Matches<'r, 'h>,
+    input: &'h str,
+    /// End of the previous match
+    last: usize,
+    finished: bool,
+}
+
+impl<'h> Iterator for Split<'_, 'h> {
+    type Item = &'h str;
+
+    fn next(&mut self) -> Option<&'h str> {
+        if self.finished {
+            return None;
+        }
+        match self.matches.next() {
+            Some(m) => {
+                let piece = &self.input[self.last..m.start];
+                self.last = m.end;
+                Some(piece)
+            }
+            None => {
+                self.finished = true;
+                Some(&self.input[self.last..])
+            }
+        }
+    }
+}
+
+/// An iterator over the pieces of an input between matches that reports a
+/// search that hit the step limit
+///
+/// Created by [`Regex::try_split`].
+pub struct TrySplit<'r, 'h> {
+    matches: TryMatches<'r, 'h>,
+    input: &'h str,
+    /// End of the previous match
+    last: usize,
+    finished: bool,
+}
+
+impl<'h> Iterator for TrySplit<'_, 'h> {
+    type Item = Result<&'h str, crate::error::RegexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.matches.next() {
+            Some(Ok(m)) => {
+                let piece = &self.input[self.last..m.start];
+                self.last = m.end;
+                Some(Ok(piece))
+            }
+            Some(Err(e)) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+            None => {
+                self.finished = true;
+                Some(Ok(&self.input[self.last..]))
             }
         }
-        None
     }
+}
 
-    /// Find all non-overlapping matches
-    pub fn find_all(&self, input: &str) -> Vec<Match> {
-        let mut matches = Vec::new();
-        let mut pos = 0;
+/// An iterator over the pieces of an input between matches and the groups
+/// each match captured
+///
+/// Created by [`Regex::split_with_captures`].
+pub struct SplitWithCaptures<'r, 'h> {
+    split: Split<'r, 'h>,
+    /// Number of groups in the pattern
+    groups: usize,
+    /// The last match, and the next of its groups to yield
+    pending: Option<(Match, usize)>,
+}
 
-        while pos <= input.len() {
-            if let Some(match_result) = self.match_from(input, pos) {
-                pos = match_result.end;
-                matches.push(match_result);
-            } else {
-                pos += 1;
+impl<'h> Iterator for SplitWithCaptures<'_, 'h> {
+    type Item = Option<&'h str>;
+
+    fn next(&mut self) -> Option<Option<&'h str>> {
+        if let Some((m, group)) = &mut self.pending {
+            if *group <= self.groups {
+                let text = m
+                    .group(*group as u32)
+                    .map(|(start, end)| &self.split.input[start..end]);
+                *group += 1;
+                return Some(text);
+            }
+            self.pending = None;
+        }
+        if self.split.finished {
+            return None;
+        }
+        match self.split.matches.next() {
+            Some(m) => {
+                let piece = &self.split.input[self.split.last..m.start];
+                self.split.last = m.end;
+                self.pending = Some((m, 1));
+                Some(Some(piece))
+            }
+            None => {
+                self.split.finished = true;
+                Some(Some(&self.split.input[self.split.last..]))
             }
         }
-
-        matches
     }
+}
 
-    /// Match the pattern starting from a specific position
-    fn match_from(&self, input: &str, start: usize) -> Option<Match> {
-        let mut simulator = NfaSimulator::new(&self.nfa, input, start);
-        simulator.run()
-    }
+/// An iterator over the pieces of an input, each with the match ending it
+///
+/// Created by [`Regex::split_inclusive`].
+pub struct SplitInclusive<'r, 'h> {
+    matches: Matches<'r, 'h>,
+    input: &'h str,
+    /// End of the previous match
+    last: usize,
+}
 
-    /// Try to match the pattern at a specific position without trying other positions
-    /// Used for lookahead assertions - checks if pattern matches at current position
-    pub fn try_match_at(&self, input: &str, pos: usize) -> bool {
-        let mut simulator = NfaSimulator::new(&self.nfa, input, pos);
-        simulator.run().is_some()
+impl<'h> Iterator for SplitInclusive<'_, 'h> {
+    type Item = &'h str;
+
+    fn next(&mut self) -> Option<&'h str> {
+        match self.matches.next() {
+            Some(m) => {
+                let piece = &self.input[self.last..m.end];
+                self.last = m.end;
+                Some(piece)
+            }
+            None if self.last < self.input.len() => {
+                let piece = &self.input[self.last..];
+                self.last = self.input.len();
+                Some(piece)
+            }
+            None => None,
+        }
     }
 }
 
@@ -165,11 +1653,16 @@ struct SimState {
 
 impl SimState {
     /// Create a new state with empty groups (sized for max_groups)
+    ///
+    /// The storage holds `max_groups` capture slots followed by the same
+    /// number of pending slots recording where a still-open group started.
+    /// Keeping open groups out of the capture slots means a group that never
+    /// closed reads as non-participating rather than as an empty capture.
     fn new(state_id: StateId, max_groups: u32) -> Self {
         // +1 because groups are 1-indexed, index 0 is unused
         SimState {
             state_id,
-            groups: vec![None; max_groups as usize],
+            groups: vec![None; 2 * max_groups as usize],
         }
     }
 
@@ -178,30 +1671,53 @@ impl SimState {
     }
 }
 
+/// Whether the assertion `transition` of `nfa` holds at `pos` in `input`,
+/// deciding it as a search from the start of the input would, or `None` if
+/// the transition is not an assertion
+pub(crate) fn assertion_holds<'a>(
+    nfa: &'a Nfa,
+    input: &'a str,
+    transition: &'a Transition,
+    pos: usize,
+) -> Option<bool> {
+    NfaSimulator::new(nfa, input, 0).assertion(transition, pos)
+}
+
 /// NFA simulator for pattern matching
 #[allow(clippy::type_complexity)]
 struct NfaSimulator<'a> {
     nfa: &'a Nfa,
     _input: &'a str,
-    /// Input as bytes for ASCII mode, or as chars for Unicode mode
+    /// The input as bytes; positions are offsets into it
     input_bytes: &'a [u8],
-    /// Whether to use ASCII/byte mode (true) or Unicode/char mode (false)
-    ascii_mode: bool,
+    /// Whether the input is a byte string rather than text
+    bytes_input: bool,
     start_pos: usize,
     /// Memoization cache: (state_id, position) -> Option<groups> (Some if can reach accept, None if cannot)
     memo: HashMap<(StateId, usize), Option<GroupStorage>>,
+    /// States visited so far
+    steps: usize,
+    /// Steps allowed before the run gives up
+    budget: usize,
+    /// When the run gives up, if the search has a timeout
+    deadline: Deadline,
+    /// Receives each step of the run, when tracing
+    trace: Option<&'a dyn TraceSink>,
 }
 
 impl<'a> NfaSimulator<'a> {
     fn new(nfa: &'a Nfa, input: &'a str, start_pos: usize) -> Self {
-        let ascii_mode = nfa.is_ascii_only();
         NfaSimulator {
             nfa,
             _input: input,
             input_bytes: input.as_bytes(),
-            ascii_mode,
+            bytes_input: false,
             start_pos,
             memo: HashMap::new(),
+            steps: 0,
+            budget: usize::MAX,
+            deadline: Deadline::default(),
+            trace: None,
         }
     }
 
@@ -216,14 +1732,98 @@ impl<'a> NfaSimulator<'a> {
     }
 
     #[allow(clippy::type_complexity)]
-    fn run(&mut self) -> Option<Match> {
-        // Determine input length and get current character/byte
-        let input_len = if self.ascii_mode {
-            self.input_bytes.len()
+    /// A simulator over a byte string, matching each byte as the Latin-1
+    /// character with the same value
+    fn over_bytes(nfa: &'a Nfa, input: &'a [u8], start_pos: usize) -> Self {
+        NfaSimulator {
+            nfa,
+            _input: "",
+            input_bytes: input,
+            bytes_input: true,
+            start_pos,
+            memo: HashMap::new(),
+            steps: 0,
+            budget: usize::MAX,
+            deadline: Deadline::default(),
+            trace: None,
+        }
+    }
+
+    /// The character at byte `pos` and its length in bytes
+    fn char_at(&self, pos: usize) -> Option<(char, usize)> {
+        let byte = *self.input_bytes.get(pos)?;
+        if self.bytes_input || byte.is_ascii() {
+            return Some((byte as char, 1));
+        }
+        let c = self._input.get(pos..)?.chars().next()?;
+        Some((c, c.len_utf8()))
+    }
+
+    /// Whether a match may start or end at byte `pos`
+    fn is_boundary(&self, pos: usize) -> bool {
+        if self.bytes_input {
+            pos <= self.input_bytes.len()
         } else {
-            // For Unicode mode, we need char length
-            self._input.chars().count()
-        };
+            self._input.is_char_boundary(pos)
+        }
+    }
+
+    /// Report an event to the trace hook, if there is one
+    fn emit(&self, event: impl FnOnce() -> TraceEvent) {
+        if let Some(sink) = self.trace {
+            sink.event(event());
+        }
+    }
+
+    /// Close a set of states that is already closed, without tracing the
+    /// events the first closure reported
+    fn reclose(&mut self, states: &[SimState], pos: usize) -> Vec<SimState> {
+        let hook = self.trace.take();
+        let closed = self.epsilon_closure(states, pos);
+        self.trace = hook;
+        closed
+    }
+
+    /// Report the states alive at `pos`, and whether one of them accepts
+    fn emit_states(
+        &self,
+        states: &[SimState],
+        pos: usize,
+        last_accept: &Option<(usize, GroupStorage)>,
+    ) {
+        self.emit(|| TraceEvent::States {
+            pos,
+            states: states.iter().map(|s| s.state_id).collect(),
+        });
+        if matches!(last_accept, Some((end, _)) if *end == pos) {
+            self.emit(|| TraceEvent::Accept { pos });
+        }
+    }
+
+    /// A simulator for a lookaround's NFA over the same input
+    fn nested(&self, inner_nfa: &'a Nfa, pos: usize) -> Self {
+        if self.bytes_input {
+            NfaSimulator::over_bytes(inner_nfa, self.input_bytes, pos)
+        } else {
+            NfaSimulator::new(inner_nfa, self._input, pos)
+        }
+    }
+
+    /// Run with the memo table `memo`, leaving it empty but still
+    /// allocated for the next run
+    fn run_with(
+        &mut self,
+        memo: &mut HashMap<(StateId, usize), Option<GroupStorage>>,
+    ) -> Option<Match> {
+        core::mem::swap(&mut self.memo, memo);
+        let found = self.run();
+        core::mem::swap(&mut self.memo, memo);
+        memo.clear();
+        found
+    }
+
+    fn run(&mut self) -> Option<Match> {
+        let input_len = self.input_bytes.len();
 
         let mut pos = self.start_pos;
         let mut last_accept: Option<(usize, GroupStorage)> = None;
@@ -237,40 +1837,48 @@ impl<'a> NfaSimulator<'a> {
         // Check if start state is accepting (empty match)
         // Use memoization for each state in the closure
         current_states = self.memoize_closure(&current_states, pos, &mut last_accept);
+        self.emit_states(&current_states, pos, &last_accept);
 
         while pos < input_len {
-            let c = if self.ascii_mode {
-                // ASCII mode: use bytes
-                self.input_bytes[pos] as char
-            } else {
-                // Unicode mode: use chars
-                self._input.chars().nth(pos).unwrap_or('\0')
+            self.steps = self.steps.saturating_add(current_states.len());
+            if self.steps > self.budget || self.deadline.tick(current_states.len()) {
+                return None;
+            }
+
+            let Some((c, width)) = self.char_at(pos) else {
+                break;
             };
 
-            let (new_states, chars_consumed) = self.step_with_backrefs(&current_states, c, pos);
+            let (new_states, consumed) = self.step_with_backrefs(&current_states, c, width, pos);
             current_states = new_states;
 
             if current_states.is_empty() {
+                self.emit(|| TraceEvent::Dead { pos });
                 break;
             }
 
-            pos += chars_consumed;
+            pos += consumed;
 
             // Apply epsilon closure and use memoization
-            current_states = self.epsilon_closure(&current_states, pos);
+            current_states = self.reclose(&current_states, pos);
             current_states = self.memoize_closure(&current_states, pos, &mut last_accept);
+            self.emit_states(&current_states, pos, &last_accept);
         }
 
         // Try to reach accept state via epsilon transitions (for end anchors)
-        current_states = self.epsilon_closure(&current_states, pos);
+        current_states = self.reclose(&current_states, pos);
         // Use memoization for final epsilon closure (result not needed after)
         self.memoize_closure(&current_states, pos, &mut last_accept);
 
-        last_accept.map(|(end, groups)| Match {
-            start: self.start_pos,
-            end,
-            groups,
-            named_groups: HashMap::new(),
+        last_accept.map(|(end, mut groups)| {
+            // Drop the pending slots, only completed captures are reported
+            groups.truncate(self.nfa.next_group_id() as usize);
+            Match {
+                start: self.start_pos,
+                end,
+                groups,
+                named_groups: HashMap::new(),
+            }
         })
     }
 
@@ -300,7 +1908,7 @@ impl<'a> NfaSimulator<'a> {
                 }
                 // Not memoized: need to check if accepting and memoize the result
                 None => {
-                    let can_accept = self.find_accepting(std::slice::from_ref(sim_state));
+                    let can_accept = self.find_accepting(core::slice::from_ref(sim_state));
                     if let Some(groups) = can_accept.clone() {
                         *last_accept = Some((pos, groups));
                     }
@@ -314,86 +1922,43 @@ impl<'a> NfaSimulator<'a> {
         result
     }
 
+    /// Move `states` past the character `c`, `width` bytes long at `pos`,
+    /// or past the text a backreference repeats there
     fn step_with_backrefs(
         &self,
         states: &[SimState],
         c: char,
+        width: usize,
         pos: usize,
     ) -> (Vec<SimState>, usize) {
         let mut new_states: Vec<SimState> = Vec::new();
-        let mut chars_consumed = 1; // Default: consume 1 character
+        // Default: consume the character at `pos`
+        let mut consumed = width;
 
         for sim_state in states {
             for (transition, target) in &self.nfa.states[sim_state.state_id].transitions {
+                let tried = new_states.len();
+                let group = match transition {
+                    Transition::Backref(group_id) => Some(*group_id),
+                    Transition::BackrefRelative(relative) => self.nfa.resolve_relative(*relative),
+                    _ => None,
+                };
                 match transition {
-                    Transition::Backref(group_id) => {
+                    Transition::Backref(_) | Transition::BackrefRelative(_) => {
                         // Try to match the backreference
-                        let idx = *group_id as usize;
-                        if idx < sim_state.groups.len()
-                            && let Some((start, end)) = sim_state.groups[idx]
+                        let captured = group
+                            .and_then(|group| {
+                                sim_state.groups.get(group as usize).copied().flatten()
+                            })
+                            .map(|(start, end)| &self.input_bytes[start..end]);
+                        if let Some(captured) = captured
+                            && self.input_bytes[pos..].starts_with(captured)
                         {
-                            if self.ascii_mode {
-                                // ASCII mode: use bytes
-                                let captured = &self.input_bytes[start..end];
-                                let remaining = &self.input_bytes[pos..];
-                                if remaining.starts_with(captured) {
-                                    let consumed = captured.len().max(1);
-                                    let new_state =
-                                        SimState::with_groups(*target, sim_state.groups.clone());
-                                    new_states.push(new_state);
-                                    chars_consumed = chars_consumed.max(consumed);
-                                }
-                            } else {
-                                // Unicode mode: use chars
-                                let captured: String =
-                                    self._input.chars().skip(start).take(end - start).collect();
-                                let remaining: String = self._input.chars().skip(pos).collect();
-                                if remaining.starts_with(&captured) {
-                                    let consumed = captured.len().max(1);
-                                    let new_state =
-                                        SimState::with_groups(*target, sim_state.groups.clone());
-                                    new_states.push(new_state);
-                                    chars_consumed = chars_consumed.max(consumed);
-                                }
-                            }
-                        }
-                    }
-                    Transition::BackrefRelative(relative) => {
-                        // Resolve relative backreference (\g{-n})
-                        if let Some(group_id) = self.nfa.resolve_relative(*relative) {
-                            let idx = group_id as usize;
-                            if idx < sim_state.groups.len()
-                                && let Some((start, end)) = sim_state.groups[idx]
-                            {
-                                if self.ascii_mode {
-                                    // ASCII mode: use bytes
-                                    let captured = &self.input_bytes[start..end];
-                                    let remaining = &self.input_bytes[pos..];
-                                    if remaining.starts_with(captured) {
-                                        let consumed = captured.len().max(1);
-                                        let new_state = SimState::with_groups(
-                                            *target,
-                                            sim_state.groups.clone(),
-                                        );
-                                        new_states.push(new_state);
-                                        chars_consumed = chars_consumed.max(consumed);
-                                    }
-                                } else {
-                                    // Unicode mode: use chars
-                                    let captured: String =
-                                        self._input.chars().skip(start).take(end - start).collect();
-                                    let remaining: String = self._input.chars().skip(pos).collect();
-                                    if remaining.starts_with(&captured) {
-                                        let consumed = captured.len().max(1);
-                                        let new_state = SimState::with_groups(
-                                            *target,
-                                            sim_state.groups.clone(),
-                                        );
-                                        new_states.push(new_state);
-                                        chars_consumed = chars_consumed.max(consumed);
-                                    }
-                                }
-                            }
+                            let new_state =
+                                SimState::with_groups(*target, sim_state.groups.clone());
+                            self.emit_step(pos, sim_state, transition, *target);
+                            new_states.push(new_state);
+                            consumed = consumed.max(captured.len());
                         }
                     }
                     _ => {
@@ -401,17 +1966,33 @@ impl<'a> NfaSimulator<'a> {
                         if let Some(new_state) =
                             self.try_transition(sim_state, transition, *target, c, pos)
                         {
+                            self.emit_step(pos, sim_state, transition, *target);
                             new_states.push(new_state);
                         }
                     }
                 }
+
+                if let Some(group) = group {
+                    self.emit(|| TraceEvent::Backref {
+                        pos,
+                        group,
+                        matched: new_states.len() > tried,
+                    });
+                }
             }
         }
 
-        (
-            self.epsilon_closure(&new_states, pos + chars_consumed),
-            chars_consumed,
-        )
+        (self.epsilon_closure(&new_states, pos + consumed), consumed)
+    }
+
+    /// Report a transition that consumed the input at `pos`
+    fn emit_step(&self, pos: usize, from: &SimState, transition: &Transition, to: StateId) {
+        self.emit(|| TraceEvent::Step {
+            pos,
+            from: from.state_id,
+            to,
+            label: transition_label(transition),
+        });
     }
 
     fn try_transition(
@@ -422,40 +2003,7 @@ impl<'a> NfaSimulator<'a> {
         c: char,
         _pos: usize,
     ) -> Option<SimState> {
-        let matches = match transition {
-            Transition::Char(tc) => {
-                if self.nfa.mode_flags.case_insensitive {
-                    tc.eq_ignore_ascii_case(&c)
-                } else {
-                    *tc == c
-                }
-            }
-            Transition::CharClass {
-                negated: _negated,
-                lookup,
-            } => {
-                // O(1) lookup using pre-computed table (negation already handled in lookup)
-                if c as u32 > 255 {
-                    // For non-ASCII, fall back to simple check
-                    false
-                } else {
-                    let byte_idx = (c as u8 / 8) as usize;
-                    let bit_idx = c as u8 % 8;
-                    (lookup[byte_idx] & (1 << bit_idx)) != 0
-                }
-            }
-            Transition::Any => {
-                // In dotall mode, . matches any character including newline
-                if self.nfa.mode_flags.dotall {
-                    true
-                } else {
-                    c != '\n'
-                }
-            }
-            _ => false, // Epsilon transitions handled in epsilon_closure
-        };
-
-        if matches {
+        if self.nfa.matches_char(transition, c) {
             let new_sim_state = SimState::with_groups(target, sim_state.groups.clone());
             Some(new_sim_state)
         } else {
@@ -475,6 +2023,10 @@ impl<'a> NfaSimulator<'a> {
             }
 
             closure.push(sim_state.clone());
+            self.emit(|| TraceEvent::Enter {
+                pos,
+                state: sim_state.state_id,
+            });
 
             // Use pre-computed epsilon closure for pure epsilon transitions
             let epsilon_targets = self.nfa.get_epsilon_closure(sim_state.state_id);
@@ -495,112 +2047,48 @@ impl<'a> NfaSimulator<'a> {
                     continue;
                 }
 
-                match transition {
-                    Transition::StartAnchor => {
-                        if self.nfa.mode_flags.multiline {
-                            // In multiline mode, ^ matches at start of string or after newline
-                            let is_start = pos == self.start_pos;
-                            let is_after_newline = if self.ascii_mode {
-                                pos > 0 && self.input_bytes[pos - 1] == b'\n'
-                            } else {
-                                pos > 0 && self._input.chars().nth(pos - 1) == Some('\n')
-                            };
-                            if is_start || is_after_newline {
-                                stack
-                                    .push(SimState::with_groups(*target, sim_state.groups.clone()));
-                            }
-                        } else if self.start_pos == 0 && pos == self.start_pos {
-                            stack.push(SimState::with_groups(*target, sim_state.groups.clone()));
-                        }
-                    }
-                    Transition::EndAnchor => {
-                        if self.nfa.mode_flags.multiline {
-                            // In multiline mode, $ matches at end of string or before newline
-                            let input_len = if self.ascii_mode {
-                                self.input_bytes.len()
-                            } else {
-                                self._input.chars().count()
-                            };
-                            let is_end = pos == input_len;
-                            let is_before_newline = if self.ascii_mode {
-                                self.input_bytes.get(pos) == Some(&b'\n')
-                            } else {
-                                self._input.chars().nth(pos) == Some('\n')
-                            };
-                            if is_end || is_before_newline {
-                                stack
-                                    .push(SimState::with_groups(*target, sim_state.groups.clone()));
-                            }
-                        } else {
-                            let input_len = if self.ascii_mode {
-                                self.input_bytes.len()
-                            } else {
-                                self._input.chars().count()
-                            };
-                            if pos == input_len {
-                                stack
-                                    .push(SimState::with_groups(*target, sim_state.groups.clone()));
-                            }
-                        }
-                    }
-                    Transition::WordBoundary => {
-                        if self.is_word_boundary(pos) {
-                            stack.push(SimState::with_groups(*target, sim_state.groups.clone()));
-                        }
-                    }
-                    Transition::NonWordBoundary => {
-                        if !self.is_word_boundary(pos) {
-                            stack.push(SimState::with_groups(*target, sim_state.groups.clone()));
-                        }
-                    }
+                let passed = match transition {
                     Transition::GroupStart(group_id) => {
                         let mut new_groups = sim_state.groups.clone();
-                        let idx = *group_id as usize;
-                        if idx < new_groups.len() {
-                            new_groups[idx] = Some((pos, pos)); // Start capturing
+                        let pending = self.nfa.next_group_id() as usize + *group_id as usize;
+                        if pending < new_groups.len() {
+                            new_groups[pending] = Some((pos, pos)); // Start capturing
                         }
+                        self.emit(|| TraceEvent::GroupStart {
+                            group: *group_id,
+                            pos,
+                        });
                         stack.push(SimState::with_groups(*target, new_groups));
+                        continue;
                     }
                     Transition::GroupEnd(group_id) => {
                         let mut new_groups = sim_state.groups.clone();
                         let idx = *group_id as usize;
-                        if idx < new_groups.len()
-                            && let Some((start, _)) = new_groups[idx]
+                        let pending = self.nfa.next_group_id() as usize + idx;
+                        if pending < new_groups.len()
+                            && let Some((start, _)) = new_groups[pending].take()
                         {
                             new_groups[idx] = Some((start, pos)); // End capturing
                         }
+                        self.emit(|| TraceEvent::GroupEnd {
+                            group: *group_id,
+                            pos,
+                        });
                         stack.push(SimState::with_groups(*target, new_groups));
+                        continue;
                     }
-                    Transition::Lookahead(inner_nfa) => {
-                        // Check if the inner pattern matches at the current position
-                        // without consuming input (lookahead is zero-width)
-                        if self.check_lookahead(inner_nfa, pos) {
-                            stack.push(SimState::with_groups(*target, sim_state.groups.clone()));
-                        }
-                    }
-                    Transition::NegativeLookahead(inner_nfa) => {
-                        // Check if the inner pattern does NOT match at the current position
-                        if !self.check_lookahead(inner_nfa, pos) {
-                            stack.push(SimState::with_groups(*target, sim_state.groups.clone()));
-                        }
-                    }
-                    Transition::Lookbehind(inner_nfa) => {
-                        // Check if the inner pattern matches at the position BEFORE current
-                        // (lookbehind checks what comes immediately before current position)
-                        // If at position 0, nothing precedes it, so lookbehind always fails
-                        if self.check_lookbehind(inner_nfa, pos) {
-                            stack.push(SimState::with_groups(*target, sim_state.groups.clone()));
-                        }
-                    }
-                    Transition::NegativeLookbehind(inner_nfa) => {
-                        // Check if the inner pattern does NOT match at the position before current
-                        // At position 0, nothing precedes it, so it's NOT preceded by any pattern
-                        // Negative lookbehind succeeds at position 0
-                        if !self.check_lookbehind(inner_nfa, pos) {
-                            stack.push(SimState::with_groups(*target, sim_state.groups.clone()));
-                        }
-                    }
-                    _ => {} // Char/CharClass handled in step
+                    _ => match self.assertion(transition, pos) {
+                        Some(passed) => passed,
+                        None => continue, // Char/CharClass handled in step
+                    },
+                };
+                self.emit(|| TraceEvent::Assertion {
+                    pos,
+                    label: transition_label(transition),
+                    passed,
+                });
+                if passed {
+                    stack.push(SimState::with_groups(*target, sim_state.groups.clone()));
                 }
             }
         }
@@ -608,6 +2096,32 @@ impl<'a> NfaSimulator<'a> {
         closure
     }
 
+    /// Whether an anchor, word boundary or lookaround holds at `pos`, or
+    /// `None` if the transition is not an assertion
+    fn assertion(&self, transition: &'a Transition, pos: usize) -> Option<bool> {
+        let passed = match transition {
+            Transition::StartAnchor => self.is_line_start(pos),
+            Transition::EndAnchor => self.is_line_end(pos),
+            Transition::WordBoundary => self.is_word_boundary(pos),
+            Transition::NonWordBoundary => !self.is_word_boundary(pos),
+            // Check if the inner pattern matches at the current position
+            // without consuming input (lookahead is zero-width)
+            Transition::Lookahead(inner_nfa) => self.check_lookahead(inner_nfa, pos),
+            // Check if the inner pattern does NOT match at the current position
+            Transition::NegativeLookahead(inner_nfa) => !self.check_lookahead(inner_nfa, pos),
+            // Check if the inner pattern matches at the position BEFORE current
+            // (lookbehind checks what comes immediately before current position)
+            // If at position 0, nothing precedes it, so lookbehind always fails
+            Transition::Lookbehind(inner_nfa) => self.check_lookbehind(inner_nfa, pos),
+            // Check if the inner pattern does NOT match at the position before current
+            // At position 0, nothing precedes it, so it's NOT preceded by any pattern
+            // Negative lookbehind succeeds at position 0
+            Transition::NegativeLookbehind(inner_nfa) => !self.check_lookbehind(inner_nfa, pos),
+            _ => return None,
+        };
+        Some(passed)
+    }
+
     fn find_accepting(&self, states: &[SimState]) -> Option<GroupStorage> {
         states
             .iter()
@@ -615,21 +2129,38 @@ impl<'a> NfaSimulator<'a> {
             .map(|s| s.groups.clone())
     }
 
-    fn is_word_boundary(&self, pos: usize) -> bool {
-        let (left_is_word, right_is_word) = if self.ascii_mode {
-            // ASCII mode: use bytes
-            let left_is_word = pos > 0 && self.is_word_byte(self.input_bytes[pos - 1]);
-            let right_is_word =
-                pos < self.input_bytes.len() && self.is_word_byte(self.input_bytes[pos]);
-            (left_is_word, right_is_word)
+    /// Whether `^` matches at `pos`: at the start of the input, or after a
+    /// line break in multiline mode
+    fn is_line_start(&self, pos: usize) -> bool {
+        if self.nfa.mode_flags.multiline {
+            // In multiline mode, ^ matches at start of string or after newline
+            // (not wherever the search resumed)
+            let is_start = pos == 0;
+            let is_after_newline = pos > 0 && self.input_bytes[pos - 1] == b'\n';
+            is_start || is_after_newline
         } else {
-            // Unicode mode: use chars
-            let left_is_word =
-                pos > 0 && self.is_word_char(self._input.chars().nth(pos - 1).unwrap_or('\0'));
-            let right_is_word = pos < self._input.chars().count()
-                && self.is_word_char(self._input.chars().nth(pos).unwrap_or('\0'));
-            (left_is_word, right_is_word)
-        };
+            self.start_pos == 0 && pos == self.start_pos
+        }
+    }
+
+    /// Whether `$` matches at `pos`: at the end of the input, or before a
+    /// line break in multiline mode
+    fn is_line_end(&self, pos: usize) -> bool {
+        let input_len = self.input_bytes.len();
+        if self.nfa.mode_flags.multiline {
+            // In multiline mode, $ matches at end of string or before newline
+            let is_before_newline = self.input_bytes.get(pos) == Some(&b'\n');
+            pos == input_len || is_before_newline
+        } else {
+            pos == input_len
+        }
+    }
+
+    fn is_word_boundary(&self, pos: usize) -> bool {
+        // Word characters are ASCII, so no byte of a longer character is one
+        let left_is_word = pos > 0 && self.is_word_byte(self.input_bytes[pos - 1]);
+        let right_is_word =
+            pos < self.input_bytes.len() && self.is_word_byte(self.input_bytes[pos]);
         left_is_word != right_is_word
     }
 
@@ -637,19 +2168,11 @@ impl<'a> NfaSimulator<'a> {
         b.is_ascii_alphanumeric() || b == b'_'
     }
 
-    fn is_word_char(&self, c: char) -> bool {
-        c.is_ascii_alphanumeric() || c == '_'
-    }
-
     /// Check if an inner NFA matches at a specific position without consuming input
     /// Used for lookahead assertions
     fn check_lookahead(&self, inner_nfa: &Nfa, pos: usize) -> bool {
-        // Create a regex from the inner NFA and try to match at the position
-        let regex = Regex {
-            nfa: inner_nfa.clone(),
-        };
-        // Use try_match_at to check if pattern matches without consuming beyond
-        regex.try_match_at(self._input, pos)
+        // Run the inner NFA from the position without consuming beyond it
+        self.nested(inner_nfa, pos).run().is_some()
     }
 
     /// Check if an inner NFA matches immediately BEFORE a specific position
@@ -657,13 +2180,10 @@ impl<'a> NfaSimulator<'a> {
     fn check_lookbehind(&self, inner_nfa: &Nfa, pos: usize) -> bool {
         // For lookbehind, we need to check if the pattern matches immediately before pos
         // That means the pattern should match ending at pos-1
-        let regex = Regex {
-            nfa: inner_nfa.clone(),
-        };
         // Try to find a match that ends exactly at pos
         // We check all possible starting positions from 0 to pos
-        for start in 0..=pos {
-            if let Some(m) = regex.match_from(self._input, start) {
+        for start in (0..=pos).filter(|&start| self.is_boundary(start)) {
+            if let Some(m) = self.nested(inner_nfa, start).run() {
                 // Check if this match ends exactly at pos (i.e., immediately before current pos)
                 if m.end == pos {
                     return true;
@@ -678,6 +2198,68 @@ impl<'a> NfaSimulator<'a> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_find_bytes() {
+        let span = |pattern: &str, input: &[u8]| {
+            Regex::new(pattern)
+                .unwrap()
+                .find_bytes_at(input, 0)
+                .unwrap()
+                .map(|m| (m.start, m.end))
+        };
+        assert_eq!(span("a.c", b"\x80a\xffc"), Some((1, 4)));
+        assert_eq!(span(r"(x:\w+)-\g{x}", b"\xfeab-ab"), Some((1, 6)));
+        assert_eq!(span(r"\d(@>:\s)", b"1\xff2 "), Some((2, 3)));
+        assert_eq!(span("é", b"caf\xe9"), Some((3, 4)));
+        assert_eq!(span("z", b"\xff"), None);
+
+        let regex = Regex::new("(n:a)").unwrap();
+        let m = regex.find_bytes_at(b"baa", 2).unwrap().unwrap();
+        assert_eq!(m.named_group("n"), Some((2, 3)));
+        assert!(regex.find_bytes_at(b"a", 2).is_err());
+    }
+
+    #[test]
+    fn test_non_ascii_positions_are_byte_offsets() {
+        let spans = |regex: &Regex, input: &str| -> Vec<(usize, usize)> {
+            regex.find_iter(input).map(|m| (m.start, m.end)).collect()
+        };
+        for backend in [Backend::Nfa, Backend::Vm] {
+            let build =
+                |pattern: &str| RegexBuilder::new(pattern).backend(backend).build().unwrap();
+            let input = "aébé";
+            assert_eq!(spans(&build("é"), input), vec![(1, 3), (4, 6)]);
+            assert_eq!(spans(&build("."), "aé"), vec![(0, 1), (1, 3)]);
+            assert_eq!(spans(&build(r"(<:é)b"), "ébéb"), vec![(2, 3), (5, 6)]);
+            assert_eq!(spans(&build(r"\bb"), "ébé b"), vec![(2, 3), (6, 7)]);
+
+            let regex = build(r"(x:é)+b");
+            let m = regex.find("ééb").unwrap();
+            assert_eq!((m.start, m.end), (0, 5));
+            assert_eq!(m.named_group_str("ééb", "x"), Some("é"));
+            let m = regex.try_find_at("bééb", 1).unwrap().unwrap();
+            assert_eq!(m.as_str("bééb"), "ééb");
+        }
+    }
+
+    #[test]
+    fn test_escape_matches_literally() {
+        for text in [
+            r"a.b*c+d?e|f^g$h\i",
+            "(name:x)(@i:y)[a-z]{1,2}",
+            "tab\there # not a comment\n",
+            "a -> ok, @2",
+        ] {
+            let escaped = escape(text);
+            let regex = Regex::new(&escaped).unwrap();
+            let m = regex.find(text).unwrap();
+            assert_eq!((m.start, m.end), (0, text.len()), "{}", escaped);
+            let extended = RegexBuilder::new(&escaped).extended(true).build().unwrap();
+            assert!(extended.is_match(text), "{}", escaped);
+        }
+        assert_eq!(escape("abc_123"), "abc_123");
+    }
+
     #[test]
     fn test_regex_literal_match() {
         let regex = Regex::new("abc").unwrap();
@@ -770,4 +2352,537 @@ mod tests {
         let matches = regex.find_all("banana");
         assert_eq!(matches.len(), 3);
     }
+
+    #[test]
+    fn test_regex_find_all_empty_matches() {
+        let regex = Regex::new("x*").unwrap();
+        let matches = regex.find_all("ab");
+        assert_eq!(matches.len(), 3);
+        assert!(matches.iter().all(|m| m.start == m.end));
+
+        let spans: Vec<_> = regex.find_iter("éa").map(|m| m.start).collect();
+        assert_eq!(spans, vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn test_regex_counted_repeat_reuses_group() {
+        let regex = Regex::new("(a){2}(b)").unwrap();
+        let m = regex.find("aab").unwrap();
+        assert_eq!(m.group(1), Some((1, 2)));
+        assert_eq!(m.group(2), Some((2, 3)));
+        assert_eq!(m.group(3), None);
+    }
+
+    #[cfg(feature = "replace")]
+    #[test]
+    fn test_regex_replace() {
+        let regex = Regex::new("(\\w+)@(\\w+)").unwrap();
+        assert_eq!(regex.replace("a@b c@d", "\\2@\\1").unwrap(), "b@a c@d");
+        assert_eq!(regex.replace_all("a@b c@d", "\\2@\\1").unwrap(), "b@a d@c");
+    }
+
+    #[cfg(feature = "replace")]
+    #[test]
+    fn test_regex_replace_named() {
+        let regex = Regex::new("@(user:\\w+)").unwrap();
+        assert_eq!(
+            regex.replace_all("hi @bob", "[\\g{user}]").unwrap(),
+            "hi [bob]"
+        );
+    }
+
+    #[test]
+    fn test_match_named_groups() {
+        let regex = Regex::new("(key:\\w+)=(\\w+)").unwrap();
+        let m = regex.find("a=1").unwrap();
+        assert_eq!(m.named_group_str("a=1", "key"), Some("a"));
+        assert_eq!(m.named_group("missing"), None);
+    }
+
+    #[cfg(feature = "replace")]
+    #[test]
+    fn test_match_expand() {
+        let input = "2024-06";
+        let regex = Regex::new("(year:\\d+)-(\\d+)").unwrap();
+        let m = regex.find(input).unwrap();
+
+        let mut dst = String::from(">");
+        m.expand(input, "\\2/\\g{year} (\\G)", &mut dst).unwrap();
+        assert_eq!(dst, ">06/2024 (2024-06)");
+    }
+
+    #[cfg(feature = "replace")]
+    #[test]
+    fn test_regex_replace_no_expand() {
+        let regex = Regex::new("(\\d+)").unwrap();
+        assert_eq!(
+            regex
+                .replace_all("1 and 2", crate::NoExpand("\\1$1"))
+                .unwrap(),
+            "\\1$1 and \\1$1"
+        );
+    }
+
+    #[cfg(all(feature = "replace", feature = "std"))]
+    #[test]
+    fn test_regex_replace_all_to_writer() {
+        let regex = Regex::new("(\\d+)").unwrap();
+        let mut out = Vec::new();
+        regex.replace_all_to("1 and 22", "<\\1>", &mut out).unwrap();
+        assert_eq!(out, b"<1> and <22>");
+
+        let err = regex
+            .replace_all_to("1", "\\2", &mut Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(all(feature = "replace", feature = "std"))]
+    #[test]
+    fn test_regex_replacen_to_writer() {
+        let regex = Regex::new("(\\d+)").unwrap();
+        let mut out = Vec::new();
+        let count = regex.replacen_to("1 2 3", 2, "<\\1>", &mut out).unwrap();
+        assert_eq!((count, out.as_slice()), (2, &b"<1> <2> 3"[..]));
+
+        out.clear();
+        assert_eq!(regex.replacen_to("1 2 3", 0, "x", &mut out).unwrap(), 3);
+        assert_eq!(out, b"x x x");
+    }
+
+    #[cfg(all(feature = "replace", feature = "std"))]
+    #[test]
+    fn test_regex_replace_all_reader_to_writer() {
+        let regex = Regex::new("o+").unwrap();
+        let mut reader = std::io::Cursor::new("foo\nboo\nbar");
+        let mut out = Vec::new();
+        regex
+            .replace_all_reader_to(&mut reader, "0", &mut out)
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "f0\nb0\nbar");
+    }
+
+    #[cfg(feature = "replace")]
+    #[test]
+    fn test_regex_replace_missing_group_policy() {
+        let regex = Regex::new("(a)|(b)").unwrap();
+        assert_eq!(regex.replace_all("ab", "[\\1]").unwrap(), "[a][]");
+
+        let options =
+            crate::ReplacementOptions::default().missing_groups(crate::MissingGroupPolicy::Error);
+        let replacement = Replacement::parse_with("[\\1]", &options).unwrap();
+        assert_eq!(regex.replace("ab", &replacement).unwrap(), "[a]b");
+        assert_eq!(
+            regex.replace_all("ab", &replacement),
+            Err(ReplacementError::GroupDidNotParticipate("1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_regex_rejects_invalid_group_names() {
+        let err = Regex::new("(a:x)(a:y)").err().unwrap();
+        assert!(matches!(
+            err,
+            crate::error::RegexError::Parse(crate::error::ParseError::DuplicateGroupName { .. })
+        ));
+        assert_eq!(err.span(), Some(crate::error::Span::new(5, 10)));
+        match Regex::new("(word:x)\\g{wrod}") {
+            Err(err) => {
+                assert_eq!(err.suggestion(), Some("\\g{word}"));
+                assert_eq!(err.span(), Some(crate::error::Span::new(8, 16)));
+            }
+            Ok(_) => panic!("undefined backreference accepted"),
+        }
+        // Forward references are resolved against the whole pattern
+        assert!(Regex::new("\\g{a}?(a:x)").is_ok());
+    }
+
+    #[test]
+    fn test_regex_group_metadata() {
+        let regex = Regex::new("(year:\\d+)-(\\d+)(?:x)(day:\\d+)").unwrap();
+        assert_eq!(regex.group_count(), 3);
+        assert_eq!(
+            regex.capture_names().collect::<Vec<_>>(),
+            vec![None, Some("year"), None, Some("day")]
+        );
+        assert_eq!(regex.group_index("day"), Some(3));
+        assert_eq!(regex.group_index("month"), None);
+
+        let regex = Regex::new("abc").unwrap();
+        assert_eq!(regex.group_count(), 0);
+        assert_eq!(regex.capture_names().count(), 1);
+    }
+
+    #[test]
+    fn test_regex_group_spans() {
+        let pattern = "(\\d+)-(day:\\d+)";
+        let regex = Regex::new(pattern).unwrap();
+        let spans: Vec<_> = regex
+            .group_registry()
+            .groups()
+            .iter()
+            .map(|g| g.span.map(|s| &pattern[s.start..s.end]))
+            .collect();
+        assert_eq!(spans, vec![Some("(\\d+)"), Some("(day:\\d+)")]);
+    }
+
+    #[test]
+    fn test_regex_captures_read() {
+        let regex = Regex::new("(\\w+)=(\\d+)?").unwrap();
+        let mut locs = regex.capture_locations();
+        assert_eq!(locs.group_count(), 2);
+
+        let input = "a=1 b=";
+        assert_eq!(regex.captures_read(&mut locs, input), Some((0, 3)));
+        assert_eq!(locs.get(1), Some((0, 1)));
+        assert_eq!(locs.get(2), Some((2, 3)));
+
+        assert_eq!(regex.captures_read_at(&mut locs, input, 3), Some((4, 6)));
+        assert_eq!(locs.get(1), Some((4, 5)));
+        assert_eq!(locs.get(2), None);
+        assert_eq!(locs.get(3), None);
+
+        assert_eq!(regex.captures_read_at(&mut locs, input, 6), None);
+        assert_eq!(locs.get(0), None);
+    }
+
+    #[test]
+    fn test_regex_group_nesting() {
+        let regex = Regex::new("((a)(b))+(c)").unwrap();
+        let parents: Vec<_> = regex
+            .group_registry()
+            .groups()
+            .iter()
+            .map(|g| g.parent)
+            .collect();
+        assert_eq!(parents, vec![None, Some(1), Some(1), None]);
+    }
+
+    #[test]
+    fn test_regex_non_participating_groups() {
+        // A group that was skipped is None, one that matched nothing is Some
+        let regex = Regex::new("(x)?(a*)b").unwrap();
+        let m = regex.find("b").unwrap();
+        assert_eq!(m.group(1), None);
+        assert_eq!(m.group(2), Some((0, 0)));
+
+        // A backreference inside its own group does not see an empty capture
+        assert!(!Regex::new("(a\\1)").unwrap().is_match("aa"));
+    }
+
+    #[test]
+    fn test_regex_group_numbering() {
+        let ogex = Regex::new("(a)(n:b)\\g{-1}").unwrap();
+        assert!(ogex.is_match("aba"));
+        assert!(!ogex.is_match("abb"));
+        assert_eq!(ogex.group_registry().numbering(), GroupNumbering::Ogex);
+
+        let pcre = RegexBuilder::new("(a)(n:b)\\g{-1}")
+            .group_numbering(GroupNumbering::Pcre)
+            .build()
+            .unwrap();
+        assert!(pcre.is_match("abb"));
+        assert!(!pcre.is_match("aba"));
+        assert_eq!(pcre.group_registry().numbered_group_count(), 2);
+    }
+
+    #[test]
+    fn test_regex_find_at_and_iter() {
+        let regex = Regex::new("\\d+").unwrap();
+        assert_eq!(
+            regex.find_at("12 34", 1).map(|m| (m.start, m.end)),
+            Some((1, 2))
+        );
+        assert_eq!(regex.find_at("12 34", 6), None);
+
+        let spans: Vec<_> = regex.find_iter_at("12 34 56", 3).map(|m| m.start).collect();
+        assert_eq!(spans, vec![3, 6]);
+        assert_eq!(regex.find_iter("12 34 56").count(), 3);
+    }
+
+    #[test]
+    fn test_builder_mode_flags() {
+        let regex = RegexBuilder::new("^abc.$")
+            .case_insensitive(true)
+            .multiline(true)
+            .dotall(true)
+            .build()
+            .unwrap();
+        assert!(regex.is_match("x\nABC\n\n"));
+        assert!(!Regex::new("^abc.$").unwrap().is_match("x\nABC\n\n"));
+    }
+
+    #[test]
+    fn test_multiline_start_anchor_ignores_search_position() {
+        let regex = RegexBuilder::new("^\\w+$").multiline(true).build().unwrap();
+        let input = "ab\n c\nd";
+        let found: Vec<_> = regex.find_iter(input).map(|m| m.as_str(input)).collect();
+        assert_eq!(found, vec!["ab", "d"]);
+        assert_eq!(
+            regex.find_at(input, 1),
+            Some(regex.find_at(input, 6).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_builder_extended() {
+        let regex = RegexBuilder::new("(y: \\d{4} )  # year\n - [ ]?(m:\\d+)")
+            .extended(true)
+            .build()
+            .unwrap();
+        let m = regex.find("on 2024- 5").unwrap();
+        assert_eq!(m.named_group_str("on 2024- 5", "y"), Some("2024"));
+        assert_eq!(m.named_group_str("on 2024- 5", "m"), Some("5"));
+    }
+
+    #[test]
+    fn test_builder_step_limit() {
+        let regex = RegexBuilder::new("a+b").step_limit(10).build().unwrap();
+        assert!(regex.try_find("ab").unwrap().is_some());
+        assert!(regex.try_find(&"a".repeat(100)).is_err());
+        assert!(regex.find(&"a".repeat(100)).is_none());
+
+        let regex = RegexBuilder::new("a+b").build().unwrap();
+        assert!(regex.try_find(&"a".repeat(100)).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_builder_haystack_limit() {
+        let regex = RegexBuilder::new("b").max_haystack_len(4).build().unwrap();
+        assert_eq!(regex.try_find("aab").unwrap().map(|m| m.start), Some(2));
+        assert_eq!(
+            regex.try_find_at("aaab", 3).unwrap().map(|m| m.start),
+            Some(3)
+        );
+        assert!(matches!(
+            regex.try_find("aaaab"),
+            Err(crate::error::RegexError::Runtime(
+                RuntimeError::HaystackTooLarge { len: 5, limit: 4 }
+            ))
+        ));
+        assert!(regex.find("aaaab").is_none());
+        assert!(regex.find_bytes_at(b"aaaab", 0).is_err());
+    }
+
+    #[test]
+    fn test_builder_timeout() {
+        let input = "a".repeat(500);
+        let regex = RegexBuilder::new("a*b")
+            .timeout(Duration::ZERO)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            regex.try_find(&input),
+            Err(crate::error::RegexError::Runtime(
+                RuntimeError::TimeoutExceeded
+            ))
+        ));
+        assert!(regex.find(&input).is_none());
+        // Searches too short to read the clock finish regardless
+        assert!(regex.try_find("aab").unwrap().is_some());
+
+        let regex = RegexBuilder::new("a*b")
+            .backend(Backend::Vm)
+            .timeout(Duration::ZERO)
+            .build()
+            .unwrap();
+        assert!(regex.try_find(&input).is_err());
+
+        let regex = RegexBuilder::new("a*b")
+            .timeout(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        assert!(regex.try_find(&input).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_step_limit_after_first_match() {
+        let regex = RegexBuilder::new("(a|a)*b|x")
+            .step_limit(200)
+            .build()
+            .unwrap();
+        let input = format!("x {} x", "a".repeat(30));
+        let limit = RuntimeError::StepLimitExceeded { steps: 200 };
+
+        let found: Vec<_> = regex.try_find_iter(&input).collect();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].as_ref().map(|m| m.start).ok(), Some(0));
+        assert!(matches!(&found[1], Err(crate::error::RegexError::Runtime(e)) if *e == limit));
+        assert_eq!(regex.find_iter(&input).count(), 1);
+
+        let pieces: Vec<_> = regex.try_split(&input).collect();
+        assert_eq!(pieces.len(), 2);
+        assert!(pieces[1].is_err());
+
+        #[cfg(feature = "replace")]
+        {
+            let expected = Err(ReplacementError::Runtime(limit));
+            assert_eq!(regex.replace_all(&input, "y"), expected);
+            assert_eq!(regex.replacen(&input, 2, "y"), expected);
+            assert_eq!(
+                regex.replacen(&input, 1, "y").unwrap(),
+                input.replacen('x', "y", 1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_regex_try_find_at_rejects_bad_offsets() {
+        let regex = Regex::new("b").unwrap();
+        assert_eq!(
+            regex.try_find_at("éb", 1).err().map(|e| e.code()),
+            Some(crate::error::ErrorCode::InvalidUtf8Offset)
+        );
+        assert!(regex.try_find_at("éb", 4).is_err());
+        assert_eq!(
+            regex.try_find_at("éb", 2).unwrap().map(|m| m.start),
+            Some(2)
+        );
+        assert!(regex.find_at("éb", 1).is_none());
+    }
+
+    #[test]
+    fn test_regex_split() {
+        let regex = Regex::new("-+").unwrap();
+        let pieces: Vec<_> = regex.split("a-b--c").collect();
+        assert_eq!(pieces, vec!["a", "b", "c"]);
+        let pieces: Vec<_> = regex.split("-a-").collect();
+        assert_eq!(pieces, vec!["", "a", ""]);
+        assert_eq!(regex.split("").collect::<Vec<_>>(), vec![""]);
+
+        let regex = Regex::new("x*").unwrap();
+        let pieces: Vec<_> = regex.split("ab").collect();
+        assert_eq!(pieces, vec!["", "a", "b", ""]);
+    }
+
+    #[test]
+    fn test_regex_split_with_captures() {
+        // Without groups it splits as `split` does
+        let regex = Regex::new("-+").unwrap();
+        let pieces: Vec<_> = regex.split_with_captures("a-b--c").collect();
+        assert_eq!(pieces, vec![Some("a"), Some("b"), Some("c")]);
+
+        let regex = Regex::new("(sep:-)(=)?").unwrap();
+        let pieces: Vec<_> = regex.split_with_captures("-a-=b").collect();
+        assert_eq!(
+            pieces,
+            vec![
+                Some(""),
+                Some("-"),
+                None,
+                Some("a"),
+                Some("-"),
+                Some("="),
+                Some("b")
+            ]
+        );
+        assert_eq!(
+            regex.split_with_captures("").collect::<Vec<_>>(),
+            vec![Some("")]
+        );
+    }
+
+    #[test]
+    fn test_regex_split_inclusive() {
+        let regex = Regex::new(";").unwrap();
+        let pieces: Vec<_> = regex.split_inclusive("a;b;;c").collect();
+        assert_eq!(pieces, vec!["a;", "b;", ";", "c"]);
+        let pieces: Vec<_> = regex.split_inclusive("a;b;").collect();
+        assert_eq!(pieces, vec!["a;", "b;"]);
+        assert_eq!(regex.split_inclusive("").count(), 0);
+        assert_eq!(regex.split_inclusive("ab").collect::<Vec<_>>(), vec!["ab"]);
+        // Agrees with the standard library on a literal delimiter
+        let input = "x;;y;z;";
+        assert!(regex.split_inclusive(input).eq(input.split_inclusive(';')));
+    }
+
+    #[test]
+    fn test_match_into_owned() {
+        let regex = Regex::new(r"(unit:[a-z]+)\:(n:\d+)(frac:\.\d+)?").unwrap();
+        let input = "é kg:42 m:7.5";
+        let m = regex.find(input).unwrap();
+        let owned = m.clone().into_owned(input);
+        assert_eq!((owned.start, owned.end), (m.start, m.end));
+        assert_eq!(owned.as_str(), "kg:42");
+        assert_eq!(owned.named_group_str("unit"), Some("kg"));
+        assert_eq!(owned.named_group("unit"), m.named_group("unit"));
+        assert_eq!(owned.group_str(2), Some("42"));
+        assert_eq!(owned.group(3), None);
+        assert_eq!(owned.named_group_str("frac"), None);
+        assert_eq!(owned.group_str(9), None);
+
+        let owned: Vec<OwnedMatch> = regex
+            .find_iter(input)
+            .map(|m| m.into_owned(input))
+            .collect();
+        assert_eq!(owned[1].named_group_str("frac"), Some(".5"));
+    }
+
+    #[test]
+    fn test_regex_is_shared_between_threads() {
+        let regex = Regex::new(r"(n:\d+)").unwrap();
+        let inputs = ["a 1", "bb 22", "ccc 333", "dddd 4444"];
+        std::thread::scope(|scope| {
+            for (i, input) in inputs.iter().enumerate() {
+                let regex = &regex;
+                scope.spawn(move || {
+                    for _ in 0..100 {
+                        let m = regex.find(input).unwrap();
+                        assert_eq!(m.named_group_str(input, "n").unwrap().len(), i + 1);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_regex_find_at_sees_preceding_text() {
+        let regex = Regex::new("^a").unwrap();
+        assert!(regex.find_at("aa", 1).is_none());
+    }
+
+    #[cfg(feature = "replace")]
+    #[test]
+    fn test_empty_matches_in_non_ascii_text() {
+        let regex = Regex::new("é*").unwrap();
+        let input = "aééb日";
+        let spans: Vec<(usize, usize)> = regex.find_iter(input).map(|m| (m.start, m.end)).collect();
+        assert_eq!(spans, vec![(0, 0), (1, 5), (5, 5), (6, 6), (9, 9)]);
+        assert_eq!(
+            regex.split(input).collect::<Vec<_>>(),
+            vec!["", "a", "", "b", "日", ""]
+        );
+        assert_eq!(regex.replace_all(input, "-").unwrap(), "-a--b-日-");
+
+        // An ASCII pattern still steps over whole characters
+        let regex = Regex::new("x?").unwrap();
+        assert_eq!(regex.find_iter("日本").count(), 3);
+        assert_eq!(regex.replacen("日本", 2, "|").unwrap(), "|日|本");
+    }
+
+    #[test]
+    fn test_regex_replacen() {
+        let regex = Regex::new("a").unwrap();
+        assert_eq!(regex.replacen("aaaa", 2, "b").unwrap(), "bbaa");
+        assert_eq!(regex.replacen("aaaa", 0, "b").unwrap(), "bbbb");
+        assert_eq!(regex.replacen_at("aaaa", 1, 2, "b").unwrap(), "abba");
+        assert_eq!(regex.replacen_at("aaaa", 4, 0, "b").unwrap(), "aaaa");
+    }
+
+    #[cfg(feature = "replace")]
+    #[test]
+    fn test_regex_replace_rejects_unknown_groups() {
+        let regex = Regex::new("(a)(b)(c)").unwrap();
+        assert_eq!(
+            regex.replace_all("abc", "\\9"),
+            Err(ReplacementError::GroupOutOfRange {
+                index: 9,
+                group_count: 3
+            })
+        );
+        assert_eq!(
+            regex.replace("abc", "\\g{typo}"),
+            Err(ReplacementError::UndefinedGroupName("typo".to_string()))
+        );
+    }
 }
@@ -3,17 +3,15 @@
 //! This module provides comprehensive error handling.
 //! Errors are categorized by their source: lexing, parsing, compilation, or runtime.
 
-use std::fmt;
-
-#[cfg(feature = "std")]
-use thiserror::Error;
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
 
 /// The main error type for the regex engine
 #[derive(Debug)]
-#[cfg_attr(feature = "std", derive(thiserror::Error))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum RegexError {
     /// Errors that occur during lexing/tokenization
-    #[cfg_attr(feature = "std", error("lexer error at position {position}: {kind}"))]
     Lexer {
         /// Position in the input where the error occurred
         position: usize,
@@ -22,16 +20,13 @@ pub enum RegexError {
     },
 
     /// Errors that occur during parsing
-    #[cfg_attr(feature = "std", error("parse error: {0}"))]
-    Parse(#[cfg_attr(feature = "std", from)] ParseError),
+    Parse(ParseError),
 
     /// Errors that occur during compilation (AST to NFA/DFA)
-    #[cfg_attr(feature = "std", error("compilation error: {0}"))]
     Compile(String),
 
     /// Errors that occur during pattern matching
-    #[cfg_attr(feature = "std", error("runtime error: {0}"))]
-    Runtime(String),
+    Runtime(RuntimeError),
 }
 
 impl Clone for RegexError {
@@ -43,26 +38,78 @@ impl Clone for RegexError {
             },
             RegexError::Parse(err) => RegexError::Parse(err.clone()),
             RegexError::Compile(msg) => RegexError::Compile(msg.clone()),
-            RegexError::Runtime(msg) => RegexError::Runtime(msg.clone()),
+            RegexError::Runtime(err) => RegexError::Runtime(err.clone()),
         }
     }
 }
 
+impl RegexError {
+    /// The stable code identifying this kind of error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RegexError::Lexer { kind, .. } => kind.code(),
+            RegexError::Parse(err) => err.code(),
+            RegexError::Compile(_) => ErrorCode::Compile,
+            RegexError::Runtime(err) => err.code(),
+        }
+    }
+
+    /// Description of the error without its code, category or position
+    pub fn message(&self) -> String {
+        match self {
+            RegexError::Lexer { kind, .. } => kind.to_string(),
+            RegexError::Parse(err) => err.to_string(),
+            RegexError::Compile(msg) => msg.clone(),
+            RegexError::Runtime(err) => err.to_string(),
+        }
+    }
+
+    /// What the user probably meant to write, if there is a likely fix
+    pub fn suggestion(&self) -> Option<&str> {
+        match self {
+            RegexError::Parse(err) => err.suggestion(),
+            _ => None,
+        }
+    }
+
+    /// Location of the error in the pattern, if known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            RegexError::Lexer { position, .. } => Some(Span::single(*position)),
+            RegexError::Parse(err) => err.span(),
+            RegexError::Compile(_) | RegexError::Runtime(_) => None,
+        }
+    }
+
+    /// A copy of the error with its location attached, if it has one
+    pub fn spanned(&self) -> Option<SpannedError> {
+        self.span()
+            .map(|span| SpannedError::new(self.clone(), span))
+    }
+}
+
 impl From<ParseError> for RegexError {
     fn from(err: ParseError) -> Self {
         RegexError::Parse(err)
     }
 }
 
+impl From<RuntimeError> for RegexError {
+    fn from(err: RuntimeError) -> Self {
+        RegexError::Runtime(err)
+    }
+}
+
 impl fmt::Display for RegexError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] ", self.code())?;
         match self {
             RegexError::Lexer { position, kind } => {
                 write!(f, "lexer error at position {}: {}", position, kind)
             }
             RegexError::Parse(err) => write!(f, "parse error: {}", err),
             RegexError::Compile(msg) => write!(f, "compilation error: {}", msg),
-            RegexError::Runtime(msg) => write!(f, "runtime error: {}", msg),
+            RegexError::Runtime(err) => write!(f, "runtime error: {}", err),
         }
     }
 }
@@ -72,6 +119,7 @@ impl std::error::Error for RegexError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             RegexError::Parse(err) => Some(err),
+            RegexError::Runtime(err) => Some(err),
             _ => None,
         }
     }
@@ -79,6 +127,7 @@ impl std::error::Error for RegexError {
 
 /// Specific kinds of lexer errors
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum LexerErrorKind {
     /// Encountered an unexpected character
     UnexpectedChar(char),
@@ -96,6 +145,19 @@ pub enum LexerErrorKind {
     InvalidGroupName(String),
 }
 
+impl LexerErrorKind {
+    /// The stable code identifying this kind of error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            LexerErrorKind::UnexpectedChar(_) => ErrorCode::UnexpectedChar,
+            LexerErrorKind::UnclosedCharacterClass => ErrorCode::UnclosedCharacterClass,
+            LexerErrorKind::InvalidEscape(_) => ErrorCode::InvalidEscape,
+            LexerErrorKind::UnclosedGroup => ErrorCode::UnclosedGroup,
+            LexerErrorKind::InvalidGroupName(_) => ErrorCode::InvalidGroupName,
+        }
+    }
+}
+
 impl fmt::Display for LexerErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -118,6 +180,7 @@ impl fmt::Display for LexerErrorKind {
 
 /// Errors that occur during parsing
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ParseError {
     /// Unexpected token encountered
     UnexpectedToken {
@@ -127,6 +190,8 @@ pub enum ParseError {
         found: String,
         /// Location in the source (optional)
         span: Option<Span>,
+        /// What the user probably meant to write (optional)
+        suggestion: Option<String>,
     },
 
     /// Unexpected end of input
@@ -136,13 +201,83 @@ pub enum ParseError {
     },
 
     /// Duplicate group name
-    DuplicateGroupName(String),
+    DuplicateGroupName {
+        /// The repeated name
+        name: String,
+        /// Location in the source (optional)
+        span: Option<Span>,
+    },
 
     /// Undefined backreference
-    UndefinedBackreference(String),
+    UndefinedBackreference {
+        /// The name that no group defines
+        name: String,
+        /// Location in the source (optional)
+        span: Option<Span>,
+        /// What the user probably meant to write (optional)
+        suggestion: Option<String>,
+    },
 
     /// Invalid quantifier
-    InvalidQuantifier(String),
+    InvalidQuantifier {
+        /// What is wrong with the quantifier
+        message: String,
+        /// Location in the source (optional)
+        span: Option<Span>,
+    },
+
+    /// Reference to a fragment the pattern library does not define
+    UndefinedFragment {
+        /// The name that no fragment has
+        name: String,
+        /// Location in the source (optional)
+        span: Option<Span>,
+    },
+}
+
+impl ParseError {
+    /// The stable code identifying this kind of error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            ParseError::UnexpectedToken { .. } => ErrorCode::UnexpectedToken,
+            ParseError::UnexpectedEof { .. } => ErrorCode::UnexpectedEof,
+            ParseError::DuplicateGroupName { .. } => ErrorCode::DuplicateGroupName,
+            ParseError::UndefinedBackreference { .. } => ErrorCode::UndefinedBackreference,
+            ParseError::InvalidQuantifier { .. } => ErrorCode::InvalidQuantifier,
+            ParseError::UndefinedFragment { .. } => ErrorCode::UndefinedFragment,
+        }
+    }
+
+    /// What the user probably meant to write, if there is a likely fix
+    pub fn suggestion(&self) -> Option<&str> {
+        match self {
+            ParseError::UnexpectedToken { suggestion, .. }
+            | ParseError::UndefinedBackreference { suggestion, .. } => suggestion.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Attach a suggestion to errors that can carry one
+    pub fn with_suggestion(mut self, text: impl Into<String>) -> Self {
+        if let ParseError::UnexpectedToken { suggestion, .. }
+        | ParseError::UndefinedBackreference { suggestion, .. } = &mut self
+        {
+            *suggestion = Some(text.into());
+        }
+        self
+    }
+
+    /// Location of the error in the source, if known
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ParseError::UnexpectedToken { span, .. }
+            | ParseError::UnexpectedEof { span }
+            | ParseError::DuplicateGroupName { span, .. }
+            | ParseError::UndefinedBackreference { span, .. }
+            | ParseError::InvalidQuantifier { span, .. }
+            | ParseError::UndefinedFragment { span, .. } => *span,
+        }
+    }
 }
 
 impl fmt::Display for ParseError {
@@ -151,27 +286,281 @@ impl fmt::Display for ParseError {
             ParseError::UnexpectedToken {
                 expected, found, ..
             } => {
-                write!(f, "expected {}, found {}", expected, found)
+                write!(f, "expected {}, found {}", expected, found)?;
+            }
+            ParseError::UnexpectedEof { .. } => write!(f, "unexpected end of input")?,
+            ParseError::DuplicateGroupName { name, .. } => {
+                write!(f, "duplicate group name '{}'", name)?;
             }
-            ParseError::UnexpectedEof { .. } => write!(f, "unexpected end of input"),
-            ParseError::DuplicateGroupName(name) => {
-                write!(f, "duplicate group name '{}'", name)
+            ParseError::UndefinedBackreference { name, .. } => {
+                write!(f, "undefined backreference '{}'", name)?;
             }
-            ParseError::UndefinedBackreference(name) => {
-                write!(f, "undefined backreference '{}'", name)
+            ParseError::InvalidQuantifier { message, .. } => {
+                write!(f, "invalid quantifier: {}", message)?;
             }
-            ParseError::InvalidQuantifier(msg) => {
-                write!(f, "invalid quantifier: {}", msg)
+            ParseError::UndefinedFragment { name, .. } => {
+                write!(f, "undefined fragment '{}'", name)?;
             }
         }
+        if let Some(suggestion) = self.suggestion() {
+            write!(f, "; did you mean `{}`?", suggestion)?;
+        }
+        Ok(())
     }
 }
 
 #[cfg(feature = "std")]
 impl std::error::Error for ParseError {}
 
+/// Errors that stop a match before it completes
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum RuntimeError {
+    /// The match took more engine steps than the configured limit
+    StepLimitExceeded {
+        /// The limit that was reached
+        steps: usize,
+    },
+
+    /// The match ran longer than the configured timeout
+    TimeoutExceeded,
+
+    /// The input is longer than the limit set with
+    /// [`RegexBuilder::max_haystack_len`](crate::RegexBuilder::max_haystack_len)
+    HaystackTooLarge {
+        /// Length of the input in bytes
+        len: usize,
+        /// The largest accepted length in bytes
+        limit: usize,
+    },
+
+    /// A byte offset that is out of bounds or not on a character boundary
+    InvalidUtf8Offset {
+        /// The offending offset
+        offset: usize,
+    },
+}
+
+impl RuntimeError {
+    /// The stable code identifying this kind of error
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RuntimeError::StepLimitExceeded { .. } => ErrorCode::StepLimitExceeded,
+            RuntimeError::TimeoutExceeded => ErrorCode::TimeoutExceeded,
+            RuntimeError::HaystackTooLarge { .. } => ErrorCode::HaystackTooLarge,
+            RuntimeError::InvalidUtf8Offset { .. } => ErrorCode::InvalidUtf8Offset,
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::StepLimitExceeded { steps } => {
+                write!(f, "step limit of {} exceeded", steps)
+            }
+            RuntimeError::TimeoutExceeded => write!(f, "timeout exceeded"),
+            RuntimeError::HaystackTooLarge { len, limit } => {
+                write!(
+                    f,
+                    "input of {} bytes exceeds the limit of {} bytes",
+                    len, limit
+                )
+            }
+            RuntimeError::InvalidUtf8Offset { offset } => {
+                write!(f, "offset {} is not on a character boundary", offset)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RuntimeError {}
+
+/// Stage of the engine an error comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ErrorCategory {
+    /// Tokenizing the pattern
+    Lexer,
+    /// Building the AST
+    Parse,
+    /// Compiling the AST into an automaton
+    Compile,
+    /// Matching input
+    Runtime,
+    /// Static analysis of a pattern that compiles but is probably wrong
+    Analysis,
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ErrorCategory::Lexer => "lexer",
+            ErrorCategory::Parse => "parse",
+            ErrorCategory::Compile => "compile",
+            ErrorCategory::Runtime => "runtime",
+            ErrorCategory::Analysis => "analysis",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A stable identifier for each kind of error
+///
+/// Codes never change meaning once published, so tools can match on
+/// [`ErrorCode::as_str`] (`"E004"`) or [`ErrorCode::name`]
+/// (`"UNCLOSED_GROUP"`) instead of on message text. The hundreds digit is
+/// the [`ErrorCategory`]: `E0xx` lexer, `E1xx` parse, `E2xx` compile and
+/// `E3xx` runtime. `Wxxx` codes are warnings reported by
+/// [`Regex::validate`](crate::Regex::validate).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// `E001`: a character that cannot appear here
+    UnexpectedChar,
+    /// `E002`: a character class without its closing `]`
+    UnclosedCharacterClass,
+    /// `E003`: an unknown escape sequence
+    InvalidEscape,
+    /// `E004`: a group without its closing `)`
+    UnclosedGroup,
+    /// `E005`: a group name that is not a valid identifier
+    InvalidGroupName,
+    /// `E101`: a token that does not fit the grammar here
+    UnexpectedToken,
+    /// `E102`: the pattern ended in the middle of an expression
+    UnexpectedEof,
+    /// `E103`: two groups with the same name
+    DuplicateGroupName,
+    /// `E104`: a backreference to a group that does not exist
+    UndefinedBackreference,
+    /// `E105`: a malformed or contradictory quantifier
+    InvalidQuantifier,
+    /// `E106`: a `(@use:name)` reference to a fragment that is not defined
+    UndefinedFragment,
+    /// `E201`: the pattern parsed but could not be compiled
+    Compile,
+    /// `E301`: the match exceeded its step limit
+    StepLimitExceeded,
+    /// `E302`: the match exceeded its time limit
+    TimeoutExceeded,
+    /// `E303`: the input exceeded the size limit
+    HaystackTooLarge,
+    /// `E304`: a byte offset that is not on a character boundary
+    InvalidUtf8Offset,
+    /// `W001`: a numbered backreference to a group the pattern lacks, which
+    /// never matches
+    BackreferenceOutOfRange,
+    /// `W002`: a quantifier that repeats zero times
+    EmptyRepetition,
+    /// `W003`: a quantifier on an assertion that matches no characters
+    RepeatedAssertion,
+    /// `W004`: an unbounded quantifier nested inside another, which can
+    /// backtrack exponentially in backtracking engines
+    NestedQuantifier,
+}
+
+impl ErrorCode {
+    /// The short code, such as `"E004"`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedChar => "E001",
+            ErrorCode::UnclosedCharacterClass => "E002",
+            ErrorCode::InvalidEscape => "E003",
+            ErrorCode::UnclosedGroup => "E004",
+            ErrorCode::InvalidGroupName => "E005",
+            ErrorCode::UnexpectedToken => "E101",
+            ErrorCode::UnexpectedEof => "E102",
+            ErrorCode::DuplicateGroupName => "E103",
+            ErrorCode::UndefinedBackreference => "E104",
+            ErrorCode::InvalidQuantifier => "E105",
+            ErrorCode::UndefinedFragment => "E106",
+            ErrorCode::Compile => "E201",
+            ErrorCode::StepLimitExceeded => "E301",
+            ErrorCode::TimeoutExceeded => "E302",
+            ErrorCode::HaystackTooLarge => "E303",
+            ErrorCode::InvalidUtf8Offset => "E304",
+            ErrorCode::BackreferenceOutOfRange => "W001",
+            ErrorCode::EmptyRepetition => "W002",
+            ErrorCode::RepeatedAssertion => "W003",
+            ErrorCode::NestedQuantifier => "W004",
+        }
+    }
+
+    /// The symbolic name, such as `"UNCLOSED_GROUP"`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedChar => "UNEXPECTED_CHAR",
+            ErrorCode::UnclosedCharacterClass => "UNCLOSED_CHARACTER_CLASS",
+            ErrorCode::InvalidEscape => "INVALID_ESCAPE",
+            ErrorCode::UnclosedGroup => "UNCLOSED_GROUP",
+            ErrorCode::InvalidGroupName => "INVALID_GROUP_NAME",
+            ErrorCode::UnexpectedToken => "UNEXPECTED_TOKEN",
+            ErrorCode::UnexpectedEof => "UNEXPECTED_EOF",
+            ErrorCode::DuplicateGroupName => "DUPLICATE_GROUP_NAME",
+            ErrorCode::UndefinedBackreference => "UNDEFINED_BACKREFERENCE",
+            ErrorCode::InvalidQuantifier => "INVALID_QUANTIFIER",
+            ErrorCode::UndefinedFragment => "UNDEFINED_FRAGMENT",
+            ErrorCode::Compile => "COMPILE",
+            ErrorCode::StepLimitExceeded => "STEP_LIMIT_EXCEEDED",
+            ErrorCode::TimeoutExceeded => "TIMEOUT_EXCEEDED",
+            ErrorCode::HaystackTooLarge => "HAYSTACK_TOO_LARGE",
+            ErrorCode::InvalidUtf8Offset => "INVALID_UTF8_OFFSET",
+            ErrorCode::BackreferenceOutOfRange => "BACKREFERENCE_OUT_OF_RANGE",
+            ErrorCode::EmptyRepetition => "EMPTY_REPETITION",
+            ErrorCode::RepeatedAssertion => "REPEATED_ASSERTION",
+            ErrorCode::NestedQuantifier => "NESTED_QUANTIFIER",
+        }
+    }
+
+    /// The stage of the engine this error comes from
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ErrorCode::UnexpectedChar
+            | ErrorCode::UnclosedCharacterClass
+            | ErrorCode::InvalidEscape
+            | ErrorCode::UnclosedGroup
+            | ErrorCode::InvalidGroupName => ErrorCategory::Lexer,
+            ErrorCode::UnexpectedToken
+            | ErrorCode::UnexpectedEof
+            | ErrorCode::DuplicateGroupName
+            | ErrorCode::UndefinedBackreference
+            | ErrorCode::InvalidQuantifier
+            | ErrorCode::UndefinedFragment => ErrorCategory::Parse,
+            ErrorCode::Compile => ErrorCategory::Compile,
+            ErrorCode::StepLimitExceeded
+            | ErrorCode::TimeoutExceeded
+            | ErrorCode::HaystackTooLarge
+            | ErrorCode::InvalidUtf8Offset => ErrorCategory::Runtime,
+            ErrorCode::BackreferenceOutOfRange
+            | ErrorCode::EmptyRepetition
+            | ErrorCode::RepeatedAssertion
+            | ErrorCode::NestedQuantifier => ErrorCategory::Analysis,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Serialized as the stable code string, such as `"E101"`
+#[cfg(feature = "serde")]
+impl serde::Serialize for ErrorCode {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 /// A span representing a location in the source code
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Span {
     /// Start position (inclusive)
     pub start: usize,
@@ -206,6 +595,7 @@ impl Span {
 
 /// An error with associated source location
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SpannedError {
     /// The underlying error
     pub error: RegexError,
@@ -227,10 +617,30 @@ impl SpannedError {
     pub fn new(error: RegexError, span: Span) -> Self {
         SpannedError { error, span }
     }
+
+    /// Render the error under the source it came from, with carets marking
+    /// the span
+    ///
+    /// ```
+    /// use ogex::Regex;
+    ///
+    /// let err = Regex::new("ab)").err().unwrap().spanned().unwrap();
+    /// assert_eq!(err.render("ab)"), "ab)\n  ^ expected EOF, found `)`");
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let message = self.error.message();
+        format!(
+            "{}\n{}{} {}",
+            source,
+            " ".repeat(self.span.start),
+            "^".repeat(self.span.len().max(1)),
+            message
+        )
+    }
 }
 
 /// Result type alias for regex operations
-pub type Result<T> = std::result::Result<T, RegexError>;
+pub type Result<T> = core::result::Result<T, RegexError>;
 
 #[cfg(test)]
 mod tests {
@@ -244,7 +654,7 @@ mod tests {
         };
         assert_eq!(
             err.to_string(),
-            "lexer error at position 5: unexpected character '!'"
+            "[E001] lexer error at position 5: unexpected character '!'"
         );
     }
 
@@ -254,13 +664,17 @@ mod tests {
             expected: "`)`".to_string(),
             found: "EOF".to_string(),
             span: None,
+            suggestion: None,
         };
         assert_eq!(err.to_string(), "expected `)`, found EOF");
     }
 
     #[test]
     fn test_parse_error_duplicate_group() {
-        let err = ParseError::DuplicateGroupName("name".to_string());
+        let err = ParseError::DuplicateGroupName {
+            name: "name".to_string(),
+            span: None,
+        };
         assert_eq!(err.to_string(), "duplicate group name 'name'");
     }
 
@@ -270,7 +684,7 @@ mod tests {
         let regex_err: RegexError = parse_err.into();
         assert_eq!(
             regex_err.to_string(),
-            "parse error: unexpected end of input"
+            "[E102] parse error: unexpected end of input"
         );
     }
 
@@ -297,4 +711,88 @@ mod tests {
         assert!(spanned.to_string().contains("unexpected end of input"));
         assert!(spanned.to_string().contains("42"));
     }
+
+    #[test]
+    fn test_spanned_error_render() {
+        let error = RegexError::Parse(ParseError::UnexpectedToken {
+            expected: "`)`".to_string(),
+            found: "EOF".to_string(),
+            span: Some(Span::new(4, 4)),
+            suggestion: None,
+        });
+        let spanned = error.spanned().unwrap();
+        assert_eq!(
+            spanned.render("(abc"),
+            "(abc\n    ^ expected `)`, found EOF"
+        );
+
+        let error = RegexError::Lexer {
+            position: 1,
+            kind: LexerErrorKind::UnexpectedChar('!'),
+        };
+        assert_eq!(
+            error.spanned().unwrap().render("a!"),
+            "a!\n ^ unexpected character '!'"
+        );
+
+        assert!(RegexError::Compile("x".to_string()).spanned().is_none());
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let err = RegexError::Parse(ParseError::InvalidQuantifier {
+            message: "{3,1}".to_string(),
+            span: None,
+        });
+        assert_eq!(err.code(), ErrorCode::InvalidQuantifier);
+        assert_eq!(err.code().as_str(), "E105");
+        assert_eq!(err.code().name(), "INVALID_QUANTIFIER");
+        assert_eq!(err.code().category(), ErrorCategory::Parse);
+
+        let err = RegexError::Compile("duplicate group name: a".to_string());
+        assert_eq!(
+            err.to_string(),
+            "[E201] compilation error: duplicate group name: a"
+        );
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        let err = RegexError::from(RuntimeError::StepLimitExceeded { steps: 1000 });
+        assert_eq!(err.code(), ErrorCode::StepLimitExceeded);
+        assert_eq!(err.code().category(), ErrorCategory::Runtime);
+        assert_eq!(
+            err.to_string(),
+            "[E301] runtime error: step limit of 1000 exceeded"
+        );
+
+        let err = RegexError::from(RuntimeError::InvalidUtf8Offset { offset: 3 });
+        assert_eq!(err.code().name(), "INVALID_UTF8_OFFSET");
+        assert_eq!(err.message(), "offset 3 is not on a character boundary");
+        assert!(err.span().is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_errors() {
+        let err = RegexError::Parse(ParseError::DuplicateGroupName {
+            name: "a".to_string(),
+            span: Some(Span::new(5, 10)),
+        });
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "Parse": {
+                    "DuplicateGroupName": {
+                        "name": "a",
+                        "span": { "start": 5, "end": 10 },
+                    }
+                }
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(ErrorCode::UnexpectedEof).unwrap(),
+            serde_json::json!("E102")
+        );
+    }
 }
@@ -0,0 +1,207 @@
+//! Parsing captures into typed values
+//!
+//! A type that implements [`FromCaptures`] pairs a pattern with a way to
+//! build itself from a match, parsing each group's text into a field. The
+//! `ogex-derive` crate derives it for structs, checking the pattern and its
+//! group names at compile time:
+//!
+//! ```text
+//! #[derive(OgexCaptures)]
+//! #[ogex(pattern = r"(ip:\d+(@?:\.\d+){3}) (status:\d{3})")]
+//! struct LogLine {
+//!     ip: String,
+//!     status: u16,
+//! }
+//! ```
+//!
+//! The functions in this module do the parsing for the generated code, and
+//! serve just as well in a hand-written impl:
+//!
+//! ```
+//! use std::sync::OnceLock;
+//!
+//! use ogex::extract::{ExtractError, FromCaptures, Group, field};
+//! use ogex::{Match, Regex};
+//!
+//! struct LogLine {
+//!     ip: String,
+//!     status: u16,
+//! }
+//!
+//! impl FromCaptures for LogLine {
+//!     const PATTERN: &'static str = r"(ip:\d+(@?:\.\d+){3}) (status:\d{3})";
+//!
+//!     fn regex() -> &'static Regex {
+//!         static REGEX: OnceLock<Regex> = OnceLock::new();
+//!         REGEX.get_or_init(|| Regex::new(Self::PATTERN).unwrap())
+//!     }
+//!
+//!     fn from_match(input: &str, m: &Match) -> Result<Self, ExtractError> {
+//!         Ok(LogLine {
+//!             ip: field(input, m, Group::Name("ip"), "ip")?,
+//!             status: field(input, m, Group::Name("status"), "status")?,
+//!         })
+//!     }
+//! }
+//!
+//! let line = LogLine::find("GET 10.0.0.1 404").unwrap().unwrap();
+//! assert_eq!((line.ip.as_str(), line.status), ("10.0.0.1", 404));
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::engine::{Match, Regex};
+
+/// Why a match could not be turned into a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractError {
+    /// The group for a required field did not take part in the match
+    Missing {
+        /// The field without a value
+        field: &'static str,
+    },
+    /// A group's text did not parse as its field's type
+    Parse {
+        /// The field being parsed
+        field: &'static str,
+        /// The group's text
+        text: String,
+        /// Why it did not parse
+        message: String,
+    },
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::Missing { field } => {
+                write!(f, "no text was captured for field `{}`", field)
+            }
+            ExtractError::Parse {
+                field,
+                text,
+                message,
+            } => write!(
+                f,
+                "cannot parse {:?} for field `{}`: {}",
+                text, field, message
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ExtractError {}
+
+/// A type built from the captures of its own pattern
+pub trait FromCaptures: Sized {
+    /// The pattern whose groups fill in the value
+    const PATTERN: &'static str;
+
+    /// The compiled pattern, built once and reused
+    fn regex() -> &'static Regex;
+
+    /// Build a value from a match of [`PATTERN`](FromCaptures::PATTERN) in
+    /// `input`
+    fn from_match(input: &str, m: &Match) -> Result<Self, ExtractError>;
+
+    /// Build a value from the first match in `input`, if there is one
+    fn find(input: &str) -> Result<Option<Self>, ExtractError> {
+        Self::regex()
+            .find(input)
+            .map(|m| Self::from_match(input, &m))
+            .transpose()
+    }
+
+    /// Build a value from every non-overlapping match in `input`
+    fn find_all(input: &str) -> Result<Vec<Self>, ExtractError> {
+        Self::regex()
+            .find_iter(input)
+            .map(|m| Self::from_match(input, &m))
+            .collect()
+    }
+}
+
+/// Where a field's text is captured
+#[derive(Debug, Clone, Copy)]
+pub enum Group<'a> {
+    /// A numbered group
+    Index(u32),
+    /// A named group
+    Name(&'a str),
+}
+
+/// Parse the text of a group that must have taken part in the match
+pub fn field<T>(
+    input: &str,
+    m: &Match,
+    group: Group<'_>,
+    field: &'static str,
+) -> Result<T, ExtractError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    optional_field(input, m, group, field)?.ok_or(ExtractError::Missing { field })
+}
+
+/// Parse the text of a group, or return `None` if it did not take part in
+/// the match
+pub fn optional_field<T>(
+    input: &str,
+    m: &Match,
+    group: Group<'_>,
+    field: &'static str,
+) -> Result<Option<T>, ExtractError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let text = match group {
+        Group::Index(index) => m.group_str(input, index),
+        Group::Name(name) => m.named_group_str(input, name),
+    };
+    text.map(|text| {
+        text.parse().map_err(|err: T::Err| ExtractError::Parse {
+            field,
+            text: text.to_string(),
+            message: err.to_string(),
+        })
+    })
+    .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_parse_group_text() {
+        let regex = Regex::new(r"(port:\d+)(-(\w+))?").unwrap();
+        let input = "8080";
+        let m = regex.find(input).unwrap();
+        let port: u16 = field(input, &m, Group::Name("port"), "port").unwrap();
+        assert_eq!(port, 8080);
+        let host: Option<String> = optional_field(input, &m, Group::Index(3), "host").unwrap();
+        assert_eq!(host, None);
+        assert_eq!(
+            field::<String>(input, &m, Group::Index(3), "host"),
+            Err(ExtractError::Missing { field: "host" })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_field() {
+        let regex = Regex::new(r"(n:\d+)").unwrap();
+        let input = "999";
+        let m = regex.find(input).unwrap();
+        let err = field::<u8>(input, &m, Group::Name("n"), "count").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cannot parse \"999\" for field `count`: number too large to fit in target type"
+        );
+    }
+}
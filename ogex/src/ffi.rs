@@ -3,21 +3,335 @@
 //! This module provides a C-compatible interface for using the regex engine
 //! from other languages via FFI. All functions are marked with #[unsafe(no_mangle)]
 //! and use C calling conventions.
+//!
+//! Functions that compile or search report failures through two optional
+//! out-parameters: `error` receives a message to free with ogex_free_error,
+//! and `code` receives an [`OgexErrorCode`] to branch on. ogex_compile and
+//! ogex_find keep their original signatures without `code`; use
+//! ogex_compile_code and ogex_find_code to get one.
+//!
+//! # Thread safety
+//!
+//! A compiled regex is immutable, so a regex handle may be used from any
+//! number of threads at once. ogex_regex_clone gives each thread its own
+//! handle to free independently; the pattern is shared and freed with the
+//! last handle. Match, iterator and split handles are not synchronized and
+//! must not be used from two threads at the same time, but may be moved
+//! between threads.
+//!
+//! Handles and returned strings are allocated with the Rust allocator unless
+//! the host installs its own with ogex_set_allocator.
 
-use crate::engine::{Match, Regex};
-use std::ffi::{CStr, CString};
+use crate::engine::{Match, OwnedMatch, Regex, RegexBuilder, next_char_boundary};
+use crate::error::ErrorCode;
+use std::borrow::Cow;
+use std::ffi::{CStr, CString, c_void};
 use std::os::raw::{c_char, c_int};
 use std::panic::catch_unwind;
+use std::sync::{Arc, OnceLock};
 
 /// Opaque handle to a compiled regex
 pub struct RegexHandle {
-    regex: Regex,
+    regex: Arc<Regex>,
 }
 
 /// Opaque handle to a match result
 pub struct MatchHandle {
-    match_result: Match,
-    input: String, // Keep input alive for string references
+    match_result: OwnedMatch,
+    /// Span of each group, where group 0 is the entire match, in the units
+    /// the caller passed the input in
+    spans: Vec<Option<(c_int, c_int)>>,
+}
+
+impl MatchHandle {
+    /// A handle for `match_result`, found in `input`, that reports positions
+    /// in UTF-16 code units if `utf16` and in bytes otherwise
+    fn new(match_result: Match, input: &str, group_count: usize, utf16: bool) -> Self {
+        let offset = |pos: usize| {
+            if utf16 {
+                input[..pos].encode_utf16().count() as c_int
+            } else {
+                pos as c_int
+            }
+        };
+        let spans = (0..=group_count)
+            .map(|n| match n {
+                0 => Some((match_result.start, match_result.end)),
+                n => match_result.group(n as u32),
+            })
+            .map(|span| span.map(|(start, end)| (offset(start), offset(end))))
+            .collect();
+        MatchHandle {
+            match_result: match_result.into_owned(input),
+            spans,
+        }
+    }
+
+    /// Span of group `idx`, where group 0 is the entire match
+    fn group(&self, idx: c_int) -> Option<(c_int, c_int)> {
+        *self.spans.get(usize::try_from(idx).ok()?)?
+    }
+
+    /// Text of group `idx`, where group 0 is the entire match
+    fn group_text(&self, idx: c_int) -> Option<&str> {
+        self.group(idx)?;
+        match idx {
+            0 => Some(self.match_result.as_str()),
+            n => self.match_result.group_str(n as u32),
+        }
+    }
+}
+
+/// Opaque handle to the pieces of a split input
+pub struct SplitHandle {
+    pieces: Vec<CString>,
+}
+
+/// Opaque handle to an iterator over the matches in an input
+pub struct FindIterHandle {
+    regex: Arc<Regex>,
+    input: Arc<str>,
+    pos: usize,
+}
+
+/// Version of [`OgexOptions`] this library understands
+pub const OGEX_OPTIONS_VERSION: u32 = 2;
+
+/// Pattern syntax: Ogex, `(name:...)`
+pub const OGEX_DIALECT_OGEX: c_int = 0;
+
+/// Pattern syntax: PCRE and .NET, `(?<name>...)`
+pub const OGEX_DIALECT_PCRE: c_int = 1;
+
+/// Pattern syntax: Python, `(?P<name>...)`
+pub const OGEX_DIALECT_PYTHON: c_int = 2;
+
+/// Options for ogex_compile_ex
+///
+/// Initialize with ogex_options_init before setting fields, so options added
+/// in later versions keep their defaults.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct OgexOptions {
+    /// Layout version of this struct, set by ogex_options_init
+    pub version: u32,
+    /// Nonzero to match letters regardless of case
+    pub case_insensitive: c_int,
+    /// Nonzero to let `^` and `$` match at line boundaries
+    pub multiline: c_int,
+    /// Nonzero to let `.` match newlines
+    pub dotall: c_int,
+    /// Most steps a single search may take, or 0 for no limit
+    pub step_limit: usize,
+    /// Syntax of the pattern, one of the OGEX_DIALECT_* constants
+    pub dialect: c_int,
+    /// Longest input in bytes a search accepts, or 0 for no limit
+    pub max_haystack_len: usize,
+    /// Longest a single search may run in milliseconds, or 0 for no limit
+    pub timeout_ms: u64,
+}
+
+/// Kinds of failure reported through the `code` out-parameter of the
+/// compile and find functions
+///
+/// Positive values mirror [`ErrorCode`]: `E101` is 101 and `W001` is 1001.
+/// Negative values are failures that have no [`ErrorCode`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OgexErrorCode {
+    /// No error
+    Ok = 0,
+    /// A null pointer, an unsupported option or dialect, or a result with a
+    /// null byte that cannot be returned as a C string
+    InvalidArgument = -1,
+    /// A pattern or input that is not valid UTF-8
+    InvalidUtf8 = -2,
+    /// The engine panicked
+    Panic = -3,
+    /// The host allocator installed with ogex_set_allocator returned null
+    AllocationFailed = -4,
+    /// A replacement template that is malformed or refers to a group the
+    /// pattern lacks
+    InvalidTemplate = -5,
+    /// An input that is not valid UTF-16
+    InvalidUtf16 = -6,
+    /// `E001`
+    UnexpectedChar = 1,
+    /// `E002`
+    UnclosedCharacterClass = 2,
+    /// `E003`
+    InvalidEscape = 3,
+    /// `E004`
+    UnclosedGroup = 4,
+    /// `E005`
+    InvalidGroupName = 5,
+    /// `E101`
+    UnexpectedToken = 101,
+    /// `E102`
+    UnexpectedEof = 102,
+    /// `E103`
+    DuplicateGroupName = 103,
+    /// `E104`
+    UndefinedBackreference = 104,
+    /// `E105`
+    InvalidQuantifier = 105,
+    /// `E106`
+    UndefinedFragment = 106,
+    /// `E201`
+    Compile = 201,
+    /// `E301`
+    StepLimitExceeded = 301,
+    /// `E302`
+    TimeoutExceeded = 302,
+    /// `E303`
+    HaystackTooLarge = 303,
+    /// `E304`
+    InvalidUtf8Offset = 304,
+    /// `W001`
+    BackreferenceOutOfRange = 1001,
+    /// `W002`
+    EmptyRepetition = 1002,
+    /// `W003`
+    RepeatedAssertion = 1003,
+    /// `W004`
+    NestedQuantifier = 1004,
+}
+
+impl From<ErrorCode> for OgexErrorCode {
+    fn from(code: ErrorCode) -> Self {
+        match code {
+            ErrorCode::UnexpectedChar => OgexErrorCode::UnexpectedChar,
+            ErrorCode::UnclosedCharacterClass => OgexErrorCode::UnclosedCharacterClass,
+            ErrorCode::InvalidEscape => OgexErrorCode::InvalidEscape,
+            ErrorCode::UnclosedGroup => OgexErrorCode::UnclosedGroup,
+            ErrorCode::InvalidGroupName => OgexErrorCode::InvalidGroupName,
+            ErrorCode::UnexpectedToken => OgexErrorCode::UnexpectedToken,
+            ErrorCode::UnexpectedEof => OgexErrorCode::UnexpectedEof,
+            ErrorCode::DuplicateGroupName => OgexErrorCode::DuplicateGroupName,
+            ErrorCode::UndefinedBackreference => OgexErrorCode::UndefinedBackreference,
+            ErrorCode::InvalidQuantifier => OgexErrorCode::InvalidQuantifier,
+            ErrorCode::UndefinedFragment => OgexErrorCode::UndefinedFragment,
+            ErrorCode::Compile => OgexErrorCode::Compile,
+            ErrorCode::StepLimitExceeded => OgexErrorCode::StepLimitExceeded,
+            ErrorCode::TimeoutExceeded => OgexErrorCode::TimeoutExceeded,
+            ErrorCode::HaystackTooLarge => OgexErrorCode::HaystackTooLarge,
+            ErrorCode::InvalidUtf8Offset => OgexErrorCode::InvalidUtf8Offset,
+            ErrorCode::BackreferenceOutOfRange => OgexErrorCode::BackreferenceOutOfRange,
+            ErrorCode::EmptyRepetition => OgexErrorCode::EmptyRepetition,
+            ErrorCode::RepeatedAssertion => OgexErrorCode::RepeatedAssertion,
+            ErrorCode::NestedQuantifier => OgexErrorCode::NestedQuantifier,
+        }
+    }
+}
+
+/// Host function that allocates `size` bytes, aligned as by C `malloc`
+pub type OgexMallocFn = unsafe extern "C" fn(size: usize) -> *mut c_void;
+
+/// Host function that frees memory returned by an [`OgexMallocFn`]
+pub type OgexFreeFn = unsafe extern "C" fn(ptr: *mut c_void);
+
+/// Allocator hooks installed by ogex_set_allocator
+#[derive(Clone, Copy)]
+struct Allocator {
+    malloc: OgexMallocFn,
+    free: OgexFreeFn,
+}
+
+/// The host allocator, or `None` for the Rust allocator. Fixed by the first
+/// allocation so that nothing is ever freed by the wrong allocator.
+static ALLOCATOR: OnceLock<Option<Allocator>> = OnceLock::new();
+
+fn allocator() -> Option<Allocator> {
+    *ALLOCATOR.get_or_init(|| None)
+}
+
+/// Move a value into memory owned by the API, returning null if the host
+/// allocator fails
+fn new_handle<T>(value: T) -> *mut T {
+    let Some(allocator) = allocator() else {
+        return Box::into_raw(Box::new(value));
+    };
+
+    let ptr = unsafe { (allocator.malloc)(std::mem::size_of::<T>()) }.cast::<T>();
+    if !ptr.is_null() {
+        debug_assert!(ptr.is_aligned());
+        unsafe { ptr.write(value) };
+    }
+    ptr
+}
+
+/// Drop and deallocate a value created by [`new_handle`]
+///
+/// # Safety
+/// - ptr must be null or a pointer returned by new_handle that has not been
+///   freed
+unsafe fn free_handle<T>(ptr: *mut T) {
+    if ptr.is_null() {
+        return;
+    }
+    match allocator() {
+        Some(allocator) => unsafe {
+            ptr.drop_in_place();
+            (allocator.free)(ptr.cast());
+        },
+        None => drop(unsafe { Box::from_raw(ptr) }),
+    }
+}
+
+/// Copy a string into memory owned by the API, returning null if the host
+/// allocator fails
+fn new_string(s: CString) -> *mut c_char {
+    let Some(allocator) = allocator() else {
+        return s.into_raw();
+    };
+
+    let bytes = s.as_bytes_with_nul();
+    let ptr = unsafe { (allocator.malloc)(bytes.len()) }.cast::<u8>();
+    if !ptr.is_null() {
+        unsafe { ptr.copy_from_nonoverlapping(bytes.as_ptr(), bytes.len()) };
+    }
+    ptr.cast()
+}
+
+/// Deallocate a string created by [`new_string`]
+///
+/// # Safety
+/// - ptr must be null or a pointer returned by new_string that has not been
+///   freed
+unsafe fn free_string(ptr: *mut c_char) {
+    if ptr.is_null() {
+        return;
+    }
+    match allocator() {
+        Some(allocator) => unsafe { (allocator.free)(ptr.cast()) },
+        None => drop(unsafe { CString::from_raw(ptr) }),
+    }
+}
+
+/// Route every handle and string the API returns through host allocation
+/// functions
+///
+/// Only memory handed to the caller is affected; the engine's internal
+/// buffers still use the Rust allocator. The allocator cannot change once
+/// anything has been allocated, so call this before any other function.
+///
+/// # Safety
+/// - malloc_fn must return null or memory of at least the requested size,
+///   aligned as by C `malloc`
+/// - free_fn must accept any non-null pointer returned by malloc_fn
+/// - both functions must be safe to call from any thread
+///
+/// Returns 0 on success, or -1 if the allocator is already fixed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_set_allocator(malloc_fn: OgexMallocFn, free_fn: OgexFreeFn) -> c_int {
+    let allocator = Allocator {
+        malloc: malloc_fn,
+        free: free_fn,
+    };
+    match ALLOCATOR.set(Some(allocator)) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
 }
 
 /// Helper to set error message
@@ -26,11 +340,40 @@ fn set_error(error: *mut *mut c_char, msg: &str) {
         && let Ok(err) = CString::new(msg)
     {
         unsafe {
-            *error = err.into_raw();
+            *error = new_string(err);
+        }
+    }
+}
+
+/// Helper to set error code
+fn set_code(code: *mut OgexErrorCode, value: OgexErrorCode) {
+    if !code.is_null() {
+        unsafe {
+            *code = value;
         }
     }
 }
 
+/// Helper to report a failure through both out-parameters
+fn fail(error: *mut *mut c_char, code: *mut OgexErrorCode, value: OgexErrorCode, msg: &str) {
+    set_error(error, msg);
+    set_code(code, value);
+}
+
+/// Helper to report a handle or string the host allocator could not
+/// allocate, passing `ptr` through
+fn check_alloc<T>(ptr: *mut T, error: *mut *mut c_char, code: *mut OgexErrorCode) -> *mut T {
+    if ptr.is_null() {
+        fail(
+            error,
+            code,
+            OgexErrorCode::AllocationFailed,
+            "allocation failed",
+        );
+    }
+    ptr
+}
+
 /// Compile a regex pattern
 ///
 /// # Safety
@@ -43,201 +386,1394 @@ pub unsafe extern "C" fn ogex_compile(
     pattern: *const c_char,
     error: *mut *mut c_char,
 ) -> *mut RegexHandle {
+    unsafe { ogex_compile_code(pattern, error, std::ptr::null_mut()) }
+}
+
+/// Compile a regex pattern, reporting the kind of any failure
+///
+/// # Safety
+/// - pattern must be a valid null-terminated UTF-8 string
+/// - error pointer can be null if you don't need error messages
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
+///
+/// Returns a handle to the compiled regex, or null on error
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_compile_code(
+    pattern: *const c_char,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut RegexHandle {
+    set_code(code, OgexErrorCode::Ok);
     // Validate inputs first (outside catch_unwind for clarity)
     if pattern.is_null() {
-        set_error(error, "pattern is null");
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "pattern is null",
+        );
         return std::ptr::null_mut();
     }
 
-    let result = catch_unwind(|| {
-        // SAFETY: pattern is checked non-null above, and caller guarantees valid UTF-8
-        let pattern_str = unsafe {
-            match CStr::from_ptr(pattern).to_str() {
-                Ok(s) => s,
-                Err(_) => {
-                    set_error(error, "pattern is not valid UTF-8");
-                    return std::ptr::null_mut();
-                }
-            }
-        };
+    // SAFETY: pattern is checked non-null above
+    match unsafe { CStr::from_ptr(pattern) }.to_str() {
+        Ok(pattern_str) => compile_str(pattern_str, error, code),
+        Err(_) => {
+            fail(
+                error,
+                code,
+                OgexErrorCode::InvalidUtf8,
+                "pattern is not valid UTF-8",
+            );
+            std::ptr::null_mut()
+        }
+    }
+}
 
-        match Regex::new(pattern_str) {
-            Ok(regex) => {
-                let handle = Box::new(RegexHandle { regex });
-                Box::into_raw(handle)
-            }
-            Err(e) => {
-                set_error(error, &e.to_string());
-                std::ptr::null_mut()
-            }
+/// Compile a regex pattern given as a pointer and a length in bytes
+///
+/// The pattern does not need to be null-terminated.
+///
+/// # Safety
+/// - pattern must point to `len` bytes of valid UTF-8
+/// - error pointer can be null if you don't need error messages
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
+///
+/// Returns a handle to the compiled regex, or null on error
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_compile_n(
+    pattern: *const c_char,
+    len: usize,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut RegexHandle {
+    set_code(code, OgexErrorCode::Ok);
+    if pattern.is_null() {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "pattern is null",
+        );
+        return std::ptr::null_mut();
+    }
+
+    // SAFETY: pattern is checked non-null above
+    match unsafe { str_from_raw_parts(pattern, len) } {
+        Ok(pattern_str) => compile_str(pattern_str, error, code),
+        Err(_) => {
+            fail(
+                error,
+                code,
+                OgexErrorCode::InvalidUtf8,
+                "pattern is not valid UTF-8",
+            );
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Borrow `len` bytes at `ptr` as text (internal helper)
+///
+/// # Safety
+/// - ptr must be non-null and point to `len` readable bytes that outlive `'a`
+unsafe fn str_from_raw_parts<'a>(
+    ptr: *const c_char,
+    len: usize,
+) -> Result<&'a str, std::str::Utf8Error> {
+    std::str::from_utf8(unsafe { std::slice::from_raw_parts(ptr.cast::<u8>(), len) })
+}
+
+/// Compile a pattern into a new handle (internal helper)
+fn compile_str(
+    pattern_str: &str,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut RegexHandle {
+    let result = catch_unwind(|| match Regex::new(pattern_str) {
+        Ok(regex) => check_alloc(
+            new_handle(RegexHandle {
+                regex: Arc::new(regex),
+            }),
+            error,
+            code,
+        ),
+        Err(e) => {
+            fail(error, code, e.code().into(), &e.to_string());
+            std::ptr::null_mut()
         }
     });
 
     match result {
         Ok(val) => val,
         Err(_) => {
-            set_error(error, "panic during regex compilation");
+            fail(
+                error,
+                code,
+                OgexErrorCode::Panic,
+                "panic during regex compilation",
+            );
             std::ptr::null_mut()
         }
     }
 }
 
-/// Free a regex handle
+/// Get the index of a named group
 ///
 /// # Safety
-/// - handle must be a valid pointer returned by ogex_compile
-/// - handle must not be used after calling this function
+/// - handle must be a valid regex handle
+/// - name must be a valid null-terminated UTF-8 string
+/// - returns -1 if either pointer is null or the pattern has no group `name`
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn ogex_free_regex(handle: *mut RegexHandle) {
+pub unsafe extern "C" fn ogex_regex_group_index(
+    handle: *const RegexHandle,
+    name: *const c_char,
+) -> c_int {
+    if handle.is_null() || name.is_null() {
+        return -1;
+    }
+
     unsafe {
-        if !handle.is_null() {
-            drop(Box::from_raw(handle));
-        }
+        let Ok(name) = CStr::from_ptr(name).to_str() else {
+            return -1;
+        };
+        (*handle)
+            .regex
+            .group_index(name)
+            .map_or(-1, |index| index as c_int)
     }
 }
 
-/// Check if a pattern matches
+/// Get the name of a group
 ///
 /// # Safety
 /// - handle must be a valid regex handle
-/// - input must be a valid null-terminated UTF-8 string
+/// - returns null if handle is null, idx is out of range, or the group is
+///   unnamed
+/// - caller must free the returned string with ogex_free_string
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn ogex_is_match(handle: *const RegexHandle, input: *const c_char) -> c_int {
-    if handle.is_null() || input.is_null() {
-        return 0;
+pub unsafe extern "C" fn ogex_regex_group_name(
+    handle: *const RegexHandle,
+    idx: c_int,
+) -> *mut c_char {
+    if handle.is_null() {
+        return std::ptr::null_mut();
     }
 
-    let result = catch_unwind(|| {
-        // SAFETY: handles are checked non-null above
-        let input_str = unsafe {
-            match CStr::from_ptr(input).to_str() {
-                Ok(s) => s,
-                Err(_) => return 0,
-            }
-        };
-
-        let regex = &unsafe { &(*handle).regex };
-        if regex.is_match(input_str) { 1 } else { 0 }
-    });
+    let regex = unsafe { &(*handle).regex };
+    let name = usize::try_from(idx)
+        .ok()
+        .and_then(|idx| regex.capture_names().nth(idx).flatten());
+    match name.map(CString::new) {
+        Some(Ok(cstr)) => new_string(cstr),
+        _ => std::ptr::null_mut(),
+    }
+}
 
-    result.unwrap_or_default()
+/// Fill an options struct with the defaults of ogex_compile
+///
+/// # Safety
+/// - options must be null or point to writable memory for an OgexOptions
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_options_init(options: *mut OgexOptions) {
+    if options.is_null() {
+        return;
+    }
+    unsafe {
+        options.write(OgexOptions {
+            version: OGEX_OPTIONS_VERSION,
+            case_insensitive: 0,
+            multiline: 0,
+            dotall: 0,
+            step_limit: 0,
+            dialect: OGEX_DIALECT_OGEX,
+            max_haystack_len: 0,
+            timeout_ms: 0,
+        });
+    }
 }
 
-/// Find the first match
+/// Compile a regex pattern with options
 ///
 /// # Safety
-/// - handle must be a valid regex handle
-/// - input must be a valid null-terminated UTF-8 string
-/// - error pointer can be null
+/// - pattern must be a valid null-terminated UTF-8 string
+/// - options must be null (for the defaults) or point to an OgexOptions
+///   initialized with ogex_options_init
+/// - error pointer can be null if you don't need error messages
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
 ///
-/// Returns a match handle, or null if no match found
+/// Returns a handle to the compiled regex, or null on error
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn ogex_find(
-    handle: *const RegexHandle,
-    input: *const c_char,
+pub unsafe extern "C" fn ogex_compile_ex(
+    pattern: *const c_char,
+    options: *const OgexOptions,
     error: *mut *mut c_char,
-) -> *mut MatchHandle {
-    // Validate inputs first
-    if handle.is_null() || input.is_null() {
-        set_error(error, "invalid handle or input");
+    code: *mut OgexErrorCode,
+) -> *mut RegexHandle {
+    set_code(code, OgexErrorCode::Ok);
+    if options.is_null() {
+        return unsafe { ogex_compile_code(pattern, error, code) };
+    }
+    if pattern.is_null() {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "pattern is null",
+        );
+        return std::ptr::null_mut();
+    }
+
+    // SAFETY: options is checked non-null above
+    let options = unsafe { *options };
+    if options.version != OGEX_OPTIONS_VERSION {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "unsupported options version",
+        );
+        return std::ptr::null_mut();
+    }
+    if !dialect_supported(options.dialect) {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "unsupported dialect",
+        );
         return std::ptr::null_mut();
     }
 
     let result = catch_unwind(|| {
-        // SAFETY: handles are checked non-null above
-        let input_str = unsafe {
-            match CStr::from_ptr(input).to_str() {
+        // SAFETY: pattern is checked non-null above, and caller guarantees valid UTF-8
+        let pattern_str = unsafe {
+            match CStr::from_ptr(pattern).to_str() {
                 Ok(s) => s,
                 Err(_) => {
-                    set_error(error, "input is not valid UTF-8");
+                    fail(
+                        error,
+                        code,
+                        OgexErrorCode::InvalidUtf8,
+                        "pattern is not valid UTF-8",
+                    );
                     return std::ptr::null_mut();
                 }
             }
         };
 
-        let regex = unsafe { &(*handle).regex };
-        match regex.find(input_str) {
-            Some(match_result) => {
-                let handle = Box::new(MatchHandle {
-                    match_result,
-                    input: input_str.to_string(),
-                });
-                Box::into_raw(handle)
+        let pattern_str = match from_dialect(pattern_str, options.dialect) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                fail(error, code, e.code().into(), &e.to_string());
+                return std::ptr::null_mut();
+            }
+        };
+
+        let mut builder = RegexBuilder::new(&pattern_str)
+            .case_insensitive(options.case_insensitive != 0)
+            .multiline(options.multiline != 0)
+            .dotall(options.dotall != 0);
+        if options.step_limit != 0 {
+            builder = builder.step_limit(options.step_limit);
+        }
+        if options.max_haystack_len != 0 {
+            builder = builder.max_haystack_len(options.max_haystack_len);
+        }
+        if options.timeout_ms != 0 {
+            builder = builder.timeout(std::time::Duration::from_millis(options.timeout_ms));
+        }
+        match builder.build() {
+            Ok(regex) => check_alloc(
+                new_handle(RegexHandle {
+                    regex: Arc::new(regex),
+                }),
+                error,
+                code,
+            ),
+            Err(e) => {
+                fail(error, code, e.code().into(), &e.to_string());
+                std::ptr::null_mut()
             }
-            None => std::ptr::null_mut(),
         }
     });
 
     match result {
         Ok(val) => val,
         Err(_) => {
-            set_error(error, "panic during regex find");
+            fail(
+                error,
+                code,
+                OgexErrorCode::Panic,
+                "panic during regex compilation",
+            );
             std::ptr::null_mut()
         }
     }
 }
 
-/// Free a match handle
-///
-/// # Safety
-/// - handle must be a valid pointer returned by ogex_find
-/// - handle must not be used after calling this function
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn ogex_free_match(handle: *mut MatchHandle) {
-    unsafe {
-        if !handle.is_null() {
-            drop(Box::from_raw(handle));
+/// Whether ogex_compile_ex can compile patterns in the OGEX_DIALECT_*
+/// syntax `dialect`
+fn dialect_supported(dialect: c_int) -> bool {
+    dialect == OGEX_DIALECT_OGEX
+        || cfg!(feature = "transpiler")
+            && matches!(dialect, OGEX_DIALECT_PCRE | OGEX_DIALECT_PYTHON)
+}
+
+/// `pattern` rewritten from the OGEX_DIALECT_* syntax `dialect` into Ogex
+fn from_dialect(pattern: &str, dialect: c_int) -> crate::error::Result<Cow<'_, str>> {
+    match dialect {
+        #[cfg(feature = "transpiler")]
+        OGEX_DIALECT_PCRE | OGEX_DIALECT_PYTHON => {
+            crate::dialect::transpile_from_legacy(pattern).map(Cow::Owned)
         }
+        _ => Ok(Cow::Borrowed(pattern)),
     }
 }
 
-/// Get match start position
+/// Create another handle to the same compiled regex
+///
+/// The clone shares the compiled pattern instead of copying it, and is
+/// freed separately with ogex_free_regex. Use one handle per thread to share
+/// a pattern between threads without coordinating when it is freed.
 ///
 /// # Safety
-/// - handle must be a valid match handle
-/// - returns -1 if handle is null
+/// - handle must be a valid regex handle
+///
+/// Returns a new handle, or null if handle is null
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn ogex_match_start(handle: *const MatchHandle) -> c_int {
-    unsafe {
-        if handle.is_null() {
-            return -1;
-        }
-        (*handle).match_result.start as c_int
+pub unsafe extern "C" fn ogex_regex_clone(handle: *const RegexHandle) -> *mut RegexHandle {
+    if handle.is_null() {
+        return std::ptr::null_mut();
     }
+
+    new_handle(RegexHandle {
+        regex: Arc::clone(unsafe { &(*handle).regex }),
+    })
 }
 
-/// Get match end position
+/// Free a regex handle
+///
+/// The compiled pattern is released once every clone of the handle and
+/// every iterator over it has been freed.
 ///
 /// # Safety
-/// - handle must be a valid match handle
-/// - returns -1 if handle is null
+/// - handle must be a valid pointer returned by ogex_compile or
+///   ogex_regex_clone
+/// - handle must not be used after calling this function
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn ogex_match_end(handle: *const MatchHandle) -> c_int {
-    unsafe {
-        if handle.is_null() {
-            return -1;
-        }
-        (*handle).match_result.end as c_int
-    }
+pub unsafe extern "C" fn ogex_free_regex(handle: *mut RegexHandle) {
+    unsafe { free_handle(handle) }
 }
 
-/// Get the matched text
+/// Check if a pattern matches
 ///
 /// # Safety
-/// - handle must be a valid match handle
-/// - returns null if handle is null
-/// - caller must free the returned string with ogex_free_string
+/// - handle must be a valid regex handle
+/// - input must be a valid null-terminated UTF-8 string
+///
+/// Returns 1 on a match and 0 otherwise, including when the search reaches
+/// the step limit set with ogex_compile_ex; use ogex_is_match_code to tell
+/// the two apart.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn ogex_match_text(handle: *const MatchHandle) -> *mut c_char {
-    unsafe {
+pub unsafe extern "C" fn ogex_is_match(handle: *const RegexHandle, input: *const c_char) -> c_int {
+    unsafe { ogex_is_match_code(handle, input, std::ptr::null_mut()) }
+}
+
+/// Check if a pattern matches, reporting the kind of any failure
+///
+/// # Safety
+/// - handle must be a valid regex handle
+/// - input must be a valid null-terminated UTF-8 string
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
+///
+/// Returns 1 on a match and 0 otherwise; code tells no match apart from an
+/// error such as a search that reaches the step limit
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_is_match_code(
+    handle: *const RegexHandle,
+    input: *const c_char,
+    code: *mut OgexErrorCode,
+) -> c_int {
+    set_code(code, OgexErrorCode::Ok);
+    if handle.is_null() || input.is_null() {
+        set_code(code, OgexErrorCode::InvalidArgument);
+        return 0;
+    }
+
+    // SAFETY: handles are checked non-null above
+    match unsafe { CStr::from_ptr(input) }.to_str() {
+        Ok(input_str) => is_match_str(unsafe { &(*handle).regex }, input_str, code),
+        Err(_) => {
+            set_code(code, OgexErrorCode::InvalidUtf8);
+            0
+        }
+    }
+}
+
+/// Check if a pattern matches an input given as a pointer and a length in bytes
+///
+/// The input does not need to be null-terminated and may contain null bytes.
+///
+/// # Safety
+/// - handle must be a valid regex handle
+/// - input must point to `len` bytes of valid UTF-8
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_is_match_n(
+    handle: *const RegexHandle,
+    input: *const c_char,
+    len: usize,
+) -> c_int {
+    if handle.is_null() || input.is_null() {
+        return 0;
+    }
+
+    // SAFETY: handles are checked non-null above
+    match unsafe { str_from_raw_parts(input, len) } {
+        Ok(input_str) => is_match_str(unsafe { &(*handle).regex }, input_str, std::ptr::null_mut()),
+        Err(_) => 0,
+    }
+}
+
+/// Check if a pattern matches an input (internal helper)
+fn is_match_str(regex: &Regex, input_str: &str, code: *mut OgexErrorCode) -> c_int {
+    let result = catch_unwind(|| match regex.try_find(input_str) {
+        Ok(found) => c_int::from(found.is_some()),
+        Err(e) => {
+            set_code(code, e.code().into());
+            0
+        }
+    });
+    result.unwrap_or_else(|_| {
+        set_code(code, OgexErrorCode::Panic);
+        0
+    })
+}
+
+/// Find the first match
+///
+/// # Safety
+/// - handle must be a valid regex handle
+/// - input must be a valid null-terminated UTF-8 string
+/// - error pointer can be null
+///
+/// Returns a match handle, or null if no match found. A search that reaches
+/// the step limit set with ogex_compile_ex also returns null and sets error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_find(
+    handle: *const RegexHandle,
+    input: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut MatchHandle {
+    unsafe { ogex_find_code(handle, input, error, std::ptr::null_mut()) }
+}
+
+/// Find the first match, reporting the kind of any failure
+///
+/// # Safety
+/// - handle must be a valid regex handle
+/// - input must be a valid null-terminated UTF-8 string
+/// - error pointer can be null
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
+///
+/// Returns a match handle, or null if no match found or on error; code
+/// tells the two apart
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_find_code(
+    handle: *const RegexHandle,
+    input: *const c_char,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut MatchHandle {
+    set_code(code, OgexErrorCode::Ok);
+    // Validate inputs first
+    if handle.is_null() || input.is_null() {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "invalid handle or input",
+        );
+        return std::ptr::null_mut();
+    }
+
+    // SAFETY: handles are checked non-null above
+    match unsafe { CStr::from_ptr(input) }.to_str() {
+        Ok(input_str) => find_str(
+            unsafe { &(*handle).regex },
+            input_str,
+            0,
+            false,
+            error,
+            code,
+        ),
+        Err(_) => {
+            fail(
+                error,
+                code,
+                OgexErrorCode::InvalidUtf8,
+                "input is not valid UTF-8",
+            );
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Find the first match in an input given as a pointer and a length in bytes
+///
+/// The input does not need to be null-terminated and may contain null bytes.
+///
+/// # Safety
+/// - handle must be a valid regex handle
+/// - input must point to `len` bytes of valid UTF-8
+/// - error pointer can be null
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
+///
+/// Returns a match handle, or null if no match found
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_find_n(
+    handle: *const RegexHandle,
+    input: *const c_char,
+    len: usize,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut MatchHandle {
+    set_code(code, OgexErrorCode::Ok);
+    if handle.is_null() || input.is_null() {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "invalid handle or input",
+        );
+        return std::ptr::null_mut();
+    }
+
+    // SAFETY: handles are checked non-null above
+    match unsafe { str_from_raw_parts(input, len) } {
+        Ok(input_str) => find_str(
+            unsafe { &(*handle).regex },
+            input_str,
+            0,
+            false,
+            error,
+            code,
+        ),
+        Err(_) => {
+            fail(
+                error,
+                code,
+                OgexErrorCode::InvalidUtf8,
+                "input is not valid UTF-8",
+            );
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Find the first match that starts at or after a position
+///
+/// Anchors and lookbehinds still see the text before `start_pos`, so a
+/// scanning loop can resume at the end of the previous match.
+///
+/// # Safety
+/// - handle must be a valid regex handle
+/// - input must be a valid null-terminated UTF-8 string
+/// - error pointer can be null
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
+///
+/// Returns a match handle, or null if no match found. A `start_pos` past
+/// the end of the input or inside a multi-byte character also returns null
+/// and sets error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_find_at(
+    handle: *const RegexHandle,
+    input: *const c_char,
+    start_pos: usize,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut MatchHandle {
+    set_code(code, OgexErrorCode::Ok);
+    if handle.is_null() || input.is_null() {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "invalid handle or input",
+        );
+        return std::ptr::null_mut();
+    }
+
+    // SAFETY: handles are checked non-null above
+    match unsafe { CStr::from_ptr(input) }.to_str() {
+        Ok(input_str) => find_str(
+            unsafe { &(*handle).regex },
+            input_str,
+            start_pos,
+            false,
+            error,
+            code,
+        ),
+        Err(_) => {
+            fail(
+                error,
+                code,
+                OgexErrorCode::InvalidUtf8,
+                "input is not valid UTF-8",
+            );
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Check if a pattern matches a UTF-16 input
+///
+/// # Safety
+/// - handle must be a valid regex handle
+/// - input must point to `len` UTF-16 code units
+/// - returns 0 if the input is not valid UTF-16
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_is_match_utf16(
+    handle: *const RegexHandle,
+    input: *const u16,
+    len: usize,
+) -> c_int {
+    if handle.is_null() || input.is_null() {
+        return 0;
+    }
+
+    // SAFETY: handles are checked non-null above
+    match String::from_utf16(unsafe { std::slice::from_raw_parts(input, len) }) {
+        Ok(input_str) => is_match_str(
+            unsafe { &(*handle).regex },
+            &input_str,
+            std::ptr::null_mut(),
+        ),
+        Err(_) => 0,
+    }
+}
+
+/// Find the first match in a UTF-16 input
+///
+/// Positions reported by the returned handle, such as ogex_match_start, are
+/// measured in UTF-16 code units. Text accessors still return UTF-8.
+///
+/// # Safety
+/// - handle must be a valid regex handle
+/// - input must point to `len` UTF-16 code units
+/// - error pointer can be null
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
+///
+/// Returns a match handle, or null if no match found
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_find_utf16(
+    handle: *const RegexHandle,
+    input: *const u16,
+    len: usize,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut MatchHandle {
+    set_code(code, OgexErrorCode::Ok);
+    if handle.is_null() || input.is_null() {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "invalid handle or input",
+        );
+        return std::ptr::null_mut();
+    }
+
+    // SAFETY: handles are checked non-null above
+    let input_str = match String::from_utf16(unsafe { std::slice::from_raw_parts(input, len) }) {
+        Ok(s) => s,
+        Err(_) => {
+            fail(
+                error,
+                code,
+                OgexErrorCode::InvalidUtf16,
+                "input is not valid UTF-16",
+            );
+            return std::ptr::null_mut();
+        }
+    };
+
+    find_str(
+        unsafe { &(*handle).regex },
+        &input_str,
+        0,
+        true,
+        error,
+        code,
+    )
+}
+
+/// Find the first match at or after `start` into a new handle, with
+/// positions in UTF-16 code units if `utf16` (internal helper)
+fn find_str(
+    regex: &Regex,
+    input_str: &str,
+    start: usize,
+    utf16: bool,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut MatchHandle {
+    let result = catch_unwind(|| match regex.try_find_at(input_str, start) {
+        Ok(Some(match_result)) => check_alloc(
+            new_handle(MatchHandle::new(
+                match_result,
+                input_str,
+                regex.group_count(),
+                utf16,
+            )),
+            error,
+            code,
+        ),
+        Ok(None) => std::ptr::null_mut(),
+        Err(e) => {
+            fail(error, code, e.code().into(), &e.to_string());
+            std::ptr::null_mut()
+        }
+    });
+
+    match result {
+        Ok(val) => val,
+        Err(_) => {
+            fail(error, code, OgexErrorCode::Panic, "panic during regex find");
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Start iterating over all non-overlapping matches in an input
+///
+/// # Safety
+/// - handle must be a valid regex handle; the iterator keeps its own
+///   reference to the pattern, so the handle may be freed first
+/// - input must be a valid null-terminated UTF-8 string; it is copied, so it
+///   may be freed once this returns
+/// - error pointer can be null
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
+///
+/// Returns an iterator handle to pass to ogex_find_iter_next, or null on error
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_find_iter_new(
+    handle: *const RegexHandle,
+    input: *const c_char,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut FindIterHandle {
+    set_code(code, OgexErrorCode::Ok);
+    if handle.is_null() || input.is_null() {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "invalid handle or input",
+        );
+        return std::ptr::null_mut();
+    }
+
+    // SAFETY: input is checked non-null above
+    match unsafe { CStr::from_ptr(input) }.to_str() {
+        Ok(input_str) => check_alloc(
+            new_handle(FindIterHandle {
+                regex: Arc::clone(unsafe { &(*handle).regex }),
+                input: Arc::from(input_str),
+                pos: 0,
+            }),
+            error,
+            code,
+        ),
+        Err(_) => {
+            fail(
+                error,
+                code,
+                OgexErrorCode::InvalidUtf8,
+                "input is not valid UTF-8",
+            );
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Get the next match from an iterator
+///
+/// # Safety
+/// - iter must be a valid iterator handle
+/// - error pointer can be null
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
+///
+/// Returns a match handle to free with ogex_free_match, or null once every
+/// match has been returned
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_find_iter_next(
+    iter: *mut FindIterHandle,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut MatchHandle {
+    set_code(code, OgexErrorCode::Ok);
+    if iter.is_null() {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "invalid iterator",
+        );
+        return std::ptr::null_mut();
+    }
+
+    let result = catch_unwind(|| {
+        // SAFETY: iter is checked non-null above
+        let iter = unsafe { &mut *iter };
+        let regex = Arc::clone(&iter.regex);
+        if iter.pos > iter.input.len() {
+            return std::ptr::null_mut();
+        }
+
+        match regex.try_find_at(&iter.input, iter.pos) {
+            Ok(Some(match_result)) => {
+                // Step past empty matches so the search always makes progress
+                iter.pos = if match_result.end == match_result.start {
+                    next_char_boundary(&iter.input, match_result.end)
+                } else {
+                    match_result.end
+                };
+                check_alloc(
+                    new_handle(MatchHandle::new(
+                        match_result,
+                        &iter.input,
+                        regex.group_count(),
+                        false,
+                    )),
+                    error,
+                    code,
+                )
+            }
+            Ok(None) => {
+                iter.pos = iter.input.len() + 1;
+                std::ptr::null_mut()
+            }
+            Err(e) => {
+                fail(error, code, e.code().into(), &e.to_string());
+                std::ptr::null_mut()
+            }
+        }
+    });
+
+    match result {
+        Ok(val) => val,
+        Err(_) => {
+            fail(error, code, OgexErrorCode::Panic, "panic during regex find");
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Replace matches with a replacement template
+///
+/// The template uses the same syntax as `Regex::replace`, including named
+/// backreferences such as `\g{name}`. At most `count` matches are replaced,
+/// or all of them if `count` is 0.
+///
+/// # Safety
+/// - handle must be a valid regex handle
+/// - input and template must be valid null-terminated UTF-8 strings
+/// - error pointer can be null
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
+/// - caller must free the returned string with ogex_free_string
+///
+/// Returns the rewritten input, or null on error (such as a template that
+/// refers to a group the pattern lacks, or a search that reaches the step
+/// limit set with ogex_compile_ex)
+#[cfg(feature = "replace")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_replace(
+    handle: *const RegexHandle,
+    input: *const c_char,
+    template: *const c_char,
+    count: usize,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut c_char {
+    set_code(code, OgexErrorCode::Ok);
+    if handle.is_null() || input.is_null() || template.is_null() {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "invalid handle, input or template",
+        );
+        return std::ptr::null_mut();
+    }
+
+    let result = catch_unwind(|| {
+        // SAFETY: pointers are checked non-null above
+        let (input_str, template_str) = unsafe {
+            match (
+                CStr::from_ptr(input).to_str(),
+                CStr::from_ptr(template).to_str(),
+            ) {
+                (Ok(input), Ok(template)) => (input, template),
+                _ => {
+                    fail(
+                        error,
+                        code,
+                        OgexErrorCode::InvalidUtf8,
+                        "input or template is not valid UTF-8",
+                    );
+                    return std::ptr::null_mut();
+                }
+            }
+        };
+
+        let regex = unsafe { &(*handle).regex };
+        match regex.replacen(input_str, count, template_str) {
+            Ok(replaced) => match CString::new(replaced) {
+                Ok(cstr) => check_alloc(new_string(cstr), error, code),
+                Err(_) => {
+                    fail(
+                        error,
+                        code,
+                        OgexErrorCode::InvalidArgument,
+                        "result contains a null byte",
+                    );
+                    std::ptr::null_mut()
+                }
+            },
+            Err(crate::replace::ReplacementError::Runtime(e)) => {
+                fail(error, code, e.code().into(), &e.to_string());
+                std::ptr::null_mut()
+            }
+            Err(e) => {
+                fail(error, code, OgexErrorCode::InvalidTemplate, &e.to_string());
+                std::ptr::null_mut()
+            }
+        }
+    });
+
+    match result {
+        Ok(val) => val,
+        Err(_) => {
+            fail(
+                error,
+                code,
+                OgexErrorCode::Panic,
+                "panic during regex replace",
+            );
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Split an input into the pieces between matches
+///
+/// # Safety
+/// - handle must be a valid regex handle
+/// - input must be a valid null-terminated UTF-8 string
+/// - error pointer can be null
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
+///
+/// Returns a split handle to free with ogex_free_split, or null on error,
+/// including a search that reaches the step limit set with ogex_compile_ex
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_split(
+    handle: *const RegexHandle,
+    input: *const c_char,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut SplitHandle {
+    set_code(code, OgexErrorCode::Ok);
+    if handle.is_null() || input.is_null() {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "invalid handle or input",
+        );
+        return std::ptr::null_mut();
+    }
+
+    let result = catch_unwind(|| {
+        // SAFETY: handles are checked non-null above
+        let input_str = unsafe {
+            match CStr::from_ptr(input).to_str() {
+                Ok(s) => s,
+                Err(_) => {
+                    fail(
+                        error,
+                        code,
+                        OgexErrorCode::InvalidUtf8,
+                        "input is not valid UTF-8",
+                    );
+                    return std::ptr::null_mut();
+                }
+            }
+        };
+
+        let regex = unsafe { &(*handle).regex };
+        // Pieces of a C string cannot contain a null byte
+        let pieces = regex
+            .try_split(input_str)
+            .map(|piece| piece.map(|piece| CString::new(piece).unwrap_or_default()))
+            .collect();
+        match pieces {
+            Ok(pieces) => check_alloc(new_handle(SplitHandle { pieces }), error, code),
+            Err(e) => {
+                fail(error, code, e.code().into(), &e.to_string());
+                std::ptr::null_mut()
+            }
+        }
+    });
+
+    match result {
+        Ok(val) => val,
+        Err(_) => {
+            fail(
+                error,
+                code,
+                OgexErrorCode::Panic,
+                "panic during regex split",
+            );
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Get the number of pieces in a split
+///
+/// # Safety
+/// - handle must be a valid split handle
+/// - returns -1 if handle is null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_split_len(handle: *const SplitHandle) -> c_int {
+    unsafe {
+        if handle.is_null() {
+            return -1;
+        }
+        (*handle).pieces.len() as c_int
+    }
+}
+
+/// Get a piece of a split
+///
+/// # Safety
+/// - handle must be a valid split handle
+/// - returns null if handle is null or idx is out of range
+/// - the returned string is owned by the handle and stays valid until
+///   ogex_free_split is called; it must not be freed by the caller
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_split_get(handle: *const SplitHandle, idx: c_int) -> *const c_char {
+    unsafe {
+        if handle.is_null() {
+            return std::ptr::null();
+        }
+        let pieces = &(*handle).pieces;
+        usize::try_from(idx)
+            .ok()
+            .and_then(|idx| pieces.get(idx))
+            .map_or(std::ptr::null(), |piece| piece.as_ptr())
+    }
+}
+
+/// Free a split handle
+///
+/// # Safety
+/// - handle must be a valid pointer returned by ogex_split
+/// - handle and the strings it returned must not be used after calling this
+///   function
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_free_split(handle: *mut SplitHandle) {
+    unsafe { free_handle(handle) }
+}
+
+/// Convert an Ogex pattern to another dialect
+///
+/// # Safety
+/// - pattern must be a valid null-terminated UTF-8 string
+/// - error pointer can be null
+/// - code pointer can be null; otherwise it receives `Ok` or the kind of
+///   failure
+/// - caller must free the returned string with ogex_free_string
+///
+/// Returns the pattern in the OGEX_DIALECT_* syntax `dialect`, or null on
+/// error
+#[cfg(feature = "transpiler")]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_transpile(
+    pattern: *const c_char,
+    dialect: c_int,
+    error: *mut *mut c_char,
+    code: *mut OgexErrorCode,
+) -> *mut c_char {
+    set_code(code, OgexErrorCode::Ok);
+    if pattern.is_null() {
+        fail(
+            error,
+            code,
+            OgexErrorCode::InvalidArgument,
+            "pattern is null",
+        );
+        return std::ptr::null_mut();
+    }
+
+    let convert = match dialect {
+        OGEX_DIALECT_OGEX => crate::transpiler::transpile_to_ogex,
+        OGEX_DIALECT_PCRE => crate::transpiler::transpile,
+        OGEX_DIALECT_PYTHON => crate::transpiler::transpile_to_python,
+        _ => {
+            fail(
+                error,
+                code,
+                OgexErrorCode::InvalidArgument,
+                "unsupported dialect",
+            );
+            return std::ptr::null_mut();
+        }
+    };
+
+    let result = catch_unwind(|| {
+        // SAFETY: pattern is checked non-null above
+        let pattern_str = match unsafe { CStr::from_ptr(pattern) }.to_str() {
+            Ok(s) => s,
+            Err(_) => {
+                fail(
+                    error,
+                    code,
+                    OgexErrorCode::InvalidUtf8,
+                    "pattern is not valid UTF-8",
+                );
+                return std::ptr::null_mut();
+            }
+        };
+
+        match convert(pattern_str).map(CString::new) {
+            Ok(Ok(cstr)) => check_alloc(new_string(cstr), error, code),
+            Ok(Err(_)) => {
+                fail(
+                    error,
+                    code,
+                    OgexErrorCode::InvalidArgument,
+                    "result contains a null byte",
+                );
+                std::ptr::null_mut()
+            }
+            Err(e) => {
+                fail(error, code, e.code().into(), &e.to_string());
+                std::ptr::null_mut()
+            }
+        }
+    });
+
+    match result {
+        Ok(val) => val,
+        Err(_) => {
+            fail(
+                error,
+                code,
+                OgexErrorCode::Panic,
+                "panic during transpilation",
+            );
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Free an iterator handle
+///
+/// Match handles returned by the iterator stay valid and must still be freed
+/// with ogex_free_match.
+///
+/// # Safety
+/// - iter must be a valid pointer returned by ogex_find_iter_new
+/// - iter must not be used after calling this function
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_find_iter_free(iter: *mut FindIterHandle) {
+    unsafe { free_handle(iter) }
+}
+
+/// Free a match handle
+///
+/// # Safety
+/// - handle must be a valid pointer returned by ogex_find
+/// - handle must not be used after calling this function
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_free_match(handle: *mut MatchHandle) {
+    unsafe { free_handle(handle) }
+}
+
+/// Get match start position
+///
+/// # Safety
+/// - handle must be a valid match handle
+/// - returns -1 if handle is null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_match_start(handle: *const MatchHandle) -> c_int {
+    unsafe {
+        if handle.is_null() {
+            return -1;
+        }
+        (*handle).group(0).map_or(-1, |(start, _)| start)
+    }
+}
+
+/// Get match end position
+///
+/// # Safety
+/// - handle must be a valid match handle
+/// - returns -1 if handle is null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_match_end(handle: *const MatchHandle) -> c_int {
+    unsafe {
+        if handle.is_null() {
+            return -1;
+        }
+        (*handle).group(0).map_or(-1, |(_, end)| end)
+    }
+}
+
+/// Get the matched text
+///
+/// # Safety
+/// - handle must be a valid match handle
+/// - returns null if handle is null
+/// - caller must free the returned string with ogex_free_string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_match_text(handle: *const MatchHandle) -> *mut c_char {
+    unsafe {
+        if handle.is_null() {
+            return std::ptr::null_mut();
+        }
+
+        match CString::new((*handle).match_result.as_str()) {
+            Ok(cstr) => new_string(cstr),
+            Err(_) => std::ptr::null_mut(),
+        }
+    }
+}
+
+/// Get the number of capture groups, not counting the entire match
+///
+/// # Safety
+/// - handle must be a valid match handle
+/// - returns -1 if handle is null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_match_group_count(handle: *const MatchHandle) -> c_int {
+    unsafe {
+        if handle.is_null() {
+            return -1;
+        }
+        ((*handle).spans.len() - 1) as c_int
+    }
+}
+
+/// Get the start position of a capture group
+///
+/// Group 0 is the entire match.
+///
+/// # Safety
+/// - handle must be a valid match handle
+/// - returns -1 if handle is null, idx is out of range, or the group did not
+///   participate in the match
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_match_group_start(handle: *const MatchHandle, idx: c_int) -> c_int {
+    unsafe {
+        if handle.is_null() {
+            return -1;
+        }
+        let handle = &*handle;
+        handle.group(idx).map_or(-1, |(start, _)| start)
+    }
+}
+
+/// Get the end position of a capture group
+///
+/// Group 0 is the entire match.
+///
+/// # Safety
+/// - handle must be a valid match handle
+/// - returns -1 if handle is null, idx is out of range, or the group did not
+///   participate in the match
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_match_group_end(handle: *const MatchHandle, idx: c_int) -> c_int {
+    unsafe {
+        if handle.is_null() {
+            return -1;
+        }
+        let handle = &*handle;
+        handle.group(idx).map_or(-1, |(_, end)| end)
+    }
+}
+
+/// Get the text of a capture group
+///
+/// Group 0 is the entire match.
+///
+/// # Safety
+/// - handle must be a valid match handle
+/// - returns null if handle is null, idx is out of range, or the group did
+///   not participate in the match
+/// - caller must free the returned string with ogex_free_string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_match_group_text(
+    handle: *const MatchHandle,
+    idx: c_int,
+) -> *mut c_char {
+    unsafe {
         if handle.is_null() {
             return std::ptr::null_mut();
         }
 
-        let m = &(*handle).match_result;
-        let text = m.as_str(&(*handle).input);
+        let handle = &*handle;
+        let Some(text) = handle.group_text(idx) else {
+            return std::ptr::null_mut();
+        };
+        match CString::new(text) {
+            Ok(cstr) => new_string(cstr),
+            Err(_) => std::ptr::null_mut(),
+        }
+    }
+}
+
+/// Get the text of a named capture group
+///
+/// # Safety
+/// - handle must be a valid match handle
+/// - name must be a valid null-terminated UTF-8 string
+/// - returns null if either pointer is null, the pattern has no group `name`,
+///   or the group did not participate in the match
+/// - caller must free the returned string with ogex_free_string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ogex_match_named_group(
+    handle: *const MatchHandle,
+    name: *const c_char,
+) -> *mut c_char {
+    if handle.is_null() || name.is_null() {
+        return std::ptr::null_mut();
+    }
+
+    unsafe {
+        let handle = &*handle;
+        let Ok(name) = CStr::from_ptr(name).to_str() else {
+            return std::ptr::null_mut();
+        };
+        let Some(text) = handle.match_result.named_group_str(name) else {
+            return std::ptr::null_mut();
+        };
         match CString::new(text) {
-            Ok(cstr) => cstr.into_raw(),
+            Ok(cstr) => new_string(cstr),
             Err(_) => std::ptr::null_mut(),
         }
     }
@@ -250,11 +1786,7 @@ pub unsafe extern "C" fn ogex_match_text(handle: *const MatchHandle) -> *mut c_c
 /// - ptr must not be used after calling this function
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn ogex_free_string(ptr: *mut c_char) {
-    unsafe {
-        if !ptr.is_null() {
-            drop(CString::from_raw(ptr));
-        }
-    }
+    unsafe { free_string(ptr) }
 }
 
 /// Free an error string
@@ -333,4 +1865,679 @@ mod tests {
             ogex_free_regex(regex);
         }
     }
+
+    #[test]
+    fn test_c_api_find_iter() {
+        unsafe {
+            let pattern = CString::new(r"\d*").unwrap();
+            let mut error: *mut c_char = std::ptr::null_mut();
+
+            let regex = ogex_compile(pattern.as_ptr(), &mut error);
+            assert!(!regex.is_null());
+
+            let input = CString::new("a12b3").unwrap();
+            let iter = ogex_find_iter_new(regex, input.as_ptr(), &mut error, std::ptr::null_mut());
+            assert!(!iter.is_null());
+
+            let mut spans = Vec::new();
+            loop {
+                let match_handle = ogex_find_iter_next(iter, &mut error, std::ptr::null_mut());
+                if match_handle.is_null() {
+                    break;
+                }
+                spans.push((ogex_match_start(match_handle), ogex_match_end(match_handle)));
+                ogex_free_match(match_handle);
+            }
+            assert!(error.is_null());
+            assert_eq!(spans, vec![(0, 0), (1, 3), (3, 3), (4, 5), (5, 5)]);
+
+            // Exhausted iterators keep returning null
+            assert!(ogex_find_iter_next(iter, &mut error, std::ptr::null_mut()).is_null());
+
+            ogex_find_iter_free(iter);
+            ogex_free_regex(regex);
+        }
+    }
+
+    #[test]
+    fn test_c_api_groups() {
+        unsafe {
+            let pattern = CString::new(r"(\d+)-(x)?(\d+)").unwrap();
+            let mut error: *mut c_char = std::ptr::null_mut();
+
+            let regex = ogex_compile(pattern.as_ptr(), &mut error);
+            assert!(!regex.is_null());
+
+            let input = CString::new("on 12-34").unwrap();
+            let match_handle = ogex_find(regex, input.as_ptr(), &mut error);
+            assert!(!match_handle.is_null());
+
+            assert_eq!(ogex_match_group_count(match_handle), 3);
+            assert_eq!(ogex_match_group_start(match_handle, 0), 3);
+            assert_eq!(ogex_match_group_end(match_handle, 0), 8);
+            assert_eq!(ogex_match_group_start(match_handle, 3), 6);
+            assert_eq!(ogex_match_group_end(match_handle, 3), 8);
+
+            // Non-participating and out-of-range groups
+            assert_eq!(ogex_match_group_start(match_handle, 2), -1);
+            assert!(ogex_match_group_text(match_handle, 2).is_null());
+            assert_eq!(ogex_match_group_end(match_handle, 4), -1);
+            assert_eq!(ogex_match_group_end(match_handle, -1), -1);
+
+            let text = ogex_match_group_text(match_handle, 1);
+            assert_eq!(CStr::from_ptr(text).to_str().unwrap(), "12");
+
+            ogex_free_string(text);
+            ogex_free_match(match_handle);
+            ogex_free_regex(regex);
+        }
+    }
+
+    #[test]
+    fn test_c_api_named_groups() {
+        unsafe {
+            let pattern = CString::new(r"(year:\d{4})-(\d+)(day:x)?").unwrap();
+            let mut error: *mut c_char = std::ptr::null_mut();
+
+            let regex = ogex_compile(pattern.as_ptr(), &mut error);
+            assert!(!regex.is_null());
+
+            let year = CString::new("year").unwrap();
+            let day = CString::new("day").unwrap();
+            let missing = CString::new("month").unwrap();
+            assert_eq!(ogex_regex_group_index(regex, year.as_ptr()), 1);
+            assert_eq!(ogex_regex_group_index(regex, day.as_ptr()), 3);
+            assert_eq!(ogex_regex_group_index(regex, missing.as_ptr()), -1);
+
+            let name = ogex_regex_group_name(regex, 1);
+            assert_eq!(CStr::from_ptr(name).to_str().unwrap(), "year");
+            ogex_free_string(name);
+            assert!(ogex_regex_group_name(regex, 0).is_null());
+            assert!(ogex_regex_group_name(regex, 2).is_null());
+            assert!(ogex_regex_group_name(regex, 4).is_null());
+
+            let input = CString::new("2024-05").unwrap();
+            let match_handle = ogex_find(regex, input.as_ptr(), &mut error);
+            assert!(!match_handle.is_null());
+
+            let text = ogex_match_named_group(match_handle, year.as_ptr());
+            assert_eq!(CStr::from_ptr(text).to_str().unwrap(), "2024");
+            ogex_free_string(text);
+            assert!(ogex_match_named_group(match_handle, day.as_ptr()).is_null());
+            assert!(ogex_match_named_group(match_handle, missing.as_ptr()).is_null());
+
+            ogex_free_match(match_handle);
+            ogex_free_regex(regex);
+        }
+    }
+
+    #[cfg(feature = "replace")]
+    #[test]
+    fn test_c_api_replace() {
+        unsafe {
+            let pattern = CString::new(r"(word:\w+)@(\d)").unwrap();
+            let mut error: *mut c_char = std::ptr::null_mut();
+
+            let regex = ogex_compile(pattern.as_ptr(), &mut error);
+            assert!(!regex.is_null());
+
+            let input = CString::new("a@1 b@2 c@3").unwrap();
+            let template = CString::new(r"\2:\g{word}").unwrap();
+
+            let all = ogex_replace(
+                regex,
+                input.as_ptr(),
+                template.as_ptr(),
+                0,
+                &mut error,
+                std::ptr::null_mut(),
+            );
+            assert_eq!(CStr::from_ptr(all).to_str().unwrap(), "1:a 2:b 3:c");
+            ogex_free_string(all);
+
+            let first = ogex_replace(
+                regex,
+                input.as_ptr(),
+                template.as_ptr(),
+                1,
+                &mut error,
+                std::ptr::null_mut(),
+            );
+            assert_eq!(CStr::from_ptr(first).to_str().unwrap(), "1:a b@2 c@3");
+            ogex_free_string(first);
+            assert!(error.is_null());
+
+            let bad = CString::new(r"\g{missing}").unwrap();
+            let result = ogex_replace(
+                regex,
+                input.as_ptr(),
+                bad.as_ptr(),
+                0,
+                &mut error,
+                std::ptr::null_mut(),
+            );
+            assert!(result.is_null());
+            assert!(!error.is_null());
+            ogex_free_error(error);
+
+            ogex_free_regex(regex);
+        }
+    }
+
+    #[test]
+    fn test_c_api_split() {
+        unsafe {
+            let pattern = CString::new(r";\s*").unwrap();
+            let mut error: *mut c_char = std::ptr::null_mut();
+
+            let regex = ogex_compile(pattern.as_ptr(), &mut error);
+            assert!(!regex.is_null());
+
+            let input = CString::new("a; b;c;").unwrap();
+            let split = ogex_split(regex, input.as_ptr(), &mut error, std::ptr::null_mut());
+            assert!(!split.is_null());
+
+            assert_eq!(ogex_split_len(split), 4);
+            let pieces: Vec<_> = (0..4)
+                .map(|i| CStr::from_ptr(ogex_split_get(split, i)).to_str().unwrap())
+                .collect();
+            assert_eq!(pieces, vec!["a", "b", "c", ""]);
+            assert!(ogex_split_get(split, 4).is_null());
+            assert!(ogex_split_get(split, -1).is_null());
+
+            ogex_free_split(split);
+            ogex_free_regex(regex);
+        }
+    }
+
+    #[test]
+    fn test_c_api_compile_ex() {
+        unsafe {
+            let mut options = std::mem::MaybeUninit::<OgexOptions>::uninit();
+            ogex_options_init(options.as_mut_ptr());
+            let mut options = options.assume_init();
+            options.case_insensitive = 1;
+            options.step_limit = 10;
+
+            let pattern = CString::new("a+b").unwrap();
+            let mut error: *mut c_char = std::ptr::null_mut();
+            let regex =
+                ogex_compile_ex(pattern.as_ptr(), &options, &mut error, std::ptr::null_mut());
+            assert!(!regex.is_null());
+
+            let input = CString::new("xAB").unwrap();
+            assert_eq!(ogex_is_match(regex, input.as_ptr()), 1);
+
+            let long = CString::new("a".repeat(100)).unwrap();
+            assert!(ogex_find(regex, long.as_ptr(), &mut error).is_null());
+            assert!(!error.is_null());
+            assert!(
+                CStr::from_ptr(error)
+                    .to_str()
+                    .unwrap()
+                    .contains("step limit")
+            );
+            ogex_free_error(error);
+            ogex_free_regex(regex);
+
+            options.step_limit = 0;
+            options.max_haystack_len = 8;
+            let regex = ogex_compile_ex(
+                pattern.as_ptr(),
+                &options,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            );
+            assert!(!regex.is_null());
+            let mut code = OgexErrorCode::Ok;
+            assert_eq!(ogex_is_match_code(regex, input.as_ptr(), &mut code), 1);
+            assert_eq!(ogex_is_match_code(regex, long.as_ptr(), &mut code), 0);
+            assert_eq!(code, OgexErrorCode::HaystackTooLarge);
+            ogex_free_regex(regex);
+
+            let mut error: *mut c_char = std::ptr::null_mut();
+            options.dialect = 7;
+            assert!(
+                ogex_compile_ex(pattern.as_ptr(), &options, &mut error, std::ptr::null_mut())
+                    .is_null()
+            );
+            ogex_free_error(error);
+
+            let mut error: *mut c_char = std::ptr::null_mut();
+            let regex = ogex_compile_ex(
+                pattern.as_ptr(),
+                std::ptr::null(),
+                &mut error,
+                std::ptr::null_mut(),
+            );
+            assert!(!regex.is_null());
+            ogex_free_regex(regex);
+        }
+    }
+
+    #[test]
+    fn test_c_api_step_limit_after_first_match() {
+        unsafe {
+            let mut options = std::mem::MaybeUninit::<OgexOptions>::uninit();
+            ogex_options_init(options.as_mut_ptr());
+            let mut options = options.assume_init();
+            options.step_limit = 200;
+
+            let pattern = CString::new("(a|a)*b|x").unwrap();
+            let regex = ogex_compile_ex(
+                pattern.as_ptr(),
+                &options,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            );
+            assert!(!regex.is_null());
+            let input = CString::new(format!("x {} x", "a".repeat(30))).unwrap();
+            let tail = CString::new(format!(" {} x", "a".repeat(30))).unwrap();
+
+            let mut code = OgexErrorCode::Ok;
+            assert_eq!(ogex_is_match_code(regex, input.as_ptr(), &mut code), 1);
+            assert_eq!(code, OgexErrorCode::Ok);
+            assert_eq!(ogex_is_match_code(regex, tail.as_ptr(), &mut code), 0);
+            assert_eq!(code, OgexErrorCode::StepLimitExceeded);
+
+            #[cfg(feature = "replace")]
+            {
+                let template = CString::new("y").unwrap();
+                let mut error: *mut c_char = std::ptr::null_mut();
+                let replaced = ogex_replace(
+                    regex,
+                    input.as_ptr(),
+                    template.as_ptr(),
+                    0,
+                    &mut error,
+                    &mut code,
+                );
+                assert!(replaced.is_null());
+                assert_eq!(code, OgexErrorCode::StepLimitExceeded);
+                ogex_free_error(error);
+            }
+
+            let split = ogex_split(regex, input.as_ptr(), std::ptr::null_mut(), &mut code);
+            assert!(split.is_null());
+            assert_eq!(code, OgexErrorCode::StepLimitExceeded);
+            ogex_free_regex(regex);
+        }
+    }
+
+    #[cfg(feature = "transpiler")]
+    #[test]
+    fn test_c_api_compile_ex_dialects() {
+        unsafe {
+            let mut options = std::mem::MaybeUninit::<OgexOptions>::uninit();
+            ogex_options_init(options.as_mut_ptr());
+            let mut options = options.assume_init();
+
+            let same = CString::new("ab-ab").unwrap();
+            let different = CString::new("ab-cd").unwrap();
+            for (dialect, pattern) in [
+                (OGEX_DIALECT_OGEX, r"(word:\w+)-\g{word}"),
+                (OGEX_DIALECT_PCRE, r"(?<word>\w+)-\k<word>"),
+                (OGEX_DIALECT_PYTHON, r"(?P<word>\w+)-(?P=word)"),
+            ] {
+                options.dialect = dialect;
+                let pattern = CString::new(pattern).unwrap();
+                let mut code = OgexErrorCode::Panic;
+                let regex =
+                    ogex_compile_ex(pattern.as_ptr(), &options, std::ptr::null_mut(), &mut code);
+                assert!(!regex.is_null(), "dialect {dialect}");
+                assert_eq!(code, OgexErrorCode::Ok);
+                assert_eq!(ogex_is_match(regex, same.as_ptr()), 1);
+                assert_eq!(ogex_is_match(regex, different.as_ptr()), 0);
+                ogex_free_regex(regex);
+            }
+
+            options.dialect = OGEX_DIALECT_PCRE;
+            let unclosed = CString::new("(?<word>a").unwrap();
+            let mut error: *mut c_char = std::ptr::null_mut();
+            let mut code = OgexErrorCode::Ok;
+            assert!(ogex_compile_ex(unclosed.as_ptr(), &options, &mut error, &mut code).is_null());
+            assert_eq!(code, OgexErrorCode::UnexpectedToken);
+            assert!(!error.is_null());
+            ogex_free_error(error);
+        }
+    }
+
+    #[test]
+    fn test_c_api_length_variants() {
+        unsafe {
+            let pattern = b"b.*c trailing";
+            let mut error: *mut c_char = std::ptr::null_mut();
+            let regex =
+                ogex_compile_n(pattern.as_ptr().cast(), 4, &mut error, std::ptr::null_mut());
+            assert!(!regex.is_null());
+
+            let input = b"a b\0\0c d";
+            assert_eq!(
+                ogex_is_match_n(regex, input.as_ptr().cast(), input.len()),
+                1
+            );
+            assert_eq!(ogex_is_match_n(regex, input.as_ptr().cast(), 5), 0);
+
+            let match_handle = ogex_find_n(
+                regex,
+                input.as_ptr().cast(),
+                input.len(),
+                &mut error,
+                std::ptr::null_mut(),
+            );
+            assert!(!match_handle.is_null());
+            assert_eq!(ogex_match_start(match_handle), 2);
+            assert_eq!(ogex_match_end(match_handle), 6);
+            ogex_free_match(match_handle);
+
+            let invalid = [0xff_u8];
+            assert!(
+                ogex_find_n(
+                    regex,
+                    invalid.as_ptr().cast(),
+                    1,
+                    &mut error,
+                    std::ptr::null_mut()
+                )
+                .is_null()
+            );
+            assert!(!error.is_null());
+            ogex_free_error(error);
+
+            ogex_free_regex(regex);
+        }
+    }
+
+    #[test]
+    fn test_c_api_find_at() {
+        unsafe {
+            let pattern = CString::new(r"\w+").unwrap();
+            let mut error: *mut c_char = std::ptr::null_mut();
+            let regex = ogex_compile(pattern.as_ptr(), &mut error);
+            assert!(!regex.is_null());
+
+            let input = CString::new("ab cd ef").unwrap();
+            let mut pos = 0;
+            let mut words = Vec::new();
+            loop {
+                let match_handle =
+                    ogex_find_at(regex, input.as_ptr(), pos, &mut error, std::ptr::null_mut());
+                if match_handle.is_null() {
+                    break;
+                }
+                words.push(ogex_match_start(match_handle));
+                pos = ogex_match_end(match_handle) as usize;
+                ogex_free_match(match_handle);
+            }
+            assert!(error.is_null());
+            assert_eq!(words, vec![0, 3, 6]);
+
+            assert!(
+                ogex_find_at(regex, input.as_ptr(), 9, &mut error, std::ptr::null_mut()).is_null()
+            );
+            assert!(!error.is_null());
+            ogex_free_error(error);
+
+            ogex_free_regex(regex);
+        }
+    }
+
+    #[cfg(feature = "transpiler")]
+    #[test]
+    fn test_c_api_transpile() {
+        unsafe {
+            let pattern = CString::new(r"(word:\w+)\g{word}").unwrap();
+            let mut error: *mut c_char = std::ptr::null_mut();
+
+            let pcre = ogex_transpile(
+                pattern.as_ptr(),
+                OGEX_DIALECT_PCRE,
+                &mut error,
+                std::ptr::null_mut(),
+            );
+            assert_eq!(
+                CStr::from_ptr(pcre).to_str().unwrap(),
+                r"(?<word>\w+)\k<word>"
+            );
+            ogex_free_string(pcre);
+
+            let python = ogex_transpile(
+                pattern.as_ptr(),
+                OGEX_DIALECT_PYTHON,
+                &mut error,
+                std::ptr::null_mut(),
+            );
+            assert_eq!(
+                CStr::from_ptr(python).to_str().unwrap(),
+                r"(?P<word>\w+)(?P=word)"
+            );
+            ogex_free_string(python);
+            assert!(error.is_null());
+
+            assert!(
+                ogex_transpile(pattern.as_ptr(), 99, &mut error, std::ptr::null_mut()).is_null()
+            );
+            ogex_free_error(error);
+
+            let mut error: *mut c_char = std::ptr::null_mut();
+            let invalid = CString::new("(a").unwrap();
+            assert!(
+                ogex_transpile(
+                    invalid.as_ptr(),
+                    OGEX_DIALECT_PCRE,
+                    &mut error,
+                    std::ptr::null_mut()
+                )
+                .is_null()
+            );
+            assert!(!error.is_null());
+            ogex_free_error(error);
+        }
+    }
+
+    #[test]
+    fn test_c_api_error_codes() {
+        unsafe {
+            let mut error: *mut c_char = std::ptr::null_mut();
+            let mut code = OgexErrorCode::Panic;
+
+            let pattern = CString::new("(a").unwrap();
+            let regex = ogex_compile_code(pattern.as_ptr(), &mut error, &mut code);
+            assert!(regex.is_null());
+            assert_eq!(code, OgexErrorCode::UnexpectedToken);
+            assert_eq!(code as c_int, 101);
+            ogex_free_error(error);
+
+            let mut error: *mut c_char = std::ptr::null_mut();
+            let regex = ogex_compile_code(std::ptr::null(), &mut error, &mut code);
+            assert!(regex.is_null());
+            assert_eq!(code, OgexErrorCode::InvalidArgument);
+            ogex_free_error(error);
+
+            let mut error: *mut c_char = std::ptr::null_mut();
+            let pattern = CString::new("a").unwrap();
+            let regex = ogex_compile_code(pattern.as_ptr(), &mut error, &mut code);
+            assert!(!regex.is_null());
+            assert_eq!(code, OgexErrorCode::Ok);
+
+            // No match and no error
+            let input = CString::new("b").unwrap();
+            assert!(ogex_find_code(regex, input.as_ptr(), &mut error, &mut code).is_null());
+            assert_eq!(code, OgexErrorCode::Ok);
+
+            let input = CString::new("é").unwrap();
+            assert!(ogex_find_at(regex, input.as_ptr(), 1, &mut error, &mut code).is_null());
+            assert_eq!(code, OgexErrorCode::InvalidUtf8Offset);
+            ogex_free_error(error);
+
+            let invalid = [b'a', 0xff, 0];
+            let mut error: *mut c_char = std::ptr::null_mut();
+            let iter = ogex_find_iter_new(regex, invalid.as_ptr().cast(), &mut error, &mut code);
+            assert!(iter.is_null());
+            assert_eq!(code, OgexErrorCode::InvalidUtf8);
+            ogex_free_error(error);
+
+            let mut error: *mut c_char = std::ptr::null_mut();
+            assert!(ogex_split(regex, std::ptr::null(), &mut error, &mut code).is_null());
+            assert_eq!(code, OgexErrorCode::InvalidArgument);
+            ogex_free_error(error);
+            let split = ogex_split(regex, input.as_ptr(), std::ptr::null_mut(), &mut code);
+            assert!(!split.is_null());
+            assert_eq!(code, OgexErrorCode::Ok);
+            ogex_free_split(split);
+
+            #[cfg(feature = "replace")]
+            {
+                let template = CString::new(r"\g{missing}").unwrap();
+                let mut error: *mut c_char = std::ptr::null_mut();
+                let replaced = ogex_replace(
+                    regex,
+                    input.as_ptr(),
+                    template.as_ptr(),
+                    0,
+                    &mut error,
+                    &mut code,
+                );
+                assert!(replaced.is_null());
+                assert_eq!(code, OgexErrorCode::InvalidTemplate);
+                ogex_free_error(error);
+            }
+
+            #[cfg(feature = "transpiler")]
+            {
+                let unclosed = CString::new("(a").unwrap();
+                let mut error: *mut c_char = std::ptr::null_mut();
+                let transpiled =
+                    ogex_transpile(unclosed.as_ptr(), OGEX_DIALECT_PCRE, &mut error, &mut code);
+                assert!(transpiled.is_null());
+                assert_eq!(code, OgexErrorCode::UnexpectedToken);
+                ogex_free_error(error);
+
+                let mut error: *mut c_char = std::ptr::null_mut();
+                assert!(ogex_transpile(pattern.as_ptr(), 99, &mut error, &mut code).is_null());
+                assert_eq!(code, OgexErrorCode::InvalidArgument);
+                ogex_free_error(error);
+            }
+
+            ogex_free_regex(regex);
+        }
+    }
+
+    #[test]
+    fn test_c_api_utf16() {
+        unsafe {
+            let pattern = CString::new(r"(\d+)").unwrap();
+            let mut error: *mut c_char = std::ptr::null_mut();
+            let regex = ogex_compile(pattern.as_ptr(), &mut error);
+            assert!(!regex.is_null());
+
+            // The emoji is two UTF-16 code units and four UTF-8 bytes
+            let input: Vec<u16> = "é😀 42".encode_utf16().collect();
+            assert_eq!(ogex_is_match_utf16(regex, input.as_ptr(), input.len()), 1);
+            assert_eq!(ogex_is_match_utf16(regex, input.as_ptr(), 3), 0);
+
+            let match_handle = ogex_find_utf16(
+                regex,
+                input.as_ptr(),
+                input.len(),
+                &mut error,
+                std::ptr::null_mut(),
+            );
+            assert!(!match_handle.is_null());
+            assert_eq!(ogex_match_start(match_handle), 4);
+            assert_eq!(ogex_match_end(match_handle), 6);
+            assert_eq!(ogex_match_group_start(match_handle, 1), 4);
+            let text = ogex_match_text(match_handle);
+            assert_eq!(CStr::from_ptr(text).to_str().unwrap(), "42");
+            ogex_free_string(text);
+            ogex_free_match(match_handle);
+
+            let mut code = OgexErrorCode::Ok;
+            let lone_surrogate = [0xd800_u16];
+            assert!(
+                ogex_find_utf16(regex, lone_surrogate.as_ptr(), 1, &mut error, &mut code).is_null()
+            );
+            assert_eq!(code, OgexErrorCode::InvalidUtf16);
+            ogex_free_error(error);
+
+            ogex_free_regex(regex);
+        }
+    }
+
+    #[test]
+    fn test_c_api_utf16_non_ascii_pattern() {
+        unsafe {
+            let pattern = CString::new("é+(😀)").unwrap();
+            let regex = ogex_compile(pattern.as_ptr(), std::ptr::null_mut());
+            assert!(!regex.is_null());
+
+            let input: Vec<u16> = "aéé😀b".encode_utf16().collect();
+            let mut code = OgexErrorCode::Panic;
+            let match_handle = ogex_find_utf16(
+                regex,
+                input.as_ptr(),
+                input.len(),
+                std::ptr::null_mut(),
+                &mut code,
+            );
+            assert!(!match_handle.is_null());
+            assert_eq!(code, OgexErrorCode::Ok);
+            assert_eq!(ogex_match_start(match_handle), 1);
+            assert_eq!(ogex_match_end(match_handle), 5);
+            assert_eq!(ogex_match_group_start(match_handle, 1), 3);
+            assert_eq!(ogex_match_group_end(match_handle, 1), 5);
+            let text = ogex_match_text(match_handle);
+            assert_eq!(CStr::from_ptr(text).to_str().unwrap(), "éé😀");
+            ogex_free_string(text);
+            ogex_free_match(match_handle);
+
+            ogex_free_regex(regex);
+        }
+    }
+
+    #[test]
+    fn test_c_api_regex_clone_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Regex>();
+
+        unsafe {
+            let pattern = CString::new(r"\d+").unwrap();
+            let regex = ogex_compile(pattern.as_ptr(), std::ptr::null_mut());
+            assert!(!regex.is_null());
+
+            let clones: Vec<usize> = (0..4).map(|_| ogex_regex_clone(regex) as usize).collect();
+            let input = CString::new("abc 123").unwrap();
+            let iter = ogex_find_iter_new(
+                regex,
+                input.as_ptr(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            );
+            ogex_free_regex(regex);
+
+            let threads: Vec<_> = clones
+                .into_iter()
+                .map(|clone| {
+                    std::thread::spawn(move || {
+                        let clone = clone as *mut RegexHandle;
+                        let input = CString::new("abc 123").unwrap();
+                        let matched = ogex_is_match(clone, input.as_ptr());
+                        ogex_free_regex(clone);
+                        matched
+                    })
+                })
+                .collect();
+            for thread in threads {
+                assert_eq!(thread.join().unwrap(), 1);
+            }
+
+            // The iterator outlives every regex handle
+            let m = ogex_find_iter_next(iter, std::ptr::null_mut(), std::ptr::null_mut());
+            assert_eq!(ogex_match_start(m), 4);
+            ogex_free_match(m);
+            ogex_find_iter_free(iter);
+
+            assert!(ogex_regex_clone(std::ptr::null()).is_null());
+        }
+    }
 }
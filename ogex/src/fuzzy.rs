@@ -0,0 +1,286 @@
+//! Approximate matching within an edit budget
+//!
+//! A [`FuzzyRegex`] finds text that a pattern would match after at most a
+//! given number of edits: characters inserted into the input, left out of
+//! it, or substituted for another. Each match reports how many edits it
+//! needed, so a search for `error` with one edit also finds `eror` and
+//! `errnr`.
+//!
+//! The search is leftmost first: of the matches starting at the earliest
+//! position, the one with the fewest edits wins, and among those the longest.
+//! A match never begins with an inserted character, since the same match
+//! starting one character later needs one edit less.
+//!
+//! Offsets are byte offsets into the input. Anchors, word boundaries and
+//! lookarounds are checked exactly, without edits. Backreferences never
+//! match in a fuzzy search, since captures are not tracked.
+//!
+//! ```
+//! use ogex::Regex;
+//!
+//! let regex = Regex::new(r"error \d+").unwrap();
+//! let found = regex.find_fuzzy("log: eror 42", 1).unwrap();
+//! assert_eq!(found.as_str("log: eror 42"), "eror 42");
+//! assert_eq!(found.edits, 1);
+//! assert!(regex.find_fuzzy("log: eror 42", 0).is_none());
+//! ```
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+
+use crate::engine::{Regex, assertion_holds, next_char_boundary};
+use crate::nfa::Transition;
+
+/// A match found by a fuzzy search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    /// Byte offset of the start of the match
+    pub start: usize,
+    /// Byte offset of the end of the match (exclusive)
+    pub end: usize,
+    /// Insertions, deletions and substitutions the match needed
+    pub edits: u32,
+}
+
+impl FuzzyMatch {
+    /// Get the matched text
+    pub fn as_str<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.start..self.end]
+    }
+}
+
+/// Finds approximate matches of a regex within an edit budget
+///
+/// `R` is anything that borrows a [`Regex`], such as `&Regex` or
+/// `Rc<Regex>`.
+///
+/// ```
+/// use ogex::{FuzzyRegex, Regex};
+///
+/// let regex = Regex::new("colou?r").unwrap();
+/// let fuzzy = FuzzyRegex::new(&regex, 1);
+/// let found: Vec<_> = fuzzy
+///     .find_all("color, colr, calour")
+///     .iter()
+///     .map(|m| m.edits)
+///     .collect();
+/// assert_eq!(found, vec![0, 1, 1]);
+/// ```
+pub struct FuzzyRegex<R> {
+    regex: R,
+    max_edits: u32,
+}
+
+impl<R: Borrow<Regex>> FuzzyRegex<R> {
+    /// Search with `regex`, allowing at most `max_edits` edits per match
+    pub fn new(regex: R, max_edits: u32) -> Self {
+        FuzzyRegex { regex, max_edits }
+    }
+
+    /// Find the first approximate match
+    pub fn find(&self, input: &str) -> Option<FuzzyMatch> {
+        self.find_at(input, 0)
+    }
+
+    /// Find the first approximate match that starts at or after the byte
+    /// offset `start`, or `None` if `start` is past the end of the input or
+    /// inside a character
+    pub fn find_at(&self, input: &str, start: usize) -> Option<FuzzyMatch> {
+        if !input.is_char_boundary(start) {
+            return None;
+        }
+        let search = Search::new(self.regex.borrow(), input, self.max_edits);
+        let first = search.chars.partition_point(|&(offset, _)| offset < start);
+        (first..=search.chars.len()).find_map(|at| search.best_from(at))
+    }
+
+    /// Find every non-overlapping approximate match
+    pub fn find_all(&self, input: &str) -> Vec<FuzzyMatch> {
+        let mut found = Vec::new();
+        let mut pos = 0;
+        while pos <= input.len() {
+            let Some(m) = self.find_at(input, pos) else {
+                break;
+            };
+            // Step past empty matches so the search always makes progress
+            pos = if m.end == m.start {
+                next_char_boundary(input, m.end)
+            } else {
+                m.end
+            };
+            found.push(m);
+        }
+        found
+    }
+}
+
+/// One fuzzy search over an input
+struct Search<'a> {
+    regex: &'a Regex,
+    input: &'a str,
+    /// The input's characters with their byte offsets
+    chars: Vec<(usize, char)>,
+    max_edits: u32,
+}
+
+impl<'a> Search<'a> {
+    fn new(regex: &'a Regex, input: &'a str, max_edits: u32) -> Self {
+        Search {
+            regex,
+            input,
+            chars: input.char_indices().collect(),
+            max_edits,
+        }
+    }
+
+    /// Byte offset of the `at`th character, or the end of the input
+    fn offset(&self, at: usize) -> usize {
+        self.chars
+            .get(at)
+            .map_or(self.input.len(), |&(offset, _)| offset)
+    }
+
+    /// The best match starting at the `start`th character: fewest edits,
+    /// then longest
+    fn best_from(&self, start: usize) -> Option<FuzzyMatch> {
+        let nfa = self.regex.nfa();
+        let mut costs = vec![u32::MAX; nfa.states.len()];
+        costs[nfa.start] = 0;
+        self.close(&mut costs, start);
+
+        let mut best: Option<(usize, u32)> = None;
+        let mut at = start;
+        loop {
+            let edits = costs[nfa.accept];
+            if edits <= self.max_edits && best.is_none_or(|(_, fewest)| edits <= fewest) {
+                best = Some((at, edits));
+            }
+            let Some(&(_, c)) = self.chars.get(at) else {
+                break;
+            };
+
+            let mut next = vec![u32::MAX; costs.len()];
+            for (state, &cost) in costs.iter().enumerate() {
+                if cost > self.max_edits {
+                    continue;
+                }
+                for (transition, target) in &nfa.states[state].transitions {
+                    if transition.consumes_char() {
+                        let cost = cost + u32::from(!nfa.matches_char(transition, c));
+                        next[*target] = next[*target].min(cost);
+                    }
+                }
+                // Insert the character, staying in the same state
+                if at > start {
+                    next[state] = next[state].min(cost + 1);
+                }
+            }
+            at += 1;
+            if next.iter().all(|&cost| cost > self.max_edits) {
+                break;
+            }
+            self.close(&mut next, at);
+            costs = next;
+        }
+
+        best.map(|(end, edits)| FuzzyMatch {
+            start: self.offset(start),
+            end: self.offset(end),
+            edits,
+        })
+    }
+
+    /// Lower each state's cost to the cheapest way of reaching it without
+    /// consuming the `at`th character, leaving characters out of the input
+    /// at one edit each
+    fn close(&self, costs: &mut [u32], at: usize) {
+        let nfa = self.regex.nfa();
+        let pos = self.offset(at);
+        let mut pending: Vec<usize> = (0..costs.len())
+            .filter(|&state| costs[state] <= self.max_edits)
+            .collect();
+        while let Some(state) = pending.pop() {
+            let cost = costs[state];
+            for (transition, target) in &nfa.states[state].transitions {
+                let cost = match transition {
+                    Transition::Epsilon | Transition::GroupStart(_) | Transition::GroupEnd(_) => {
+                        cost
+                    }
+                    Transition::Backref(_) | Transition::BackrefRelative(_) => continue,
+                    _ if transition.consumes_char() => cost + 1,
+                    _ => match assertion_holds(nfa, self.input, transition, pos) {
+                        Some(true) => cost,
+                        _ => continue,
+                    },
+                };
+                if cost <= self.max_edits && cost < costs[*target] {
+                    costs[*target] = cost;
+                    pending.push(*target);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fuzzy<'a>(pattern: &str, input: &'a str, max_edits: u32) -> Option<(&'a str, u32)> {
+        let regex = Regex::new(pattern).unwrap();
+        regex
+            .find_fuzzy(input, max_edits)
+            .map(|m| (m.as_str(input), m.edits))
+    }
+
+    #[test]
+    fn test_each_kind_of_edit() {
+        assert_eq!(fuzzy("hello", "say hello", 1), Some(("hello", 0)));
+        assert_eq!(fuzzy("hello", "say hallo", 1), Some(("hallo", 1)));
+        assert_eq!(fuzzy("hello", "say helo!", 1), Some(("helo", 1)));
+        assert_eq!(fuzzy("hello", "say helllo", 1), Some(("helllo", 1)));
+        assert_eq!(fuzzy("hello", "say hxllx", 1), None);
+        assert_eq!(fuzzy("hello", "say hxllx", 2), Some(("hxllx", 2)));
+    }
+
+    #[test]
+    fn test_edits_apply_to_classes_and_repetition() {
+        assert_eq!(
+            fuzzy(r"\d{3}-\d{4}", "call 555-12a4", 1),
+            Some(("555-12a4", 1))
+        );
+        assert_eq!(
+            fuzzy(r"[a-z]+@[a-z]+\.com", "mail: bob#home.com", 1),
+            Some(("bob#home.com", 1))
+        );
+    }
+
+    #[test]
+    fn test_assertions_are_exact() {
+        assert_eq!(fuzzy(r"^cat", "xcat", 1), None);
+        assert_eq!(fuzzy(r"^cat", "cxat", 1), Some(("cxat", 1)));
+        assert_eq!(fuzzy(r"\bcat\b", "concat cot", 1), Some(("cot", 1)));
+    }
+
+    #[test]
+    fn test_offsets_are_bytes() {
+        let regex = Regex::new("café").unwrap();
+        let input = "été cafe";
+        let found = regex.find_fuzzy(input, 1).unwrap();
+        assert_eq!((found.start, found.end, found.edits), (6, 10, 1));
+        assert_eq!(found.as_str(input), "cafe");
+    }
+
+    #[test]
+    fn test_find_all_does_not_overlap() {
+        let regex = Regex::new("abc").unwrap();
+        let fuzzy = FuzzyRegex::new(&regex, 1);
+        let found: Vec<_> = fuzzy
+            .find_all("abc abd xbc")
+            .iter()
+            .map(|m| (m.start, m.end, m.edits))
+            .collect();
+        assert_eq!(found, vec![(0, 3, 0), (4, 7, 1), (8, 11, 1)]);
+    }
+}
@@ -0,0 +1,428 @@
+//! Random strings that match a pattern
+//!
+//! [`Generator`] walks a pattern's syntax tree and builds text the pattern
+//! matches, which is handy for test data and for exercising code that
+//! consumes matches. Unbounded repetitions such as `*` and `{2,}` repeat at
+//! most [`max_repeat`](Generator::max_repeat) times beyond their minimum.
+//!
+//! Anchors, word boundaries and lookarounds are not planned for while
+//! drawing. Instead every sample is checked against the pattern and redrawn
+//! if it does not match, so a pattern whose assertions rarely hold may yield
+//! no sample at all.
+//!
+//! Given a [`seed`](Generator::seed), a generator draws the same samples on
+//! every run, which keeps property tests reproducible.
+//!
+//! ```
+//! use ogex::{Generator, Regex};
+//!
+//! let mut generator = Generator::new(r"(id:[a-f]{2})-\d+")
+//!     .unwrap()
+//!     .max_repeat(2)
+//!     .seed(7);
+//! let sample = generator.generate().unwrap();
+//! assert!(Regex::new(r"^[a-f]{2}-\d{1,3}$").unwrap().is_match(&sample));
+//!
+//! let regex = Regex::new("(yes|no)!").unwrap();
+//! assert!(regex.is_match(&regex.generate().unwrap()));
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use crate::ast::{CharacterClass, ClassItem, Expr, Quantifier};
+use crate::collections::{HashMap, RandomState};
+use crate::engine::{Regex, RegexBuilder};
+use crate::error::RegexError;
+
+/// How many drafts are drawn before giving up on a sample
+const ATTEMPTS: usize = 100;
+
+/// Characters drawn for `.`, negated classes and negated shorthands
+fn pool() -> impl Iterator<Item = char> {
+    core::iter::once('\t').chain(' '..='~')
+}
+
+/// How a [`Generator`] chooses between the branches of an alternation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Weighting {
+    /// Every branch is equally likely
+    #[default]
+    Uniform,
+    /// Branches are weighted by the alternatives nested inside them, so
+    /// every path through the alternations is equally likely: in
+    /// `a|(b|c|d)` the group is taken three times as often as `a`
+    Alternatives,
+}
+
+/// Draws random strings matched by a pattern
+pub struct Generator {
+    ast: Expr,
+    /// The pattern anchored at both ends, used to check each draft
+    verifier: Regex,
+    max_repeat: u32,
+    weighting: Weighting,
+    rng: Rng,
+}
+
+impl Generator {
+    /// Parse a pattern to generate samples for
+    pub fn new(pattern: &str) -> Result<Self, RegexError> {
+        Self::from_regex(&Regex::new(pattern)?)
+    }
+
+    /// Generate samples for a compiled regex, honoring the options it was
+    /// built with
+    ///
+    /// With multiline mode on, samples are only checked to match within a
+    /// line, so they may not match in full.
+    pub fn from_regex(regex: &Regex) -> Result<Self, RegexError> {
+        let flags = &regex.nfa().mode_flags;
+        let mut parser = crate::parser::Parser::with_extended(regex.as_str(), flags.extended);
+        if let Some(library) = regex.library() {
+            parser = parser.library(library);
+        }
+        let ast = parser.parse()?;
+        // A line break ends a trailing comment in extended mode
+        let close = if flags.extended { "\n)$" } else { ")$" };
+        let mut verifier = RegexBuilder::new(&format!("^(@?:{}{}", regex.as_str(), close))
+            .group_numbering(regex.group_registry().numbering())
+            .case_insensitive(flags.case_insensitive)
+            .multiline(flags.multiline)
+            .dotall(flags.dotall)
+            .extended(flags.extended);
+        if let Some(library) = regex.library() {
+            verifier = verifier.library(library);
+        }
+        let verifier = verifier.build()?;
+        Ok(Generator {
+            ast,
+            verifier,
+            max_repeat: 3,
+            weighting: Weighting::default(),
+            rng: Rng::from_entropy(),
+        })
+    }
+
+    /// Set how many times an unbounded repetition may repeat beyond its
+    /// minimum (default 3)
+    pub fn max_repeat(mut self, max_repeat: u32) -> Self {
+        self.max_repeat = max_repeat;
+        self
+    }
+
+    /// Seed the random number generator so the same samples are drawn on
+    /// every run (by default it is seeded randomly)
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = Rng(seed);
+        self
+    }
+
+    /// Set how branches of alternations are chosen
+    pub fn weighting(mut self, weighting: Weighting) -> Self {
+        self.weighting = weighting;
+        self
+    }
+
+    /// Draw a string the pattern matches in full
+    ///
+    /// Returns `None` if no draft satisfied the pattern's assertions.
+    pub fn generate(&mut self) -> Option<String> {
+        let ast = self.ast.clone();
+        for _ in 0..ATTEMPTS {
+            let mut draft = Draft::default();
+            if self.draw(&ast, &mut draft).is_some() && self.verifier.is_match(&draft.text) {
+                return Some(draft.text);
+            }
+        }
+        None
+    }
+
+    /// Append text matching `expr` to the draft, or fail if nothing can
+    fn draw(&mut self, expr: &Expr, draft: &mut Draft) -> Option<()> {
+        match expr {
+            Expr::Literal(c) => draft.text.push(*c),
+            Expr::Any => {
+                let c = self.pick(pool().filter(|&c| c != '\n'))?;
+                draft.text.push(c);
+            }
+            Expr::Shorthand(sh) => {
+                let class = CharacterClass {
+                    negated: sh.is_ascii_uppercase(),
+                    items: vec![ClassItem::Shorthand(sh.to_ascii_lowercase())],
+                };
+                let c = self.class_char(&class)?;
+                draft.text.push(c);
+            }
+            Expr::CharacterClass(class) => {
+                let c = self.class_char(class)?;
+                draft.text.push(c);
+            }
+            Expr::Sequence(exprs) => {
+                for expr in exprs {
+                    self.draw(expr, draft)?;
+                }
+            }
+            Expr::Alternation(branches) => {
+                let branch = match self.weighting {
+                    Weighting::Uniform => &branches[self.rng.below(branches.len())],
+                    Weighting::Alternatives => {
+                        let weights: Vec<usize> = branches.iter().map(paths).collect();
+                        let mut pick = self
+                            .rng
+                            .below(weights.iter().fold(0, |a, &b| a.saturating_add(b)));
+                        let mut chosen = &branches[0];
+                        for (branch, weight) in branches.iter().zip(weights) {
+                            if pick < weight {
+                                chosen = branch;
+                                break;
+                            }
+                            pick -= weight;
+                        }
+                        chosen
+                    }
+                };
+                self.draw(branch, draft)?;
+            }
+            Expr::Quantified {
+                expr, quantifier, ..
+            } => {
+                let (min, max) = self.repeat_range(*quantifier);
+                let count = min + self.rng.below((max - min) as usize + 1) as u32;
+                for _ in 0..count {
+                    self.draw(expr, draft)?;
+                }
+            }
+            Expr::Group(inner) => {
+                draft.groups += 1;
+                let group = draft.groups;
+                draft.unnamed.push(group);
+                let start = draft.text.len();
+                self.draw(inner, draft)?;
+                draft
+                    .captures
+                    .insert(group, draft.text[start..].to_string());
+            }
+            Expr::NamedGroup { name, pattern } => {
+                draft.groups += 1;
+                let group = draft.groups;
+                let start = draft.text.len();
+                self.draw(pattern, draft)?;
+                let text = draft.text[start..].to_string();
+                draft.captures.insert(group, text.clone());
+                draft.named.insert(name.clone(), text);
+            }
+            Expr::NonCapturingGroup(inner)
+            | Expr::AtomicGroup(inner)
+            | Expr::ConditionalGroup(inner)
+            | Expr::ModeFlagsGroup { pattern: inner, .. } => self.draw(inner, draft)?,
+            Expr::Backreference(n) => {
+                let text = draft.captures.get(n).cloned().unwrap_or_default();
+                draft.text.push_str(&text);
+            }
+            Expr::RelativeBackreference(n) => {
+                let index = draft.unnamed.len() as i64 + *n as i64;
+                let text = usize::try_from(index)
+                    .ok()
+                    .and_then(|index| draft.unnamed.get(index))
+                    .and_then(|group| draft.captures.get(group))
+                    .cloned()
+                    .unwrap_or_default();
+                draft.text.push_str(&text);
+            }
+            Expr::NamedBackreference(name) => {
+                let text = draft.named.get(name).cloned().unwrap_or_default();
+                draft.text.push_str(&text);
+            }
+            // Assertions consume nothing; the verifier decides whether they
+            // held
+            Expr::Empty
+            | Expr::StartAnchor
+            | Expr::EndAnchor
+            | Expr::WordBoundary
+            | Expr::NonWordBoundary
+            | Expr::Lookahead(_)
+            | Expr::NegativeLookahead(_)
+            | Expr::Lookbehind(_)
+            | Expr::NegativeLookbehind(_) => {}
+        }
+        Some(())
+    }
+
+    /// The fewest and most repetitions to draw for a quantifier
+    fn repeat_range(&self, quantifier: Quantifier) -> (u32, u32) {
+        match quantifier {
+            Quantifier::ZeroOrMore => (0, self.max_repeat),
+            Quantifier::OneOrMore => (1, self.max_repeat.saturating_add(1)),
+            Quantifier::Optional => (0, 1),
+            Quantifier::Exactly(n) => (n, n),
+            Quantifier::AtLeast(n) => (n, n.saturating_add(self.max_repeat)),
+            Quantifier::Between(n, m) => (n, m.max(n)),
+        }
+    }
+
+    /// Pick a character a class matches
+    fn class_char(&mut self, class: &CharacterClass) -> Option<char> {
+        let lookup = class.to_lookup_table();
+        if class.negated || class.items.is_empty() {
+            return self.pick(pool().filter(|&c| class.matches(c, &lookup)));
+        }
+        match class.items[self.rng.below(class.items.len())] {
+            ClassItem::Char(c) => Some(c),
+            ClassItem::Range(start, end) => {
+                let span = (end as u32).saturating_sub(start as u32) as usize + 1;
+                let c = start as u32 + self.rng.below(span) as u32;
+                Some(char::from_u32(c).unwrap_or(start))
+            }
+            ClassItem::Shorthand(_) => self.pick(pool().filter(|&c| class.matches(c, &lookup))),
+        }
+    }
+
+    /// Pick one of the candidates, or `None` if there are none
+    fn pick(&mut self, candidates: impl Iterator<Item = char>) -> Option<char> {
+        let candidates: Vec<char> = candidates.collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(candidates[self.rng.below(candidates.len())])
+    }
+}
+
+/// How many different paths through alternations an expression has
+fn paths(expr: &Expr) -> usize {
+    match expr {
+        Expr::Alternation(branches) => branches.iter().map(paths).fold(0, usize::saturating_add),
+        // Lookarounds are never drawn from
+        Expr::Lookahead(_)
+        | Expr::NegativeLookahead(_)
+        | Expr::Lookbehind(_)
+        | Expr::NegativeLookbehind(_) => 1,
+        _ => expr
+            .children()
+            .iter()
+            .map(paths)
+            .fold(1, usize::saturating_mul),
+    }
+}
+
+/// A sample being drawn, with the text each group captured so far
+#[derive(Default)]
+struct Draft {
+    text: String,
+    /// Groups opened so far, which is also the number of the latest one
+    groups: u32,
+    /// Numbers of the unnamed groups, for relative backreferences
+    unnamed: Vec<u32>,
+    captures: HashMap<u32, String>,
+    named: HashMap<String, String>,
+}
+
+/// A small SplitMix64 generator, so sampling needs no extra dependency
+struct Rng(u64);
+
+impl Rng {
+    /// Seed from the per-process randomness kept for hash maps
+    fn from_entropy() -> Self {
+        use core::hash::{BuildHasher, Hasher};
+        let mut hasher = RandomState::default().build_hasher();
+        hasher.write_u64(0);
+        Rng(hasher.finish())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`; `n` must not be zero
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(pattern: &str, max_repeat: u32) -> Vec<String> {
+        let mut generator = Generator::new(pattern).unwrap().max_repeat(max_repeat);
+        (0..50).map(|_| generator.generate().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_samples_match_the_pattern() {
+        for pattern in [
+            r"[a-z]+@[a-z]+\.(com|org)",
+            r"\d{3}-\w\s\W.",
+            r"[^a-z0-9]{2,4}x?",
+            r"(word:\d+)=\g{word}",
+            r"(ab|cd)\1\g{-1}",
+            r"^\bfoo\b$",
+        ] {
+            let regex = Regex::new(&format!("^(@?:{})$", pattern)).unwrap();
+            for sample in samples(pattern, 3) {
+                assert!(regex.is_match(&sample), "{pattern:?} gave {sample:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_repeat_bounds_repetition() {
+        assert!(samples("a*", 2).iter().all(|s| s.len() <= 2));
+        assert!(samples("a{3,}", 0).iter().all(|s| s == "aaa"));
+        assert!(samples("a+", 0).iter().all(|s| s == "a"));
+    }
+
+    #[test]
+    fn test_seed_makes_samples_repeatable() {
+        let draw = |seed| {
+            let mut generator = Generator::new(r"\w+(-\d{2})?").unwrap().seed(seed);
+            (0..10)
+                .map(|_| generator.generate().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draw(42), draw(42));
+        assert_ne!(draw(42), draw(43));
+    }
+
+    #[test]
+    fn test_alternatives_weighting() {
+        let count_a = |weighting| {
+            let mut generator = Generator::new("a|(b|c|d|e|f|g|h|i|j)")
+                .unwrap()
+                .weighting(weighting)
+                .seed(1);
+            (0..1000)
+                .filter(|_| generator.generate().unwrap() == "a")
+                .count()
+        };
+        assert!(count_a(Weighting::Uniform) > 400);
+        assert!(count_a(Weighting::Alternatives) < 200);
+    }
+
+    #[test]
+    fn test_generator_honors_regex_options() {
+        let regex = RegexBuilder::new("[a-c]+ # letters")
+            .extended(true)
+            .build()
+            .unwrap();
+        let mut generator = regex.generator().unwrap().seed(3);
+        for _ in 0..20 {
+            let sample = generator.generate().unwrap();
+            assert!(
+                sample.chars().all(|c| ('a'..='c').contains(&c)),
+                "{sample:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unsatisfiable_pattern_gives_none() {
+        let mut generator = Generator::new("a(@>:b)").unwrap();
+        assert_eq!(generator.generate(), None);
+    }
+}
@@ -20,7 +20,10 @@
 //! assert_eq!(m.group_str("hello world", 2), Some("world"));
 //! ```
 
-use std::collections::HashMap;
+use crate::collections::HashMap;
+use crate::error::Span;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
 /// Information about a capture group
 #[derive(Debug, Clone, PartialEq)]
@@ -31,6 +34,35 @@ pub struct GroupInfo {
     pub name: Option<String>,
     /// Whether this is a named group
     pub is_named: bool,
+    /// Where the group is defined in the pattern (character offsets), if known
+    pub span: Option<Span>,
+    /// The index of the innermost group enclosing this one, if any
+    pub parent: Option<u32>,
+    /// The pattern library fragment the group was defined in, if it came
+    /// from a `(@use:name)` reference rather than the pattern itself
+    pub fragment: Option<String>,
+}
+
+/// A capture group together with the groups nested inside it
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupNode {
+    /// The index of the group
+    pub index: u32,
+    /// The name of the group (if it's a named group)
+    pub name: Option<String>,
+    /// Groups directly nested inside this one, in order of appearance
+    pub children: Vec<GroupNode>,
+}
+
+/// Which groups are counted when resolving relative backreferences
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GroupNumbering {
+    /// Ogex numbering: named groups are skipped, so `\g{-1}` refers to the
+    /// last unnamed group
+    #[default]
+    Ogex,
+    /// PCRE/Python numbering: named groups are counted like any other group
+    Pcre,
 }
 
 /// Registry for tracking capture groups
@@ -44,6 +76,8 @@ pub struct GroupRegistry {
     numbered_groups: Vec<u32>,
     /// The next group index to assign
     next_index: u32,
+    /// Which groups relative backreferences count
+    numbering: GroupNumbering,
 }
 
 impl GroupRegistry {
@@ -54,9 +88,23 @@ impl GroupRegistry {
             name_to_index: HashMap::new(),
             numbered_groups: Vec::new(),
             next_index: 1, // Groups are 1-indexed
+            numbering: GroupNumbering::default(),
+        }
+    }
+
+    /// Create a new empty registry using the given group numbering
+    pub fn with_numbering(numbering: GroupNumbering) -> Self {
+        GroupRegistry {
+            numbering,
+            ..Self::new()
         }
     }
 
+    /// Get the group numbering used for relative backreferences
+    pub fn numbering(&self) -> GroupNumbering {
+        self.numbering
+    }
+
     /// Register a new capture group
     ///
     /// # Arguments
@@ -68,14 +116,30 @@ impl GroupRegistry {
     /// # Errors
     /// Returns an error if the name is already in use
     pub fn register_group(&mut self, name: Option<String>) -> Result<u32, GroupRegistryError> {
+        self.register_nested_group(name, None)
+    }
+
+    /// Register a new capture group nested inside the group `parent`
+    ///
+    /// # Errors
+    /// Returns an error if the name is already in use
+    pub fn register_nested_group(
+        &mut self,
+        name: Option<String>,
+        parent: Option<u32>,
+    ) -> Result<u32, GroupRegistryError> {
+        // Check for duplicate names before taking an index, so a rejected
+        // group does not leave a gap in the numbering
+        if let Some(ref group_name) = name
+            && self.name_to_index.contains_key(group_name)
+        {
+            return Err(GroupRegistryError::DuplicateGroupName(group_name.clone()));
+        }
+
         let index = self.next_index;
         self.next_index += 1;
 
-        // Check for duplicate names
         if let Some(ref group_name) = name {
-            if self.name_to_index.contains_key(group_name) {
-                return Err(GroupRegistryError::DuplicateGroupName(group_name.clone()));
-            }
             self.name_to_index.insert(group_name.clone(), index);
         } else {
             // Track numbered (non-named) groups
@@ -86,6 +150,9 @@ impl GroupRegistry {
             index,
             name: name.clone(),
             is_named: name.is_some(),
+            span: None,
+            parent,
+            fragment: None,
         };
 
         self.groups.push(info);
@@ -97,6 +164,20 @@ impl GroupRegistry {
         self.groups.iter().find(|g| g.index == index)
     }
 
+    /// Record where the group with the given index is defined in the pattern
+    pub fn set_span(&mut self, index: u32, span: Span) {
+        if let Some(info) = self.groups.iter_mut().find(|g| g.index == index) {
+            info.span = Some(span);
+        }
+    }
+
+    /// Record the library fragment the group with the given index came from
+    pub fn set_fragment(&mut self, index: u32, fragment: String) {
+        if let Some(info) = self.groups.iter_mut().find(|g| g.index == index) {
+            info.fragment = Some(fragment);
+        }
+    }
+
     /// Get group index by name
     pub fn get_by_name(&self, name: &str) -> Option<u32> {
         self.name_to_index.get(name).copied()
@@ -117,6 +198,26 @@ impl GroupRegistry {
         &self.groups
     }
 
+    /// Groups directly nested inside `parent` (top-level groups for `None`)
+    pub fn children(&self, parent: Option<u32>) -> impl Iterator<Item = &GroupInfo> {
+        self.groups.iter().filter(move |g| g.parent == parent)
+    }
+
+    /// The groups arranged as a tree by nesting, starting from the top-level groups
+    pub fn tree(&self) -> Vec<GroupNode> {
+        self.subtree(None)
+    }
+
+    fn subtree(&self, parent: Option<u32>) -> Vec<GroupNode> {
+        self.children(parent)
+            .map(|g| GroupNode {
+                index: g.index,
+                name: g.name.clone(),
+                children: self.subtree(Some(g.index)),
+            })
+            .collect()
+    }
+
     /// Validate that a backreference name exists
     pub fn validate_backref_name(&self, name: &str) -> Result<u32, GroupRegistryError> {
         self.get_by_name(name)
@@ -132,10 +233,25 @@ impl GroupRegistry {
         }
     }
 
-    /// Get the count of numbered (non-named) capture groups
-    /// This is used for relative backreference resolution
+    /// Get the count of groups counted by relative backreferences
+    ///
+    /// This is the number of unnamed groups under [`GroupNumbering::Ogex`]
+    /// and the number of all groups under [`GroupNumbering::Pcre`].
     pub fn numbered_group_count(&self) -> usize {
-        self.numbered_groups.len()
+        match self.numbering {
+            GroupNumbering::Ogex => self.numbered_groups.len(),
+            GroupNumbering::Pcre => self.groups.len(),
+        }
+    }
+
+    /// Indices of the unnamed groups, in order of appearance (the Ogex view)
+    pub fn unnamed_groups(&self) -> &[u32] {
+        &self.numbered_groups
+    }
+
+    /// Indices of all groups, named or not, in order of appearance (the PCRE view)
+    pub fn all_groups(&self) -> impl Iterator<Item = u32> + '_ {
+        self.groups.iter().map(|g| g.index)
     }
 
     /// Get a numbered group by reverse index for relative backreferences
@@ -154,14 +270,18 @@ impl GroupRegistry {
         &self,
         reverse_index: usize,
     ) -> Result<u32, GroupRegistryError> {
-        if reverse_index == 0 || reverse_index > self.numbered_groups.len() {
+        let count = self.numbered_group_count();
+        if reverse_index == 0 || reverse_index > count {
             return Err(GroupRegistryError::InvalidRelativeBackreference(
                 reverse_index as i32,
             ));
         }
         // -1 because reverse_index is 1-based, and we want from the end
-        let actual_index = self.numbered_groups.len() - reverse_index;
-        Ok(self.numbered_groups[actual_index])
+        let actual_index = count - reverse_index;
+        Ok(match self.numbering {
+            GroupNumbering::Ogex => self.numbered_groups[actual_index],
+            GroupNumbering::Pcre => self.groups[actual_index].index,
+        })
     }
 
     /// Resolve a relative backreference (\g{-n}) to an absolute group index
@@ -194,8 +314,8 @@ pub enum GroupRegistryError {
     InvalidRelativeBackreference(i32),
 }
 
-impl std::fmt::Display for GroupRegistryError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for GroupRegistryError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             GroupRegistryError::DuplicateGroupName(name) => {
                 write!(f, "duplicate group name: {}", name)
@@ -213,6 +333,7 @@ impl std::fmt::Display for GroupRegistryError {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for GroupRegistryError {}
 
 /// A visitor that collects group information from an AST
@@ -224,61 +345,87 @@ impl GroupCollector {
         expr: &crate::ast::Expr,
         registry: &mut GroupRegistry,
     ) -> Result<(), GroupRegistryError> {
-        Self::visit_expr(expr, registry)
+        match Self::collect_all(expr, registry).into_iter().next() {
+            Some((_, err)) => Err(err),
+            None => Ok(()),
+        }
     }
 
-    fn visit_expr(
+    /// Collect all groups, continuing past errors
+    ///
+    /// Each error is paired with the index of the group that caused it. A
+    /// group whose name is already taken is registered without a name so
+    /// later groups keep their numbers.
+    pub fn collect_all(
         expr: &crate::ast::Expr,
         registry: &mut GroupRegistry,
+    ) -> Vec<(u32, GroupRegistryError)> {
+        let mut errors = Vec::new();
+        Self::visit_expr(expr, registry, None, &mut errors);
+        errors
+    }
+
+    /// Check that every named backreference in an expression refers to a
+    /// group in the registry
+    ///
+    /// Run this after [`GroupCollector::collect`] so forward references to
+    /// groups later in the pattern are accepted. Numbered backreferences are
+    /// not checked: one past the last group compiles and never matches.
+    pub fn validate_backref_names(
+        expr: &crate::ast::Expr,
+        registry: &GroupRegistry,
     ) -> Result<(), GroupRegistryError> {
         match expr {
-            crate::ast::Expr::Empty
-            | crate::ast::Expr::Literal(_)
-            | crate::ast::Expr::Any
-            | crate::ast::Expr::StartAnchor
-            | crate::ast::Expr::EndAnchor
-            | crate::ast::Expr::Backreference(_)
-            | crate::ast::Expr::RelativeBackreference(_)
-            | crate::ast::Expr::NamedBackreference(_)
-            | crate::ast::Expr::Shorthand(_)
-            | crate::ast::Expr::WordBoundary
-            | crate::ast::Expr::NonWordBoundary => Ok(()),
-
-            crate::ast::Expr::Sequence(exprs) => {
-                for expr in exprs {
-                    Self::visit_expr(expr, registry)?;
-                }
-                Ok(())
+            crate::ast::Expr::NamedBackreference(name) => {
+                registry.validate_backref_name(name).map(|_| ())
             }
+            _ => expr
+                .children()
+                .iter()
+                .try_for_each(|expr| Self::validate_backref_names(expr, registry)),
+        }
+    }
 
-            crate::ast::Expr::Alternation(exprs) => {
-                for expr in exprs {
-                    Self::visit_expr(expr, registry)?;
-                }
-                Ok(())
-            }
-
-            crate::ast::Expr::Quantified { expr, .. } => Self::visit_expr(expr, registry),
-
+    fn visit_expr(
+        expr: &crate::ast::Expr,
+        registry: &mut GroupRegistry,
+        parent: Option<u32>,
+        errors: &mut Vec<(u32, GroupRegistryError)>,
+    ) {
+        match expr {
             crate::ast::Expr::Group(expr) => {
-                registry.register_group(None)?;
-                Self::visit_expr(expr, registry)
-            }
-
-            crate::ast::Expr::NonCapturingGroup(expr) => {
-                // Non-capturing groups don't register
-                Self::visit_expr(expr, registry)
+                let index = registry
+                    .register_nested_group(None, parent)
+                    .expect("unnamed groups always register");
+                Self::visit_expr(expr, registry, Some(index), errors);
             }
 
             crate::ast::Expr::NamedGroup { name, pattern } => {
-                registry.register_group(Some(name.clone()))?;
-                Self::visit_expr(pattern, registry)
+                let index = match registry.register_nested_group(Some(name.clone()), parent) {
+                    Ok(index) => index,
+                    Err(err) => {
+                        let index = registry
+                            .register_nested_group(None, parent)
+                            .expect("unnamed groups always register");
+                        errors.push((index, err));
+                        index
+                    }
+                };
+                Self::visit_expr(pattern, registry, Some(index), errors);
             }
 
-            crate::ast::Expr::CharacterClass(_) => Ok(()),
+            // Lookarounds are compiled into separate NFAs, so their groups
+            // are not part of the pattern's numbering
+            crate::ast::Expr::Lookahead(_)
+            | crate::ast::Expr::NegativeLookahead(_)
+            | crate::ast::Expr::Lookbehind(_)
+            | crate::ast::Expr::NegativeLookbehind(_) => {}
 
-            // Handle new assertion and group types
-            _ => Ok(()),
+            _ => {
+                for child in expr.children() {
+                    Self::visit_expr(child, registry, parent, errors);
+                }
+            }
         }
     }
 }
@@ -423,6 +570,26 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_collector_visits_atomic_and_flag_groups() {
+        use crate::ast::Expr;
+
+        let expr = Expr::sequence(vec![
+            Expr::AtomicGroup(Box::new(Expr::named_group("a", Expr::literal('a')))),
+            Expr::ModeFlagsGroup {
+                flags: "i".to_string(),
+                pattern: Box::new(Expr::group(Expr::literal('b'))),
+            },
+            Expr::Lookahead(Box::new(Expr::group(Expr::literal('c')))),
+        ]);
+
+        let mut registry = GroupRegistry::new();
+        GroupCollector::collect(&expr, &mut registry).unwrap();
+
+        assert_eq!(registry.group_count(), 2);
+        assert_eq!(registry.get_by_name("a"), Some(1));
+    }
+
     #[test]
     fn test_numbered_group_count() {
         let mut registry = GroupRegistry::new();
@@ -481,4 +648,44 @@ mod tests {
             Err(GroupRegistryError::InvalidRelativeBackreference(_))
         ));
     }
+
+    #[test]
+    fn test_pcre_numbering_counts_named_groups() {
+        let mut registry = GroupRegistry::with_numbering(GroupNumbering::Pcre);
+        registry.register_group(None).unwrap(); // group 1
+        registry.register_group(Some("named".to_string())).unwrap(); // group 2
+        registry.register_group(None).unwrap(); // group 3
+
+        assert_eq!(registry.numbering(), GroupNumbering::Pcre);
+        assert_eq!(registry.numbered_group_count(), 3);
+        assert_eq!(registry.resolve_relative_backreference(-2).unwrap(), 2);
+        assert_eq!(registry.resolve_relative_backreference(-3).unwrap(), 1);
+
+        // Both views are available regardless of numbering
+        assert_eq!(registry.unnamed_groups(), &[1, 3]);
+        assert_eq!(registry.all_groups().collect::<Vec<_>>(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_group_tree() {
+        let expr = crate::parser::parse("(date:(year:\\d+)-(month:\\d+))(x)").unwrap();
+        let mut registry = GroupRegistry::new();
+        GroupCollector::collect(&expr, &mut registry).unwrap();
+
+        assert_eq!(registry.get_by_index(2).unwrap().parent, Some(1));
+        assert_eq!(
+            registry
+                .children(Some(1))
+                .map(|g| g.index)
+                .collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+
+        let tree = registry.tree();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].name.as_deref(), Some("date"));
+        let children: Vec<_> = tree[0].children.iter().map(|c| c.name.as_deref()).collect();
+        assert_eq!(children, vec![Some("year"), Some("month")]);
+        assert!(tree[1].children.is_empty());
+    }
 }
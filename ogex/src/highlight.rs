@@ -0,0 +1,137 @@
+//! Spans for rendering matches in color
+//!
+//! [`Regex::highlight`] splits each match into pieces that a frontend can
+//! color one by one: wherever a capture group starts or ends inside a match,
+//! a new piece begins, labelled with the innermost group covering it. The
+//! pieces of a match never overlap and together cover exactly the match, so
+//! rendering is a single pass over the input with no captures to recompute.
+//!
+//! ```
+//! use ogex::Regex;
+//!
+//! let regex = Regex::new(r"(key:\w+)=(\d+)").unwrap();
+//! let input = "a=1 bb=22";
+//! let pieces: Vec<_> = regex
+//!     .highlight(input)
+//!     .iter()
+//!     .map(|span| (&input[span.start..span.end], span.match_index, span.group))
+//!     .collect();
+//! assert_eq!(
+//!     pieces,
+//!     [
+//!         ("a", 0, Some(1)),
+//!         ("=", 0, None),
+//!         ("1", 0, Some(2)),
+//!         ("bb", 1, Some(1)),
+//!         ("=", 1, None),
+//!         ("22", 1, Some(2)),
+//!     ]
+//! );
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::engine::{Match, Regex};
+
+/// A piece of a match, with the group that captured it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HighlightSpan {
+    /// The start position of the piece
+    pub start: usize,
+    /// The end position of the piece (exclusive)
+    pub end: usize,
+    /// Which match, counting from 0, the piece belongs to
+    pub match_index: usize,
+    /// The innermost capture group covering the piece, or `None` for text
+    /// matched outside every group
+    pub group: Option<u32>,
+    /// The name of that group, if it has one
+    pub name: Option<String>,
+}
+
+/// Split match number `match_index` into pieces at every group boundary
+pub(crate) fn match_spans(regex: &Regex, m: &Match, match_index: usize) -> Vec<HighlightSpan> {
+    if m.start == m.end {
+        return Vec::new();
+    }
+    // Groups inside a lookaround can capture text outside the match, so each
+    // is clipped to it
+    let groups: Vec<(u32, (usize, usize))> = (1..m.groups.len() as u32)
+        .filter_map(|n| {
+            let (start, end) = m.group(n)?;
+            let (start, end) = (start.max(m.start), end.min(m.end));
+            (start < end).then_some((n, (start, end)))
+        })
+        .collect();
+    let mut bounds: Vec<usize> = groups
+        .iter()
+        .flat_map(|&(_, (start, end))| [start, end])
+        .chain([m.start, m.end])
+        .collect();
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    bounds
+        .windows(2)
+        .map(|piece| {
+            let (start, end) = (piece[0], piece[1]);
+            // Nested groups have higher numbers, so the innermost one is the
+            // last to cover the piece
+            let group = groups
+                .iter()
+                .rev()
+                .find(|&&(_, (s, e))| s <= start && end <= e)
+                .map(|&(n, _)| n);
+            HighlightSpan {
+                start,
+                end,
+                match_index,
+                group,
+                name: group.and_then(|n| regex.group_registry().get_by_index(n)?.name.clone()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces<'a>(pattern: &str, input: &'a str) -> Vec<(&'a str, Option<u32>)> {
+        Regex::new(pattern)
+            .unwrap()
+            .highlight(input)
+            .iter()
+            .map(|span| (&input[span.start..span.end], span.group))
+            .collect()
+    }
+
+    #[test]
+    fn test_innermost_group_wins() {
+        assert_eq!(
+            pieces(r"<((\w)\w*)>", "x <abc> y"),
+            vec![("<", None), ("a", Some(2)), ("bc", Some(1)), (">", None)]
+        );
+        let spans = Regex::new(r"(year:\d+)-(\d+)")
+            .unwrap()
+            .highlight("2024-06");
+        assert_eq!(spans[0].name.as_deref(), Some("year"));
+        assert_eq!(spans[2].name, None);
+    }
+
+    #[test]
+    fn test_empty_matches_and_unset_groups() {
+        assert_eq!(pieces("x*", "ab"), vec![]);
+        assert_eq!(
+            pieces(r"a(b)?c", "ac abc"),
+            vec![("ac", None), ("a", None), ("b", Some(1)), ("c", None)]
+        );
+    }
+
+    #[test]
+    fn test_groups_in_lookarounds_are_clipped() {
+        assert_eq!(pieces(r"a(@>:(b))", "ab"), vec![("a", None)]);
+    }
+}
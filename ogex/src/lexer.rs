@@ -6,7 +6,10 @@
 // Re-export Span from error module to avoid duplication
 pub use crate::error::Span;
 
-use std::fmt;
+use crate::error::{LexerErrorKind, RegexError};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
 
 /// A token with its span in the input
 #[derive(Debug, Clone, PartialEq)]
@@ -76,6 +79,9 @@ pub enum Token {
     /// Mode flags for pattern modification (e.g., @i:pattern)
     ModeFlags(String),
     NamedGroupStart(String),
+    /// Reference to a pattern library fragment `(@use:name)`, without its
+    /// closing paren
+    Use(String),
     /// An escaped character (e.g., \n, \t, \\, etc.)
     Escape(char),
     /// A backreference by number (e.g., \1, \2)
@@ -136,6 +142,7 @@ impl fmt::Display for Token {
             Token::Conditional => write!(f, "`@%:`"),
             Token::ModeFlags(flags) => write!(f, "mode flags `@{}:`", flags),
             Token::NamedGroupStart(name) => write!(f, "named group `{}`", name),
+            Token::Use(name) => write!(f, "fragment `(@use:{})`", name),
             Token::Escape(c) => write!(f, "escape `\\{}`", c),
             Token::BackrefNumber(n) => write!(f, "backref `\\{}`", n),
             Token::BackrefRelative(n) => write!(f, "relative backref `\\g{{{}}}`", n),
@@ -154,28 +161,109 @@ impl fmt::Display for Token {
     }
 }
 
+impl Token {
+    /// Highlighting category of the token, such as `"quantifier"` or
+    /// `"groupName"`
+    ///
+    /// Tokens are classified without context, so a `^` negating a character
+    /// class or a digit inside `{2,3}` get their standalone kinds.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Token::LeftParen
+            | Token::RightParen
+            | Token::NonCapturing
+            | Token::Atomic
+            | Token::Conditional => "group",
+            Token::NamedGroupStart(_) | Token::Use(_) => "groupName",
+            Token::Lookahead
+            | Token::NegativeLookahead
+            | Token::Lookbehind
+            | Token::NegativeLookbehind
+            | Token::WordBoundary
+            | Token::NonWordBoundary => "assertion",
+            Token::Caret | Token::Dollar => "anchor",
+            Token::LeftBracket
+            | Token::RightBracket
+            | Token::Dot
+            | Token::WordChar
+            | Token::NonWordChar
+            | Token::Digit
+            | Token::NonDigit
+            | Token::Whitespace
+            | Token::NonWhitespace => "class",
+            Token::LeftBrace
+            | Token::RightBrace
+            | Token::Comma
+            | Token::Star
+            | Token::StarLazy
+            | Token::Plus
+            | Token::PlusLazy
+            | Token::Question => "quantifier",
+            Token::Pipe => "alternation",
+            Token::ModeFlags(_) => "flags",
+            Token::Escape(_) => "escape",
+            Token::BackrefNumber(_) | Token::BackrefRelative(_) | Token::BackrefName(_) => {
+                "backreference"
+            }
+            Token::Colon | Token::Literal(_) => "literal",
+            Token::Eof => "eof",
+        }
+    }
+}
+
 /// Lexer for tokenizing regex patterns
 pub struct Lexer<'a> {
     input: &'a str,
     position: usize,
     current_char: Option<char>,
-    /// Whether we're currently inside a character class
-    in_char_class: bool,
+    /// Position of the `[` opening the character class we're inside, if any
+    class_start: Option<usize>,
+    /// Error found while scanning the current token
+    error: Option<RegexError>,
+    /// Skip whitespace and `#` comments outside character classes
+    extended: bool,
 }
 
 impl<'a> Lexer<'a> {
     /// Create a new lexer for the given input string
     pub fn new(input: &'a str) -> Self {
+        Lexer::with_extended(input, false)
+    }
+
+    /// Create a lexer that, if `extended` is set, skips whitespace and `#`
+    /// comments outside character classes, as in the `@x` mode
+    pub fn with_extended(input: &'a str, extended: bool) -> Self {
         let mut lexer = Lexer {
             input,
             position: 0,
             current_char: None,
-            in_char_class: false,
+            class_start: None,
+            error: None,
+            extended,
         };
         lexer.advance();
         lexer
     }
 
+    /// In extended mode, move past any whitespace and comments before the
+    /// next token
+    fn skip_ignored(&mut self) {
+        if !self.extended || self.class_start.is_some() {
+            return;
+        }
+        while let Some(c) = self.current_char {
+            if c == '#' {
+                while !matches!(self.current_char, None | Some('\n')) {
+                    self.advance();
+                }
+            } else if c.is_whitespace() {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+    }
+
     /// Advance to the next character
     fn advance(&mut self) {
         self.current_char = self.input.chars().nth(self.position);
@@ -188,6 +276,11 @@ impl<'a> Lexer<'a> {
         self.input.chars().nth(self.position)
     }
 
+    /// The input from the current character on
+    fn rest(&self) -> String {
+        self.input.chars().skip(self.position - 1).collect()
+    }
+
     /// Check if a character is valid for an identifier (group name)
     fn is_identifier_char(c: char) -> bool {
         c.is_alphanumeric() || c == '_'
@@ -255,8 +348,14 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    /// Read a backreference with \g{name} syntax
-    fn read_g_backref(&mut self) -> Token {
+    /// Record a lexer error at `position` and stop scanning
+    fn fail(&mut self, position: usize, kind: LexerErrorKind) -> Token {
+        self.error = Some(RegexError::Lexer { position, kind });
+        Token::Eof
+    }
+
+    /// Read a backreference with \g{name} syntax starting at `start`
+    fn read_g_backref(&mut self, start_of_escape: usize) -> Token {
         // Assumes we've already consumed '\' and 'g' and '{'
         // current_char is at the first character of the name
         let start = self.position - 1; // position - 1 is where current_char is
@@ -269,8 +368,12 @@ impl<'a> Lexer<'a> {
         }
         // Now position points past the last character of name, and current_char is '}'
         let content = self.input[start..self.position - 1].to_string();
-        if self.current_char == Some('}') {
-            self.advance(); // consume '}'
+        if self.current_char != Some('}') {
+            return self.fail(start_of_escape, LexerErrorKind::InvalidEscape('g'));
+        }
+        self.advance(); // consume '}'
+        if content.is_empty() {
+            return self.fail(start_of_escape, LexerErrorKind::InvalidGroupName(content));
         }
 
         // Check if this is a relative backreference (negative index)
@@ -287,24 +390,43 @@ impl<'a> Lexer<'a> {
     }
 
     /// Get the next token from the input
-    pub fn next_token(&mut self) -> Token {
+    pub fn next_token(&mut self) -> Result<Token, RegexError> {
+        self.skip_ignored();
+        let token = self.scan_token();
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        if token == Token::Eof
+            && let Some(position) = self.class_start
+        {
+            return Err(RegexError::Lexer {
+                position,
+                kind: LexerErrorKind::UnclosedCharacterClass,
+            });
+        }
+        Ok(token)
+    }
+
+    /// Scan the next token, recording any error in `self.error`
+    fn scan_token(&mut self) -> Token {
         match self.current_char {
             None => Token::Eof,
             Some('\\') => {
+                let start = self.position - 1;
                 self.advance(); // consume backslash
                 match self.current_char {
                     Some('g') => {
                         self.advance(); // consume 'g'
                         if self.current_char == Some('{') {
                             self.advance(); // consume '{'
-                            self.read_g_backref()
+                            self.read_g_backref(start)
                         } else {
                             // Read 'g' as escape, but check if followed by digits
                             Token::Escape('g')
                         }
                     }
                     Some(_c) => self.read_escape(),
-                    None => Token::Escape('\0'),
+                    None => self.fail(start, LexerErrorKind::UnexpectedChar('\\')),
                 }
             }
             Some('(') => {
@@ -315,6 +437,22 @@ impl<'a> Lexer<'a> {
                 if self.current_char == Some('@') {
                     self.advance(); // consume '@'
 
+                    // A reference to a library fragment: (@use:name)
+                    if self.rest().starts_with("use:") {
+                        for _ in 0..4 {
+                            self.advance();
+                        }
+                        return match self.current_char {
+                            Some(c) if c.is_alphabetic() || c == '_' => {
+                                Token::Use(self.read_identifier())
+                            }
+                            _ => self.fail(
+                                self.position - 1,
+                                LexerErrorKind::InvalidGroupName(String::new()),
+                            ),
+                        };
+                    }
+
                     // Parse mode flags (i, m, s, x in any combination)
                     let mut mode_flags = String::new();
                     while let Some(c) = self.current_char {
@@ -627,17 +765,17 @@ impl<'a> Lexer<'a> {
             }
             Some(')') => {
                 self.advance();
-                self.in_char_class = false;
+                self.class_start = None;
                 Token::RightParen
             }
             Some('[') => {
+                self.class_start = Some(self.position - 1);
                 self.advance();
-                self.in_char_class = true;
                 Token::LeftBracket
             }
             Some(']') => {
                 self.advance();
-                self.in_char_class = false;
+                self.class_start = None;
                 Token::RightBracket
             }
             Some('{') => {
@@ -702,41 +840,41 @@ impl<'a> Lexer<'a> {
     }
 
     /// Tokenize the entire input and return a vector of tokens
-    pub fn tokenize(&mut self) -> Vec<Token> {
+    pub fn tokenize(&mut self) -> Result<Vec<Token>, RegexError> {
         let mut tokens = Vec::new();
         loop {
-            let token = self.next_token();
+            let token = self.next_token()?;
             if token == Token::Eof {
                 tokens.push(token);
                 break;
             }
             tokens.push(token);
         }
-        tokens
+        Ok(tokens)
     }
 
     /// Tokenize and return tokens with their spans
-    pub fn tokenize_spanned(&mut self) -> Vec<Spanned<Token>> {
+    pub fn tokenize_spanned(&mut self) -> Result<Vec<Spanned<Token>>, RegexError> {
         let mut tokens = Vec::new();
         loop {
-            let start = self.position.saturating_sub(1);
-            let token = self.next_token();
-            let end = self.position;
-            if token == Token::Eof {
-                tokens.push(Spanned::new(token, Span::new(start, end)));
+            let spanned = self.next_spanned()?;
+            let is_eof = spanned.token == Token::Eof;
+            tokens.push(spanned);
+            if is_eof {
                 break;
             }
-            tokens.push(Spanned::new(token, Span::new(start, end)));
         }
-        tokens
+        Ok(tokens)
     }
 
     /// Get the next token with its span
-    pub fn next_spanned(&mut self) -> Spanned<Token> {
+    pub fn next_spanned(&mut self) -> Result<Spanned<Token>, RegexError> {
+        self.skip_ignored();
+        // `position` is one past the current character
         let start = self.position.saturating_sub(1);
-        let token = self.next_token();
-        let end = self.position;
-        Spanned::new(token, Span::new(start, end))
+        let token = self.next_token()?;
+        let end = self.position.saturating_sub(1);
+        Ok(Spanned::new(token, Span::new(start, end)))
     }
 }
 
@@ -747,7 +885,7 @@ mod tests {
     #[test]
     fn test_simple_named_group() {
         let mut lexer = Lexer::new("(name:abc)");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -765,7 +903,7 @@ mod tests {
     #[test]
     fn test_literal_sequence() {
         let mut lexer = Lexer::new("abc");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -781,7 +919,7 @@ mod tests {
     #[test]
     fn test_empty_input() {
         let mut lexer = Lexer::new("");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(tokens, vec![Token::Eof]);
     }
@@ -789,7 +927,7 @@ mod tests {
     #[test]
     fn test_parentheses_only() {
         let mut lexer = Lexer::new("()");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -800,7 +938,7 @@ mod tests {
     #[test]
     fn test_named_group_with_underscore() {
         let mut lexer = Lexer::new("(my_name:test)");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(tokens[0], Token::NamedGroupStart("my_name".to_string()));
     }
@@ -808,7 +946,7 @@ mod tests {
     #[test]
     fn test_quantifiers() {
         let mut lexer = Lexer::new("a*b+c?");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -827,7 +965,7 @@ mod tests {
     #[test]
     fn test_alternation() {
         let mut lexer = Lexer::new("a|b|c");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -845,7 +983,7 @@ mod tests {
     #[test]
     fn test_anchors() {
         let mut lexer = Lexer::new("^start$");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -865,7 +1003,7 @@ mod tests {
     #[test]
     fn test_character_class() {
         let mut lexer = Lexer::new("[abc]");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -883,7 +1021,7 @@ mod tests {
     #[test]
     fn test_escape_sequences() {
         let mut lexer = Lexer::new(r"\n\t\\");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -899,7 +1037,7 @@ mod tests {
     #[test]
     fn test_backreference_number() {
         let mut lexer = Lexer::new(r"\1\2\12");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -915,7 +1053,7 @@ mod tests {
     #[test]
     fn test_backreference_name() {
         let mut lexer = Lexer::new(r"\g{name}");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -926,7 +1064,7 @@ mod tests {
     #[test]
     fn test_non_capturing_group() {
         let mut lexer = Lexer::new("(?:abc)");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -944,7 +1082,7 @@ mod tests {
     #[test]
     fn test_dot() {
         let mut lexer = Lexer::new("a.b");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -960,7 +1098,7 @@ mod tests {
     #[test]
     fn test_quantifier_braces() {
         let mut lexer = Lexer::new("a{3,5}");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -980,7 +1118,7 @@ mod tests {
     fn test_backref_relative() {
         // \g{-1} - relative backreference to last numbered group
         let mut lexer = Lexer::new(r"\g{-1}");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(tokens, vec![Token::BackrefRelative(-1), Token::Eof,]);
     }
@@ -989,7 +1127,7 @@ mod tests {
     fn test_backref_relative_large() {
         // \g{-27} - relative backreference
         let mut lexer = Lexer::new(r"\g{-27}");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(tokens, vec![Token::BackrefRelative(-27), Token::Eof,]);
     }
@@ -998,7 +1136,7 @@ mod tests {
     fn test_backref_relative_in_pattern() {
         // Pattern with relative backreference
         let mut lexer = Lexer::new(r"(a)(b)\g{-1}");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -1019,7 +1157,7 @@ mod tests {
     fn test_escape_uppercase_g() {
         // \G in patterns is a literal G (escaped character)
         let mut lexer = Lexer::new(r"\G");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(tokens, vec![Token::Escape('G'), Token::Eof,]);
     }
@@ -1028,7 +1166,7 @@ mod tests {
     fn test_escape_g_no_brace() {
         // \g without { is just an escaped 'g'
         let mut lexer = Lexer::new(r"\g");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(tokens, vec![Token::Escape('g'), Token::Eof,]);
     }
@@ -1037,7 +1175,7 @@ mod tests {
     fn test_backref_name_with_number() {
         // \g{1} is treated as a named backreference with name "1"
         let mut lexer = Lexer::new(r"\g{1}");
-        let tokens = lexer.tokenize();
+        let tokens = lexer.tokenize().unwrap();
 
         assert_eq!(
             tokens,
@@ -1048,7 +1186,7 @@ mod tests {
     #[test]
     fn test_tokenize_spanned() {
         let mut lexer = Lexer::new("abc");
-        let tokens = lexer.tokenize_spanned();
+        let tokens = lexer.tokenize_spanned().unwrap();
 
         // Should have 4 tokens: 'a', 'b', 'c', EOF
         assert!(tokens.len() >= 3);
@@ -1058,14 +1196,105 @@ mod tests {
 
         // Check last token is EOF
         assert_eq!(tokens[tokens.len() - 1].token, Token::Eof);
+
+        // Each literal covers exactly its own character, EOF is empty
+        assert_eq!(tokens[1].span, Span::new(1, 2));
+        assert_eq!(tokens[3].span, Span::new(3, 3));
     }
 
     #[test]
     fn test_tokenize_spanned_named_group() {
         let mut lexer = Lexer::new("(name:abc)");
-        let tokens = lexer.tokenize_spanned();
+        let tokens = lexer.tokenize_spanned().unwrap();
 
-        // First token should be NamedGroupStart
+        // First token should be NamedGroupStart, covering `(name:`
         assert_eq!(tokens[0].token, Token::NamedGroupStart("name".to_string()));
+        assert_eq!(tokens[0].span, Span::new(0, 6));
+    }
+
+    #[test]
+    fn test_token_kinds() {
+        let tokens = Lexer::new(r"(y:\d{4})+?|\g{y}\.[^a]$").tokenize().unwrap();
+        let kinds: Vec<_> = tokens.iter().map(Token::kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "groupName",
+                "class",
+                "quantifier",
+                "literal",
+                "quantifier",
+                "group",
+                "quantifier",
+                "alternation",
+                "backreference",
+                "escape",
+                "class",
+                "anchor",
+                "literal",
+                "class",
+                "anchor",
+                "eof",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extended_mode() {
+        let pattern = "a b # comment\n [ c]\\ ";
+        let tokens = Lexer::with_extended(pattern, true)
+            .tokenize_spanned()
+            .unwrap();
+        let tokens: Vec<_> = tokens.into_iter().map(|t| (t.token, t.span)).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (Token::Literal('a'), Span::new(0, 1)),
+                (Token::Literal('b'), Span::new(2, 3)),
+                (Token::LeftBracket, Span::new(15, 16)),
+                (Token::Literal(' '), Span::new(16, 17)),
+                (Token::Literal('c'), Span::new(17, 18)),
+                (Token::RightBracket, Span::new(18, 19)),
+                (Token::Escape(' '), Span::new(19, 21)),
+                (Token::Eof, Span::new(21, 21)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lexer_errors() {
+        let lexer_error = |pattern| match Lexer::new(pattern).tokenize() {
+            Err(RegexError::Lexer { position, kind }) => (position, kind),
+            other => panic!("expected a lexer error, got {:?}", other),
+        };
+
+        assert_eq!(
+            lexer_error("ab\\"),
+            (2, LexerErrorKind::UnexpectedChar('\\'))
+        );
+        assert_eq!(
+            lexer_error("a\\g{name"),
+            (1, LexerErrorKind::InvalidEscape('g'))
+        );
+        assert_eq!(
+            lexer_error("\\g{}"),
+            (0, LexerErrorKind::InvalidGroupName(String::new()))
+        );
+        assert_eq!(
+            lexer_error("x[abc"),
+            (1, LexerErrorKind::UnclosedCharacterClass)
+        );
+        assert_eq!(
+            lexer_error("(@use:)"),
+            (6, LexerErrorKind::InvalidGroupName(String::new()))
+        );
+    }
+
+    #[test]
+    fn test_fragment_reference() {
+        let tokens = Lexer::new("(@use:ipv4)+").tokenize_spanned().unwrap();
+        assert_eq!(tokens[0].token, Token::Use("ipv4".to_string()));
+        assert_eq!(tokens[0].span, Span::new(0, 10));
+        assert_eq!(tokens[1].token, Token::RightParen);
     }
 }
@@ -1,43 +1,132 @@
 //! Ogex Core Library
 //!
 //! A custom regex engine with unified syntax for named groups and backreferences.
+//!
+//! Without the default `std` feature the crate is `#![no_std]`: the parser,
+//! compiler and matcher only need `alloc`, which the `alloc` feature turns
+//! on. The C API, the I/O helpers and the `std::error::Error` impls need
+//! `std`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "alloc")))]
+compile_error!("ogex needs either the `std` or the `alloc` feature");
 
+extern crate alloc;
+
+#[cfg(feature = "transpiler")]
+use alloc::string::String;
+
+pub mod algebra;
 pub mod ast;
+pub mod cache;
+pub mod diagnostics;
+#[cfg(feature = "transpiler")]
+pub mod dialect;
 pub mod engine;
 pub mod error;
+pub mod extract;
+#[cfg(feature = "std")]
 pub mod ffi;
+pub mod fuzzy;
+pub mod generate;
 pub mod groups;
+pub mod highlight;
 pub mod lexer;
+pub mod library;
 pub mod nfa;
 pub mod parser;
+mod partial;
+pub mod pat;
+#[cfg(feature = "patterns")]
+pub mod patterns;
+mod pool;
+pub mod program;
+#[cfg(feature = "replace")]
+pub mod redact;
+#[cfg(feature = "replace")]
 pub mod replace;
+#[cfg(feature = "diagnostics")]
+pub mod report;
+pub mod scanner;
+pub mod set;
+pub mod stream;
+mod suggest;
+pub mod trace;
+#[cfg(feature = "transpiler")]
 pub mod transpiler;
+mod vm;
 
 #[cfg(feature = "wasm")]
 pub mod wasm;
 
 pub use ast::Expr;
-pub use engine::{Match, Regex};
-pub use error::{LexerErrorKind, ParseError, RegexError, Result, Span, SpannedError};
-pub use groups::{GroupCollector, GroupInfo, GroupRegistry, GroupRegistryError};
+pub use cache::RegexCache;
+#[cfg(feature = "sync")]
+pub use cache::SyncRegexCache;
+pub use diagnostics::{Diagnostic, Severity};
+#[cfg(feature = "transpiler")]
+pub use dialect::{Dialect, TranspileError, transpile_from_legacy, transpile_to_dialect};
+pub use engine::{
+    CaptureLocations, Match, Matches, OwnedMatch, Regex, RegexBuilder, Split, SplitInclusive,
+    SplitWithCaptures, TryMatches, TrySplit, escape,
+};
+pub use error::{
+    ErrorCategory, ErrorCode, LexerErrorKind, ParseError, RegexError, Result, RuntimeError, Span,
+    SpannedError,
+};
+pub use fuzzy::{FuzzyMatch, FuzzyRegex};
+pub use generate::{Generator, Weighting};
+pub use groups::{
+    GroupCollector, GroupInfo, GroupNode, GroupNumbering, GroupRegistry, GroupRegistryError,
+};
+pub use highlight::HighlightSpan;
 pub use lexer::{Lexer, Spanned, Token};
+pub use library::PatternLibrary;
 pub use nfa::{Nfa, State, StateId, Transition};
 pub use parser::{Parser, parse};
-pub use replace::{Replacement, ReplacementError, ReplacementPart};
+pub use pat::Pat;
+pub use program::ProgramError;
+#[cfg(feature = "replace")]
+pub use redact::{RedactStyle, Redaction};
+#[cfg(feature = "replace")]
+pub use replace::{
+    MissingGroupPolicy, NoExpand, PairError, Replacement, ReplacementError, ReplacementOptions,
+    ReplacementPart, ReplacementSyntax, Replacer, validate_pair,
+};
+#[cfg(feature = "diagnostics")]
+pub use report::PatternError;
+pub use scanner::{Lexeme, ScanMode, Scanner, Tokens, UnexpectedInput};
+pub use set::RegexSet;
+#[cfg(feature = "async")]
+pub use stream::FindStream;
+pub use stream::{StreamMatch, StreamMatcher};
+pub use trace::TraceEvent;
+#[cfg(feature = "transpiler")]
 pub use transpiler::{
     ConvertResult, ExplainResult, TranspileResult, convert_all, explain, transpile,
     transpile_debug, transpile_to_ogex, transpile_to_python,
 };
+pub use vm::Backend;
+
+/// Hash maps from the standard library, or from `hashbrown` without it
+mod collections {
+    #[cfg(not(feature = "std"))]
+    pub(crate) use hashbrown::{DefaultHashBuilder as RandomState, HashMap, HashSet};
+    #[cfg(feature = "std")]
+    pub(crate) use std::collections::{HashMap, HashSet, hash_map::RandomState};
+}
 
 /// Compile a regex pattern with custom syntax
 ///
 /// This is the main entry point for compiling patterns.
 /// For now, it just transpiles to legacy syntax.
+#[cfg(feature = "transpiler")]
 pub fn compile(input: &str) -> Result<String> {
     transpile(input)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "transpiler"))]
 mod tests {
     use super::*;
 
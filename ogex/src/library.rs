@@ -0,0 +1,246 @@
+//! Named fragments shared between patterns
+//!
+//! A [`PatternLibrary`] holds sub-patterns defined once under a name, such as
+//! `ipv4` or `quoted_string`, that any pattern compiled with the library can
+//! refer to as `(@use:name)`. The parser expands each reference in place, as
+//! a non-capturing group, so a quantifier after it repeats the whole
+//! fragment.
+//!
+//! Capture groups inside a fragment become groups of the pattern that uses
+//! it, numbered from where the reference appears. The
+//! [`GroupRegistry`](crate::GroupRegistry) records which fragment each of
+//! them came from in [`GroupInfo::fragment`](crate::GroupInfo::fragment).
+//!
+//! A fragment may use the fragments defined before it, which rules out
+//! cycles: each definition is expanded when it is added, so redefining a
+//! name later does not change the fragments already built on it.
+//!
+//! ```
+//! use ogex::PatternLibrary;
+//!
+//! let mut library = PatternLibrary::new();
+//! library.define("octet", r"\d{1,3}").unwrap();
+//! library
+//!     .define("ipv4", r"(@use:octet)(@?:\.(@use:octet)){3}")
+//!     .unwrap();
+//! library.define("port", r"(port:\d+)").unwrap();
+//!
+//! let regex = library.compile(r"(host:(@use:ipv4))\:(@use:port)").unwrap();
+//! let input = "connect 10.0.0.1:8080";
+//! let m = regex.find(input).unwrap();
+//! assert_eq!(m.named_group_str(input, "host"), Some("10.0.0.1"));
+//! assert_eq!(m.named_group_str(input, "port"), Some("8080"));
+//!
+//! let port = regex.group_registry().get_by_name("port").unwrap();
+//! let info = regex.group_registry().get_by_index(port).unwrap();
+//! assert_eq!(info.fragment.as_deref(), Some("port"));
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::ast::Expr;
+use crate::collections::HashMap;
+use crate::engine::{Regex, RegexBuilder};
+use crate::error::{LexerErrorKind, RegexError};
+use crate::parser::Parser;
+
+/// A fragment, expanded when it was defined
+#[derive(Debug, Clone)]
+pub(crate) struct Fragment {
+    /// The pattern as it was written
+    pub(crate) source: String,
+    /// The pattern with every fragment it uses expanded
+    pub(crate) ast: Expr,
+    /// For each capturing group outside lookarounds, in order, the fragment
+    /// that defines it
+    pub(crate) groups: Vec<String>,
+}
+
+/// Named sub-patterns that patterns can refer to with `(@use:name)`
+#[derive(Debug, Clone, Default)]
+pub struct PatternLibrary {
+    fragments: HashMap<String, Fragment>,
+}
+
+impl PatternLibrary {
+    /// Create an empty library
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A library holding the patterns in [`crate::patterns`], named
+    /// `email`, `url`, `uuid`, `ipv4`, `ipv6`, `iso_date`, `iso_time` and
+    /// `iso_datetime`
+    #[cfg(feature = "patterns")]
+    pub fn prelude() -> Self {
+        let mut library = Self::new();
+        for (name, pattern) in crate::patterns::ALL {
+            library
+                .define(name, pattern)
+                .expect("the built-in patterns are valid");
+        }
+        library
+    }
+
+    /// Define the fragment `name`, replacing any fragment of that name
+    ///
+    /// The pattern may use the fragments already in the library. It is
+    /// parsed but not compiled on its own, so it may refer to groups that
+    /// only the patterns using it define.
+    ///
+    /// # Errors
+    /// Fails if `name` is not a valid group name or the pattern does not
+    /// parse.
+    pub fn define(&mut self, name: &str, pattern: &str) -> Result<(), RegexError> {
+        let mut chars = name.chars();
+        let valid = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_');
+        if !valid {
+            return Err(RegexError::Lexer {
+                position: 0,
+                kind: LexerErrorKind::InvalidGroupName(name.to_string()),
+            });
+        }
+
+        let mut parser = Parser::new(pattern).library(self);
+        let ast = parser.parse()?;
+        let groups = parser
+            .group_fragments()
+            .iter()
+            .map(|fragment| fragment.as_deref().unwrap_or(name).to_string())
+            .collect();
+        self.fragments.insert(
+            name.to_string(),
+            Fragment {
+                source: pattern.to_string(),
+                ast,
+                groups,
+            },
+        );
+        Ok(())
+    }
+
+    /// The pattern defining the fragment `name`, as it was written
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fragments
+            .get(name)
+            .map(|fragment| fragment.source.as_str())
+    }
+
+    /// Names of the fragments in the library, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.fragments.keys().map(String::as_str)
+    }
+
+    /// Number of fragments in the library
+    pub fn len(&self) -> usize {
+        self.fragments.len()
+    }
+
+    /// Whether the library has no fragments
+    pub fn is_empty(&self) -> bool {
+        self.fragments.is_empty()
+    }
+
+    /// Compile a pattern that may use the library's fragments
+    ///
+    /// Use [`RegexBuilder::library`] to set other options as well.
+    pub fn compile(&self, pattern: &str) -> Result<Regex, RegexError> {
+        RegexBuilder::new(pattern).library(self).build()
+    }
+
+    pub(crate) fn fragment(&self, name: &str) -> Option<&Fragment> {
+        self.fragments.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorCode, Span};
+
+    fn library() -> PatternLibrary {
+        let mut library = PatternLibrary::new();
+        library.define("digits", r"\d+").unwrap();
+        library
+            .define("pair", r"(@use:digits)-(@use:digits)")
+            .unwrap();
+        library.define("quoted", r#""(text:[^"]*)""#).unwrap();
+        library
+    }
+
+    #[test]
+    fn test_fragments_expand_as_groups() {
+        let library = library();
+        let regex = library.compile(r"^\[(@use:pair)\]$").unwrap();
+        assert!(regex.is_match("[12-345]"));
+        assert!(!regex.is_match("[12-]"));
+
+        // A quantifier repeats the whole fragment
+        let regex = library.compile(r"^(@use:pair)+$").unwrap();
+        assert!(regex.is_match("1-23-4"));
+        assert!(!regex.is_match("1-2-"));
+        assert!(regex.is_match(&regex.generate().unwrap()));
+    }
+
+    #[test]
+    fn test_groups_record_their_fragment() {
+        let library = library();
+        let regex = library.compile(r"(key:\w+)=(@use:quoted)(\d)").unwrap();
+        let registry = regex.group_registry();
+        let origins: Vec<_> = registry
+            .groups()
+            .iter()
+            .map(|group| group.fragment.as_deref())
+            .collect();
+        assert_eq!(origins, vec![None, Some("quoted"), None]);
+        // Groups from a fragment point at the reference
+        assert_eq!(
+            registry.get_by_index(2).unwrap().span,
+            Some(Span::new(10, 23))
+        );
+
+        let input = r#"name="ogex"7"#;
+        let m = regex.find(input).unwrap();
+        assert_eq!(m.named_group_str(input, "text"), Some("ogex"));
+        assert_eq!(m.group_str(input, 3), Some("7"));
+
+        let restored = Regex::from_bytes(&regex.to_bytes()).unwrap();
+        assert_eq!(
+            restored.group_registry().get_by_index(2).unwrap().fragment,
+            Some("quoted".to_string())
+        );
+    }
+
+    #[test]
+    fn test_nested_fragments_keep_the_innermost_origin() {
+        let mut library = library();
+        library.define("field", r"(@use:quoted)|(n:\d+)").unwrap();
+        let regex = library.compile(r"\{(@use:field)\}").unwrap();
+        let origins: Vec<_> = regex
+            .group_registry()
+            .groups()
+            .iter()
+            .map(|group| group.fragment.clone().unwrap())
+            .collect();
+        assert_eq!(origins, vec!["quoted", "field"]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let library = library();
+        let err = library.compile("a(@use:nope)").err().unwrap();
+        assert_eq!(err.code(), ErrorCode::UndefinedFragment);
+        assert_eq!(err.span(), Some(Span::new(1, 11)));
+        // Without a library, no fragment is defined
+        assert!(Regex::new("(@use:digits)").is_err());
+
+        let mut library = library.clone();
+        assert!(library.define("bad name", "x").is_err());
+        assert!(library.define("broken", "(x").is_err());
+        assert!(library.define("self", "(@use:self)").is_err());
+        assert_eq!(library.get("digits"), Some(r"\d+"));
+        assert_eq!(library.len(), 3);
+    }
+}
@@ -4,9 +4,14 @@
 //! from a regex AST, and provides NFA simulation for pattern matching.
 
 use crate::ast::{CharacterClass, ClassItem, Expr, Quantifier};
-use std::collections::{HashMap, HashSet};
+use crate::collections::{HashMap, HashSet};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::Write;
 
 use crate::engine::ModeFlags;
+use crate::groups::GroupNumbering;
 
 /// An NFA state ID
 pub type StateId = usize;
@@ -58,6 +63,16 @@ pub enum Transition {
     NegativeLookbehind(Nfa),
 }
 
+impl Transition {
+    /// Whether the transition consumes exactly one character
+    pub(crate) fn consumes_char(&self) -> bool {
+        matches!(
+            self,
+            Transition::Char(_) | Transition::Any | Transition::CharClass { .. }
+        )
+    }
+}
+
 /// An NFA state
 #[derive(Debug, Clone)]
 pub struct State {
@@ -86,19 +101,21 @@ pub struct Nfa {
     /// The accepting state
     pub accept: StateId,
     /// Next state ID to allocate
-    next_state_id: StateId,
+    pub(crate) next_state_id: StateId,
     /// Next group ID to allocate
-    next_group_id: u32,
+    pub(crate) next_group_id: u32,
     /// Named group mapping (name -> group_id)
-    named_groups: HashMap<String, u32>,
-    /// List of numbered (non-named) group indices, in order of appearance
+    pub(crate) named_groups: HashMap<String, u32>,
+    /// List of numbered group indices, in order of appearance
     /// Used for relative backreference resolution
-    numbered_groups: Vec<u32>,
+    pub(crate) numbered_groups: Vec<u32>,
+    /// Whether named groups are also counted as numbered groups
+    pub(crate) numbering: GroupNumbering,
     /// Mode flags for regex matching
     pub mode_flags: ModeFlags,
     /// Pre-computed epsilon closure for each state
     /// epsilon_closure[state_id] = all states reachable from state_id via epsilon transitions
-    epsilon_closure: Vec<Vec<StateId>>,
+    pub(crate) epsilon_closure: Vec<Vec<StateId>>,
 }
 
 impl Nfa {
@@ -112,6 +129,7 @@ impl Nfa {
             next_group_id: 1, // Group 0 is the entire match
             named_groups: HashMap::new(),
             numbered_groups: Vec::new(),
+            numbering: GroupNumbering::default(),
             mode_flags: ModeFlags::default(),
             epsilon_closure: Vec::new(),
         }
@@ -132,7 +150,15 @@ impl Nfa {
 
     /// Build an NFA from an AST expression
     pub fn from_expr(expr: &Expr) -> Self {
-        let mut nfa = Nfa::new();
+        Self::from_expr_with_numbering(expr, GroupNumbering::default())
+    }
+
+    /// Build an NFA from an AST expression using the given group numbering
+    pub fn from_expr_with_numbering(expr: &Expr, numbering: GroupNumbering) -> Self {
+        let mut nfa = Nfa {
+            numbering,
+            ..Nfa::new()
+        };
         let (start, accept) = nfa.compile_expr(expr);
         nfa.start = start;
         nfa.accept = accept;
@@ -145,7 +171,7 @@ impl Nfa {
     }
 
     /// Pre-compute epsilon closure for each state
-    fn compute_epsilon_closures(&mut self) {
+    pub(crate) fn compute_epsilon_closures(&mut self) {
         let num_states = self.states.len();
         self.epsilon_closure = Vec::with_capacity(num_states);
 
@@ -238,7 +264,7 @@ impl Nfa {
     /// * `positive` - If true, positive lookahead (@>:), if false, negative lookahead (@>~:)
     fn compile_lookahead(&mut self, expr: &Expr, positive: bool) -> (StateId, StateId) {
         // Compile the inner pattern into a separate NFA
-        let inner_nfa = Nfa::from_expr(expr);
+        let inner_nfa = Nfa::from_expr_with_numbering(expr, self.numbering);
 
         let start = self.new_state();
         let accept = self.new_state();
@@ -259,7 +285,7 @@ impl Nfa {
     /// * `positive` - If true, positive lookbehind (@<:), if false, negative lookbehind (@<~:)
     fn compile_lookbehind(&mut self, expr: &Expr, positive: bool) -> (StateId, StateId) {
         // Compile the inner pattern into a separate NFA
-        let inner_nfa = Nfa::from_expr(expr);
+        let inner_nfa = Nfa::from_expr_with_numbering(expr, self.numbering);
 
         let start = self.new_state();
         let accept = self.new_state();
@@ -364,7 +390,35 @@ impl Nfa {
         quantifier: Quantifier,
         greedy: bool,
     ) -> (StateId, StateId) {
-        let (inner_start, inner_accept) = self.compile_expr(expr);
+        let group_base = self.next_group_id;
+        self.compile_quantified_from(expr, quantifier, greedy, group_base)
+    }
+
+    /// Compile a quantified expression whose groups are numbered from `group_base`
+    fn compile_quantified_from(
+        &mut self,
+        expr: &Expr,
+        quantifier: Quantifier,
+        greedy: bool,
+        group_base: u32,
+    ) -> (StateId, StateId) {
+        match quantifier {
+            Quantifier::Exactly(n) => {
+                // {n}: Match exactly n times
+                return self.compile_repeat_exact(expr, n, group_base);
+            }
+            Quantifier::AtLeast(n) => {
+                // {n,}: Greedy - prefer to match more
+                return self.compile_repeat_at_least(expr, n, greedy, group_base);
+            }
+            Quantifier::Between(n, m) => {
+                // {n,m}: Greedy - prefer to match more
+                return self.compile_repeat_between(expr, n, m, greedy, group_base);
+            }
+            _ => {}
+        }
+
+        let (inner_start, inner_accept) = self.compile_repeat_copy(expr, group_base);
         let start = self.new_state();
         let accept = self.new_state();
 
@@ -403,37 +457,46 @@ impl Nfa {
                 self.add_transition(start, Transition::Epsilon, accept);
                 self.add_transition(inner_accept, Transition::Epsilon, accept);
             }
-            Quantifier::Exactly(n) => {
-                // {n}: Match exactly n times
-                return self.compile_repeat_exact(expr, n);
-            }
-            Quantifier::AtLeast(n) => {
-                // {n,}: Greedy - prefer to match more
-                return self.compile_repeat_at_least(expr, n, greedy);
-            }
-            Quantifier::Between(n, m) => {
-                // {n,m}: Greedy - prefer to match more
-                return self.compile_repeat_between(expr, n, m, greedy);
+            Quantifier::Exactly(_) | Quantifier::AtLeast(_) | Quantifier::Between(_, _) => {
+                unreachable!("counted repetitions are compiled above")
             }
         }
 
         (start, accept)
     }
 
+    /// Compile one copy of a repeated expression
+    ///
+    /// The first copy allocates group IDs starting at `group_base`; later copies
+    /// reuse them so every repetition captures into the same group.
+    fn compile_repeat_copy(&mut self, expr: &Expr, group_base: u32) -> (StateId, StateId) {
+        let next_group_id = self.next_group_id;
+        if next_group_id == group_base {
+            return self.compile_expr(expr);
+        }
+
+        let numbered_groups = self.numbered_groups.len();
+        self.next_group_id = group_base;
+        let compiled = self.compile_expr(expr);
+        self.next_group_id = next_group_id;
+        self.numbered_groups.truncate(numbered_groups);
+        compiled
+    }
+
     /// Compile repeat exactly n times
-    fn compile_repeat_exact(&mut self, expr: &Expr, n: u32) -> (StateId, StateId) {
+    fn compile_repeat_exact(&mut self, expr: &Expr, n: u32, group_base: u32) -> (StateId, StateId) {
         if n == 0 {
             return self.compile_empty();
         }
         if n == 1 {
-            return self.compile_expr(expr);
+            return self.compile_repeat_copy(expr, group_base);
         }
 
         let mut start = None;
         let mut prev_accept = None;
 
         for _ in 0..n {
-            let (s, a) = self.compile_expr(expr);
+            let (s, a) = self.compile_repeat_copy(expr, group_base);
             if start.is_none() {
                 start = Some(s);
             }
@@ -447,11 +510,17 @@ impl Nfa {
     }
 
     /// Compile repeat at least n times
-    fn compile_repeat_at_least(&mut self, expr: &Expr, n: u32, greedy: bool) -> (StateId, StateId) {
+    fn compile_repeat_at_least(
+        &mut self,
+        expr: &Expr,
+        n: u32,
+        greedy: bool,
+        group_base: u32,
+    ) -> (StateId, StateId) {
         // Match exactly n times, then add a * (zero or more)
-        let (exact_start, exact_accept) = self.compile_repeat_exact(expr, n);
+        let (exact_start, exact_accept) = self.compile_repeat_exact(expr, n, group_base);
         let (star_start, star_accept) =
-            self.compile_quantified(expr, Quantifier::ZeroOrMore, greedy);
+            self.compile_quantified_from(expr, Quantifier::ZeroOrMore, greedy, group_base);
 
         self.add_transition(exact_accept, Transition::Epsilon, star_start);
         (exact_start, star_accept)
@@ -464,13 +533,14 @@ impl Nfa {
         n: u32,
         m: u32,
         greedy: bool,
+        group_base: u32,
     ) -> (StateId, StateId) {
         if n == m {
-            return self.compile_repeat_exact(expr, n);
+            return self.compile_repeat_exact(expr, n, group_base);
         }
 
         // Match exactly n times, then match (m-n) optional times
-        let (exact_start, exact_accept) = self.compile_repeat_exact(expr, n);
+        let (exact_start, exact_accept) = self.compile_repeat_exact(expr, n, group_base);
 
         let start = self.new_state();
         let accept = self.new_state();
@@ -480,7 +550,7 @@ impl Nfa {
         // Add (m-n) optional matches
         let mut prev_accept = exact_accept;
         for _ in 0..(m - n) {
-            let (s, a) = self.compile_expr(expr);
+            let (s, a) = self.compile_repeat_copy(expr, group_base);
             if greedy {
                 // Greedy: try to match first, then exit
                 self.add_transition(prev_accept, Transition::Epsilon, s);
@@ -502,11 +572,13 @@ impl Nfa {
         let group_id = self.next_group_id;
         self.next_group_id += 1;
 
-        // Register named group if applicable, otherwise track as numbered
+        // Register named group if applicable; unnamed groups are always
+        // numbered, named ones only under PCRE numbering
+        if name.is_none() || self.numbering == GroupNumbering::Pcre {
+            self.numbered_groups.push(group_id);
+        }
         if let Some(n) = name {
             self.named_groups.insert(n, group_id);
-        } else {
-            self.numbered_groups.push(group_id);
         }
 
         let start = self.new_state();
@@ -646,17 +718,13 @@ impl Nfa {
         for state in &self.states {
             for (transition, _) in &state.transitions {
                 match transition {
-                    Transition::Char(c) => {
-                        if *c as u32 > 127 {
-                            return false;
-                        }
-                    }
-                    Transition::CharClass { lookup, .. } => {
-                        // Check if lookup table has any bits set for non-ASCII (128-255)
-                        // Bytes 16-31 correspond to characters 128-255
-                        if lookup[16..32].iter().any(|&b| b != 0) {
-                            return false;
-                        }
+                    Transition::Char(c) if *c as u32 > 127 => return false,
+                    // Check if lookup table has any bits set for non-ASCII (128-255)
+                    // Bytes 16-31 correspond to characters 128-255
+                    Transition::CharClass { lookup, .. }
+                        if lookup[16..32].iter().any(|&b| b != 0) =>
+                    {
+                        return false;
                     }
                     // All other transition types are ASCII-compatible
                     // - Any (.) works with bytes
@@ -670,6 +738,321 @@ impl Nfa {
         }
         true
     }
+
+    /// Whether a transition that consumes one character accepts `c` under
+    /// this NFA's mode flags
+    ///
+    /// Transitions that consume nothing, and backreferences, accept nothing.
+    pub(crate) fn matches_char(&self, transition: &Transition, c: char) -> bool {
+        match transition {
+            Transition::Char(tc) => {
+                if self.mode_flags.case_insensitive {
+                    tc.eq_ignore_ascii_case(&c)
+                } else {
+                    *tc == c
+                }
+            }
+            Transition::CharClass {
+                negated: _negated,
+                lookup,
+            } => {
+                // O(1) lookup using pre-computed table (negation already handled in lookup)
+                if c as u32 > 255 {
+                    // For non-ASCII, fall back to simple check
+                    false
+                } else {
+                    let byte_idx = (c as u8 / 8) as usize;
+                    let bit_idx = c as u8 % 8;
+                    (lookup[byte_idx] & (1 << bit_idx)) != 0
+                }
+            }
+            Transition::Any => {
+                // In dotall mode, . matches any character including newline
+                if self.mode_flags.dotall {
+                    true
+                } else {
+                    c != '\n'
+                }
+            }
+            _ => false, // Epsilon transitions handled in epsilon_closure
+        }
+    }
+}
+
+impl Nfa {
+    /// Render the NFA as a Graphviz DOT graph
+    ///
+    /// Each state is a node, with the accepting state drawn as a double
+    /// circle. Edges are labelled with what they match; epsilon edges are
+    /// dashed and group markers are blue. The NFA of a lookaround is drawn
+    /// as a cluster, with a dotted edge from the state that checks it.
+    ///
+    /// ```
+    /// use ogex::Regex;
+    ///
+    /// let regex = Regex::new("a|b").unwrap();
+    /// let dot = regex.nfa().to_dot();
+    /// assert!(dot.starts_with("digraph nfa {"));
+    /// assert!(dot.contains("label=\"'a'\""));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph nfa {\n    rankdir=LR;\n    node [shape=circle];\n");
+        let mut clusters = 0;
+        self.write_dot(&mut dot, "s_", &mut clusters);
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Write this NFA's states and edges, naming each state `prefix` followed
+    /// by its ID so nested lookaround NFAs don't collide
+    fn write_dot(&self, dot: &mut String, prefix: &str, clusters: &mut usize) {
+        // Writing to a String cannot fail
+        let _ = writeln!(dot, "    {}start [shape=point];", prefix);
+        let _ = writeln!(dot, "    {}start -> {}{};", prefix, prefix, self.start);
+        for (id, state) in self.states.iter().enumerate() {
+            let shape = if state.is_accepting {
+                "doublecircle"
+            } else {
+                "circle"
+            };
+            let _ = writeln!(
+                dot,
+                "    {}{} [label=\"{}\", shape={}];",
+                prefix, id, id, shape
+            );
+        }
+        for (from, state) in self.states.iter().enumerate() {
+            for (transition, to) in &state.transitions {
+                let (label, style) = match transition {
+                    Transition::Epsilon => ("ε".to_string(), ", style=dashed"),
+                    Transition::GroupStart(n) => (format!("({}", n), ", color=blue"),
+                    Transition::GroupEnd(n) => (format!("{})", n), ", color=blue"),
+                    _ => (transition_label(transition), ""),
+                };
+                let _ = writeln!(
+                    dot,
+                    "    {}{} -> {}{} [label=\"{}\"{}];",
+                    prefix,
+                    from,
+                    prefix,
+                    to,
+                    dot_escape(&label),
+                    style
+                );
+                if let Transition::Lookahead(inner)
+                | Transition::NegativeLookahead(inner)
+                | Transition::Lookbehind(inner)
+                | Transition::NegativeLookbehind(inner) = transition
+                {
+                    *clusters += 1;
+                    let inner_prefix = format!("c{}_", clusters);
+                    let _ = writeln!(dot, "    subgraph cluster_{} {{", clusters);
+                    let _ = writeln!(dot, "    label=\"{}\";", label);
+                    inner.write_dot(dot, &inner_prefix, clusters);
+                    let _ = writeln!(dot, "    }}");
+                    let _ = writeln!(
+                        dot,
+                        "    {}{} -> {}start [style=dotted, arrowhead=none];",
+                        prefix, from, inner_prefix
+                    );
+                }
+            }
+        }
+    }
+
+    /// Describe the NFA as JSON, for tools that draw or analyse it
+    ///
+    /// The object holds the `start` and `accept` state IDs, the mode
+    /// `flags`, the `named_groups` as an array of `{"name", "index"}`
+    /// objects in order of index, and the `states`, each with its `id`,
+    /// whether it is `accepting` and its `transitions`. A transition has
+    /// the state it goes `to`, its `kind` in snake case (`char`, `any`,
+    /// `epsilon`, `class`, `group_start`, `group_end`, `backref`,
+    /// `backref_relative`, `start_anchor`, `end_anchor`, `word_boundary`,
+    /// `non_word_boundary`, `lookahead`, `negative_lookahead`, `lookbehind`
+    /// or `negative_lookbehind`), the `label` [`Nfa::to_dot`] gives it and
+    /// the kind's own fields: `char`, `negated`, `group`, `offset` or the
+    /// lookaround's `nfa`.
+    ///
+    /// ```
+    /// use ogex::Regex;
+    ///
+    /// let regex = Regex::new("(x:a)").unwrap();
+    /// let json = regex.nfa().to_json();
+    /// assert!(json.contains(r#""named_groups":[{"name":"x","index":1}]"#));
+    /// assert!(json.contains(r#""kind":"char","label":"'a'","char":"a""#));
+    /// ```
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        self.write_json(&mut json);
+        json
+    }
+
+    fn write_json(&self, json: &mut String) {
+        let flags = &self.mode_flags;
+        let _ = write!(
+            json,
+            r#"{{"start":{},"accept":{},"flags":{{"case_insensitive":{},"multiline":{},"#,
+            self.start, self.accept, flags.case_insensitive, flags.multiline
+        );
+        let _ = write!(
+            json,
+            r#""dotall":{},"extended":{}}},"named_groups":["#,
+            flags.dotall, flags.extended
+        );
+        let mut names: Vec<(&String, &u32)> = self.named_groups.iter().collect();
+        names.sort_by_key(|(_, index)| **index);
+        for (i, (name, index)) in names.into_iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            json.push_str(r#"{"name":"#);
+            json_string(json, name);
+            let _ = write!(json, r#","index":{}}}"#, index);
+        }
+        json.push_str(r#"],"states":["#);
+        for (id, state) in self.states.iter().enumerate() {
+            if id > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                r#"{{"id":{},"accepting":{},"transitions":["#,
+                id, state.is_accepting
+            );
+            for (i, (transition, to)) in state.transitions.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                let kind = match transition {
+                    Transition::Char(_) => "char",
+                    Transition::Any => "any",
+                    Transition::Epsilon => "epsilon",
+                    Transition::CharClass { .. } => "class",
+                    Transition::GroupStart(_) => "group_start",
+                    Transition::GroupEnd(_) => "group_end",
+                    Transition::Backref(_) => "backref",
+                    Transition::BackrefRelative(_) => "backref_relative",
+                    Transition::StartAnchor => "start_anchor",
+                    Transition::EndAnchor => "end_anchor",
+                    Transition::WordBoundary => "word_boundary",
+                    Transition::NonWordBoundary => "non_word_boundary",
+                    Transition::Lookahead(_) => "lookahead",
+                    Transition::NegativeLookahead(_) => "negative_lookahead",
+                    Transition::Lookbehind(_) => "lookbehind",
+                    Transition::NegativeLookbehind(_) => "negative_lookbehind",
+                };
+                let _ = write!(json, r#"{{"to":{},"kind":"{}","label":"#, to, kind);
+                json_string(json, &transition_label(transition));
+                match transition {
+                    Transition::Char(c) => {
+                        json.push_str(r#","char":"#);
+                        json_string(json, c.encode_utf8(&mut [0; 4]));
+                    }
+                    Transition::CharClass { negated, .. } => {
+                        let _ = write!(json, r#","negated":{}"#, negated);
+                    }
+                    Transition::GroupStart(n)
+                    | Transition::GroupEnd(n)
+                    | Transition::Backref(n) => {
+                        let _ = write!(json, r#","group":{}"#, n);
+                    }
+                    Transition::BackrefRelative(n) => {
+                        let _ = write!(json, r#","offset":{}"#, n);
+                    }
+                    Transition::Lookahead(inner)
+                    | Transition::NegativeLookahead(inner)
+                    | Transition::Lookbehind(inner)
+                    | Transition::NegativeLookbehind(inner) => {
+                        json.push_str(r#","nfa":"#);
+                        inner.write_json(json);
+                    }
+                    _ => {}
+                }
+                json.push('}');
+            }
+            json.push_str("]}");
+        }
+        json.push_str("]}");
+    }
+}
+
+/// Append `text` to `json` as a JSON string literal
+pub(crate) fn json_string(json: &mut String, text: &str) {
+    json.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// How a transition is labelled in [`Nfa::to_dot`]
+pub(crate) fn transition_label(transition: &Transition) -> String {
+    match transition {
+        Transition::Char(c) => format!("'{}'", c.escape_default()),
+        Transition::Any => "any".to_string(),
+        Transition::Epsilon => "ε".to_string(),
+        Transition::CharClass { lookup, .. } => class_label(lookup),
+        Transition::GroupStart(n) => format!("({}", n),
+        Transition::GroupEnd(n) => format!("{})", n),
+        Transition::Backref(n) => format!("\\{}", n),
+        Transition::BackrefRelative(n) => format!("\\g{{{}}}", n),
+        Transition::StartAnchor => "^".to_string(),
+        Transition::EndAnchor => "$".to_string(),
+        Transition::WordBoundary => "\\b".to_string(),
+        Transition::NonWordBoundary => "\\B".to_string(),
+        Transition::Lookahead(_) => "lookahead".to_string(),
+        Transition::NegativeLookahead(_) => "negative lookahead".to_string(),
+        Transition::Lookbehind(_) => "lookbehind".to_string(),
+        Transition::NegativeLookbehind(_) => "negative lookbehind".to_string(),
+    }
+}
+
+/// Describe a character class lookup table as ranges, such as `[0-9a-f]`,
+/// or by the characters it excludes if that is shorter
+fn class_label(lookup: &[u8; 32]) -> String {
+    let contains = |byte: u8| lookup[byte as usize / 8] & (1 << (byte % 8)) != 0;
+    let included = (0..=255).filter(|&b| contains(b)).count();
+    let negated = included > 128;
+
+    let mut label = String::from(if negated { "[^" } else { "[" });
+    let mut byte = 0u16;
+    while byte <= 255 {
+        if contains(byte as u8) == negated {
+            byte += 1;
+            continue;
+        }
+        let start = byte as u8;
+        while byte < 255 && contains(byte as u8 + 1) != negated {
+            byte += 1;
+        }
+        let end = byte as u8;
+        label.extend(char::from(start).escape_default());
+        if end > start {
+            if end > start + 1 {
+                label.push('-');
+            }
+            label.extend(char::from(end).escape_default());
+        }
+        byte += 1;
+    }
+    label.push(']');
+    label
+}
+
+/// Escape a label for use inside a quoted DOT string
+fn dot_escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
 }
 
 impl Default for Nfa {
@@ -683,6 +1066,63 @@ mod tests {
     use super::*;
     use crate::ast::Expr;
 
+    #[test]
+    fn test_to_dot() {
+        let nfa = Nfa::from_expr(&crate::parse("(a[0-9x])(@>~:b)").unwrap());
+        let dot = nfa.to_dot();
+        assert!(dot.contains("label=\"'a'\""));
+        assert!(dot.contains("label=\"[0-9x]\""));
+        assert!(dot.contains("label=\"(1\", color=blue"));
+        assert!(dot.contains("subgraph cluster_1 {"));
+        assert!(dot.contains("c1_start [shape=point]"));
+        assert!(dot.contains("shape=doublecircle"));
+        assert!(dot.trim_end().ends_with('}'));
+
+        assert_eq!(transition_label(&Transition::Backref(2)), "\\2");
+        let mut digits = [0u8; 32];
+        for byte in b'0'..=b'9' {
+            digits[byte as usize / 8] |= 1 << (byte % 8);
+        }
+        assert_eq!(class_label(&digits), "[0-9]");
+        assert_eq!(class_label(&digits.map(|b| !b)), "[^0-9]");
+    }
+
+    #[test]
+    fn test_to_json() {
+        let regex = crate::Regex::new(r#"(q:")(\d)\1(@>~:b)"#).unwrap();
+        let nfa = regex.nfa();
+        let json: serde_json::Value = serde_json::from_str(&nfa.to_json()).unwrap();
+        assert_eq!(json["start"], nfa.start);
+        assert_eq!(json["accept"], nfa.accept);
+        assert_eq!(json["flags"]["dotall"], false);
+        assert_eq!(
+            json["named_groups"],
+            serde_json::json!([{"name": "q", "index": 1}])
+        );
+
+        let states = json["states"].as_array().unwrap();
+        assert_eq!(states.len(), nfa.states.len());
+        assert_eq!(states[nfa.accept]["accepting"], true);
+        let transitions: Vec<&serde_json::Value> = states
+            .iter()
+            .flat_map(|state| state["transitions"].as_array().unwrap())
+            .collect();
+        let find = |kind: &str| {
+            *transitions
+                .iter()
+                .find(|transition| transition["kind"] == kind)
+                .unwrap()
+        };
+        assert_eq!(find("char")["char"], "\"");
+        assert_eq!(find("char")["label"], "'\\\"'");
+        assert_eq!(find("class")["label"], "[0-9]");
+        assert_eq!(find("group_start")["group"], 1);
+        assert_eq!(find("backref")["group"], 1);
+        let lookahead = find("negative_lookahead");
+        assert_eq!(lookahead["nfa"]["states"][0]["id"], 0);
+        assert!(lookahead["to"].is_u64());
+    }
+
     #[test]
     fn test_nfa_from_literal() {
         let expr = Expr::literal('a');
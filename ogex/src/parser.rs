@@ -23,35 +23,157 @@
 //!   escape    := '\' char
 
 use crate::ast::{ClassItem, Expr, Quantifier};
-use crate::error::{ParseError, Span};
+use crate::error::{ParseError, RegexError, Span};
 use crate::lexer::{Lexer, Token};
+use crate::library::PatternLibrary;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
 /// Parser for regex patterns
 pub struct Parser<'a> {
+    source: &'a str,
     lexer: Lexer<'a>,
     current_token: Token,
     current_span: Span,
+    /// Spans of the capturing groups outside lookarounds, in group number order
+    group_spans: Vec<Span>,
+    /// The fragment each of those groups came from, if not the pattern itself
+    group_fragments: Vec<Option<String>>,
+    /// Fragments that `(@use:name)` can refer to
+    library: Option<&'a PatternLibrary>,
+    /// How many lookarounds enclose the current position
+    lookaround_depth: usize,
+    /// Spans of quantified expressions, innermost first
+    quantifier_spans: Vec<Span>,
+    /// Spans of backreferences, in the order they appear in the AST
+    backref_spans: Vec<Span>,
+    /// End of the last consumed token
+    previous_end: usize,
+    /// First lexer error; the parser sees `Eof` from that point on
+    lexer_error: Option<RegexError>,
 }
 
 impl<'a> Parser<'a> {
     /// Create a new parser for the given input string
     pub fn new(input: &'a str) -> Self {
-        let mut lexer = Lexer::new(input);
-        let spanned = lexer.next_spanned();
-        let current_token = spanned.token;
-        let current_span = spanned.span;
-        Parser {
-            lexer,
-            current_token,
-            current_span,
+        Parser::with_extended(input, false)
+    }
+
+    /// Create a parser that, if `extended` is set, ignores whitespace and `#`
+    /// comments outside character classes
+    pub fn with_extended(input: &'a str, extended: bool) -> Self {
+        let mut parser = Parser {
+            source: input,
+            lexer: Lexer::with_extended(input, extended),
+            current_token: Token::Eof,
+            current_span: Span::default(),
+            group_spans: Vec::new(),
+            group_fragments: Vec::new(),
+            library: None,
+            lookaround_depth: 0,
+            quantifier_spans: Vec::new(),
+            backref_spans: Vec::new(),
+            previous_end: 0,
+            lexer_error: None,
+        };
+        parser.advance();
+        parser
+    }
+
+    /// Expand `(@use:name)` references with the fragments in `library`
+    pub fn library(mut self, library: &'a PatternLibrary) -> Self {
+        self.library = Some(library);
+        self
+    }
+
+    /// Spans of the capturing groups parsed so far, in group number order
+    ///
+    /// Groups inside lookarounds are numbered separately by the engine and
+    /// are not included.
+    pub fn group_spans(&self) -> &[Span] {
+        &self.group_spans
+    }
+
+    /// The library fragment each capturing group parsed so far came from,
+    /// in group number order; `None` for groups written in the pattern itself
+    ///
+    /// Groups from a fragment have the span of the reference that expanded
+    /// it in [`Parser::group_spans`].
+    pub fn group_fragments(&self) -> &[Option<String>] {
+        &self.group_fragments
+    }
+
+    /// Spans of the quantified expressions parsed so far, each covering the
+    /// atom and its quantifier
+    ///
+    /// Spans are recorded as each quantifier finishes parsing, so an inner
+    /// quantifier comes before the one enclosing it: the order of a
+    /// post-order walk of the AST, including lookarounds.
+    pub fn quantifier_spans(&self) -> &[Span] {
+        &self.quantifier_spans
+    }
+
+    /// Spans of the backreferences parsed so far, in the order of a walk of
+    /// the AST, including lookarounds
+    ///
+    /// Backreferences from a fragment have the span of the reference that
+    /// expanded it.
+    pub fn backref_spans(&self) -> &[Span] {
+        &self.backref_spans
+    }
+
+    /// Record a capturing group opening at the current token
+    fn open_group(&mut self, start: usize) -> Option<usize> {
+        if self.lookaround_depth > 0 {
+            return None;
         }
+        self.group_spans
+            .push(Span::new(start, self.current_span().end));
+        self.group_fragments.push(None);
+        Some(self.group_spans.len() - 1)
+    }
+
+    /// Consume the closing paren of a group opened with [`Parser::open_group`]
+    fn close_group(&mut self, slot: Option<usize>) -> Result<(), ParseError> {
+        let end = self.current_span().end;
+        self.expect(Token::RightParen)?;
+        if let Some(slot) = slot {
+            self.group_spans[slot].end = end;
+        }
+        Ok(())
+    }
+
+    /// Parse the body and closing paren of a lookaround
+    fn parse_lookaround(&mut self) -> Result<Expr, ParseError> {
+        self.advance();
+        self.lookaround_depth += 1;
+        let pattern = self.parse_alternation();
+        self.lookaround_depth -= 1;
+        let pattern = pattern?;
+        self.expect(Token::RightParen)?;
+        Ok(pattern)
     }
 
     /// Advance to the next token
     fn advance(&mut self) {
-        let spanned = self.lexer.next_spanned();
-        self.current_token = spanned.token;
-        self.current_span = spanned.span;
+        if self.lexer_error.is_some() {
+            return;
+        }
+        self.previous_end = self.current_span.end;
+        match self.lexer.next_spanned() {
+            Ok(spanned) => {
+                self.current_token = spanned.token;
+                self.current_span = spanned.span;
+            }
+            Err(err) => {
+                let position = err.span().map_or(0, |span| span.start);
+                self.current_token = Token::Eof;
+                self.current_span = Span::new(position, position);
+                self.lexer_error = Some(err);
+            }
+        }
     }
 
     /// Get current token's span
@@ -59,31 +181,55 @@ impl<'a> Parser<'a> {
         self.current_span
     }
 
+    /// Build an error for the current token, suggesting a fix for common
+    /// mistakes: a brace meant literally, or another flavor's group syntax
+    fn unexpected(&self, expected: &str) -> ParseError {
+        let span = self.current_span();
+        let suggestion = match self.current_token {
+            Token::LeftBrace => Some("\\{".to_string()),
+            Token::RightBrace => Some("\\}".to_string()),
+            Token::Question => span.start.checked_sub(1).and_then(|start| {
+                let rest: String = self.source.chars().skip(start).collect();
+                crate::suggest::foreign_syntax(&rest)
+            }),
+            _ => None,
+        };
+        ParseError::UnexpectedToken {
+            expected: expected.to_string(),
+            found: self.current_token.to_string(),
+            span: Some(span),
+            suggestion,
+        }
+    }
+
     /// Expect a specific token, error if not found
     fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
         if self.current_token == expected {
             self.advance();
             Ok(())
         } else {
-            Err(ParseError::UnexpectedToken {
-                expected: format!("{:?}", expected),
-                found: self.current_token.to_string(),
-                span: Some(self.current_span()),
-            })
+            Err(self.unexpected(&expected.to_string()))
         }
     }
 
     /// Parse the entire input and return the AST
-    pub fn parse(&mut self) -> Result<Expr, ParseError> {
+    ///
+    /// A lexer error takes precedence over any parse error it caused, since
+    /// the parser only saw the input end early.
+    pub fn parse(&mut self) -> Result<Expr, RegexError> {
+        let result = self.parse_all();
+        match self.lexer_error.take() {
+            Some(err) => Err(err),
+            None => Ok(result?),
+        }
+    }
+
+    fn parse_all(&mut self) -> Result<Expr, ParseError> {
         let expr = self.parse_alternation()?;
 
         // Ensure we've consumed all tokens
         if self.current_token != Token::Eof {
-            return Err(ParseError::UnexpectedToken {
-                expected: "EOF".to_string(),
-                found: self.current_token.to_string(),
-                span: Some(self.current_span()),
-            });
+            return Err(self.unexpected("EOF"));
         }
 
         Ok(expr)
@@ -140,10 +286,13 @@ impl<'a> Parser<'a> {
     /// Parse a quantified expression
     /// quantified := atom quantifier?
     fn parse_quantified(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span().start;
         let atom = self.parse_atom()?;
 
         // Check for quantifier
         if let Some((quantifier, greedy)) = self.parse_quantifier()? {
+            self.quantifier_spans
+                .push(Span::new(start, self.previous_end));
             Ok(Expr::Quantified {
                 expr: Box::new(atom),
                 quantifier,
@@ -179,8 +328,11 @@ impl<'a> Parser<'a> {
                 Ok(Some((Quantifier::Optional, true)))
             }
             Token::LeftBrace => {
+                let start = self.current_span().start;
                 self.advance(); // consume '{'
-                let min = self.parse_number()?;
+                // A brace that does not start a valid {n,m} was likely meant literally
+                let literal_brace = |err: ParseError| err.with_suggestion("\\{");
+                let min = self.parse_number().map_err(literal_brace)?;
 
                 let quantifier = if self.current_token == Token::Comma {
                     self.advance(); // consume ','
@@ -189,7 +341,7 @@ impl<'a> Parser<'a> {
                         Quantifier::AtLeast(min)
                     } else {
                         // {n,m} - between n and m
-                        let max = self.parse_number()?;
+                        let max = self.parse_number().map_err(literal_brace)?;
                         Quantifier::Between(min, max)
                     }
                 } else {
@@ -197,7 +349,17 @@ impl<'a> Parser<'a> {
                     Quantifier::Exactly(min)
                 };
 
-                self.expect(Token::RightBrace)?;
+                let end = self.current_span().end;
+                self.expect(Token::RightBrace).map_err(literal_brace)?;
+
+                if let Quantifier::Between(min, max) = quantifier
+                    && max < min
+                {
+                    return Err(ParseError::InvalidQuantifier {
+                        message: format!("{{{},{}}} has a maximum below its minimum", min, max),
+                        span: Some(Span::new(start, end)),
+                    });
+                }
 
                 // Check for lazy modifier (?) after {n,m} or {n,}
                 let greedy = if self.current_token == Token::Question {
@@ -230,11 +392,7 @@ impl<'a> Parser<'a> {
                 }
                 Ok(num)
             }
-            _ => Err(ParseError::UnexpectedToken {
-                expected: "number".to_string(),
-                found: self.current_token.to_string(),
-                span: Some(self.current_span()),
-            }),
+            _ => Err(self.unexpected("number")),
         }
     }
 
@@ -268,16 +426,19 @@ impl<'a> Parser<'a> {
             }
             Token::BackrefNumber(n) => {
                 let expr = Expr::Backreference(*n);
+                self.backref_spans.push(self.current_span());
                 self.advance();
                 Ok(expr)
             }
             Token::BackrefRelative(n) => {
                 let expr = Expr::RelativeBackreference(*n);
+                self.backref_spans.push(self.current_span());
                 self.advance();
                 Ok(expr)
             }
             Token::BackrefName(name) => {
                 let expr = Expr::NamedBackreference(name.clone());
+                self.backref_spans.push(self.current_span());
                 self.advance();
                 Ok(expr)
             }
@@ -317,14 +478,16 @@ impl<'a> Parser<'a> {
             Token::NamedGroupStart(name) => {
                 // Direct named group without explicit paren handling
                 let name = name.clone();
+                let slot = self.open_group(self.current_span().start);
                 self.advance();
                 let pattern = self.parse_alternation()?;
-                self.expect(Token::RightParen)?;
+                self.close_group(slot)?;
                 Ok(Expr::NamedGroup {
                     name,
                     pattern: Box::new(pattern),
                 })
             }
+            Token::Use(name) => self.parse_use(name.clone()),
             Token::NonCapturing => {
                 self.advance();
                 let pattern = self.parse_alternation()?;
@@ -332,27 +495,19 @@ impl<'a> Parser<'a> {
                 Ok(Expr::NonCapturingGroup(Box::new(pattern)))
             }
             Token::Lookahead => {
-                self.advance();
-                let pattern = self.parse_alternation()?;
-                self.expect(Token::RightParen)?;
+                let pattern = self.parse_lookaround()?;
                 Ok(Expr::Lookahead(Box::new(pattern)))
             }
             Token::NegativeLookahead => {
-                self.advance();
-                let pattern = self.parse_alternation()?;
-                self.expect(Token::RightParen)?;
+                let pattern = self.parse_lookaround()?;
                 Ok(Expr::NegativeLookahead(Box::new(pattern)))
             }
             Token::Lookbehind => {
-                self.advance();
-                let pattern = self.parse_alternation()?;
-                self.expect(Token::RightParen)?;
+                let pattern = self.parse_lookaround()?;
                 Ok(Expr::Lookbehind(Box::new(pattern)))
             }
             Token::NegativeLookbehind => {
-                self.advance();
-                let pattern = self.parse_alternation()?;
-                self.expect(Token::RightParen)?;
+                let pattern = self.parse_lookaround()?;
                 Ok(Expr::NegativeLookbehind(Box::new(pattern)))
             }
             Token::Atomic => {
@@ -381,43 +536,46 @@ impl<'a> Parser<'a> {
             Token::Eof => Err(ParseError::UnexpectedEof {
                 span: Some(self.current_span()),
             }),
-            _ => Err(ParseError::UnexpectedToken {
-                expected: "expression".to_string(),
-                found: self.current_token.to_string(),
-                span: Some(self.current_span()),
-            }),
+            _ => Err(self.unexpected("expression")),
         }
     }
 
     /// Parse a group
     /// group := '(' group_inner ')'
     fn parse_group(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current_span().start;
         self.expect(Token::LeftParen)?;
 
-        let expr = match &self.current_token {
-            Token::NamedGroupStart(name) => {
-                let name = name.clone();
-                self.advance(); // consume the name and colon
-                let pattern = self.parse_alternation()?;
-                Expr::NamedGroup {
-                    name,
-                    pattern: Box::new(pattern),
-                }
-            }
-            Token::NonCapturing => {
-                self.advance(); // consume '?:'
-                let pattern = self.parse_alternation()?;
-                Expr::NonCapturingGroup(Box::new(pattern))
-            }
-            _ => {
-                // Regular capturing group
-                let pattern = self.parse_alternation()?;
-                Expr::Group(Box::new(pattern))
-            }
-        };
+        // Named and non-capturing groups lex as a single token including
+        // their paren, so one here begins a nested group inside this one
+        let slot = self.open_group(start);
+        let pattern = self.parse_alternation()?;
+        self.close_group(slot)?;
+        Ok(Expr::Group(Box::new(pattern)))
+    }
 
+    /// Expand a `(@use:name)` reference to a library fragment
+    fn parse_use(&mut self, name: String) -> Result<Expr, ParseError> {
+        let start = self.current_span().start;
+        let Some(fragment) = self.library.and_then(|library| library.fragment(&name)) else {
+            return Err(ParseError::UndefinedFragment {
+                name,
+                span: Some(self.current_span()),
+            });
+        };
+        self.advance();
+        let end = self.current_span().end;
         self.expect(Token::RightParen)?;
-        Ok(expr)
+        if self.lookaround_depth == 0 {
+            for origin in &fragment.groups {
+                self.group_spans.push(Span::new(start, end));
+                self.group_fragments.push(Some(origin.clone()));
+            }
+        }
+        let backrefs = fragment.ast.backreferences().len();
+        self.backref_spans
+            .extend(core::iter::repeat_n(Span::new(start, end), backrefs));
+        Ok(Expr::NonCapturingGroup(Box::new(fragment.ast.clone())))
     }
 
     /// Parse a character class
@@ -440,11 +598,7 @@ impl<'a> Parser<'a> {
         }
 
         if items.is_empty() {
-            return Err(ParseError::UnexpectedToken {
-                expected: "character class item".to_string(),
-                found: self.current_token.to_string(),
-                span: Some(self.current_span()),
-            });
+            return Err(self.unexpected("character class item"));
         }
 
         self.expect(Token::RightBracket)?;
@@ -484,17 +638,13 @@ impl<'a> Parser<'a> {
                 self.advance();
                 Ok(ClassItem::Shorthand(c))
             }
-            _ => Err(ParseError::UnexpectedToken {
-                expected: "character or escape".to_string(),
-                found: self.current_token.to_string(),
-                span: Some(self.current_span()),
-            }),
+            _ => Err(self.unexpected("character or escape")),
         }
     }
 }
 
 /// Parse a regex pattern string into an AST
-pub fn parse(input: &str) -> Result<Expr, ParseError> {
+pub fn parse(input: &str) -> Result<Expr, RegexError> {
     let mut parser = Parser::new(input);
     parser.parse()
 }
@@ -534,6 +684,44 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_parse_error_spans() {
+        let err = parse("(name:abc").unwrap_err();
+        assert_eq!(err.span(), Some(Span::new(9, 9)));
+
+        let err = parse("a{3,1}").unwrap_err();
+        assert!(matches!(
+            err,
+            RegexError::Parse(ParseError::InvalidQuantifier { .. })
+        ));
+        assert_eq!(err.span(), Some(Span::new(1, 6)));
+    }
+
+    #[test]
+    fn test_parse_reports_lexer_errors() {
+        // The lexer error wins over the parse error it would otherwise cause
+        assert!(matches!(
+            parse("(a\\"),
+            Err(RegexError::Lexer {
+                position: 2,
+                kind: crate::error::LexerErrorKind::UnexpectedChar('\\')
+            })
+        ));
+        assert!(matches!(parse("a\\g{b"), Err(RegexError::Lexer { .. })));
+    }
+
+    #[test]
+    fn test_parse_error_suggestions() {
+        assert_eq!(parse("a{x}").unwrap_err().suggestion(), Some("\\{"));
+        assert_eq!(parse("a}").unwrap_err().suggestion(), Some("\\}"));
+        assert_eq!(parse("(?=x)").unwrap_err().suggestion(), Some("(>:"));
+        assert_eq!(
+            parse("(?P=word)").unwrap_err().suggestion(),
+            Some("\\g{word}")
+        );
+        assert_eq!(parse("a)").unwrap_err().suggestion(), None);
+    }
+
     #[test]
     fn test_parse_simple_parens() {
         // (abc) - capturing group
@@ -609,6 +797,12 @@ mod tests {
         assert_eq!(expr.to_regex_string(), "(?:abc)");
     }
 
+    #[test]
+    fn test_parse_non_capturing_group_inside_group() {
+        let expr = parse("((?:a)b)").unwrap();
+        assert_eq!(expr.to_regex_string(), "((?:a)b)");
+    }
+
     #[test]
     fn test_parse_backreference_number() {
         let expr = parse(r"\1").unwrap();
@@ -645,6 +839,41 @@ mod tests {
         assert_eq!(expr.to_regex_string(), "[a-z]");
     }
 
+    #[test]
+    fn test_group_spans() {
+        let mut parser = Parser::new("(a)(?:b)((name:c)d)(>:(e))");
+        parser.parse().unwrap();
+        assert_eq!(
+            parser.group_spans(),
+            &[Span::new(0, 3), Span::new(8, 19), Span::new(9, 17)]
+        );
+    }
+
+    #[test]
+    fn test_backref_spans() {
+        let mut parser = Parser::new(r"(a:x)\1(>:\g{-1})\g{a}");
+        parser.parse().unwrap();
+        assert_eq!(
+            parser.backref_spans(),
+            &[Span::new(5, 7), Span::new(10, 16), Span::new(17, 22)]
+        );
+    }
+
+    #[test]
+    fn test_parse_group_starting_with_named_group() {
+        let expr = parse("((n:x)y)").unwrap();
+        assert_eq!(
+            expr,
+            Expr::Group(Box::new(Expr::Sequence(vec![
+                Expr::NamedGroup {
+                    name: "n".to_string(),
+                    pattern: Box::new(Expr::Literal('x')),
+                },
+                Expr::Literal('y'),
+            ])))
+        );
+    }
+
     #[test]
     fn test_parse_complex_pattern() {
         // Test a more complex pattern with multiple features
@@ -0,0 +1,216 @@
+//! Whether input could still grow into a match
+//!
+//! A partial match is text that ends partway through a match: more
+//! characters could complete it. This is what as-you-type validation needs,
+//! where `12-3` is not yet a valid `^\d{3}-\d{4}$` but could become one while
+//! `12a` never can.
+//!
+//! Input that already matches also counts. Otherwise the search looks for a
+//! match that starts before the end of the input, consumes the rest of it,
+//! and still has characters left to consume. Assertions after the last
+//! character, such as `$`, `\b` and lookaheads, are assumed to hold, since
+//! they depend on what comes next. A backreference is assumed to match
+//! whatever text follows it, because captures are not tracked.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::engine::{Regex, assertion_holds};
+use crate::nfa::{Nfa, StateId, Transition};
+
+/// Whether `input` matches `regex` or could with more characters appended
+pub(crate) fn is_partial_match(regex: &Regex, input: &str) -> bool {
+    if regex.is_match(input) {
+        return true;
+    }
+    let search = Search {
+        nfa: regex.nfa(),
+        input,
+    };
+    search.earliest_start(0).is_some()
+}
+
+/// The earliest position from `from` on where a match could start that
+/// consumes the rest of `input` and still has characters left to consume
+///
+/// A match that starts before this position is settled: more text cannot
+/// change where it ends, and no match that starts earlier can appear.
+pub(crate) fn earliest_partial_start(regex: &Regex, input: &str, from: usize) -> Option<usize> {
+    Search {
+        nfa: regex.nfa(),
+        input,
+    }
+    .earliest_start(from)
+}
+
+/// Marks a state that no thread is in
+const OFF: usize = usize::MAX;
+
+/// The indices of the states that some thread is in
+fn on(states: &[usize]) -> impl Iterator<Item = StateId> + '_ {
+    states
+        .iter()
+        .enumerate()
+        .filter_map(|(state, &start)| (start != OFF).then_some(state))
+}
+
+struct Search<'a> {
+    nfa: &'a Nfa,
+    input: &'a str,
+}
+
+impl Search<'_> {
+    /// The earliest start, at a character from `from` on, of a thread that
+    /// consumes the rest of the input and can still consume more
+    ///
+    /// Each state holds the earliest start of the threads in it, or [`OFF`].
+    fn earliest_start(&self, from: usize) -> Option<usize> {
+        let nfa = self.nfa;
+        let mut active = vec![OFF; nfa.states.len()];
+        let mut next = vec![OFF; nfa.states.len()];
+        for (offset, c) in self.input[from..].char_indices() {
+            let offset = from + offset;
+            // A partial match may start at any character
+            active[nfa.start] = active[nfa.start].min(offset);
+            self.close(&mut active, offset, false);
+
+            next.fill(OFF);
+            for state in on(&active) {
+                let start = active[state];
+                for (transition, target) in &nfa.states[state].transitions {
+                    match transition {
+                        Transition::Backref(_) | Transition::BackrefRelative(_) => {
+                            next[state] = next[state].min(start);
+                            next[*target] = next[*target].min(start);
+                        }
+                        _ if nfa.matches_char(transition, c) => {
+                            next[*target] = next[*target].min(start);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            core::mem::swap(&mut active, &mut next);
+        }
+        if from == self.input.len() {
+            active[nfa.start] = from;
+        }
+        self.close(&mut active, self.input.len(), true);
+
+        let live = self.live();
+        on(&active)
+            .filter(|&state| {
+                nfa.states[state]
+                    .transitions
+                    .iter()
+                    .any(|(transition, target)| {
+                        let consumes = transition.consumes_char()
+                            || matches!(
+                                transition,
+                                Transition::Backref(_) | Transition::BackrefRelative(_)
+                            );
+                        consumes && live[*target]
+                    })
+            })
+            .map(|state| active[state])
+            .min()
+    }
+
+    /// Add every state reachable without consuming a character; at the end
+    /// of the input, assertions that depend on the text after it are assumed
+    /// to hold
+    fn close(&self, active: &mut [usize], pos: usize, at_end: bool) {
+        let mut pending: Vec<StateId> = on(active).collect();
+        while let Some(state) = pending.pop() {
+            for (transition, target) in &self.nfa.states[state].transitions {
+                let follow = match transition {
+                    Transition::Epsilon | Transition::GroupStart(_) | Transition::GroupEnd(_) => {
+                        true
+                    }
+                    Transition::EndAnchor
+                    | Transition::WordBoundary
+                    | Transition::NonWordBoundary
+                    | Transition::Lookahead(_)
+                    | Transition::NegativeLookahead(_)
+                        if at_end =>
+                    {
+                        true
+                    }
+                    _ if transition.consumes_char() => false,
+                    Transition::Backref(_) | Transition::BackrefRelative(_) => false,
+                    _ => assertion_holds(self.nfa, self.input, transition, pos) == Some(true),
+                };
+                if follow && active[state] < active[*target] {
+                    active[*target] = active[state];
+                    pending.push(*target);
+                }
+            }
+        }
+    }
+
+    /// Which states can still reach the accepting state, following every
+    /// transition
+    fn live(&self) -> Vec<bool> {
+        let mut incoming = vec![Vec::new(); self.nfa.states.len()];
+        for (state, node) in self.nfa.states.iter().enumerate() {
+            for (_, target) in &node.transitions {
+                incoming[*target].push(state);
+            }
+        }
+        let mut live = vec![false; self.nfa.states.len()];
+        live[self.nfa.accept] = true;
+        let mut pending = vec![self.nfa.accept];
+        while let Some(state) = pending.pop() {
+            for &source in &incoming[state] {
+                if !live[source] {
+                    live[source] = true;
+                    pending.push(source);
+                }
+            }
+        }
+        live
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partial(pattern: &str, input: &str) -> bool {
+        Regex::new(pattern).unwrap().is_partial_match(input)
+    }
+
+    #[test]
+    fn test_prefixes_of_a_match() {
+        let phone = r"^\d{3}-\d{4}$";
+        for input in ["", "5", "555", "555-", "555-12", "555-1234"] {
+            assert!(partial(phone, input), "{input:?}");
+        }
+        for input in ["55a", "5555", "555-12345", "555-1234x"] {
+            assert!(!partial(phone, input), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn test_unanchored_match_may_start_anywhere() {
+        assert!(partial("abc", "xxab"));
+        assert!(partial("abc", "xabcx"));
+        assert!(!partial("abc", "xxa b"));
+        assert!(!partial("abc", "xyz"));
+    }
+
+    #[test]
+    fn test_assertions_after_the_input() {
+        assert!(partial(r"\bfoo\b", "fo"));
+        assert!(!partial(r"\bfoo\b", "xfo"));
+        assert!(partial(r"(@>:\d)\w+x", "4"));
+        assert!(!partial(r"(@<:x)a", "ya"));
+    }
+
+    #[test]
+    fn test_backreferences_and_unicode() {
+        assert!(partial(r"(a+)-\1!", "aa-a"));
+        assert!(partial("cafés", "un café"));
+        assert!(!partial("cafés", "un cafe"));
+    }
+}
@@ -0,0 +1,492 @@
+//! Building patterns in code
+//!
+//! [`Pat`] assembles a pattern from typed pieces instead of strings, so text
+//! taken from elsewhere never needs escaping and a quantifier always applies
+//! to the piece it is called on:
+//!
+//! ```
+//! use ogex::Pat;
+//!
+//! let id = Pat::lit("#").then(Pat::class("0-9").repeat(1..).named("id"));
+//! assert_eq!(id.to_pattern(), r"\#(id:[0-9]+)");
+//!
+//! let regex = id.build().unwrap();
+//! let input = "see #42";
+//! let m = regex.find(input).unwrap();
+//! assert_eq!(m.named_group_str(input, "id"), Some("42"));
+//! ```
+//!
+//! Each piece is an [`Expr`], available with [`Pat::expr`]. Building checks
+//! what the pieces cannot check on their own, such as group names and
+//! backreferences.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt;
+use core::ops::{Bound, RangeBounds};
+
+use crate::ast::{CharacterClass, ClassItem, Expr, Quantifier};
+use crate::engine::{Regex, RegexBuilder};
+use crate::error::RegexError;
+
+/// A pattern under construction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pat {
+    expr: Expr,
+}
+
+impl Pat {
+    /// Match `text` exactly
+    pub fn lit(text: &str) -> Self {
+        Pat {
+            expr: Expr::sequence(text.chars().map(Expr::Literal).collect()),
+        }
+    }
+
+    /// Match one character from `set`, written as in a character class
+    /// without the brackets: single characters and ranges such as `a-z`
+    ///
+    /// A `-` at the start or end of `set` stands for itself. No character
+    /// needs escaping.
+    pub fn class(set: &str) -> Self {
+        Self::char_class(false, set)
+    }
+
+    /// Match one character that is not in `set`, written as for
+    /// [`Pat::class`]
+    pub fn not_class(set: &str) -> Self {
+        Self::char_class(true, set)
+    }
+
+    fn char_class(negated: bool, set: &str) -> Self {
+        let chars: Vec<char> = set.chars().collect();
+        let mut items = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            if i + 2 < chars.len() && chars[i + 1] == '-' {
+                let (start, end) = (chars[i], chars[i + 2]);
+                // A range is written with its ends unescaped, so one that
+                // starts or ends at punctuation is listed a character at a
+                // time
+                if needs_escape(start) || needs_escape(end) {
+                    items.extend((start..=end).map(ClassItem::Char));
+                } else {
+                    items.push(ClassItem::Range(start, end));
+                }
+                i += 3;
+            } else {
+                items.push(ClassItem::Char(chars[i]));
+                i += 1;
+            }
+        }
+        Pat {
+            expr: Expr::CharacterClass(CharacterClass { negated, items }),
+        }
+    }
+
+    /// Match any character except a newline, as `.` does
+    pub fn any() -> Self {
+        Pat { expr: Expr::Any }
+    }
+
+    /// Match an ASCII digit, as `\d` does
+    pub fn digit() -> Self {
+        Pat {
+            expr: Expr::Shorthand('d'),
+        }
+    }
+
+    /// Match an ASCII letter, digit or `_`, as `\w` does
+    pub fn word() -> Self {
+        Pat {
+            expr: Expr::Shorthand('w'),
+        }
+    }
+
+    /// Match ASCII whitespace, as `\s` does
+    pub fn space() -> Self {
+        Pat {
+            expr: Expr::Shorthand('s'),
+        }
+    }
+
+    /// Match at the start of the input, as `^` does
+    pub fn start() -> Self {
+        Pat {
+            expr: Expr::StartAnchor,
+        }
+    }
+
+    /// Match at the end of the input, as `$` does
+    pub fn end() -> Self {
+        Pat {
+            expr: Expr::EndAnchor,
+        }
+    }
+
+    /// Match between a word character and a non-word character, as `\b`
+    /// does
+    pub fn word_boundary() -> Self {
+        Pat {
+            expr: Expr::WordBoundary,
+        }
+    }
+
+    /// Match the text last captured by the group `name`, as `\g{name}` does
+    ///
+    /// A number refers to a numbered group.
+    pub fn backref(name: &str) -> Self {
+        Pat {
+            expr: Expr::NamedBackreference(name.to_string()),
+        }
+    }
+
+    /// Match any one of `alternatives`
+    pub fn either(alternatives: impl IntoIterator<Item = Pat>) -> Self {
+        alternatives
+            .into_iter()
+            .reduce(Pat::or)
+            .unwrap_or(Pat { expr: Expr::Empty })
+    }
+
+    /// Match this pattern and then `next`
+    pub fn then(self, next: Pat) -> Self {
+        let mut items = self.into_sequence();
+        items.extend(next.into_sequence());
+        Pat {
+            expr: Expr::sequence(items),
+        }
+    }
+
+    /// Match this pattern or `other`
+    pub fn or(self, other: Pat) -> Self {
+        let mut alternatives = self.into_alternatives();
+        alternatives.extend(other.into_alternatives());
+        Pat {
+            expr: Expr::Alternation(alternatives),
+        }
+    }
+
+    /// Match this pattern a number of times in `range`, as many as possible
+    ///
+    /// `repeat(1..)` is `+`, `repeat(..)` is `*`, `repeat(..=1)` is `?` and
+    /// `repeat(2..5)` is `{2,4}`.
+    ///
+    /// # Panics
+    /// Panics if `range` is empty.
+    pub fn repeat(self, range: impl RangeBounds<u32>) -> Self {
+        let min = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let max = match range.end_bound() {
+            Bound::Included(&n) => Some(n),
+            Bound::Excluded(&n) => Some(n.checked_sub(1).expect("empty repetition range")),
+            Bound::Unbounded => None,
+        };
+        let quantifier = match (min, max) {
+            (_, Some(max)) if max < min => panic!("empty repetition range"),
+            (0, None) => Quantifier::ZeroOrMore,
+            (1, None) => Quantifier::OneOrMore,
+            (0, Some(1)) => Quantifier::Optional,
+            (min, None) => Quantifier::AtLeast(min),
+            (min, Some(max)) if min == max => Quantifier::Exactly(min),
+            (min, Some(max)) => Quantifier::Between(min, max),
+        };
+        Pat {
+            expr: Expr::quantified(self.into_atom(), quantifier, true),
+        }
+    }
+
+    /// Match this pattern exactly `n` times
+    pub fn times(self, n: u32) -> Self {
+        self.repeat(n..=n)
+    }
+
+    /// Match this pattern or nothing, as `?` does
+    pub fn optional(self) -> Self {
+        self.repeat(..=1)
+    }
+
+    /// Make the repetition just applied match as few times as possible
+    ///
+    /// Does nothing if the pattern is not a repetition.
+    pub fn lazy(mut self) -> Self {
+        if let Expr::Quantified { greedy, .. } = &mut self.expr {
+            *greedy = false;
+        }
+        self
+    }
+
+    /// Capture the text this pattern matches in a group called `name`
+    pub fn named(self, name: &str) -> Self {
+        Pat {
+            expr: Expr::named_group(name, self.expr),
+        }
+    }
+
+    /// Capture the text this pattern matches in a numbered group
+    pub fn capture(self) -> Self {
+        Pat {
+            expr: Expr::group(self.expr),
+        }
+    }
+
+    /// Match letters in this pattern regardless of case, as the `@i` flag
+    /// does
+    pub fn case_insensitive(self) -> Self {
+        Pat {
+            expr: Expr::ModeFlagsGroup {
+                flags: "i".to_string(),
+                pattern: Box::new(self.expr),
+            },
+        }
+    }
+
+    /// The syntax tree of the pattern
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Take the syntax tree of the pattern
+    pub fn into_expr(self) -> Expr {
+        self.expr
+    }
+
+    /// The pattern in Ogex syntax
+    pub fn to_pattern(&self) -> String {
+        let mut out = String::new();
+        write_expr(&mut out, &self.expr);
+        out
+    }
+
+    /// Compile the pattern
+    ///
+    /// Use [`RegexBuilder::new`] with [`Pat::to_pattern`] to set other
+    /// options as well.
+    ///
+    /// # Errors
+    /// Fails if a group name is not a valid name or is used twice, or if a
+    /// backreference names a group the pattern does not have.
+    pub fn build(&self) -> Result<Regex, RegexError> {
+        RegexBuilder::new(&self.to_pattern()).build()
+    }
+
+    /// The pieces matched one after another
+    fn into_sequence(self) -> Vec<Expr> {
+        match self.expr {
+            Expr::Empty => Vec::new(),
+            Expr::Sequence(items) => items,
+            Expr::Alternation(_) => vec![Expr::non_capturing_group(self.expr)],
+            expr => vec![expr],
+        }
+    }
+
+    /// The alternatives matched one instead of another
+    fn into_alternatives(self) -> Vec<Expr> {
+        match self.expr {
+            Expr::Alternation(alternatives) => alternatives,
+            expr => vec![expr],
+        }
+    }
+
+    /// The pattern as a single piece that a quantifier can follow
+    fn into_atom(self) -> Expr {
+        match self.expr {
+            Expr::Empty
+            | Expr::Sequence(_)
+            | Expr::Alternation(_)
+            | Expr::Quantified { .. }
+            | Expr::StartAnchor
+            | Expr::EndAnchor
+            | Expr::WordBoundary
+            | Expr::NonWordBoundary => Expr::non_capturing_group(self.expr),
+            expr => expr,
+        }
+    }
+}
+
+impl fmt::Display for Pat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_pattern())
+    }
+}
+
+/// Whether `c` must be escaped to stand for itself, in or out of a class
+fn needs_escape(c: char) -> bool {
+    let mut buf = [0; 4];
+    crate::escape(c.encode_utf8(&mut buf)).len() > c.len_utf8()
+}
+
+fn push_char(out: &mut String, c: char) {
+    if needs_escape(c) {
+        out.push('\\');
+    }
+    out.push(c);
+}
+
+/// Write `expr` in Ogex syntax
+fn write_expr(out: &mut String, expr: &Expr) {
+    let group = |out: &mut String, open: &str, inner: &Expr| {
+        out.push_str(open);
+        write_expr(out, inner);
+        out.push(')');
+    };
+    match expr {
+        Expr::Empty => {}
+        Expr::Literal(c) => push_char(out, *c),
+        Expr::Any => out.push('.'),
+        Expr::Sequence(items) => items.iter().for_each(|item| write_expr(out, item)),
+        Expr::Alternation(alternatives) => {
+            for (i, alternative) in alternatives.iter().enumerate() {
+                if i > 0 {
+                    out.push('|');
+                }
+                write_expr(out, alternative);
+            }
+        }
+        Expr::CharacterClass(class) => {
+            out.push('[');
+            if class.negated {
+                out.push('^');
+            }
+            for item in &class.items {
+                match item {
+                    ClassItem::Char(c) => push_char(out, *c),
+                    ClassItem::Range(start, end) => {
+                        out.push(*start);
+                        out.push('-');
+                        out.push(*end);
+                    }
+                    ClassItem::Shorthand(c) => {
+                        out.push('\\');
+                        out.push(*c);
+                    }
+                }
+            }
+            out.push(']');
+        }
+        Expr::Quantified {
+            expr,
+            quantifier,
+            greedy,
+        } => {
+            write_expr(out, expr);
+            out.push_str(&quantifier.to_regex_string(*greedy));
+        }
+        Expr::Group(inner) => group(out, "(", inner),
+        Expr::NonCapturingGroup(inner) => group(out, "(@?:", inner),
+        Expr::NamedGroup { name, pattern } => {
+            out.push('(');
+            out.push_str(name);
+            group(out, ":", pattern);
+        }
+        Expr::StartAnchor => out.push('^'),
+        Expr::EndAnchor => out.push('$'),
+        Expr::Backreference(n) => out.push_str(&format!("\\g{{{}}}", n)),
+        Expr::RelativeBackreference(n) => out.push_str(&format!("\\g{{{}}}", n)),
+        Expr::NamedBackreference(name) => out.push_str(&format!("\\g{{{}}}", name)),
+        Expr::Shorthand(c) => {
+            out.push('\\');
+            out.push(*c);
+        }
+        Expr::WordBoundary => out.push_str("\\b"),
+        Expr::NonWordBoundary => out.push_str("\\B"),
+        Expr::Lookahead(inner) => group(out, "(@>:", inner),
+        Expr::NegativeLookahead(inner) => group(out, "(@>~:", inner),
+        Expr::Lookbehind(inner) => group(out, "(@<:", inner),
+        Expr::NegativeLookbehind(inner) => group(out, "(@<~:", inner),
+        Expr::AtomicGroup(inner) => group(out, "(@*:", inner),
+        Expr::ConditionalGroup(inner) => group(out, "(@%:", inner),
+        Expr::ModeFlagsGroup { flags, pattern } => {
+            out.push_str("(@");
+            out.push_str(flags);
+            group(out, ":", pattern);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_is_escaped() {
+        let pat = Pat::lit("1+1=2? (yes: a-ok)");
+        assert_eq!(pat.to_pattern(), r"1\+1=2\?\ \(yes\:\ a\-ok\)");
+        let regex = Pat::start().then(pat).then(Pat::end()).build().unwrap();
+        assert!(regex.is_match("1+1=2? (yes: a-ok)"));
+        assert!(!regex.is_match("11=2 (yes: a-ok)"));
+
+        let class = Pat::class("a-c.^]/-");
+        assert_eq!(class.to_pattern(), r"[a-c\.\^\]/\-]");
+        let regex = class.repeat(1..).build().unwrap();
+        let input = "x b.^]/- y";
+        let m = regex.find(input).unwrap();
+        assert_eq!(m.as_str(input), "b.^]/-");
+    }
+
+    #[test]
+    fn test_repetition_groups_its_operand() {
+        assert_eq!(Pat::lit("ab").repeat(2..5).to_pattern(), "(@?:ab){2,4}");
+        assert_eq!(Pat::lit("a").repeat(..).to_pattern(), "a*");
+        assert_eq!(Pat::digit().repeat(3..).to_pattern(), r"\d{3,}");
+        assert_eq!(Pat::word().times(2).lazy().to_pattern(), r"\w{2}?");
+        assert_eq!(
+            Pat::lit("a").or(Pat::lit("b")).optional().to_pattern(),
+            "(@?:a|b)?"
+        );
+        assert_eq!(
+            Pat::lit("x")
+                .then(Pat::either([Pat::lit("a"), Pat::lit("bc")]))
+                .to_pattern(),
+            "x(@?:a|bc)"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "empty repetition range")]
+    fn test_empty_range_panics() {
+        let _ = Pat::any().repeat(3..3);
+    }
+
+    #[test]
+    fn test_pattern_parses_back_to_the_same_matches() {
+        let word = Pat::class("a-zA-Z").repeat(1..);
+        let pat = word
+            .clone()
+            .named("first")
+            .then(Pat::space().repeat(1..))
+            .then(Pat::backref("first"))
+            .then(Pat::word_boundary())
+            .then(Pat::lit("!").optional())
+            .case_insensitive();
+        assert_eq!(pat.to_pattern(), r"(@i:(first:[a-zA-Z]+)\s+\g{first}\b!?)");
+        let regex = pat.build().unwrap();
+        let input = "so so! no";
+        let m = regex.find(input).unwrap();
+        assert_eq!(m.as_str(input), "so so!");
+        assert_eq!(regex.group_count(), 1);
+        assert_eq!(
+            crate::parse(&pat.to_pattern()).unwrap().to_regex_string(),
+            pat.expr().to_regex_string()
+        );
+    }
+
+    #[test]
+    fn test_build_errors() {
+        assert!(Pat::any().named("not a name").build().is_err());
+        assert!(Pat::backref("missing").build().is_err());
+        assert!(Pat::class("").build().is_err());
+        assert!(
+            Pat::digit()
+                .named("n")
+                .then(Pat::digit().named("n"))
+                .build()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,424 @@
+//! Vetted patterns for common formats
+//!
+//! Each format comes as a pattern constant, to embed in a larger pattern, and
+//! a function that compiles it. The patterns have no capture groups and are
+//! not anchored: wrap one in `^...$` to check a whole string, or search with
+//! it as it is to find the format inside text.
+//!
+//! [`PatternLibrary::prelude`] holds the same patterns as fragments, named in
+//! lower case, so a pattern can refer to them with `(@use:name)`:
+//!
+//! ```
+//! use ogex::PatternLibrary;
+//!
+//! let library = PatternLibrary::prelude();
+//! let regex = library
+//!     .compile(r"^(from:(@use:email)) at (when:(@use:iso_datetime))$")
+//!     .unwrap();
+//! let input = "ada@example.org at 2024-06-01T09:30:00Z";
+//! let m = regex.find(input).unwrap();
+//! assert_eq!(m.named_group_str(input, "from"), Some("ada@example.org"));
+//! assert_eq!(m.named_group_str(input, "when"), Some("2024-06-01T09:30:00Z"));
+//! ```
+//!
+//! The patterns check the shape of a value, not whether it exists: a date
+//! such as `2023-02-31` passes, as does an email address nobody owns.
+
+use crate::engine::Regex;
+
+// The constants are assembled from these pieces with `concat!`, which only
+// takes literals
+macro_rules! hex {
+    () => {
+        "[0-9A-Fa-f]"
+    };
+}
+
+macro_rules! h16 {
+    () => {
+        concat!(hex!(), "{1,4}")
+    };
+}
+
+macro_rules! ipv4 {
+    () => {
+        concat!(
+            r"(@?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)",
+            r"(@?:\.(@?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)){3}"
+        )
+    };
+}
+
+macro_rules! ipv6 {
+    () => {
+        concat!(
+            "(@?:",
+            // Ending in an IPv4 address, as in `::ffff:192.0.2.1`
+            r"\:\:(@?:[Ff]{4}(@?:\:0{1,4})?\:)?",
+            ipv4!(),
+            r"|(@?:",
+            h16!(),
+            r"\:){1,4}\:",
+            ipv4!(),
+            // Eight groups
+            r"|(@?:",
+            h16!(),
+            r"\:){7}",
+            h16!(),
+            // Zeros left out with `::`
+            r"|(@?:",
+            h16!(),
+            r"\:){1,7}\:",
+            r"|(@?:",
+            h16!(),
+            r"\:){1,6}\:",
+            h16!(),
+            r"|(@?:",
+            h16!(),
+            r"\:){1,5}(@?:\:",
+            h16!(),
+            r"){1,2}",
+            r"|(@?:",
+            h16!(),
+            r"\:){1,4}(@?:\:",
+            h16!(),
+            r"){1,3}",
+            r"|(@?:",
+            h16!(),
+            r"\:){1,3}(@?:\:",
+            h16!(),
+            r"){1,4}",
+            r"|(@?:",
+            h16!(),
+            r"\:){1,2}(@?:\:",
+            h16!(),
+            r"){1,5}",
+            "|",
+            h16!(),
+            r"\:(@?:\:",
+            h16!(),
+            r"){1,6}",
+            r"|\:(@?:(@?:\:",
+            h16!(),
+            r"){1,7}|\:)",
+            ")"
+        )
+    };
+}
+
+macro_rules! label {
+    () => {
+        r"[A-Za-z0-9](@?:[A-Za-z0-9\-]{0,61}[A-Za-z0-9])?"
+    };
+}
+
+macro_rules! iso_date {
+    () => {
+        r"\d{4}-(@?:0[1-9]|1[0-2])-(@?:0[1-9]|[12]\d|3[01])"
+    };
+}
+
+macro_rules! iso_time {
+    () => {
+        r"(@?:[01]\d|2[0-3])\:[0-5]\d(@?:\:[0-5]\d(@?:\.\d+)?)?"
+    };
+}
+
+/// Characters allowed unescaped in a URL path, query or fragment
+macro_rules! url_char {
+    () => {
+        r"[A-Za-z0-9\-\.\_\~\!\$\&\'\(\)\*\+\,\;\=\:\@\%\/]"
+    };
+}
+
+/// An IPv4 address in dotted decimal, such as `192.0.2.1`
+///
+/// Each part is a number from 0 to 255, without leading zeros.
+pub const IPV4: &str = ipv4!();
+
+/// An IPv6 address, such as `2001:db8::1` or `::ffff:192.0.2.1`
+///
+/// Covers the full form, every way of leaving out zeros with `::`, and
+/// addresses ending in an IPv4 address. Zone indexes (`%eth0`) are not
+/// included.
+pub const IPV6: &str = ipv6!();
+
+/// An email address, such as `ada@example.org`
+///
+/// The part before the `@` takes the characters the HTML standard allows in
+/// `<input type="email">`; the domain needs at least two labels. Quoted
+/// local parts and IP address domains are not included.
+pub const EMAIL: &str = concat!(
+    r"[A-Za-z0-9\.\!\#\$\%\&\'\*\+\/\=\?\^\_\`\{\|\}\~\-]+@",
+    label!(),
+    r"(@?:\.",
+    label!(),
+    ")+"
+);
+
+/// An `http` or `https` URL, such as `https://example.org:8080/a?b=c#d`
+///
+/// The host is a domain name, an IPv4 address or an IPv6 address in
+/// brackets. A search stops at whitespace and at characters that URLs
+/// must escape, but punctuation that ends a sentence is still taken in.
+pub const URL: &str = concat!(
+    r"https?\:\/\/(@?:",
+    label!(),
+    r"(@?:\.",
+    label!(),
+    r")*|\[",
+    ipv6!(),
+    r"\])(@?:\:\d{1,5})?(@?:\/",
+    url_char!(),
+    r"*)?(@?:\?(@?:",
+    url_char!(),
+    r"|\?)*)?(@?:\#(@?:",
+    url_char!(),
+    r"|\?)*)?"
+);
+
+/// A UUID in its hyphenated form, such as
+/// `123e4567-e89b-12d3-a456-426614174000`
+///
+/// Any version and variant is accepted, in either case.
+pub const UUID: &str = concat!(
+    hex!(),
+    "{8}-",
+    hex!(),
+    "{4}-",
+    hex!(),
+    "{4}-",
+    hex!(),
+    "{4}-",
+    hex!(),
+    "{12}"
+);
+
+/// An ISO 8601 calendar date, such as `2024-06-01`
+pub const ISO_DATE: &str = iso_date!();
+
+/// An ISO 8601 time of day, such as `09:30`, `09:30:15` or `09:30:15.250`
+pub const ISO_TIME: &str = iso_time!();
+
+/// An ISO 8601 date and time, such as `2024-06-01T09:30:00+02:00`
+///
+/// The time zone, `Z` or an offset from UTC, may be left out.
+pub const ISO_DATETIME: &str = concat!(
+    iso_date!(),
+    "T",
+    iso_time!(),
+    r"(@?:Z|[\+\-](@?:[01]\d|2[0-3])\:?[0-5]\d)?"
+);
+
+/// Every pattern in the module, with its name in [`PatternLibrary::prelude`]
+///
+/// [`PatternLibrary::prelude`]: crate::PatternLibrary::prelude
+pub const ALL: &[(&str, &str)] = &[
+    ("ipv4", IPV4),
+    ("ipv6", IPV6),
+    ("email", EMAIL),
+    ("url", URL),
+    ("uuid", UUID),
+    ("iso_date", ISO_DATE),
+    ("iso_time", ISO_TIME),
+    ("iso_datetime", ISO_DATETIME),
+];
+
+fn compile(pattern: &str) -> Regex {
+    Regex::new(pattern).expect("the built-in patterns are valid")
+}
+
+/// Compile [`IPV4`]
+pub fn ipv4() -> Regex {
+    compile(IPV4)
+}
+
+/// Compile [`IPV6`]
+pub fn ipv6() -> Regex {
+    compile(IPV6)
+}
+
+/// Compile [`EMAIL`]
+pub fn email() -> Regex {
+    compile(EMAIL)
+}
+
+/// Compile [`URL`]
+pub fn url() -> Regex {
+    compile(URL)
+}
+
+/// Compile [`UUID`]
+pub fn uuid() -> Regex {
+    compile(UUID)
+}
+
+/// Compile [`ISO_DATE`]
+pub fn iso_date() -> Regex {
+    compile(ISO_DATE)
+}
+
+/// Compile [`ISO_TIME`]
+pub fn iso_time() -> Regex {
+    compile(ISO_TIME)
+}
+
+/// Compile [`ISO_DATETIME`]
+pub fn iso_datetime() -> Regex {
+    compile(ISO_DATETIME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    /// Check that `pattern` matches each of `valid` in full and none of
+    /// `invalid`
+    fn check(pattern: &str, valid: &[&str], invalid: &[&str]) {
+        let regex = Regex::new(&format!("^(@?:{})$", pattern)).unwrap();
+        for input in valid {
+            assert!(regex.is_match(input), "{:?} should match", input);
+        }
+        for input in invalid {
+            assert!(!regex.is_match(input), "{:?} should not match", input);
+        }
+    }
+
+    #[test]
+    fn test_ip_addresses() {
+        check(
+            IPV4,
+            &["0.0.0.0", "192.0.2.1", "255.255.255.255", "10.20.30.40"],
+            &["256.0.0.1", "1.2.3", "1.2.3.4.5", "01.2.3.4", "1.2.3.-4"],
+        );
+        check(
+            IPV6,
+            &[
+                "2001:0db8:85a3:0000:0000:8a2e:0370:7334",
+                "2001:db8::1",
+                "::1",
+                "::",
+                "fe80::",
+                "1::2:3:4:5:6:7",
+                "::ffff:192.0.2.1",
+                "64:ff9b::192.0.2.33",
+                "FE80::ABCD",
+            ],
+            &[
+                "2001:db8:::1",
+                "1:2:3:4:5:6:7:8:9",
+                "12345::",
+                "g::1",
+                "1:2:3:4:5:6:7",
+                "::ffff:256.0.0.1",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_email_and_url() {
+        check(
+            EMAIL,
+            &[
+                "ada@example.org",
+                "first.last+tag@mail.example.co.uk",
+                "o'brien@x-y.io",
+            ],
+            &[
+                "ada@localhost",
+                "ada.example.org",
+                "ada@-example.org",
+                "ada@example..org",
+                "a b@example.org",
+            ],
+        );
+        check(
+            URL,
+            &[
+                "http://example.org",
+                "https://example.org:8080/a/b?c=d&e=f#top",
+                "https://192.0.2.1/",
+                "http://[2001:db8::1]:80/index.html",
+                "https://example.org/search?q=a%20b",
+            ],
+            &[
+                "ftp://example.org",
+                "https://",
+                "https://exa mple.org",
+                "https://example.org/a b",
+                "example.org",
+            ],
+        );
+        let text = "see https://example.org/docs and ada@example.org";
+        assert_eq!(
+            url().find(text).map(|m| &text[m.start..m.end]),
+            Some("https://example.org/docs")
+        );
+        assert_eq!(
+            email().find(text).map(|m| &text[m.start..m.end]),
+            Some("ada@example.org")
+        );
+    }
+
+    #[test]
+    fn test_uuid_and_dates() {
+        check(
+            UUID,
+            &[
+                "123e4567-e89b-12d3-a456-426614174000",
+                "00000000-0000-0000-0000-000000000000",
+                "F47AC10B-58CC-4372-A567-0E02B2C3D479",
+            ],
+            &[
+                "123e4567e89b12d3a456426614174000",
+                "123e4567-e89b-12d3-a456-42661417400",
+                "g23e4567-e89b-12d3-a456-426614174000",
+            ],
+        );
+        check(
+            ISO_DATE,
+            &["2024-06-01", "1999-12-31", "2000-02-29"],
+            &[
+                "2024-13-01",
+                "2024-00-10",
+                "2024-06-32",
+                "24-06-01",
+                "2024/06/01",
+            ],
+        );
+        check(
+            ISO_TIME,
+            &["09:30", "23:59:59", "00:00:00.125"],
+            &["24:00", "9:30", "12:60", "12:30:61"],
+        );
+        check(
+            ISO_DATETIME,
+            &[
+                "2024-06-01T09:30",
+                "2024-06-01T09:30:00Z",
+                "2024-06-01T09:30:00.5+02:00",
+                "2024-06-01T09:30:00-0530",
+            ],
+            &["2024-06-01 09:30", "2024-06-01T09:30Z+01:00", "2024-06-01T"],
+        );
+    }
+
+    #[test]
+    fn test_constructors_compile_every_pattern() {
+        let compiled = [
+            ipv4(),
+            ipv6(),
+            email(),
+            url(),
+            uuid(),
+            iso_date(),
+            iso_time(),
+            iso_datetime(),
+        ];
+        for (regex, (_, pattern)) in compiled.iter().zip(ALL) {
+            assert_eq!(regex.as_str(), *pattern);
+            assert_eq!(regex.group_count(), 0);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Scratch space shared between searches
+//!
+//! A [`Regex`](crate::Regex) is never changed by a search, so one regex can
+//! serve many threads at once, but each search needs memory of its own while
+//! it runs. Rather than allocating that memory for every search, a search
+//! takes a value from the regex's [`Pool`] and returns it when done. A pool
+//! holds at most as many values as there have been searches running at the
+//! same time.
+//!
+//! Without `std` there is no lock to guard the values, so each search
+//! starts from a fresh one.
+
+#[cfg(not(feature = "std"))]
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+
+/// Values handed out to one search at a time
+#[derive(Default)]
+pub(crate) struct Pool<T> {
+    #[cfg(feature = "std")]
+    free: std::sync::Mutex<Vec<T>>,
+    #[cfg(not(feature = "std"))]
+    free: PhantomData<fn() -> T>,
+}
+
+impl<T: Default> Pool<T> {
+    /// Take a value, which goes back to the pool when the guard is dropped
+    pub(crate) fn get(&self) -> PoolGuard<'_, T> {
+        #[cfg(feature = "std")]
+        let value = self
+            .free
+            .lock()
+            .ok()
+            .and_then(|mut free| free.pop())
+            .unwrap_or_default();
+        #[cfg(not(feature = "std"))]
+        let value = T::default();
+        PoolGuard {
+            pool: self,
+            value: Some(value),
+        }
+    }
+}
+
+/// A value taken from a [`Pool`]
+pub(crate) struct PoolGuard<'a, T> {
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pool: &'a Pool<T>,
+    value: Option<T>,
+}
+
+impl<T> Deref for PoolGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+            .as_ref()
+            .expect("the value is only taken on drop")
+    }
+}
+
+impl<T> DerefMut for PoolGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+            .as_mut()
+            .expect("the value is only taken on drop")
+    }
+}
+
+impl<T> Drop for PoolGuard<'_, T> {
+    fn drop(&mut self) {
+        #[cfg(feature = "std")]
+        if let (Some(value), Ok(mut free)) = (self.value.take(), self.pool.free.lock()) {
+            free.push(value);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_are_reused() {
+        let pool: Pool<Vec<u8>> = Pool::default();
+        {
+            let mut first = pool.get();
+            first.push(1);
+            // A second search running at the same time gets its own value
+            assert!(pool.get().is_empty());
+        }
+        let values: Vec<Vec<u8>> = pool.free.lock().unwrap().drain(..).collect();
+        assert_eq!(values.len(), 2);
+        assert!(values.contains(&vec![1]));
+    }
+}
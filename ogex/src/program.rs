@@ -0,0 +1,686 @@
+//! Binary encoding of compiled regexes
+//!
+//! [`Regex::to_bytes`](crate::Regex::to_bytes) writes the compiled NFA and
+//! group table in a compact little-endian format, and
+//! [`Regex::from_bytes`](crate::Regex::from_bytes) restores it without
+//! parsing the pattern again. The encoding starts with a magic number and a
+//! format version; programs written by a different format version are
+//! rejected rather than misread.
+//!
+//! With the `serde` feature, [`Regex`](crate::Regex) also implements
+//! `Serialize` and `Deserialize` using the same encoding, stored as a byte
+//! string, so compiled regexes can be embedded in any serde format.
+
+use crate::collections::HashMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
+
+use crate::engine::{Limits, ModeFlags};
+use crate::error::Span;
+use crate::groups::{GroupNumbering, GroupRegistry};
+use crate::nfa::{Nfa, State, Transition};
+use crate::vm::Backend;
+
+/// Bytes every encoded program starts with
+const MAGIC: &[u8; 4] = b"OGEX";
+
+/// Version of the encoding written by this library
+const FORMAT_VERSION: u8 = 5;
+
+/// Deepest nesting of lookaround NFAs accepted when decoding
+const MAX_NESTING: usize = 64;
+
+/// Errors that can occur when decoding a compiled program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgramError {
+    /// The bytes do not start with the program header
+    NotAProgram,
+    /// The program was written in a format version this library cannot read
+    UnsupportedVersion(u8),
+    /// The program is truncated or malformed
+    Corrupt,
+}
+
+impl fmt::Display for ProgramError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgramError::NotAProgram => write!(f, "not a compiled Ogex program"),
+            ProgramError::UnsupportedVersion(version) => {
+                write!(f, "unsupported program format version {}", version)
+            }
+            ProgramError::Corrupt => write!(f, "compiled program is corrupt"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ProgramError {}
+
+/// Encode the parts of a compiled regex
+pub(crate) fn encode(
+    pattern: &str,
+    nfa: &Nfa,
+    registry: &GroupRegistry,
+    limits: &Limits,
+    backend: Backend,
+) -> Vec<u8> {
+    let mut w = Writer(Vec::new());
+    w.0.extend_from_slice(MAGIC);
+    w.u8(FORMAT_VERSION);
+    w.str(pattern);
+
+    w.opt_usize(limits.steps);
+    w.opt_usize(limits.haystack_len);
+    w.opt_duration(limits.timeout);
+    w.u8(match backend {
+        Backend::Nfa => 0,
+        Backend::Vm => 1,
+    });
+
+    w.numbering(registry.numbering());
+    w.len(registry.groups().len());
+    for group in registry.groups() {
+        w.opt_str(group.name.as_deref());
+        match group.span {
+            Some(span) => {
+                w.u8(1);
+                w.len(span.start);
+                w.len(span.end);
+            }
+            None => w.u8(0),
+        }
+        w.opt_u32(group.parent);
+        w.opt_str(group.fragment.as_deref());
+    }
+
+    w.nfa(nfa);
+    w.0
+}
+
+/// Decode the parts of a compiled regex written by [`encode`]
+pub(crate) fn decode(
+    bytes: &[u8],
+) -> Result<(String, Nfa, GroupRegistry, Limits, Backend), ProgramError> {
+    let Some(rest) = bytes.strip_prefix(MAGIC) else {
+        return Err(ProgramError::NotAProgram);
+    };
+    let mut r = Reader(rest);
+    let version = r.u8()?;
+    if version != FORMAT_VERSION {
+        return Err(ProgramError::UnsupportedVersion(version));
+    }
+    let pattern = r.str()?;
+
+    let limits = Limits {
+        steps: r.opt_usize()?,
+        haystack_len: r.opt_usize()?,
+        timeout: r.opt_duration()?,
+    };
+    let backend = match r.u8()? {
+        0 => Backend::Nfa,
+        1 => Backend::Vm,
+        _ => return Err(ProgramError::Corrupt),
+    };
+
+    let mut registry = GroupRegistry::with_numbering(r.numbering()?);
+    for expected in 1..=r.len()? {
+        let name = r.opt_str()?;
+        let span = match r.u8()? {
+            0 => None,
+            1 => Some(Span::new(r.len()?, r.len()?)),
+            _ => return Err(ProgramError::Corrupt),
+        };
+        let parent = r.opt_u32()?;
+        let fragment = r.opt_str()?;
+        // Groups are numbered in the order they are registered
+        let index = registry
+            .register_nested_group(name, parent)
+            .map_err(|_| ProgramError::Corrupt)?;
+        if index as usize != expected {
+            return Err(ProgramError::Corrupt);
+        }
+        if let Some(span) = span {
+            registry.set_span(index, span);
+        }
+        if let Some(fragment) = fragment {
+            registry.set_fragment(index, fragment);
+        }
+    }
+
+    let nfa = r.nfa(0)?;
+    // Every group the automaton captures into is one the registry describes
+    if nfa.next_group_id as usize != registry.group_count() + 1 {
+        return Err(ProgramError::Corrupt);
+    }
+    if !r.0.is_empty() {
+        return Err(ProgramError::Corrupt);
+    }
+    Ok((pattern, nfa, registry, limits, backend))
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for crate::Regex {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.to_bytes())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for crate::Regex {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ProgramVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ProgramVisitor {
+            type Value = crate::Regex;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a compiled Ogex program")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+                crate::Regex::from_bytes(bytes).map_err(E::custom)
+            }
+
+            // Formats without a byte string type, such as JSON, store bytes
+            // as a sequence of numbers
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                self.visit_bytes(&bytes)
+            }
+        }
+
+        deserializer.deserialize_bytes(ProgramVisitor)
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn len(&mut self, value: usize) {
+        self.u32(value as u32);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.len(value.len());
+        self.0.extend_from_slice(value.as_bytes());
+    }
+
+    fn opt_str(&mut self, value: Option<&str>) {
+        match value {
+            Some(s) => {
+                self.u8(1);
+                self.str(s);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn opt_u32(&mut self, value: Option<u32>) {
+        match value {
+            Some(n) => {
+                self.u8(1);
+                self.u32(n);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn opt_usize(&mut self, value: Option<usize>) {
+        match value {
+            Some(n) => {
+                self.u8(1);
+                self.u64(n as u64);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn opt_duration(&mut self, value: Option<Duration>) {
+        match value {
+            Some(duration) => {
+                self.u8(1);
+                self.u64(duration.as_secs());
+                self.u32(duration.subsec_nanos());
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn numbering(&mut self, numbering: GroupNumbering) {
+        self.u8(match numbering {
+            GroupNumbering::Ogex => 0,
+            GroupNumbering::Pcre => 1,
+        });
+    }
+
+    fn nfa(&mut self, nfa: &Nfa) {
+        self.len(nfa.states.len());
+        for state in &nfa.states {
+            self.u8(u8::from(state.is_accepting));
+            self.len(state.transitions.len());
+            for (transition, target) in &state.transitions {
+                self.transition(transition);
+                self.len(*target);
+            }
+        }
+        self.len(nfa.start);
+        self.len(nfa.accept);
+        self.u32(nfa.next_group_id);
+
+        // Sorted so that the same regex always encodes to the same bytes
+        let mut named: Vec<_> = nfa.named_groups.iter().collect();
+        named.sort();
+        self.len(named.len());
+        for (name, index) in named {
+            self.str(name);
+            self.u32(*index);
+        }
+        self.len(nfa.numbered_groups.len());
+        for index in &nfa.numbered_groups {
+            self.u32(*index);
+        }
+        self.numbering(nfa.numbering);
+
+        let flags = &nfa.mode_flags;
+        self.u8(u8::from(flags.case_insensitive)
+            | u8::from(flags.multiline) << 1
+            | u8::from(flags.dotall) << 2
+            | u8::from(flags.extended) << 3);
+    }
+
+    fn transition(&mut self, transition: &Transition) {
+        match transition {
+            Transition::Char(c) => {
+                self.u8(0);
+                self.u32(*c as u32);
+            }
+            Transition::Any => self.u8(1),
+            Transition::Epsilon => self.u8(2),
+            Transition::CharClass { lookup, negated } => {
+                self.u8(3);
+                self.0.extend_from_slice(lookup);
+                self.u8(u8::from(*negated));
+            }
+            Transition::GroupStart(index) => {
+                self.u8(4);
+                self.u32(*index);
+            }
+            Transition::GroupEnd(index) => {
+                self.u8(5);
+                self.u32(*index);
+            }
+            Transition::Backref(index) => {
+                self.u8(6);
+                self.u32(*index);
+            }
+            Transition::BackrefRelative(relative) => {
+                self.u8(7);
+                self.u32(*relative as u32);
+            }
+            Transition::StartAnchor => self.u8(8),
+            Transition::EndAnchor => self.u8(9),
+            Transition::WordBoundary => self.u8(10),
+            Transition::NonWordBoundary => self.u8(11),
+            Transition::Lookahead(inner) => {
+                self.u8(12);
+                self.nfa(inner);
+            }
+            Transition::NegativeLookahead(inner) => {
+                self.u8(13);
+                self.nfa(inner);
+            }
+            Transition::Lookbehind(inner) => {
+                self.u8(14);
+                self.nfa(inner);
+            }
+            Transition::NegativeLookbehind(inner) => {
+                self.u8(15);
+                self.nfa(inner);
+            }
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ProgramError> {
+        let (bytes, rest) = self.0.split_first_chunk().ok_or(ProgramError::Corrupt)?;
+        self.0 = rest;
+        Ok(*bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ProgramError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, ProgramError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, ProgramError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn len(&mut self) -> Result<usize, ProgramError> {
+        Ok(self.u32()? as usize)
+    }
+
+    fn bool(&mut self) -> Result<bool, ProgramError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(ProgramError::Corrupt),
+        }
+    }
+
+    fn str(&mut self) -> Result<String, ProgramError> {
+        let len = self.len()?;
+        if len > self.0.len() {
+            return Err(ProgramError::Corrupt);
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        String::from_utf8(bytes.to_vec()).map_err(|_| ProgramError::Corrupt)
+    }
+
+    fn opt_str(&mut self) -> Result<Option<String>, ProgramError> {
+        if self.bool()? {
+            Ok(Some(self.str()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn opt_u32(&mut self) -> Result<Option<u32>, ProgramError> {
+        if self.bool()? {
+            Ok(Some(self.u32()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn opt_usize(&mut self) -> Result<Option<usize>, ProgramError> {
+        if self.bool()? {
+            let n = usize::try_from(self.u64()?).map_err(|_| ProgramError::Corrupt)?;
+            Ok(Some(n))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn opt_duration(&mut self) -> Result<Option<Duration>, ProgramError> {
+        if self.bool()? {
+            let secs = self.u64()?;
+            let nanos = self.u32()?;
+            if nanos >= 1_000_000_000 {
+                return Err(ProgramError::Corrupt);
+            }
+            Ok(Some(Duration::new(secs, nanos)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn numbering(&mut self) -> Result<GroupNumbering, ProgramError> {
+        match self.u8()? {
+            0 => Ok(GroupNumbering::Ogex),
+            1 => Ok(GroupNumbering::Pcre),
+            _ => Err(ProgramError::Corrupt),
+        }
+    }
+
+    fn nfa(&mut self, depth: usize) -> Result<Nfa, ProgramError> {
+        if depth > MAX_NESTING {
+            return Err(ProgramError::Corrupt);
+        }
+
+        let state_count = self.len()?;
+        // Every state takes at least five bytes, so a count larger than the
+        // input cannot be genuine and must not size an allocation
+        if state_count > self.0.len() {
+            return Err(ProgramError::Corrupt);
+        }
+        let mut states = Vec::with_capacity(state_count);
+        for _ in 0..state_count {
+            let is_accepting = self.bool()?;
+            let mut transitions = Vec::new();
+            for _ in 0..self.len()? {
+                let transition = self.transition(depth)?;
+                let target = self.len()?;
+                if target >= state_count {
+                    return Err(ProgramError::Corrupt);
+                }
+                transitions.push((transition, target));
+            }
+            states.push(State {
+                transitions,
+                is_accepting,
+            });
+        }
+
+        let start = self.len()?;
+        let accept = self.len()?;
+        if start >= state_count || accept >= state_count {
+            return Err(ProgramError::Corrupt);
+        }
+        let next_group_id = self.u32()?;
+        // Searches keep a slot per group, so a group past the last one
+        // would be out of bounds. Backreferences are not checked: one to a
+        // group that does not exist compiles, and never matches.
+        let is_group = |group: u32| (1..next_group_id).contains(&group);
+        let captures_outside =
+            states
+                .iter()
+                .flat_map(|state| &state.transitions)
+                .any(|(transition, _)| match transition {
+                    Transition::GroupStart(group) | Transition::GroupEnd(group) => {
+                        !is_group(*group)
+                    }
+                    _ => false,
+                });
+        if captures_outside {
+            return Err(ProgramError::Corrupt);
+        }
+
+        let mut named_groups = HashMap::new();
+        for _ in 0..self.len()? {
+            let name = self.str()?;
+            let group = self.u32()?;
+            if !is_group(group) {
+                return Err(ProgramError::Corrupt);
+            }
+            named_groups.insert(name, group);
+        }
+        let mut numbered_groups = Vec::new();
+        for _ in 0..self.len()? {
+            let group = self.u32()?;
+            if !is_group(group) {
+                return Err(ProgramError::Corrupt);
+            }
+            numbered_groups.push(group);
+        }
+        let numbering = self.numbering()?;
+
+        let flags = self.u8()?;
+        let mode_flags = ModeFlags {
+            case_insensitive: flags & 1 != 0,
+            multiline: flags & 1 << 1 != 0,
+            dotall: flags & 1 << 2 != 0,
+            extended: flags & 1 << 3 != 0,
+        };
+
+        let mut nfa = Nfa {
+            states,
+            start,
+            accept,
+            next_state_id: state_count,
+            next_group_id,
+            named_groups,
+            numbered_groups,
+            numbering,
+            mode_flags,
+            epsilon_closure: Vec::new(),
+        };
+        nfa.compute_epsilon_closures();
+        Ok(nfa)
+    }
+
+    fn transition(&mut self, depth: usize) -> Result<Transition, ProgramError> {
+        Ok(match self.u8()? {
+            0 => Transition::Char(char::from_u32(self.u32()?).ok_or(ProgramError::Corrupt)?),
+            1 => Transition::Any,
+            2 => Transition::Epsilon,
+            3 => Transition::CharClass {
+                lookup: self.take()?,
+                negated: self.bool()?,
+            },
+            4 => Transition::GroupStart(self.u32()?),
+            5 => Transition::GroupEnd(self.u32()?),
+            6 => Transition::Backref(self.u32()?),
+            7 => Transition::BackrefRelative(self.u32()? as i32),
+            8 => Transition::StartAnchor,
+            9 => Transition::EndAnchor,
+            10 => Transition::WordBoundary,
+            11 => Transition::NonWordBoundary,
+            12 => Transition::Lookahead(self.nfa(depth + 1)?),
+            13 => Transition::NegativeLookahead(self.nfa(depth + 1)?),
+            14 => Transition::Lookbehind(self.nfa(depth + 1)?),
+            15 => Transition::NegativeLookbehind(self.nfa(depth + 1)?),
+            _ => return Err(ProgramError::Corrupt),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Regex, RegexBuilder};
+
+    fn round_trip(regex: &Regex) -> Regex {
+        Regex::from_bytes(&regex.to_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_matches_the_same() {
+        let patterns = [
+            r"(year:\d{4})-(\d+)\g{year}",
+            r"(a)(b)\g{-1}\1",
+            r"\bfoo(>:bar)",
+            r"(<~:x)[^a-c]+$",
+            "é.*ü",
+        ];
+        let inputs = ["2024-052024", "abba abb", "foobar foobaz", "yzz xzz", "éaü"];
+        for pattern in patterns {
+            let regex = Regex::new(pattern).unwrap();
+            let restored = round_trip(&regex);
+            for input in inputs {
+                assert_eq!(
+                    regex.find(input).map(|m| (m.start, m.end, m.groups)),
+                    restored.find(input).map(|m| (m.start, m.end, m.groups)),
+                    "{pattern} on {input}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde_round_trip() {
+        let regex = Regex::new(r"(key:\w+)=(\d+)").unwrap();
+        let json = serde_json::to_string(&regex).unwrap();
+        let restored: Regex = serde_json::from_str(&json).unwrap();
+        let found = restored.find("a b=12").unwrap();
+        assert_eq!((found.start, found.end), (2, 6));
+        assert_eq!(found.named_group("key"), Some((2, 3)));
+
+        let Err(err) = serde_json::from_str::<Regex>("[1, 2, 3]") else {
+            panic!("decoded a program that is not one");
+        };
+        assert!(err.to_string().contains("not a compiled Ogex program"));
+    }
+
+    #[test]
+    fn test_round_trip_keeps_options_and_groups() {
+        let regex = RegexBuilder::new("(word:a+)")
+            .case_insensitive(true)
+            .step_limit(20)
+            .max_haystack_len(500)
+            .timeout(Duration::from_millis(1500))
+            .build()
+            .unwrap();
+        let restored = round_trip(&regex);
+        assert!(restored.is_match("xA"));
+        assert!(restored.try_find(&"b".repeat(100)).is_err());
+        assert!(matches!(
+            restored.try_find(&"a".repeat(501)),
+            Err(crate::RegexError::Runtime(
+                crate::RuntimeError::HaystackTooLarge {
+                    len: 501,
+                    limit: 500
+                }
+            ))
+        ));
+        assert_eq!(restored.group_index("word"), Some(1));
+        assert_eq!(restored.as_str(), "(word:a+)");
+        assert_eq!(restored.to_bytes(), regex.to_bytes());
+    }
+
+    #[test]
+    fn test_rejects_bad_programs() {
+        let bytes = Regex::new("a(b)c").unwrap().to_bytes();
+
+        assert!(matches!(
+            Regex::from_bytes(b"nope"),
+            Err(ProgramError::NotAProgram)
+        ));
+
+        let mut future = bytes.clone();
+        future[4] = FORMAT_VERSION + 1;
+        assert!(matches!(
+            Regex::from_bytes(&future),
+            Err(ProgramError::UnsupportedVersion(_))
+        ));
+
+        // Capturing into a group the program does not have
+        let group_starts: Vec<_> = (0..bytes.len() - 5)
+            .filter(|&at| bytes[at..at + 5] == [4, 1, 0, 0, 0])
+            .collect();
+        let [group_start] = group_starts[..] else {
+            panic!("expected one GroupStart(1) in {bytes:?}");
+        };
+        let mut out_of_range = bytes.clone();
+        out_of_range[group_start + 1] = 200;
+        assert!(matches!(
+            Regex::from_bytes(&out_of_range),
+            Err(ProgramError::Corrupt)
+        ));
+
+        for len in 5..bytes.len() {
+            assert!(
+                matches!(Regex::from_bytes(&bytes[..len]), Err(ProgramError::Corrupt)),
+                "truncated to {len}"
+            );
+        }
+    }
+}
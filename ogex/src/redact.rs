@@ -0,0 +1,260 @@
+//! Scrubbing matched text out of an input
+//!
+//! [`Regex::redact`] replaces each match, or only chosen named groups inside
+//! each match, with a mask, a fixed token or a hash of the text, as when
+//! removing personal data from logs before they are stored:
+//!
+//! ```
+//! use ogex::{Redaction, Regex};
+//!
+//! let regex = Regex::new(r"(user:\w+)@(domain:\w+\.\w+)").unwrap();
+//! let line = "login ada@example.org ok";
+//!
+//! let masked = regex.redact(line, &Redaction::mask('*')).unwrap();
+//! assert_eq!(masked, "login **** ok");
+//!
+//! let users = Redaction::mask('*').preserve_length(true).groups(["user"]);
+//! assert_eq!(regex.redact(line, &users).unwrap(), "login ***@example.org ok");
+//!
+//! let token = Redaction::token("[EMAIL]");
+//! assert_eq!(regex.redact(line, &token).unwrap(), "login [EMAIL] ok");
+//! ```
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::iter;
+
+use crate::engine::Regex;
+use crate::replace::ReplacementError;
+
+/// What redacted text is replaced with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedactStyle {
+    /// The character repeated, four times or once per character replaced
+    Mask(char),
+    /// Fixed text such as `[REDACTED]`
+    Token(String),
+    /// A digest of the text, so equal values can still be matched up
+    ///
+    /// The digest is 16 hex digits of a 64-bit FNV-1a hash of the salt and
+    /// the text. It is the same on every run and platform, but it is not a
+    /// cryptographic hash: it keeps values apart, not secret from someone
+    /// who can guess them and knows the salt.
+    Hash {
+        /// Text hashed along with each value
+        salt: String,
+    },
+}
+
+/// How [`Regex::redact`] replaces text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redaction {
+    /// What the text is replaced with
+    pub style: RedactStyle,
+    /// Make each replacement as many characters long as the text it
+    /// replaces, cutting the token or digest short or repeating it
+    pub preserve_length: bool,
+    /// Named groups to redact inside each match, or none to redact the
+    /// whole match
+    pub groups: Vec<String>,
+}
+
+impl Redaction {
+    /// Redact with `RedactStyle::Mask(c)`
+    pub fn mask(c: char) -> Self {
+        RedactStyle::Mask(c).into()
+    }
+
+    /// Redact with `RedactStyle::Token(token)`
+    pub fn token(token: &str) -> Self {
+        RedactStyle::Token(token.to_string()).into()
+    }
+
+    /// Redact with `RedactStyle::Hash { salt }`
+    pub fn hash(salt: &str) -> Self {
+        RedactStyle::Hash {
+            salt: salt.to_string(),
+        }
+        .into()
+    }
+
+    /// Set whether replacements keep the length of the text they replace
+    pub fn preserve_length(mut self, yes: bool) -> Self {
+        self.preserve_length = yes;
+        self
+    }
+
+    /// Redact only these named groups, leaving the rest of each match
+    pub fn groups<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.groups = names.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Append the replacement for `text` to `out`
+    fn write(&self, out: &mut String, text: &str) {
+        let len = text.chars().count();
+        let fill = |out: &mut String, replacement: &str| {
+            if self.preserve_length {
+                out.extend(replacement.chars().cycle().take(len));
+            } else {
+                out.push_str(replacement);
+            }
+        };
+        match &self.style {
+            RedactStyle::Mask(c) => {
+                let count = if self.preserve_length { len } else { 4 };
+                out.extend(iter::repeat_n(*c, count));
+            }
+            RedactStyle::Token(token) => fill(out, token),
+            RedactStyle::Hash { salt } => fill(out, &format!("{:016x}", fnv1a(salt, text))),
+        }
+    }
+}
+
+impl From<RedactStyle> for Redaction {
+    fn from(style: RedactStyle) -> Self {
+        Redaction {
+            style,
+            preserve_length: false,
+            groups: Vec::new(),
+        }
+    }
+}
+
+/// 64-bit FNV-1a hash of `salt` and `text`, kept apart by a byte UTF-8
+/// never contains
+fn fnv1a(salt: &str, text: &str) -> u64 {
+    salt.bytes()
+        .chain([0xff])
+        .chain(text.bytes())
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Redact every match of `regex` in `input`
+pub(crate) fn redact(
+    regex: &Regex,
+    input: &str,
+    redaction: &Redaction,
+) -> Result<String, ReplacementError> {
+    if let Some(name) = redaction
+        .groups
+        .iter()
+        .find(|name| regex.group_index(name).is_none())
+    {
+        return Err(ReplacementError::UndefinedGroupName(name.clone()));
+    }
+
+    let mut out = String::with_capacity(input.len());
+    let mut last_end = 0;
+    // Stopping at a search that hit the step limit would leave the rest of
+    // the input unredacted
+    for m in regex.runtime_matches(input, 0) {
+        let m = m.map_err(ReplacementError::Runtime)?;
+        let mut spans: Vec<(usize, usize)> = if redaction.groups.is_empty() {
+            vec![(m.start, m.end)]
+        } else {
+            redaction
+                .groups
+                .iter()
+                .filter_map(|name| m.named_group(name))
+                .collect()
+        };
+        spans.sort_unstable();
+        // Nested and overlapping groups are redacted as one piece of text
+        let mut merged: Vec<(usize, usize)> = Vec::new();
+        for (start, end) in spans {
+            match merged.last_mut() {
+                Some(last) if start < last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        for (start, end) in merged {
+            // A group in a lookbehind can reach back into text already
+            // written
+            let start = start.max(last_end);
+            if start >= end {
+                continue;
+            }
+            out.push_str(&input[last_end..start]);
+            redaction.write(&mut out, &input[start..end]);
+            last_end = end;
+        }
+    }
+    out.push_str(&input[last_end..]);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_styles() {
+        let regex = Regex::new(r"\d{3}-\d{4}").unwrap();
+        let input = "call 555-1234 or 555-9876";
+        let redact = |redaction: Redaction| regex.redact(input, &redaction).unwrap();
+
+        assert_eq!(
+            redact(Redaction::mask('#').preserve_length(true)),
+            "call ######## or ########"
+        );
+        assert_eq!(
+            redact(Redaction::token("<phone>").preserve_length(true)),
+            "call <phone>< or <phone><"
+        );
+        let hashed = redact(Redaction::hash("pepper"));
+        let digests: Vec<&str> = hashed.split(' ').filter(|word| word.len() == 16).collect();
+        assert_eq!(digests.len(), 2);
+        assert_ne!(digests[0], digests[1]);
+        // The same value always hashes the same way, unless the salt changes
+        assert_eq!(redact(Redaction::hash("pepper")), hashed);
+        assert_ne!(redact(Redaction::hash("salt")), hashed);
+        assert_eq!(
+            redact(Redaction::hash("pepper").preserve_length(true))
+                .split(' ')
+                .nth(1)
+                .unwrap(),
+            &digests[0][..8]
+        );
+    }
+
+    #[test]
+    fn test_selected_groups() {
+        let regex = Regex::new(r"(card:\d{4}) (@?:\d{4} ){2}(last:\d{4})").unwrap();
+        let redaction = Redaction::mask('x')
+            .preserve_length(true)
+            .groups(["card", "last"]);
+        assert_eq!(
+            regex
+                .redact("pay 1234 5678 9012 3456 now", &redaction)
+                .unwrap(),
+            "pay xxxx 5678 9012 xxxx now"
+        );
+
+        // Nested groups are merged, and groups that did not match are left
+        let regex = Regex::new(r"(outer:a(inner:b)c)(rest:d)?").unwrap();
+        let redaction = Redaction::token("_").groups(["inner", "outer", "rest"]);
+        assert_eq!(regex.redact("abc abcd", &redaction).unwrap(), "_ __");
+
+        assert_eq!(
+            regex.redact("abc", &Redaction::mask('*').groups(["nope"])),
+            Err(ReplacementError::UndefinedGroupName("nope".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_empty_matches_are_left_alone() {
+        let regex = Regex::new("x*").unwrap();
+        assert_eq!(
+            regex.redact("axxb", &Redaction::token("[X]")).unwrap(),
+            "a[X]b"
+        );
+    }
+}
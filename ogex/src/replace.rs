@@ -5,6 +5,13 @@
 //! - `\G` for the entire match
 //! - `\g{0}` for the entire match (deprecated, use `\G` instead)
 //!
+//! Python's `\g<name>` and `\g<1>` spellings are accepted as aliases.
+//!
+//! With [`ReplacementSyntax::Dollar`] or [`ReplacementSyntax::Mixed`], the
+//! `$1`, `${name}`, `$&` and `$$` forms used by the regex crate and JavaScript
+//! are accepted as well. [`ReplacementSyntax::Braces`] instead reads
+//! format-string style `{1}` and `{name}` references.
+//!
 //! # Example
 //!
 //! ```ignore
@@ -18,7 +25,13 @@
 //! assert_eq!(result, "[hello]");
 //! ```
 
-use std::collections::HashMap;
+use crate::collections::HashMap;
+use crate::engine::Match;
+use crate::error::RegexError;
+use crate::groups::GroupRegistry;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
 
 /// A part of a replacement string
 #[derive(Debug, Clone, PartialEq)]
@@ -33,21 +46,175 @@ pub enum ReplacementPart {
     EntireMatch,
 }
 
+/// Which reference syntax a replacement string is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplacementSyntax {
+    /// Ogex syntax: `\1`, `\g{name}`, `\G`
+    #[default]
+    Ogex,
+    /// Dollar syntax: `$1`, `${name}`, `$&`, `$$` (backslashes are literal)
+    Dollar,
+    /// Both Ogex and dollar syntax
+    Mixed,
+    /// Format-string syntax: `{1}`, `{name}`, `{0}` for the entire match, and
+    /// `{{`/`}}` for literal braces (backslashes and dollars are literal)
+    Braces,
+}
+
+impl ReplacementSyntax {
+    /// Whether backslash references (`\1`, `\g{name}`) are recognized
+    fn accepts_backslash(self) -> bool {
+        matches!(self, ReplacementSyntax::Ogex | ReplacementSyntax::Mixed)
+    }
+
+    /// Whether dollar references (`$1`, `${name}`) are recognized
+    fn accepts_dollar(self) -> bool {
+        matches!(self, ReplacementSyntax::Dollar | ReplacementSyntax::Mixed)
+    }
+}
+
+/// What to substitute for a reference to a group that did not participate in a match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingGroupPolicy {
+    /// Expand the reference to empty text
+    #[default]
+    Empty,
+    /// Fail with [`ReplacementError::GroupDidNotParticipate`]
+    Error,
+}
+
+/// Options controlling how replacement strings are parsed
+#[derive(Debug, Clone, Default)]
+pub struct ReplacementOptions {
+    /// The reference syntax to recognize
+    pub syntax: ReplacementSyntax,
+    /// Expand `\n`, `\t`, `\r` and `\xNN` to the characters they name
+    /// instead of the literal letter, as sed does
+    pub expand_escapes: bool,
+    /// How references to groups that did not participate are expanded
+    pub missing_groups: MissingGroupPolicy,
+}
+
+impl ReplacementOptions {
+    /// Create options for the given reference syntax
+    pub fn with_syntax(syntax: ReplacementSyntax) -> Self {
+        ReplacementOptions {
+            syntax,
+            ..Default::default()
+        }
+    }
+
+    /// Enable or disable expansion of escape sequences
+    pub fn expand_escapes(mut self, enabled: bool) -> Self {
+        self.expand_escapes = enabled;
+        self
+    }
+
+    /// Set how references to groups that did not participate are expanded
+    pub fn missing_groups(mut self, policy: MissingGroupPolicy) -> Self {
+        self.missing_groups = policy;
+        self
+    }
+}
+
 /// A parsed replacement string
 #[derive(Debug, Clone)]
 pub struct Replacement {
     parts: Vec<ReplacementPart>,
+    missing_groups: MissingGroupPolicy,
 }
 
 impl Replacement {
-    /// Parse a replacement string
+    /// Parse a replacement string using Ogex syntax
     pub fn parse(input: &str) -> Result<Self, ReplacementError> {
+        Self::parse_with(input, &ReplacementOptions::default())
+    }
+
+    /// Create a replacement that inserts `text` verbatim
+    pub fn literal(text: &str) -> Self {
+        let parts = if text.is_empty() {
+            Vec::new()
+        } else {
+            vec![ReplacementPart::Literal(text.to_string())]
+        };
+        Replacement {
+            parts,
+            missing_groups: MissingGroupPolicy::default(),
+        }
+    }
+
+    /// Parse a replacement string with explicit options
+    ///
+    /// # Example
+    /// ```
+    /// use ogex::{Replacement, ReplacementOptions, ReplacementSyntax};
+    ///
+    /// let options = ReplacementOptions::with_syntax(ReplacementSyntax::Dollar);
+    /// let repl = Replacement::parse_with("${2}-$1 costs $$5", &options).unwrap();
+    /// assert_eq!(repl.apply("ab", 0, 2, &[(0, 1), (1, 2)]), "b-a costs $5");
+    /// ```
+    pub fn parse_with(input: &str, options: &ReplacementOptions) -> Result<Self, ReplacementError> {
         let mut parts = Vec::new();
         let mut chars = input.chars().peekable();
         let mut current_literal = String::new();
 
         while let Some(c) = chars.next() {
-            if c == '\\' {
+            if c == '$' && options.syntax.accepts_dollar() {
+                match chars.peek().copied() {
+                    Some('$') => {
+                        // $$ - literal dollar sign
+                        chars.next();
+                        current_literal.push('$');
+                    }
+                    Some('&') => {
+                        // $& - entire match
+                        chars.next();
+                        Self::flush_literal(&mut parts, &mut current_literal);
+                        parts.push(ReplacementPart::EntireMatch);
+                    }
+                    Some(d) if d.is_ascii_digit() => {
+                        // $1, $2, etc. ($0 is the entire match)
+                        let mut digits = String::new();
+                        while let Some(&d) = chars.peek() {
+                            if d.is_ascii_digit() {
+                                chars.next();
+                                digits.push(d);
+                            } else {
+                                break;
+                            }
+                        }
+                        Self::flush_literal(&mut parts, &mut current_literal);
+                        parts.push(Self::reference(&digits));
+                    }
+                    Some('{') => {
+                        // ${name} or ${1}
+                        chars.next(); // consume '{'
+                        let reference = Self::braced_reference(&mut chars, "$")?;
+                        Self::flush_literal(&mut parts, &mut current_literal);
+                        parts.push(reference);
+                    }
+                    // Lone dollar sign, keep it literally
+                    _ => current_literal.push(c),
+                }
+            } else if options.syntax == ReplacementSyntax::Braces && (c == '{' || c == '}') {
+                if chars.peek() == Some(&c) {
+                    // {{ or }} - literal brace
+                    chars.next();
+                    current_literal.push(c);
+                } else if c == '{' {
+                    let reference = Self::braced_reference(&mut chars, "")?;
+                    Self::flush_literal(&mut parts, &mut current_literal);
+                    parts.push(reference);
+                } else {
+                    // Lone closing brace, keep it literally
+                    current_literal.push(c);
+                }
+            } else if c == '\\'
+                && options.expand_escapes
+                && let Some(escaped) = Self::escape_sequence(&mut chars)?
+            {
+                current_literal.push(escaped);
+            } else if c == '\\' && options.syntax.accepts_backslash() {
                 // Check for backreference
                 if let Some(&next) = chars.peek() {
                     if next.is_ascii_digit() {
@@ -69,13 +236,18 @@ impl Replacement {
                         }
                         parts.push(ReplacementPart::BackrefNumber(num));
                     } else if next == 'g' {
-                        // \g{name} or \g{1} or \g{0}
+                        // \g{name} or \g{1} or \g{0}, or Python's \g<name>
                         chars.next(); // consume 'g'
-                        if chars.peek() == Some(&'{') {
-                            chars.next(); // consume '{'
-                            let name = Self::read_until(&mut chars, '}');
-                            if chars.peek() == Some(&'}') {
-                                chars.next(); // consume '}'
+                        let close = match chars.peek() {
+                            Some('{') => Some('}'),
+                            Some('<') => Some('>'),
+                            _ => None,
+                        };
+                        if let Some(close) = close {
+                            chars.next(); // consume the opening delimiter
+                            let name = Self::read_until(&mut chars, close);
+                            if chars.peek() == Some(&close) {
+                                chars.next(); // consume the closing delimiter
                             }
                             if !current_literal.is_empty() {
                                 parts.push(ReplacementPart::Literal(current_literal.clone()));
@@ -121,11 +293,81 @@ impl Replacement {
             parts.push(ReplacementPart::Literal(current_literal));
         }
 
-        Ok(Replacement { parts })
+        Ok(Replacement {
+            parts,
+            missing_groups: options.missing_groups,
+        })
+    }
+
+    /// Read the escape sequence following a backslash, if there is one
+    ///
+    /// Returns `None` (consuming nothing) when the next character does not
+    /// start an escape sequence.
+    fn escape_sequence(
+        chars: &mut core::iter::Peekable<core::str::Chars>,
+    ) -> Result<Option<char>, ReplacementError> {
+        let escaped = match chars.peek() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('x') => {
+                chars.next(); // consume 'x'
+                let digits: String = chars.by_ref().take(2).collect();
+                return u8::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(|_| digits.len() == 2 && digits.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .map(|byte| Some(char::from(byte)))
+                    .ok_or(ReplacementError::InvalidEscape(format!("\\x{}", digits)));
+            }
+            _ => return Ok(None),
+        };
+        chars.next();
+        Ok(Some(escaped))
+    }
+
+    /// Move any pending literal text into the parts list
+    fn flush_literal(parts: &mut Vec<ReplacementPart>, current_literal: &mut String) {
+        if !current_literal.is_empty() {
+            parts.push(ReplacementPart::Literal(core::mem::take(current_literal)));
+        }
+    }
+
+    /// Build the part for a `{...}` reference: `0` is the entire match,
+    /// other numbers are numbered groups, anything else is a group name
+    fn reference(name: &str) -> ReplacementPart {
+        if name == "0" {
+            ReplacementPart::EntireMatch
+        } else if let Ok(num) = name.parse::<u32>() {
+            ReplacementPart::BackrefNumber(num)
+        } else {
+            ReplacementPart::BackrefName(name.to_string())
+        }
+    }
+
+    /// Read the rest of a `{...}` reference after its opening brace;
+    /// `sigil` is what precedes the brace, for error messages
+    fn braced_reference(
+        chars: &mut core::iter::Peekable<core::str::Chars>,
+        sigil: &str,
+    ) -> Result<ReplacementPart, ReplacementError> {
+        let name = Self::read_until(chars, '}');
+        if chars.next() != Some('}') {
+            return Err(ReplacementError::InvalidBackreference(format!(
+                "unclosed {}{{{}",
+                sigil, name
+            )));
+        }
+        if name.is_empty() {
+            return Err(ReplacementError::InvalidBackreference(format!(
+                "empty {}{{}}",
+                sigil
+            )));
+        }
+        Ok(Self::reference(&name))
     }
 
     /// Read characters until delimiter
-    fn read_until(chars: &mut std::iter::Peekable<std::str::Chars>, delimiter: char) -> String {
+    fn read_until(chars: &mut core::iter::Peekable<core::str::Chars>, delimiter: char) -> String {
         let mut result = String::new();
         while let Some(&c) = chars.peek() {
             if c == delimiter {
@@ -198,29 +440,294 @@ impl Replacement {
         result
     }
 
+    /// Check that every group referenced by the replacement exists
+    ///
+    /// Without validation, a reference to an unknown group silently expands
+    /// to empty text; this reports it up front instead.
+    pub fn validate(&self, registry: &GroupRegistry) -> Result<(), ReplacementError> {
+        match self.reference_errors(registry).into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Every reference to a group that does not exist, in template order
+    pub fn reference_errors(&self, registry: &GroupRegistry) -> Vec<ReplacementError> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                ReplacementPart::BackrefNumber(n)
+                    if *n > 0 && registry.validate_backref_number(*n).is_err() =>
+                {
+                    Some(ReplacementError::GroupOutOfRange {
+                        index: *n,
+                        group_count: registry.group_count(),
+                    })
+                }
+                ReplacementPart::BackrefName(name) if !registry.has_name(name) => {
+                    Some(ReplacementError::UndefinedGroupName(name.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Append the replacement for a match of a regex in `input` to `dst`
+    pub fn append_match(
+        &self,
+        dst: &mut String,
+        input: &str,
+        m: &Match,
+    ) -> Result<(), ReplacementError> {
+        for piece in self.pieces(input, m) {
+            dst.push_str(piece?);
+        }
+        Ok(())
+    }
+
+    /// The pieces of text the replacement expands to for a match
+    pub(crate) fn pieces<'a>(
+        &'a self,
+        input: &'a str,
+        m: &'a Match,
+    ) -> impl Iterator<Item = Result<&'a str, ReplacementError>> + 'a {
+        self.parts.iter().filter_map(move |part| {
+            let span = match part {
+                ReplacementPart::Literal(text) => return Some(Ok(text.as_str())),
+                ReplacementPart::BackrefNumber(0) | ReplacementPart::EntireMatch => {
+                    Some((m.start, m.end))
+                }
+                ReplacementPart::BackrefNumber(n) => m.group(*n),
+                ReplacementPart::BackrefName(name) => m.named_group(name),
+            };
+            match (span, self.missing_groups) {
+                (Some((start, end)), _) => Some(Ok(&input[start..end])),
+                (None, MissingGroupPolicy::Empty) => None,
+                (None, MissingGroupPolicy::Error) => {
+                    let group = match part {
+                        ReplacementPart::BackrefName(name) => name.clone(),
+                        ReplacementPart::BackrefNumber(n) => n.to_string(),
+                        _ => unreachable!("only group references can be missing"),
+                    };
+                    Some(Err(ReplacementError::GroupDidNotParticipate(group)))
+                }
+            }
+        })
+    }
+
     /// Get the parts of the replacement
     pub fn parts(&self) -> &[ReplacementPart] {
         &self.parts
     }
 }
 
+/// A source of replacement text for [`Regex::replace`](crate::Regex::replace)
+/// and [`Regex::replace_all`](crate::Regex::replace_all)
+pub trait Replacer {
+    /// Produce the parsed replacement to substitute for each match
+    fn to_replacement(&self) -> Result<Replacement, ReplacementError>;
+}
+
+/// Replacement templates are parsed with Ogex syntax
+impl Replacer for &str {
+    fn to_replacement(&self) -> Result<Replacement, ReplacementError> {
+        Replacement::parse(self)
+    }
+}
+
+impl Replacer for &String {
+    fn to_replacement(&self) -> Result<Replacement, ReplacementError> {
+        Replacement::parse(self)
+    }
+}
+
+impl Replacer for &Replacement {
+    fn to_replacement(&self) -> Result<Replacement, ReplacementError> {
+        Ok((*self).clone())
+    }
+}
+
+/// Replacement text that is inserted verbatim, without expanding references
+///
+/// # Example
+/// ```
+/// use ogex::{NoExpand, Regex};
+///
+/// let regex = Regex::new("price").unwrap();
+/// let result = regex.replace("price", NoExpand(r"$5 \1")).unwrap();
+/// assert_eq!(result, r"$5 \1");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct NoExpand<'a>(pub &'a str);
+
+impl Replacer for NoExpand<'_> {
+    fn to_replacement(&self) -> Result<Replacement, ReplacementError> {
+        Ok(Replacement::literal(self.0))
+    }
+}
+
+/// Escape text so it parses as a literal replacement
+///
+/// Backslashes and dollar signs are escaped, so the result expands to `text`
+/// under both [`ReplacementSyntax::Ogex`] and [`ReplacementSyntax::Mixed`].
+/// Use [`escape_with`] for the other syntaxes.
+///
+/// # Example
+/// ```
+/// use ogex::Replacement;
+/// use ogex::replace::escape;
+///
+/// let repl = Replacement::parse(&escape(r"C:\1 costs $1")).unwrap();
+/// assert_eq!(repl.apply("x", 0, 1, &[]), r"C:\1 costs $1");
+/// ```
+pub fn escape(text: &str) -> String {
+    escape_with(text, ReplacementSyntax::Ogex)
+}
+
+/// Escape text so it parses as a literal replacement under `syntax`
+///
+/// Ogex and mixed syntax escape backslashes and dollar signs with a
+/// backslash, dollar syntax doubles dollar signs (`$$`), and brace syntax
+/// doubles braces. Backslashes cannot be escaped in dollar or brace syntax,
+/// so there the result only round-trips with
+/// [`ReplacementOptions::expand_escapes`] off.
+///
+/// # Example
+/// ```
+/// use ogex::{Replacement, ReplacementOptions, ReplacementSyntax};
+/// use ogex::replace::escape_with;
+///
+/// let options = ReplacementOptions::with_syntax(ReplacementSyntax::Dollar);
+/// let escaped = escape_with(r"$1 costs \$5", ReplacementSyntax::Dollar);
+/// assert_eq!(escaped, r"$$1 costs \$$5");
+/// let repl = Replacement::parse_with(&escaped, &options).unwrap();
+/// assert_eq!(repl.apply("x", 0, 1, &[]), r"$1 costs \$5");
+/// ```
+pub fn escape_with(text: &str, syntax: ReplacementSyntax) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match (syntax, c) {
+            (ReplacementSyntax::Ogex | ReplacementSyntax::Mixed, '\\' | '$') => escaped.push('\\'),
+            (ReplacementSyntax::Dollar, '$') | (ReplacementSyntax::Braces, '{' | '}') => {
+                escaped.push(c)
+            }
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Check a pattern and a replacement template against each other without
+/// matching any input
+///
+/// The pattern is compiled and the template parsed with
+/// [`Replacement::parse`]; every reference to a group the pattern does not
+/// define is reported, not just the first. Meant for linting stored
+/// pattern/replacement pairs.
+///
+/// ```
+/// use ogex::{PairError, validate_pair};
+///
+/// assert!(validate_pair(r"(year:\d+)-(\d+)", r"\g{year}/\1").is_ok());
+///
+/// let Err(PairError::Replacement(errors)) = validate_pair("(a)", r"\2 \g{b}") else {
+///     panic!("expected replacement errors");
+/// };
+/// assert_eq!(errors.len(), 2);
+/// ```
+pub fn validate_pair(pattern: &str, replacement: &str) -> Result<(), PairError> {
+    let regex = crate::engine::Regex::new(pattern).map_err(PairError::Pattern)?;
+    let replacement =
+        Replacement::parse(replacement).map_err(|err| PairError::Replacement(vec![err]))?;
+    let errors = replacement.reference_errors(regex.group_registry());
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(PairError::Replacement(errors))
+    }
+}
+
+/// Why a pattern/replacement pair failed [`validate_pair`]
+#[derive(Debug, Clone)]
+pub enum PairError {
+    /// The pattern does not compile
+    Pattern(RegexError),
+    /// The replacement does not parse, or references groups the pattern lacks
+    Replacement(Vec<ReplacementError>),
+}
+
+impl core::fmt::Display for PairError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PairError::Pattern(err) => write!(f, "invalid pattern: {}", err),
+            PairError::Replacement(errors) => {
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", err)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PairError {}
+
 /// Errors that can occur during replacement parsing
 #[derive(Debug, Clone, PartialEq)]
 pub enum ReplacementError {
     /// Invalid backreference
     InvalidBackreference(String),
+    /// Malformed escape sequence such as `\x4`
+    InvalidEscape(String),
+    /// Reference to a group name the pattern does not define
+    UndefinedGroupName(String),
+    /// Reference to a group number beyond the pattern's groups
+    GroupOutOfRange {
+        /// The referenced group number
+        index: u32,
+        /// The number of groups in the pattern
+        group_count: usize,
+    },
+    /// Reference to a group that did not participate in the match, under
+    /// [`MissingGroupPolicy::Error`]
+    GroupDidNotParticipate(String),
+    /// A search for the next match hit the step limit
+    Runtime(crate::error::RuntimeError),
 }
 
-impl std::fmt::Display for ReplacementError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for ReplacementError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             ReplacementError::InvalidBackreference(s) => {
                 write!(f, "invalid backreference: {}", s)
             }
+            ReplacementError::InvalidEscape(s) => {
+                write!(f, "invalid escape sequence: {}", s)
+            }
+            ReplacementError::UndefinedGroupName(name) => {
+                write!(f, "undefined group name in replacement: {}", name)
+            }
+            ReplacementError::GroupOutOfRange { index, group_count } => {
+                write!(
+                    f,
+                    "replacement references group {} but the pattern has {} group(s)",
+                    index, group_count
+                )
+            }
+            ReplacementError::GroupDidNotParticipate(group) => {
+                write!(f, "group {} did not participate in the match", group)
+            }
+            ReplacementError::Runtime(err) => write!(f, "{}", err),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for ReplacementError {}
 
 #[cfg(test)]
@@ -248,6 +755,19 @@ mod tests {
         assert!(matches!(&repl.parts[0], ReplacementPart::BackrefName(s) if s == "name"));
     }
 
+    #[test]
+    fn test_parse_python_style_reference() {
+        let repl = Replacement::parse("\\g<name>-\\g<2>").unwrap();
+        assert_eq!(
+            repl.parts,
+            vec![
+                ReplacementPart::BackrefName("name".to_string()),
+                ReplacementPart::Literal("-".to_string()),
+                ReplacementPart::BackrefNumber(2),
+            ]
+        );
+    }
+
     #[test]
     fn test_parse_entire_match() {
         let repl = Replacement::parse("\\g{0}").unwrap();
@@ -379,6 +899,195 @@ mod tests {
         assert_eq!(result, "b-a-ab");
     }
 
+    #[test]
+    fn test_dollar_syntax_numbered() {
+        let options = ReplacementOptions::with_syntax(ReplacementSyntax::Dollar);
+        let repl = Replacement::parse_with("$2-$1", &options).unwrap();
+        assert_eq!(repl.parts.len(), 3);
+        assert!(matches!(&repl.parts[0], ReplacementPart::BackrefNumber(2)));
+        assert!(matches!(&repl.parts[2], ReplacementPart::BackrefNumber(1)));
+        assert_eq!(repl.apply("ab", 0, 2, &[(0, 1), (1, 2)]), "b-a");
+    }
+
+    #[test]
+    fn test_dollar_syntax_braced_and_special() {
+        let options = ReplacementOptions::with_syntax(ReplacementSyntax::Dollar);
+        let repl = Replacement::parse_with("${name}${1}x $& $0 $$ $", &options).unwrap();
+        assert!(matches!(&repl.parts[0], ReplacementPart::BackrefName(s) if s == "name"));
+        assert!(matches!(&repl.parts[1], ReplacementPart::BackrefNumber(1)));
+
+        let mut named = HashMap::new();
+        named.insert("name".to_string(), 1);
+        let result = repl.apply_with_names("hi", 0, 2, &[(0, 1)], &named);
+        assert_eq!(result, "hhx hi hi $ $");
+    }
+
+    #[test]
+    fn test_dollar_syntax_keeps_backslashes() {
+        let options = ReplacementOptions::with_syntax(ReplacementSyntax::Dollar);
+        let repl = Replacement::parse_with(r"\1", &options).unwrap();
+        assert!(matches!(&repl.parts[0], ReplacementPart::Literal(s) if s == r"\1"));
+    }
+
+    #[test]
+    fn test_dollar_is_literal_in_ogex_syntax() {
+        let repl = Replacement::parse("$1").unwrap();
+        assert!(matches!(&repl.parts[0], ReplacementPart::Literal(s) if s == "$1"));
+    }
+
+    #[test]
+    fn test_mixed_syntax() {
+        let options = ReplacementOptions::with_syntax(ReplacementSyntax::Mixed);
+        let repl = Replacement::parse_with(r"\g{2}$1", &options).unwrap();
+        assert_eq!(repl.apply("ab", 0, 2, &[(0, 1), (1, 2)]), "ba");
+    }
+
+    #[test]
+    fn test_dollar_syntax_unclosed_brace() {
+        let options = ReplacementOptions::with_syntax(ReplacementSyntax::Dollar);
+        assert!(matches!(
+            Replacement::parse_with("${name", &options),
+            Err(ReplacementError::InvalidBackreference(_))
+        ));
+        assert!(Replacement::parse_with("${}", &options).is_err());
+    }
+
+    #[test]
+    fn test_braces_syntax() {
+        let options = ReplacementOptions::with_syntax(ReplacementSyntax::Braces);
+        let repl = Replacement::parse_with(r"{name} -> {2} {{{0}}} \1 $1 }", &options).unwrap();
+        assert!(matches!(&repl.parts[0], ReplacementPart::BackrefName(s) if s == "name"));
+        assert!(matches!(&repl.parts[2], ReplacementPart::BackrefNumber(2)));
+
+        let mut named = HashMap::new();
+        named.insert("name".to_string(), 1);
+        let result = repl.apply_with_names("ab", 0, 2, &[(0, 1), (1, 2)], &named);
+        assert_eq!(result, r"a -> b {ab} \1 $1 }");
+
+        assert!(Replacement::parse_with("{name", &options).is_err());
+        assert!(Replacement::parse_with("{}", &options).is_err());
+    }
+
+    #[test]
+    fn test_validate_known_groups() {
+        let mut registry = GroupRegistry::new();
+        registry.register_group(None).unwrap();
+        registry.register_group(Some("word".to_string())).unwrap();
+
+        let repl = Replacement::parse("\\G \\1 \\2 \\g{word}").unwrap();
+        assert_eq!(repl.validate(&registry), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_rejects_unknown_groups() {
+        let mut registry = GroupRegistry::new();
+        registry.register_group(Some("word".to_string())).unwrap();
+
+        let repl = Replacement::parse("\\g{wrod}").unwrap();
+        assert_eq!(
+            repl.validate(&registry),
+            Err(ReplacementError::UndefinedGroupName("wrod".to_string()))
+        );
+
+        let repl = Replacement::parse("\\9").unwrap();
+        assert_eq!(
+            repl.validate(&registry),
+            Err(ReplacementError::GroupOutOfRange {
+                index: 9,
+                group_count: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_pair_reports_every_mismatch() {
+        assert!(validate_pair("(a)(word:b)", "\\1 \\g{word}").is_ok());
+
+        match validate_pair("(word:b)", "\\g{wrod} \\1 \\3") {
+            Err(PairError::Replacement(errors)) => assert_eq!(
+                errors,
+                vec![
+                    ReplacementError::UndefinedGroupName("wrod".to_string()),
+                    ReplacementError::GroupOutOfRange {
+                        index: 3,
+                        group_count: 1
+                    },
+                ]
+            ),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        assert!(matches!(
+            validate_pair("(a", "\\1"),
+            Err(PairError::Pattern(_))
+        ));
+    }
+
+    #[test]
+    fn test_escape_sequences_are_literal_by_default() {
+        let repl = Replacement::parse(r"a\nb\t").unwrap();
+        assert_eq!(repl.apply("", 0, 0, &[]), "anbt");
+    }
+
+    #[test]
+    fn test_expand_escapes() {
+        let options = ReplacementOptions::default().expand_escapes(true);
+        let repl = Replacement::parse_with(r"\1\n\t\r\x41\\\q", &options).unwrap();
+        assert_eq!(repl.apply("ab", 0, 2, &[(0, 1)]), "a\n\t\rA\\q");
+
+        let options =
+            ReplacementOptions::with_syntax(ReplacementSyntax::Dollar).expand_escapes(true);
+        let repl = Replacement::parse_with(r"$1\n\1", &options).unwrap();
+        assert_eq!(repl.apply("ab", 0, 2, &[(0, 1)]), "a\n\\1");
+    }
+
+    #[test]
+    fn test_expand_escapes_invalid_hex() {
+        let options = ReplacementOptions::default().expand_escapes(true);
+        assert_eq!(
+            Replacement::parse_with(r"\x4", &options).unwrap_err(),
+            ReplacementError::InvalidEscape(r"\x4".to_string())
+        );
+        assert!(Replacement::parse_with(r"\xZZ", &options).is_err());
+    }
+
+    #[test]
+    fn test_literal_replacement() {
+        let repl = NoExpand(r"\\1 $1 \\G").to_replacement().unwrap();
+        assert_eq!(repl.apply("ab", 0, 2, &[(0, 1)]), r"\\1 $1 \\G");
+        assert!(Replacement::literal("").parts().is_empty());
+    }
+
+    #[test]
+    fn test_escape_round_trips() {
+        let text = r"\\g{name} $1 ${x} $$ \\";
+        let escaped = escape(text);
+
+        let repl = Replacement::parse(&escaped).unwrap();
+        assert_eq!(repl.apply("ab", 0, 2, &[(0, 1)]), text);
+
+        let options = ReplacementOptions::with_syntax(ReplacementSyntax::Mixed);
+        let repl = Replacement::parse_with(&escaped, &options).unwrap();
+        assert_eq!(repl.apply("ab", 0, 2, &[(0, 1)]), text);
+    }
+
+    #[test]
+    fn test_escape_with_round_trips() {
+        let text = r"\g{name} $1 ${x} $& $$ {0} {{ }} \";
+        for syntax in [
+            ReplacementSyntax::Ogex,
+            ReplacementSyntax::Dollar,
+            ReplacementSyntax::Mixed,
+            ReplacementSyntax::Braces,
+        ] {
+            let escaped = escape_with(text, syntax);
+            let options = ReplacementOptions::with_syntax(syntax);
+            let repl = Replacement::parse_with(&escaped, &options).unwrap();
+            assert_eq!(repl.apply("ab", 0, 2, &[(0, 1)]), text, "{:?}", syntax);
+        }
+        assert_eq!(escape_with("$1", ReplacementSyntax::Dollar), "$$1");
+    }
+
     #[test]
     fn test_apply_missing_named_backref() {
         let repl = Replacement::parse("\\g{missing}").unwrap();
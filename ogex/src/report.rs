@@ -0,0 +1,124 @@
+//! [`miette`] integration for rich error reports
+//!
+//! [`RegexError`] implements [`miette::Diagnostic`] with its code and any
+//! suggestion as help text. Spans only point somewhere once the pattern is
+//! attached, so [`RegexError::with_pattern`] wraps the error in a
+//! [`PatternError`] that also labels the offending part of the pattern:
+//!
+//! ```
+//! use ogex::Regex;
+//!
+//! fn compile(pattern: &str) -> miette::Result<Regex> {
+//!     Ok(Regex::new(pattern).map_err(|err| err.with_pattern(pattern))?)
+//! }
+//!
+//! assert!(compile("(a|b").is_err());
+//! ```
+
+use std::fmt;
+
+use miette::{Diagnostic, LabeledSpan, SourceCode};
+
+use crate::error::{RegexError, Span};
+
+impl Diagnostic for RegexError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.code()))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.suggestion()
+            .map(|suggestion| Box::new(format!("did you mean `{}`?", suggestion)) as _)
+    }
+}
+
+/// A [`RegexError`] together with the pattern that caused it
+#[derive(Debug, Clone)]
+pub struct PatternError {
+    /// The pattern that failed to compile
+    pub pattern: String,
+    /// The underlying error
+    pub error: RegexError,
+}
+
+impl RegexError {
+    /// Attach the pattern this error came from, for a report that points
+    /// at the problem
+    pub fn with_pattern(self, pattern: &str) -> PatternError {
+        PatternError {
+            pattern: pattern.to_string(),
+            error: self,
+        }
+    }
+}
+
+impl PatternError {
+    /// Byte offset of a character index into the pattern
+    fn byte_offset(&self, index: usize) -> usize {
+        self.pattern
+            .char_indices()
+            .nth(index)
+            .map_or(self.pattern.len(), |(offset, _)| offset)
+    }
+
+    fn byte_span(&self, span: Span) -> (usize, usize) {
+        let start = self.byte_offset(span.start);
+        (start, self.byte_offset(span.end) - start)
+    }
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for PatternError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl Diagnostic for PatternError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Diagnostic::code(&self.error)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.error.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.pattern)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let span = self.error.span()?;
+        let (offset, len) = self.byte_span(span);
+        let label = LabeledSpan::new(Some(self.error.message()), offset, len);
+        Some(Box::new(std::iter::once(label)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Regex;
+
+    #[test]
+    fn test_pattern_error_labels() {
+        let err = Regex::new("é(a").err().unwrap().with_pattern("é(a");
+        assert_eq!(Diagnostic::code(&err).unwrap().to_string(), "E101");
+
+        let labels: Vec<_> = err.labels().unwrap().collect();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].offset(), 4);
+        assert_eq!(labels[0].label(), Some("expected `)`, found EOF"));
+    }
+
+    #[test]
+    fn test_help_from_suggestion() {
+        let err = Regex::new(r"(year:\d+)\g{yaer}").err().unwrap();
+        assert_eq!(err.help().unwrap().to_string(), "did you mean `\\g{year}`?");
+    }
+}
@@ -0,0 +1,530 @@
+//! Searching for many patterns with one automaton
+//!
+//! A [`Scanner`] compiles a list of patterns into a single NFA, as the
+//! branches of one alternation, so the input is searched once however many
+//! patterns there are. Each match reports which pattern found it along with
+//! that pattern's own captures, numbered and named as if the pattern had
+//! been compiled alone. This is the building block for routers and rule
+//! sets; use [`RegexSet`](crate::RegexSet) to only ask which patterns match.
+//!
+//! The match starting earliest wins; at the same start the longest wins,
+//! then the pattern given first, as [`RegexSet::find_all`] orders them.
+//!
+//! Built from named rules, a scanner also splits text into tokens, as the
+//! first stage of a parser does. [`Scanner::tokens`] takes the token at the
+//! current position and carries on from its end, never skipping text:
+//!
+//! ```
+//! use ogex::Scanner;
+//!
+//! let scanner = Scanner::from_rules([
+//!     ("number", r"\d+"),
+//!     ("name", r"[a-z]+"),
+//!     ("op", r"[=\+]"),
+//!     ("space", r"\s+"),
+//! ])
+//! .unwrap();
+//! let input = "x = y+42";
+//! let tokens: Vec<_> = scanner
+//!     .tokens(input)
+//!     .map(|token| token.map(|token| (token.name.unwrap(), token.captures.as_str(input))))
+//!     .collect::<Result<_, _>>()
+//!     .unwrap();
+//! assert_eq!(
+//!     tokens,
+//!     [
+//!         ("name", "x"),
+//!         ("space", " "),
+//!         ("op", "="),
+//!         ("space", " "),
+//!         ("name", "y"),
+//!         ("op", "+"),
+//!         ("number", "42"),
+//!     ]
+//! );
+//! assert_eq!(scanner.tokens("x ?").nth(2).unwrap().unwrap_err().position, 2);
+//! ```
+//!
+//! [`RegexSet::find_all`]: crate::RegexSet::find_all
+
+use crate::ast::Expr;
+use crate::engine::{Match, Regex, RegexBuilder, next_char_boundary};
+use crate::error::RegexError;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// How one pattern's groups sit in the combined automaton
+struct PatternGroups {
+    /// The pattern compiled alone
+    regex: Regex,
+    /// The rule's name, if it has one
+    name: Option<String>,
+    /// The group wrapping the whole pattern, which is set when it matches;
+    /// the pattern's own group `n` is group `marker + n`
+    marker: u32,
+    /// Number of groups the pattern has
+    count: u32,
+    /// The pattern's group names, by the pattern's own numbers
+    names: Vec<(u32, String)>,
+}
+
+/// Many patterns searched for in one pass
+///
+/// ```
+/// use ogex::Scanner;
+///
+/// let scanner = Scanner::new([r"GET (path:/\S*)", r"(code:\d{3}) (\w+)"]).unwrap();
+/// let input = "GET /index 200 OK";
+/// let found = scanner.find_all(input);
+/// assert_eq!(found.len(), 2);
+///
+/// let (pattern, m) = &found[0];
+/// assert_eq!(*pattern, 0);
+/// assert_eq!(m.named_group_str(input, "path"), Some("/index"));
+///
+/// let (pattern, m) = &found[1];
+/// assert_eq!(*pattern, 1);
+/// assert_eq!(m.group_str(input, 2), Some("OK"));
+/// ```
+pub struct Scanner {
+    /// The combined automaton, or `None` when there are no patterns
+    regex: Option<Regex>,
+    patterns: Vec<PatternGroups>,
+    mode: ScanMode,
+}
+
+/// Which rule [`Scanner::tokens`] picks when several match at a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScanMode {
+    /// The rule with the longest match, and of those the one given first,
+    /// as most lexers do
+    #[default]
+    Longest,
+    /// The first rule given that matches, however short its match
+    Priority,
+}
+
+/// A token found by [`Scanner::tokens`]
+#[derive(Debug, Clone)]
+pub struct Lexeme<'s> {
+    /// Index of the rule that matched
+    pub rule: usize,
+    /// Name of that rule, if it has one
+    pub name: Option<&'s str>,
+    /// The token's span, with the rule's captures numbered and named as if
+    /// the rule had been compiled alone
+    pub captures: Match,
+}
+
+/// Text at which no rule of a [`Scanner`] matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnexpectedInput {
+    /// Where the text starts
+    pub position: usize,
+}
+
+impl fmt::Display for UnexpectedInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no rule matches at position {}", self.position)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnexpectedInput {}
+
+impl Scanner {
+    /// Compile every pattern into one automaton, failing on the first that
+    /// is invalid
+    pub fn new<I, S>(patterns: I) -> Result<Self, RegexError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::build(patterns.into_iter().map(|pattern| (None, pattern)))
+    }
+
+    /// Compile named rules, in order of priority, failing on the first
+    /// pattern that is invalid
+    pub fn from_rules<I, N, S>(rules: I) -> Result<Self, RegexError>
+    where
+        I: IntoIterator<Item = (N, S)>,
+        N: Into<String>,
+        S: AsRef<str>,
+    {
+        Self::build(
+            rules
+                .into_iter()
+                .map(|(name, pattern)| (Some(name.into()), pattern)),
+        )
+    }
+
+    fn build<S: AsRef<str>>(
+        patterns: impl Iterator<Item = (Option<String>, S)>,
+    ) -> Result<Self, RegexError> {
+        let mut sources = Vec::new();
+        let mut branches = Vec::new();
+        let mut groups = Vec::new();
+        let mut next_group = 1;
+        for (index, (name, pattern)) in patterns.enumerate() {
+            let pattern = pattern.as_ref();
+            // Compiling each pattern alone validates it and resolves its
+            // relative backreferences
+            let regex = Regex::new(pattern)?;
+            let ast = crate::parser::parse(pattern)?;
+            let marker = next_group;
+            let count = regex.group_count() as u32;
+            let rebase = Rebase {
+                regex: &regex,
+                index,
+                offset: marker,
+                count,
+            };
+            branches.push(Expr::Group(Box::new(rebase.expr(&ast))));
+            groups.push(PatternGroups {
+                marker,
+                count,
+                names: regex
+                    .group_registry()
+                    .groups()
+                    .iter()
+                    .filter_map(|group| Some((group.index, group.name.clone()?)))
+                    .collect(),
+                regex,
+                name,
+            });
+            sources.push(pattern.to_string());
+            next_group += count + 1;
+        }
+
+        let regex = if branches.is_empty() {
+            None
+        } else {
+            let source = sources.join("|");
+            Some(RegexBuilder::new(&source).build_ast(&Expr::Alternation(branches), None)?)
+        };
+        Ok(Scanner {
+            regex,
+            patterns: groups,
+            mode: ScanMode::default(),
+        })
+    }
+
+    /// Set which rule [`Scanner::tokens`] picks when several match
+    pub fn mode(mut self, mode: ScanMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The name of rule `index`, if it has one
+    pub fn name(&self, index: usize) -> Option<&str> {
+        self.patterns.get(index)?.name.as_deref()
+    }
+
+    /// Number of patterns in the scanner
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    /// Whether the scanner has no patterns
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Find the first match of any pattern, with the index of the pattern
+    /// that found it
+    pub fn find(&self, input: &str) -> Option<(usize, Match)> {
+        self.find_at(input, 0)
+    }
+
+    /// Find the first match of any pattern that starts at or after `start`
+    pub fn find_at(&self, input: &str, start: usize) -> Option<(usize, Match)> {
+        let found = self.regex.as_ref()?.find_at(input, start)?;
+        let index = self
+            .patterns
+            .iter()
+            .position(|pattern| found.group(pattern.marker).is_some())?;
+        Some((index, self.split(index, found)))
+    }
+
+    /// Every non-overlapping match of any pattern, in order, each with the
+    /// index of the pattern that found it
+    pub fn find_all(&self, input: &str) -> Vec<(usize, Match)> {
+        let mut found = Vec::new();
+        let mut pos = 0;
+        while pos <= input.len() {
+            let Some((index, m)) = self.find_at(input, pos) else {
+                break;
+            };
+            // Step past empty matches so the search always makes progress
+            pos = if m.end == m.start {
+                next_char_boundary(input, m.end)
+            } else {
+                m.end
+            };
+            found.push((index, m));
+        }
+        found
+    }
+
+    /// The token starting exactly at `pos`, if a rule matches there
+    ///
+    /// Rules are chosen as the scanner's [`ScanMode`] says. Empty matches
+    /// are not tokens.
+    pub fn token_at(&self, input: &str, pos: usize) -> Option<Lexeme<'_>> {
+        let (rule, captures) = match self.mode {
+            ScanMode::Longest => {
+                let found = self.regex.as_ref()?.match_at(input, pos)?;
+                let rule = self
+                    .patterns
+                    .iter()
+                    .position(|pattern| found.group(pattern.marker).is_some())?;
+                (rule, self.split(rule, found))
+            }
+            ScanMode::Priority => {
+                self.patterns
+                    .iter()
+                    .enumerate()
+                    .find_map(|(rule, pattern)| {
+                        let found = pattern.regex.match_at(input, pos)?;
+                        (found.end > found.start).then_some((rule, found))
+                    })?
+            }
+        };
+        (captures.end > captures.start).then(|| Lexeme {
+            rule,
+            name: self.name(rule),
+            captures,
+        })
+    }
+
+    /// The tokens of `input`, one after another from its start
+    ///
+    /// The iterator stops after the first position where no rule matches,
+    /// reporting it as an error.
+    pub fn tokens<'s, 'h>(&'s self, input: &'h str) -> Tokens<'s, 'h> {
+        Tokens {
+            scanner: self,
+            input,
+            pos: 0,
+            failed: false,
+        }
+    }
+
+    /// Renumber a match of the combined automaton as a match of pattern
+    /// `index` alone
+    fn split(&self, index: usize, combined: Match) -> Match {
+        let pattern = &self.patterns[index];
+        let groups: Vec<_> = (0..=pattern.count)
+            .map(|n| match n {
+                0 => None,
+                n => combined.group(pattern.marker + n),
+            })
+            .collect();
+        let named_groups = pattern
+            .names
+            .iter()
+            .filter_map(|(n, name)| Some((name.clone(), groups[*n as usize]?)))
+            .collect();
+        Match {
+            start: combined.start,
+            end: combined.end,
+            groups,
+            named_groups,
+        }
+    }
+}
+
+/// Iterator over the tokens of an input
+///
+/// Created by [`Scanner::tokens`].
+pub struct Tokens<'s, 'h> {
+    scanner: &'s Scanner,
+    input: &'h str,
+    pos: usize,
+    failed: bool,
+}
+
+impl<'s> Iterator for Tokens<'s, '_> {
+    type Item = Result<Lexeme<'s>, UnexpectedInput>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.pos >= self.input.len() {
+            return None;
+        }
+        match self.scanner.token_at(self.input, self.pos) {
+            Some(token) => {
+                self.pos = token.captures.end;
+                Some(Ok(token))
+            }
+            None => {
+                self.failed = true;
+                Some(Err(UnexpectedInput { position: self.pos }))
+            }
+        }
+    }
+}
+
+impl core::iter::FusedIterator for Tokens<'_, '_> {}
+
+/// Rewrites one pattern's syntax tree to sit inside the combined automaton
+struct Rebase<'a> {
+    /// The pattern compiled alone
+    regex: &'a Regex,
+    /// Position of the pattern in the scanner, to keep group names apart
+    index: usize,
+    /// Added to each of the pattern's group numbers
+    offset: u32,
+    /// Number of groups the pattern has
+    count: u32,
+}
+
+impl Rebase<'_> {
+    fn expr(&self, expr: &Expr) -> Expr {
+        let boxed = |inner: &Expr| Box::new(self.expr(inner));
+        match expr {
+            Expr::Sequence(exprs) => Expr::Sequence(exprs.iter().map(|e| self.expr(e)).collect()),
+            Expr::Alternation(exprs) => {
+                Expr::Alternation(exprs.iter().map(|e| self.expr(e)).collect())
+            }
+            Expr::Quantified {
+                expr,
+                quantifier,
+                greedy,
+            } => Expr::Quantified {
+                expr: boxed(expr),
+                quantifier: *quantifier,
+                greedy: *greedy,
+            },
+            Expr::Group(inner) => Expr::Group(boxed(inner)),
+            Expr::NonCapturingGroup(inner) => Expr::NonCapturingGroup(boxed(inner)),
+            Expr::AtomicGroup(inner) => Expr::AtomicGroup(boxed(inner)),
+            Expr::ConditionalGroup(inner) => Expr::ConditionalGroup(boxed(inner)),
+            Expr::ModeFlagsGroup { flags, pattern } => Expr::ModeFlagsGroup {
+                flags: flags.clone(),
+                pattern: boxed(pattern),
+            },
+            Expr::NamedGroup { name, pattern } => Expr::NamedGroup {
+                name: self.name(name),
+                pattern: boxed(pattern),
+            },
+            Expr::Backreference(n) => self.backref(Some(*n)),
+            Expr::RelativeBackreference(n) => self.backref(self.regex.nfa().resolve_relative(*n)),
+            Expr::NamedBackreference(name) => Expr::NamedBackreference(self.name(name)),
+            // Lookarounds are compiled into NFAs of their own, with their
+            // own groups, so they are left as they are
+            _ => expr.clone(),
+        }
+    }
+
+    /// A backreference to the pattern's group `n`; one to a group that does
+    /// not exist becomes group 0, which never matches
+    fn backref(&self, n: Option<u32>) -> Expr {
+        match n {
+            Some(n) if (1..=self.count).contains(&n) => Expr::Backreference(self.offset + n),
+            _ => Expr::Backreference(0),
+        }
+    }
+
+    /// A group name that cannot clash with another pattern's
+    fn name(&self, name: &str) -> String {
+        format!("{}#{}", self.index, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(patterns: &[&str], input: &str) -> Vec<(usize, usize, usize)> {
+        Scanner::new(patterns)
+            .unwrap()
+            .find_all(input)
+            .into_iter()
+            .map(|(index, m)| (index, m.start, m.end))
+            .collect()
+    }
+
+    #[test]
+    fn test_reports_the_pattern_that_matched() {
+        assert_eq!(
+            scan(&[r"\d+", "[a-z]+", "!"], "ab 12!"),
+            vec![(1, 0, 2), (0, 3, 5), (2, 5, 6)]
+        );
+        // At the same start the longest match wins, then the pattern given
+        // first
+        assert_eq!(scan(&["ab", "abc"], "abc"), vec![(1, 0, 3)]);
+        assert_eq!(scan(&["a.", ".b"], "ab"), vec![(0, 0, 2)]);
+    }
+
+    #[test]
+    fn test_captures_are_per_pattern() {
+        let scanner = Scanner::new([r"(a)(key:b)", r"(key:\d)(\d)"]).unwrap();
+        let input = "ab 12";
+        let found = scanner.find_all(input);
+        assert_eq!(found[0].1.group(1), Some((0, 1)));
+        assert_eq!(found[0].1.named_group("key"), Some((1, 2)));
+        assert_eq!(found[1].0, 1);
+        assert_eq!(found[1].1.named_group_str(input, "key"), Some("1"));
+        assert_eq!(found[1].1.group_str(input, 2), Some("2"));
+        assert_eq!(found[1].1.group(3), None);
+    }
+
+    #[test]
+    fn test_backreferences_stay_within_their_pattern() {
+        assert_eq!(
+            scan(&[r"(x)\1", r"(w:[a-z])\g{w}", r"(\d)\g{-1}"], "xx qq 77 xy"),
+            vec![(0, 0, 2), (1, 3, 5), (2, 6, 8)]
+        );
+    }
+
+    #[test]
+    fn test_tokens_by_longest_match_or_priority() {
+        let rules = [("keyword", "if|else"), ("ident", r"\w+"), ("space", " ")];
+        let names = |scanner: &Scanner, input: &str| -> Vec<String> {
+            scanner
+                .tokens(input)
+                .map(|token| token.unwrap().name.unwrap().to_string())
+                .collect()
+        };
+        let longest = Scanner::from_rules(rules).unwrap();
+        assert_eq!(names(&longest, "if iffy"), ["keyword", "space", "ident"]);
+        let priority = Scanner::from_rules(rules).unwrap().mode(ScanMode::Priority);
+        assert_eq!(
+            names(&priority, "if iffy"),
+            ["keyword", "space", "keyword", "ident"]
+        );
+        assert_eq!(priority.name(1), Some("ident"));
+        assert_eq!(priority.name(3), None);
+    }
+
+    #[test]
+    fn test_tokens_never_skip_text() {
+        let scanner = Scanner::from_rules([("digits", r"\d+"), ("maybe", "x*")]).unwrap();
+        let input = "12?3";
+        let mut tokens = scanner.tokens(input);
+        let first = tokens.next().unwrap().unwrap();
+        assert_eq!((first.rule, first.captures.as_str(input)), (0, "12"));
+        // `x*` matches the empty string, which is not a token
+        assert_eq!(
+            tokens.next().unwrap().unwrap_err(),
+            UnexpectedInput { position: 2 }
+        );
+        assert!(tokens.next().is_none());
+
+        let scanner = Scanner::from_rules([("pair", r"(a:\w)=(b:\w)")]).unwrap();
+        let token = scanner.token_at("x k=v", 2).unwrap();
+        assert_eq!(token.captures.named_group("b"), Some((4, 5)));
+        assert!(scanner.token_at("x k=v", 1).is_none());
+    }
+
+    #[test]
+    fn test_invalid_and_empty() {
+        assert!(Scanner::new(["a", "(b"]).is_err());
+        let scanner = Scanner::new(Vec::<&str>::new()).unwrap();
+        assert!(scanner.is_empty());
+        assert!(scanner.find("abc").is_none());
+    }
+}
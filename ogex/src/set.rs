@@ -0,0 +1,134 @@
+//! Matching several patterns at once
+//!
+//! [`RegexSet`] answers which of a list of patterns match an input, as used
+//! by `grep -e` style searches. Each pattern is compiled and run on its own,
+//! so a set costs about as much as searching with each pattern in turn.
+
+use crate::engine::{Match, Regex};
+use crate::error::RegexError;
+use alloc::vec::Vec;
+
+/// A list of patterns searched together
+///
+/// ```
+/// use ogex::RegexSet;
+///
+/// let set = RegexSet::new([r"\d+", "[a-z]+", "!"]).unwrap();
+/// assert_eq!(set.matches("abc 42"), vec![0, 1]);
+/// assert!(!set.is_match("?"));
+/// ```
+pub struct RegexSet {
+    regexes: Vec<Regex>,
+}
+
+impl RegexSet {
+    /// Compile every pattern, failing on the first that is invalid
+    pub fn new<I, S>(patterns: I) -> Result<Self, RegexError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let regexes = patterns
+            .into_iter()
+            .map(|pattern| Regex::new(pattern.as_ref()))
+            .collect::<Result<_, _>>()?;
+        Ok(RegexSet { regexes })
+    }
+
+    /// Number of patterns in the set
+    pub fn len(&self) -> usize {
+        self.regexes.len()
+    }
+
+    /// Whether the set has no patterns
+    pub fn is_empty(&self) -> bool {
+        self.regexes.is_empty()
+    }
+
+    /// The compiled patterns, in the order they were given
+    pub fn regexes(&self) -> &[Regex] {
+        &self.regexes
+    }
+
+    /// Whether any pattern matches the input
+    pub fn is_match(&self, input: &str) -> bool {
+        self.regexes.iter().any(|regex| regex.is_match(input))
+    }
+
+    /// Indices of the patterns that match the input, in ascending order
+    pub fn matches(&self, input: &str) -> Vec<usize> {
+        (0..self.regexes.len())
+            .filter(|&i| self.regexes[i].is_match(input))
+            .collect()
+    }
+
+    /// Non-overlapping matches of any pattern, each with the index of the
+    /// pattern that found it
+    ///
+    /// Matches are ordered by position. Where matches of different patterns
+    /// overlap, the one starting first wins, then the longest, then the
+    /// pattern given first.
+    pub fn find_all(&self, input: &str) -> Vec<(usize, Match)> {
+        let mut candidates: Vec<(usize, Match)> = (0..self.regexes.len())
+            .flat_map(|i| self.regexes[i].find_iter(input).map(move |m| (i, m)))
+            .collect();
+        candidates.sort_by_key(|(i, m)| (m.start, core::cmp::Reverse(m.end), *i));
+
+        let mut found: Vec<(usize, Match)> = Vec::new();
+        for (i, m) in candidates {
+            let keep = match found.last() {
+                None => true,
+                // An empty match may follow a match ending where it starts,
+                // but not another empty match at the same place
+                Some((_, last)) => {
+                    m.start > last.end || m.start == last.end && last.start < last.end
+                }
+            };
+            if keep {
+                found.push((i, m));
+            }
+        }
+        found
+    }
+}
+
+impl From<Vec<Regex>> for RegexSet {
+    fn from(regexes: Vec<Regex>) -> Self {
+        RegexSet { regexes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let set = RegexSet::new(["foo", r"\d", "^x"]).unwrap();
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.matches("foo 1"), vec![0, 1]);
+        assert_eq!(set.matches("xfoo"), vec![0, 2]);
+        assert!(set.matches("bar").is_empty());
+        assert!(set.is_match("x"));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(RegexSet::new(["a", "(b"]).is_err());
+        assert!(RegexSet::new(Vec::<&str>::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_find_all_drops_overlaps() {
+        let set = RegexSet::new(["ab", "abc", "c", r"\d*"]).unwrap();
+        let found: Vec<_> = set
+            .find_all("abc c1")
+            .into_iter()
+            .map(|(i, m)| (i, m.start, m.end))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(1, 0, 3), (3, 3, 3), (2, 4, 5), (3, 5, 6), (3, 6, 6)]
+        );
+    }
+}
@@ -0,0 +1,597 @@
+//! Matching over input that arrives in chunks
+//!
+//! [`StreamMatcher`] buffers text until it holds a complete line and then
+//! searches that line, so memory use is bounded by the longest line rather
+//! than the whole input. Like [`Regex::replace_all_reader_to`], it searches
+//! line by line: a match never spans a line break.
+//!
+//! [`StreamMatcher::spanning`] lets matches span line breaks instead. The
+//! matcher then keeps text only while a match could still start in it, so
+//! memory use is bounded by the longest line plus the longest match in
+//! progress.
+//!
+//! With the `async` feature, [`Regex::find_stream`] runs a stream matcher
+//! over a tokio reader and yields the matches as a [`Stream`].
+//!
+//! [`Stream`]: futures_core::Stream
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+#[cfg(feature = "async")]
+use std::collections::VecDeque;
+#[cfg(feature = "async")]
+use std::io;
+#[cfg(feature = "async")]
+use std::pin::Pin;
+#[cfg(feature = "async")]
+use std::task::{Context, Poll, ready};
+
+#[cfg(feature = "async")]
+use futures_core::Stream;
+#[cfg(feature = "async")]
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::engine::{Match, Regex, next_char_boundary};
+use crate::error::RegexError;
+
+/// A match found by a [`StreamMatcher`]
+#[derive(Debug, Clone)]
+pub struct StreamMatch {
+    /// Byte offset of the line containing the match within the stream
+    pub line_offset: usize,
+    /// Number of line breaks in the stream before `line_offset`
+    pub line_number: usize,
+    /// The line containing the match, including its line break
+    ///
+    /// For a [spanning](StreamMatcher::spanning) matcher, this is the
+    /// buffered text containing the match instead, which may hold several
+    /// lines and starts at or just before a line break.
+    pub line: Arc<str>,
+    /// The match, with positions relative to the start of `line`
+    pub match_result: Match,
+}
+
+impl StreamMatch {
+    /// Byte offset of the start of the match within the stream
+    pub fn start(&self) -> usize {
+        self.line_offset + self.match_result.start
+    }
+
+    /// Byte offset of the end of the match within the stream
+    pub fn end(&self) -> usize {
+        self.line_offset + self.match_result.end
+    }
+
+    /// The matched text
+    pub fn as_str(&self) -> &str {
+        self.match_result.as_str(&self.line)
+    }
+}
+
+/// Finds matches in text that is fed in pieces
+///
+/// `R` is anything that borrows a [`Regex`], such as `&Regex` or
+/// `Rc<Regex>`.
+///
+/// ```
+/// use ogex::{Regex, StreamMatcher};
+///
+/// let regex = Regex::new(r"\d+").unwrap();
+/// let mut stream = StreamMatcher::new(&regex);
+/// assert!(stream.push("order 4").unwrap().is_empty());
+/// let found = stream.push("2 shipped\nnone\n").unwrap();
+/// assert_eq!(found[0].as_str(), "42");
+/// assert_eq!(found[0].start(), 6);
+/// assert!(stream.finish().unwrap().is_empty());
+/// ```
+pub struct StreamMatcher<R> {
+    regex: R,
+    /// Text of the current, incomplete line, or for a spanning matcher the
+    /// text a match may still use
+    pending: String,
+    /// Byte offset of `pending` within the stream
+    offset: usize,
+    /// Number of line breaks before `pending`
+    lines: usize,
+    /// Whether matches may span line breaks
+    spanning: bool,
+    /// Where the next search of `pending` starts, for a spanning matcher
+    pos: usize,
+    /// Length of `pending` after the last search, for a spanning matcher
+    searched: usize,
+}
+
+impl<R: Borrow<Regex>> StreamMatcher<R> {
+    /// Start matching a new stream
+    pub fn new(regex: R) -> Self {
+        StreamMatcher {
+            regex,
+            pending: String::new(),
+            offset: 0,
+            lines: 0,
+            spanning: false,
+            pos: 0,
+            searched: 0,
+        }
+    }
+
+    /// Let matches span line breaks
+    ///
+    /// A match is returned once no more text could change it, which for a
+    /// pattern such as `a.*` may not be until the stream ends. Text that has
+    /// to be kept is only searched again once as much again has been pushed,
+    /// so a match may be returned a few chunks after it settles. Lookbehinds
+    /// see back only as far as the buffered text, which starts at the line
+    /// break before the earliest place a match could still start.
+    ///
+    /// ```
+    /// use ogex::{RegexBuilder, StreamMatcher};
+    ///
+    /// let regex = RegexBuilder::new(r"\{[^\}]*\}").build().unwrap();
+    /// let mut stream = StreamMatcher::new(&regex).spanning(true);
+    /// assert!(stream.push("a {\nb").unwrap().is_empty());
+    /// let found = stream.push("} and more\n").unwrap();
+    /// assert_eq!(found[0].as_str(), "{\nb}");
+    /// assert_eq!((found[0].start(), found[0].line_number), (2, 0));
+    /// ```
+    pub fn spanning(mut self, yes: bool) -> Self {
+        self.spanning = yes;
+        self
+    }
+
+    /// Byte offset in the stream before which no further match can start
+    pub fn settled(&self) -> usize {
+        match self.spanning {
+            true => self.offset + self.pos,
+            false => self.offset,
+        }
+    }
+
+    /// Feed the next chunk of the stream, returning the matches in every
+    /// line it completes, or for a spanning matcher every match it settles
+    pub fn push(&mut self, chunk: &str) -> Result<Vec<StreamMatch>, RegexError> {
+        self.pending.push_str(chunk);
+        if self.spanning {
+            let mut found = Vec::new();
+            // Searching the kept text again only once it has doubled bounds
+            // the total work by twice that of searching the whole stream
+            if self.pending.len() >= 2 * self.searched {
+                self.search_spanning(false, &mut found)?;
+            }
+            return Ok(found);
+        }
+        let Some(last_break) = self.pending.rfind('\n') else {
+            return Ok(Vec::new());
+        };
+
+        let rest = self.pending.split_off(last_break + 1);
+        let complete = core::mem::replace(&mut self.pending, rest);
+        let mut found = Vec::new();
+        for line in complete.split_inclusive('\n') {
+            self.search_line(line, &mut found)?;
+        }
+        Ok(found)
+    }
+
+    /// End the stream, returning the matches in its final line if it did not
+    /// end with a line break
+    pub fn finish(mut self) -> Result<Vec<StreamMatch>, RegexError> {
+        let mut found = Vec::new();
+        if self.spanning {
+            self.search_spanning(true, &mut found)?;
+        } else if !self.pending.is_empty() {
+            let line = core::mem::take(&mut self.pending);
+            self.search_line(&line, &mut found)?;
+        }
+        Ok(found)
+    }
+
+    /// Collect the matches in one complete line and advance past it
+    fn search_line(&mut self, line: &str, found: &mut Vec<StreamMatch>) -> Result<(), RegexError> {
+        let regex = self.regex.borrow();
+        let shared: Arc<str> = Arc::from(line);
+        let mut pos = 0;
+        while pos <= line.len() {
+            let Some(match_result) = regex.try_find_at(line, pos)? else {
+                break;
+            };
+            // Step past empty matches so the search always makes progress
+            pos = if match_result.end == match_result.start {
+                next_char_boundary(line, match_result.end)
+            } else {
+                match_result.end
+            };
+            found.push(StreamMatch {
+                line_offset: self.offset,
+                line_number: self.lines,
+                line: Arc::clone(&shared),
+                match_result,
+            });
+        }
+        self.offset += line.len();
+        self.lines += usize::from(line.ends_with('\n'));
+        Ok(())
+    }
+
+    /// Collect the settled matches in the buffered text, or every match at
+    /// the end of the stream, and drop the text no match can use
+    fn search_spanning(
+        &mut self,
+        at_end: bool,
+        found: &mut Vec<StreamMatch>,
+    ) -> Result<(), RegexError> {
+        let regex = self.regex.borrow();
+        let text = self.pending.as_str();
+        // Matches starting before this are settled
+        let unsettled = match at_end {
+            true => None,
+            false => crate::partial::earliest_partial_start(regex, text, self.pos),
+        };
+        let mut shared: Option<Arc<str>> = None;
+        let mut pos = self.pos;
+        while pos <= text.len() {
+            let Some(match_result) = regex.try_find_at(text, pos)? else {
+                if !at_end {
+                    pos = pos.max(unsettled.unwrap_or(text.len()));
+                }
+                break;
+            };
+            // A match reaching the end may still grow, or fail an assertion
+            // about the text after it
+            if !at_end
+                && (match_result.end == text.len()
+                    || unsettled.is_some_and(|start| start <= match_result.start))
+            {
+                pos = pos.max(unsettled.unwrap_or(text.len()).min(match_result.start));
+                break;
+            }
+            pos = if match_result.end == match_result.start {
+                next_char_boundary(text, match_result.end)
+            } else {
+                match_result.end
+            };
+            found.push(StreamMatch {
+                line_offset: self.offset,
+                line_number: self.lines,
+                line: Arc::clone(shared.get_or_insert_with(|| Arc::from(text))),
+                match_result,
+            });
+        }
+        self.pos = pos;
+
+        if !at_end {
+            // Keep the line break before the first text still needed, so `^`
+            // and `\b` see what precedes it
+            let cut = self.pending[..pos].rfind('\n').unwrap_or(0);
+            self.lines += self.pending[..cut].matches('\n').count();
+            self.pending.drain(..cut);
+            self.offset += cut;
+            self.pos -= cut;
+            self.searched = self.pending.len();
+        }
+        Ok(())
+    }
+}
+
+/// Bytes read from the reader at a time
+#[cfg(feature = "async")]
+const READ_SIZE: usize = 8 * 1024;
+
+/// The matches in text read from an [`AsyncRead`], created by
+/// [`Regex::find_stream`]
+///
+/// Each match is yielded once the line containing it has been read, with
+/// offsets counted from the start of the stream. Reading fails with
+/// [`io::ErrorKind::InvalidData`] on text that is not UTF-8, and a regex
+/// error such as an exceeded step limit is reported as an
+/// [`io::ErrorKind::Other`] error; the stream ends after any error.
+///
+/// ```
+/// use ogex::Regex;
+/// use tokio_stream::StreamExt;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let regex = Regex::new(r"ERROR (code:\d+)").unwrap();
+/// let log: &[u8] = b"INFO ok\nERROR 42 disk\nERROR 7\n";
+/// let mut matches = regex.find_stream(log);
+///
+/// let first = matches.next().await.unwrap().unwrap();
+/// assert_eq!((first.start(), first.as_str()), (8, "ERROR 42"));
+/// let second = matches.next().await.unwrap().unwrap();
+/// assert_eq!(second.start(), 22);
+/// assert!(matches.next().await.is_none());
+/// # }
+/// ```
+#[cfg(feature = "async")]
+pub struct FindStream<'r, R> {
+    reader: R,
+    /// The line matcher, or `None` once the stream has ended
+    matcher: Option<StreamMatcher<&'r Regex>>,
+    buffer: Box<[u8]>,
+    /// Bytes read but not yet decoded, at most an incomplete character
+    partial: Vec<u8>,
+    /// Matches found but not yet yielded
+    found: VecDeque<StreamMatch>,
+}
+
+#[cfg(feature = "async")]
+impl<'r, R: AsyncRead + Unpin> FindStream<'r, R> {
+    pub(crate) fn new(regex: &'r Regex, reader: R) -> Self {
+        FindStream {
+            reader,
+            matcher: Some(StreamMatcher::new(regex)),
+            buffer: vec![0; READ_SIZE].into_boxed_slice(),
+            partial: Vec::new(),
+            found: VecDeque::new(),
+        }
+    }
+
+    /// Feed newly read bytes to the matcher, keeping back a character split
+    /// across reads
+    fn push_bytes(&mut self, filled: usize) -> io::Result<()> {
+        self.partial.extend_from_slice(&self.buffer[..filled]);
+        let valid = match core::str::from_utf8(&self.partial) {
+            Ok(text) => text.len(),
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        };
+        let text = core::str::from_utf8(&self.partial[..valid]).expect("validated above");
+        if let Some(matcher) = self.matcher.as_mut() {
+            self.found
+                .extend(matcher.push(text).map_err(io::Error::other)?);
+        }
+        self.partial.drain(..valid);
+        Ok(())
+    }
+
+    /// End the stream, collecting the matches in its final line
+    fn finish(&mut self) -> io::Result<()> {
+        if !self.partial.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream ended inside a UTF-8 character",
+            ));
+        }
+        if let Some(matcher) = self.matcher.take() {
+            self.found
+                .extend(matcher.finish().map_err(io::Error::other)?);
+        }
+        Ok(())
+    }
+
+    /// Read until there is a match to yield or the stream ends
+    fn poll_fill(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.found.is_empty() && self.matcher.is_some() {
+            let mut read = ReadBuf::new(&mut self.buffer);
+            ready!(Pin::new(&mut self.reader).poll_read(cx, &mut read))?;
+            match read.filled().len() {
+                0 => self.finish()?,
+                filled => self.push_bytes(filled)?,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "async")]
+impl<R: AsyncRead + Unpin> Stream for FindStream<'_, R> {
+    type Item = io::Result<StreamMatch>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Err(err) = ready!(this.poll_fill(cx)) {
+            this.matcher = None;
+            this.found.clear();
+            return Poll::Ready(Some(Err(err)));
+        }
+        Poll::Ready(this.found.pop_front().map(Ok))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(found: &[StreamMatch]) -> Vec<(usize, usize)> {
+        found.iter().map(|m| (m.start(), m.end())).collect()
+    }
+
+    #[test]
+    fn test_matches_split_across_chunks() {
+        let regex = Regex::new(r"(word:[a-z]+)=\d+").unwrap();
+        let mut stream = StreamMatcher::new(&regex);
+        let mut found = Vec::new();
+        for chunk in ["a=1 bc", "=22\nd", "ef=3", "\n", "g=4"] {
+            found.extend(stream.push(chunk).unwrap());
+        }
+        found.extend(stream.finish().unwrap());
+
+        let texts: Vec<_> = found.iter().map(StreamMatch::as_str).collect();
+        assert_eq!(texts, vec!["a=1", "bc=22", "def=3", "g=4"]);
+        assert_eq!(spans(&found), vec![(0, 3), (4, 9), (10, 15), (16, 19)]);
+        assert_eq!(
+            found[2]
+                .match_result
+                .named_group_str(&found[2].line, "word"),
+            Some("def")
+        );
+    }
+
+    #[test]
+    fn test_matches_do_not_span_lines() {
+        let regex = Regex::new(r"a\sb").unwrap();
+        let mut stream = StreamMatcher::new(&regex);
+        assert!(stream.push("a\nb a b").unwrap().is_empty());
+        assert_eq!(spans(&stream.finish().unwrap()), vec![(4, 7)]);
+    }
+
+    #[test]
+    fn test_empty_matches_advance() {
+        let regex = Regex::new("x*").unwrap();
+        let mut stream = StreamMatcher::new(&regex);
+        let found = stream.push("éx\n").unwrap();
+        assert_eq!(spans(&found), vec![(0, 0), (2, 3), (3, 3), (4, 4)]);
+    }
+
+    #[test]
+    fn test_step_limit_is_reported() {
+        let regex = crate::RegexBuilder::new("a+b")
+            .step_limit(10)
+            .build()
+            .unwrap();
+        let mut stream = StreamMatcher::new(regex);
+        assert!(stream.push(&"a".repeat(100)).unwrap().is_empty());
+        assert!(stream.finish().is_err());
+    }
+
+    #[test]
+    fn test_spanning_matches_cross_lines() {
+        let regex = Regex::new(r"\{[^\}]*\}").unwrap();
+        let mut stream = StreamMatcher::new(&regex).spanning(true);
+        let mut found = Vec::new();
+        for chunk in ["a {x", "\ny} {", "z}\n{", "open"] {
+            found.extend(stream.push(chunk).unwrap());
+        }
+        found.extend(stream.finish().unwrap());
+
+        assert_eq!(spans(&found), vec![(2, 7), (8, 11)]);
+        assert_eq!(found[0].as_str(), "{x\ny}");
+        let lines: Vec<_> = found
+            .iter()
+            .map(|m| m.line_number + m.line[..m.match_result.start].matches('\n').count())
+            .collect();
+        assert_eq!(lines, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_spanning_agrees_with_whole_input() {
+        let input = "foo {a\nb} aab\nfoox foo\né ab x\n\nzz";
+        let patterns = [
+            r"\{[^\}]*\}",
+            "a+",
+            "x*",
+            r"\bfoo\b",
+            r"(<:a)b",
+            r"\w+$",
+            r"b\s+f",
+        ];
+        for pattern in patterns {
+            let regex = crate::RegexBuilder::new(pattern)
+                .multiline(true)
+                .build()
+                .unwrap();
+            let whole: Vec<_> = regex.find_iter(input).map(|m| (m.start, m.end)).collect();
+            let boundaries = (0..=input.len()).filter(|&i| input.is_char_boundary(i));
+            for split in boundaries {
+                let mut stream = StreamMatcher::new(&regex).spanning(true);
+                let mut found = stream.push(&input[..split]).unwrap();
+                found.extend(stream.push(&input[split..]).unwrap());
+                found.extend(stream.finish().unwrap());
+                assert_eq!(spans(&found), whole, "{pattern} split at {split}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_spanning_waits_for_matches_that_may_grow() {
+        let regex = Regex::new("a+").unwrap();
+        let mut stream = StreamMatcher::new(&regex).spanning(true);
+        assert!(stream.push("xaa").unwrap().is_empty());
+        assert_eq!(stream.settled(), 1);
+        assert_eq!(spans(&stream.push("a b").unwrap()), vec![(1, 4)]);
+        assert_eq!(stream.settled(), 6);
+    }
+
+    #[test]
+    fn test_spanning_drops_settled_text() {
+        let regex = Regex::new(r"\{[^\}]*\}").unwrap();
+        let mut stream = StreamMatcher::new(&regex).spanning(true);
+        let mut count = 0;
+        for _ in 0..1000 {
+            count += stream.push("{a} xx\n").unwrap().len();
+        }
+        assert_eq!(count, 1000);
+        assert!(stream.pending.len() < 100);
+    }
+
+    #[test]
+    fn test_spanning_long_match() {
+        let regex = Regex::new(r"\{[^\}]*\}").unwrap();
+        let mut stream = StreamMatcher::new(&regex).spanning(true);
+        let mut found = stream.push("{").unwrap();
+        let line = format!("{}\n", "x".repeat(99));
+        for _ in 0..100 {
+            found.extend(stream.push(&line).unwrap());
+        }
+        found.extend(stream.push("} {y}").unwrap());
+        found.extend(stream.finish().unwrap());
+        assert_eq!(spans(&found), vec![(0, 10_002), (10_003, 10_006)]);
+    }
+
+    /// A reader that hands out one byte per read
+    #[cfg(feature = "async")]
+    struct Trickle<'a>(&'a [u8]);
+
+    #[cfg(feature = "async")]
+    impl AsyncRead for Trickle<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            if let Some((first, rest)) = self.0.split_first() {
+                buf.put_slice(&[*first]);
+                self.0 = rest;
+            }
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[cfg(feature = "async")]
+    async fn collect_stream(pattern: &str, input: &[u8]) -> Vec<io::Result<(usize, String)>> {
+        use tokio_stream::StreamExt;
+
+        let regex = Regex::new(pattern).unwrap();
+        regex
+            .find_stream(Trickle(input))
+            .map(|found| found.map(|m| (m.start(), m.as_str().to_string())))
+            .collect()
+            .await
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_find_stream_across_reads() {
+        let found = collect_stream(r"\w+=.+", "a=é\nbc=éé\nx=é".as_bytes()).await;
+        let found: Vec<_> = found.into_iter().map(Result::unwrap).collect();
+        assert_eq!(
+            found,
+            vec![
+                (0, "a=é".to_string()),
+                (5, "bc=éé".to_string()),
+                (13, "x=é".to_string())
+            ]
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_find_stream_rejects_invalid_utf8() {
+        let found = collect_stream("a", b"a\n\xffa\n").await;
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].as_ref().unwrap().0, 0);
+        assert_eq!(
+            found[1].as_ref().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let found = collect_stream("a", "aé".as_bytes().split_last().unwrap().1).await;
+        assert_eq!(
+            found.last().unwrap().as_ref().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! "Did you mean" suggestions for common pattern mistakes
+//!
+//! These helpers look at the text around an error and propose the spelling
+//! the user most likely meant: the Ogex form of a construct written in
+//! another flavor's syntax, an escaped brace, or the closest group name to
+//! a misspelled backreference.
+
+use crate::groups::GroupRegistry;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+/// Constructs from other flavors that Ogex spells differently, with the
+/// Ogex spelling
+const FOREIGN_SYNTAX: &[(&str, &str)] = &[
+    ("(?<=", "(<:"),
+    ("(?<!", "(<~:"),
+    ("(?=", "(>:"),
+    ("(?!", "(>~:"),
+    ("(?>", "(@*:"),
+];
+
+/// Number of single-character edits needed to turn `a` into `b`, counting
+/// a swap of two adjacent characters as one edit
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// The candidate closest to `target`, if it is close enough to be a typo
+pub(crate) fn closest_name<'a>(
+    target: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let limit = (target.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(target, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// A backreference to the group whose name is closest to `name`
+pub(crate) fn backreference(name: &str, registry: &GroupRegistry) -> Option<String> {
+    let names = registry.groups().iter().filter_map(|g| g.name.as_deref());
+    closest_name(name, names).map(|closest| format!("\\g{{{}}}", closest))
+}
+
+/// The Ogex spelling of a construct from another flavor at the start of
+/// `rest`, which begins at an opening paren
+pub(crate) fn foreign_syntax(rest: &str) -> Option<String> {
+    if let Some(name) = rest.strip_prefix("(?P=") {
+        let name: String = name
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+        return Some(format!("\\g{{{}}}", name));
+    }
+    if let Some(name) = rest
+        .strip_prefix("(?P<")
+        .or_else(|| rest.strip_prefix("(?<"))
+    {
+        let name: String = name
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || *c == '_')
+            .collect();
+        if !name.is_empty() {
+            return Some(format!("({}:", name));
+        }
+    }
+    FOREIGN_SYNTAX
+        .iter()
+        .find(|(foreign, _)| rest.starts_with(foreign))
+        .map(|(_, ogex)| ogex.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("name", "name"), 0);
+        assert_eq!(edit_distance("nmae", "name"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("nam", "name"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_closest_name() {
+        let names = ["year", "month", "day"];
+        assert_eq!(closest_name("yaer", names), Some("year"));
+        assert_eq!(closest_name("mnth", names), Some("month"));
+        assert_eq!(closest_name("dya", names), Some("day"));
+        assert_eq!(closest_name("second", names), None);
+    }
+
+    #[test]
+    fn test_foreign_syntax() {
+        assert_eq!(foreign_syntax("(?=x)").as_deref(), Some("(>:"));
+        assert_eq!(foreign_syntax("(?<!x)").as_deref(), Some("(<~:"));
+        assert_eq!(foreign_syntax("(?P=word)").as_deref(), Some("\\g{word}"));
+        assert_eq!(foreign_syntax("(?P<word x)").as_deref(), Some("(word:"));
+        assert_eq!(foreign_syntax("(abc)"), None);
+    }
+}
@@ -0,0 +1,261 @@
+//! Step-by-step reports of how the matcher runs
+//!
+//! The NFA simulation can report every step it takes to a [`TraceSink`] as
+//! a [`TraceEvent`]: each attempt, the states entered and alive at each
+//! position, the transitions that consume input, the backreferences tried,
+//! the groups that open and close and the assertions that pass or fail.
+//! This shows exactly where and why a match fails.
+//!
+//! [`Regex::trace`](crate::Regex::trace) traces a single search.
+//! [`RegexBuilder::trace_sink`](crate::RegexBuilder::trace_sink) attaches a
+//! sink to a regex, so that every search it runs is reported, which suits
+//! debuggers and other tools that watch a regex in use.
+//!
+//! Positions are in the same units as [`Match`](crate::Match) offsets.
+//! Lookarounds are reported as single assertions; the steps inside them are
+//! not traced.
+//!
+//! For cost rather than steps, the `tracing` feature reports to the
+//! [`tracing`](https://docs.rs/tracing) crate. Each compile runs in a
+//! `compile` span at debug level, recording the pattern, with `parsed` and
+//! `built automaton` events giving the time each stage took in
+//! microseconds and the automaton's state and group counts. Each search
+//! runs in a `search` span at trace level, recording where it started, how
+//! many steps it took and whether it matched; a search stopped by its step
+//! limit logs a debug event instead.
+//!
+//! ```
+//! use ogex::{Regex, TraceEvent};
+//!
+//! let regex = Regex::new("ab").unwrap();
+//! let mut events = Vec::new();
+//! regex.trace("xab", |event| events.push(event)).unwrap();
+//! assert_eq!(events[0], TraceEvent::Attempt { start: 0 });
+//! assert!(events.contains(&TraceEvent::Dead { pos: 0 }));
+//! assert_eq!(events.last(), Some(&TraceEvent::Accept { pos: 3 }));
+//! ```
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::nfa::StateId;
+
+/// Receives the events of traced searches
+///
+/// Any `Fn(TraceEvent)` closure is a sink.
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+/// use ogex::{RegexBuilder, TraceEvent};
+///
+/// let attempts = Arc::new(Mutex::new(0));
+/// let counter = Arc::clone(&attempts);
+/// let regex = RegexBuilder::new("b")
+///     .trace_sink(move |event| {
+///         if let TraceEvent::Attempt { .. } = event {
+///             *counter.lock().unwrap() += 1;
+///         }
+///     })
+///     .build()
+///     .unwrap();
+/// assert!(regex.is_match("aab"));
+/// assert_eq!(*attempts.lock().unwrap(), 3);
+/// ```
+pub trait TraceSink {
+    /// Handle one step of a search
+    fn event(&self, event: TraceEvent);
+}
+
+impl<F: Fn(TraceEvent)> TraceSink for F {
+    fn event(&self, event: TraceEvent) {
+        self(event)
+    }
+}
+
+/// A sink shared by a builder and the regexes it builds
+#[derive(Clone)]
+pub(crate) struct SharedSink(pub(crate) Arc<dyn TraceSink + Send + Sync>);
+
+// A sink that panics mid-event can only leave its own state inconsistent,
+// never the regex's, so regexes stay usable across `catch_unwind`
+impl core::panic::UnwindSafe for SharedSink {}
+impl core::panic::RefUnwindSafe for SharedSink {}
+
+impl fmt::Debug for SharedSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TraceSink")
+    }
+}
+
+/// One step of a traced search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    /// A match attempt begins at `start`
+    Attempt {
+        /// Where the attempt starts
+        start: usize,
+    },
+    /// The states alive at `pos`, in priority order
+    States {
+        /// The position in the input
+        pos: usize,
+        /// The live states
+        states: Vec<StateId>,
+    },
+    /// State `state` became alive at `pos`
+    Enter {
+        /// The position in the input
+        pos: usize,
+        /// The state entered
+        state: StateId,
+    },
+    /// A transition from `from` to `to` consumed the input at `pos`
+    Step {
+        /// The position of the consumed input
+        pos: usize,
+        /// The state the transition leaves
+        from: StateId,
+        /// The state the transition enters
+        to: StateId,
+        /// What the transition matches, such as `'a'` or `[0-9]`
+        label: String,
+    },
+    /// A backreference to `group` was tried at `pos`
+    Backref {
+        /// The position in the input
+        pos: usize,
+        /// The group referred to
+        group: u32,
+        /// Whether the group's text follows at `pos`
+        matched: bool,
+    },
+    /// Capture group `group` opened at `pos`
+    GroupStart {
+        /// The group number
+        group: u32,
+        /// The position in the input
+        pos: usize,
+    },
+    /// Capture group `group` closed at `pos`
+    GroupEnd {
+        /// The group number
+        group: u32,
+        /// The position in the input
+        pos: usize,
+    },
+    /// An anchor, word boundary or lookaround was checked at `pos`
+    Assertion {
+        /// The position in the input
+        pos: usize,
+        /// The assertion, such as `^` or `lookahead`
+        label: String,
+        /// Whether it held
+        passed: bool,
+    },
+    /// The accept state is alive at `pos`, so a match can end there
+    Accept {
+        /// The position in the input
+        pos: usize,
+    },
+    /// No state survived the input at `pos`, ending the attempt
+    Dead {
+        /// The position in the input
+        pos: usize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Regex;
+
+    fn trace(pattern: &str, input: &str) -> Vec<TraceEvent> {
+        let mut events = Vec::new();
+        Regex::new(pattern)
+            .unwrap()
+            .trace(input, |event| events.push(event))
+            .unwrap();
+        events
+    }
+
+    #[test]
+    fn test_trace_reports_attempts_and_steps() {
+        let events = trace("b", "ab");
+        let attempts: Vec<_> = events
+            .iter()
+            .filter(|event| matches!(event, TraceEvent::Attempt { .. }))
+            .collect();
+        assert_eq!(
+            attempts,
+            vec![
+                &TraceEvent::Attempt { start: 0 },
+                &TraceEvent::Attempt { start: 1 }
+            ]
+        );
+        assert!(events.contains(&TraceEvent::Dead { pos: 0 }));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            TraceEvent::Step { pos: 1, label, .. } if label == "'b'"
+        )));
+        assert_eq!(events.last(), Some(&TraceEvent::Accept { pos: 2 }));
+    }
+
+    #[test]
+    fn test_trace_reports_groups_and_assertions() {
+        let events = trace(r"^(a)\b", "a");
+        assert!(events.contains(&TraceEvent::GroupStart { group: 1, pos: 0 }));
+        assert!(events.contains(&TraceEvent::GroupEnd { group: 1, pos: 1 }));
+        assert!(events.contains(&TraceEvent::Assertion {
+            pos: 1,
+            label: "\\b".to_string(),
+            passed: true,
+        }));
+    }
+
+    #[test]
+    fn test_trace_reports_entered_states_and_backrefs() {
+        let regex = Regex::new(r"(a)\1").unwrap();
+        let mut events = Vec::new();
+        regex.trace("ab", |event| events.push(event)).unwrap();
+        assert!(events.contains(&TraceEvent::Enter {
+            pos: 0,
+            state: regex.nfa().start,
+        }));
+        assert!(events.contains(&TraceEvent::Backref {
+            pos: 1,
+            group: 1,
+            matched: false,
+        }));
+    }
+
+    #[test]
+    fn test_builder_sink_sees_every_search() {
+        use std::sync::Mutex;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let regex = crate::RegexBuilder::new("x")
+            .trace_sink(move |event| sink.lock().unwrap().push(event))
+            .build()
+            .unwrap();
+        assert!(regex.find("ax").is_some());
+        assert!(regex.find_bytes_at(b"x", 0).unwrap().is_some());
+        let events = events.lock().unwrap();
+        let attempts: Vec<_> = events
+            .iter()
+            .filter(|event| matches!(event, TraceEvent::Attempt { .. }))
+            .collect();
+        assert_eq!(attempts.len(), 3);
+        assert_eq!(events.last(), Some(&TraceEvent::Accept { pos: 1 }));
+    }
+
+    #[test]
+    fn test_trace_finds_the_same_match() {
+        let regex = Regex::new(r"(n:\d+)x").unwrap();
+        let found = regex.trace("a 12x", |_| {}).unwrap().unwrap();
+        assert_eq!((found.start, found.end), (2, 5));
+        assert_eq!(found.named_group("n"), Some((2, 4)));
+    }
+}
@@ -7,6 +7,8 @@
 
 use crate::error::Result;
 use crate::parser::parse;
+use alloc::format;
+use alloc::string::{String, ToString};
 
 /// Transpile Ogex to legacy/PCRE syntax
 ///
@@ -73,6 +75,7 @@ pub struct ConvertResult {
 
 impl ConvertResult {
     /// Print a formatted report of all conversions
+    #[cfg(feature = "std")]
     pub fn report(&self) {
         println!("Conversion Result");
         println!("================");
@@ -97,6 +100,7 @@ pub struct TranspileResult {
 
 impl TranspileResult {
     /// Print a formatted report of the transpilation
+    #[cfg(feature = "std")]
     pub fn report(&self) {
         println!("Transpilation Report");
         println!("====================");
@@ -144,6 +148,7 @@ pub struct ExplainResult {
 
 impl ExplainResult {
     /// Print a formatted explanation
+    #[cfg(feature = "std")]
     pub fn explain(&self) {
         println!("Regex Pattern Explanation");
         println!("========================");
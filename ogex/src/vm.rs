@@ -0,0 +1,710 @@
+//! A bytecode backend for the matcher
+//!
+//! [`Backend::Vm`] flattens the compiled NFA into a [`Program`]: one array
+//! of instructions, where each state's edges are a run of instructions
+//! (a state with several edges is a split), with character classes moved
+//! to a table and referred to by index. The VM walks the same states in the
+//! same order as the NFA simulator, so the two backends find the same
+//! matches with the same groups and take the same number of steps, but the
+//! VM keeps its threads in lists it allocates once per regex and checks
+//! which states it has reached in constant time.
+//!
+//! Searches that report their steps to a [`TraceSink`](crate::TraceSink)
+//! always run on the NFA simulator, which is the one that emits the events.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::engine::Deadline;
+use crate::nfa::{Nfa, Transition};
+
+/// Positions of a group, or of an open group's start
+type Slot = Option<(usize, usize)>;
+
+/// Which engine runs a regex's searches
+///
+/// Both backends accept the same patterns and find the same matches; set
+/// one with [`RegexBuilder::backend`](crate::RegexBuilder::backend).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Backend {
+    /// Simulate the NFA directly
+    #[default]
+    Nfa,
+    /// Run the NFA compiled to a flat bytecode program, which is faster on
+    /// most patterns at the cost of compiling the program
+    Vm,
+}
+
+/// A step that does not consume input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assertion {
+    LineStart,
+    LineEnd,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+/// The kind of a lookaround
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Look {
+    Ahead,
+    NotAhead,
+    Behind,
+    NotBehind,
+}
+
+/// One edge of a state, leading to the state `goto`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Inst {
+    /// Consume the character
+    Char { c: char, goto: u32 },
+    /// Consume any character, or any but a line break without dotall
+    Any { goto: u32 },
+    /// Consume a character of class number `class`
+    Class { class: u32, goto: u32 },
+    /// Move without consuming input; followed through the precomputed
+    /// epsilon closure rather than one instruction at a time
+    Jmp { goto: u32 },
+    /// Save where a group starts
+    Open { group: u32, goto: u32 },
+    /// Save where a group ends, completing it
+    Close { group: u32, goto: u32 },
+    /// Consume the text group `group` captured
+    Backref { group: u32, goto: u32 },
+    /// Move if the assertion holds
+    Assert { assertion: Assertion, goto: u32 },
+    /// Move if the lookaround program number `program` holds
+    Look { look: Look, program: u32, goto: u32 },
+}
+
+/// An NFA flattened into arrays of instructions
+#[derive(Debug, Clone)]
+pub(crate) struct Program {
+    insts: Vec<Inst>,
+    /// Where each state's instructions start in `insts`, followed by the
+    /// end of the last state's
+    blocks: Vec<u32>,
+    /// The states each state reaches through empty edges alone, itself
+    /// first, laid out like `insts`
+    epsilon: Vec<u32>,
+    epsilon_blocks: Vec<u32>,
+    /// Lookup tables of the character classes, one bit per Latin-1 character
+    classes: Vec<[u8; 32]>,
+    /// Programs of the lookarounds
+    looks: Vec<Program>,
+    start: u32,
+    accept: u32,
+    /// Number of groups, counting the whole match as group 0
+    groups: usize,
+    case_insensitive: bool,
+    multiline: bool,
+    dotall: bool,
+}
+
+/// The input of a search, with positions counted in bytes
+#[derive(Clone, Copy)]
+pub(crate) struct Haystack<'a> {
+    bytes: &'a [u8],
+    /// The input as text, or `None` for a byte string
+    text: Option<&'a str>,
+}
+
+impl<'a> Haystack<'a> {
+    /// A text input, matched one character at a time
+    pub(crate) fn text(input: &'a str) -> Self {
+        Haystack {
+            bytes: input.as_bytes(),
+            text: Some(input),
+        }
+    }
+
+    /// A byte string input, each byte matched as a Latin-1 character
+    pub(crate) fn bytes(input: &'a [u8]) -> Self {
+        Haystack {
+            bytes: input,
+            text: None,
+        }
+    }
+
+    /// The character at byte `pos` and its length in bytes
+    fn at(&self, pos: usize) -> Option<(char, usize)> {
+        let byte = *self.bytes.get(pos)?;
+        match self.text {
+            Some(text) if !byte.is_ascii() => {
+                let c = text.get(pos..)?.chars().next()?;
+                Some((c, c.len_utf8()))
+            }
+            _ => Some((byte as char, 1)),
+        }
+    }
+
+    /// Whether a match may start or end at `pos`
+    fn is_boundary(&self, pos: usize) -> bool {
+        match self.text {
+            Some(text) => text.is_char_boundary(pos),
+            None => pos <= self.bytes.len(),
+        }
+    }
+
+    fn is_word(&self, pos: usize) -> bool {
+        // Word characters are ASCII, so no byte of a longer character is one
+        self.bytes
+            .get(pos)
+            .is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_')
+    }
+}
+
+/// Threads in order of priority, each a state and its group slots
+#[derive(Default)]
+struct Threads {
+    states: Vec<u32>,
+    /// `width` slots per thread
+    slots: Vec<Slot>,
+    /// Where each state last pushed sits in `states`
+    sparse: Vec<u32>,
+}
+
+impl Threads {
+    fn reset(&mut self, states: usize) {
+        self.states.clear();
+        self.slots.clear();
+        if self.sparse.len() < states {
+            self.sparse.resize(states, 0);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    fn contains(&self, state: u32) -> bool {
+        let index = self.sparse[state as usize] as usize;
+        self.states.get(index) == Some(&state)
+    }
+
+    fn push(&mut self, state: u32, slots: &[Slot]) {
+        self.sparse[state as usize] = self.states.len() as u32;
+        self.states.push(state);
+        self.slots.extend_from_slice(slots);
+    }
+
+    fn slots(&self, index: usize, width: usize) -> &[Slot] {
+        &self.slots[index * width..(index + 1) * width]
+    }
+}
+
+/// Memory a run works in, kept from one run to the next
+#[derive(Default)]
+pub(crate) struct Cache {
+    current: Threads,
+    next: Threads,
+    /// States still to close over, with their slots
+    stack: Vec<u32>,
+    stack_slots: Vec<Slot>,
+    /// Slots of the longest match so far
+    best: Vec<Slot>,
+    /// Memory for lookarounds, which run while this run's is in use
+    inner: Option<Box<Cache>>,
+}
+
+impl Program {
+    /// Flatten `nfa` and the NFAs of its lookarounds
+    pub(crate) fn new(nfa: &Nfa) -> Self {
+        let mut program = Program {
+            insts: Vec::new(),
+            blocks: Vec::with_capacity(nfa.states.len() + 1),
+            epsilon: Vec::new(),
+            epsilon_blocks: Vec::with_capacity(nfa.states.len() + 1),
+            classes: Vec::new(),
+            looks: Vec::new(),
+            start: nfa.start as u32,
+            accept: nfa.accept as u32,
+            groups: nfa.next_group_id() as usize,
+            case_insensitive: nfa.mode_flags.case_insensitive,
+            multiline: nfa.mode_flags.multiline,
+            dotall: nfa.mode_flags.dotall,
+        };
+        program.blocks.push(0);
+        program.epsilon_blocks.push(0);
+        for (id, state) in nfa.states.iter().enumerate() {
+            for (transition, target) in &state.transitions {
+                let inst = program.inst(nfa, transition, *target as u32);
+                program.insts.push(inst);
+            }
+            program.blocks.push(program.insts.len() as u32);
+            program.epsilon.extend(
+                nfa.get_epsilon_closure(id)
+                    .iter()
+                    .map(|&state| state as u32),
+            );
+            program.epsilon_blocks.push(program.epsilon.len() as u32);
+        }
+        program
+    }
+
+    fn inst(&mut self, nfa: &Nfa, transition: &Transition, goto: u32) -> Inst {
+        match transition {
+            Transition::Char(c) => Inst::Char { c: *c, goto },
+            Transition::Any => Inst::Any { goto },
+            Transition::Epsilon => Inst::Jmp { goto },
+            Transition::CharClass { lookup, .. } => {
+                let class = match self.classes.iter().position(|class| class == lookup) {
+                    Some(class) => class,
+                    None => {
+                        self.classes.push(*lookup);
+                        self.classes.len() - 1
+                    }
+                };
+                Inst::Class {
+                    class: class as u32,
+                    goto,
+                }
+            }
+            Transition::GroupStart(group) => Inst::Open {
+                group: *group,
+                goto,
+            },
+            Transition::GroupEnd(group) => Inst::Close {
+                group: *group,
+                goto,
+            },
+            Transition::Backref(group) => Inst::Backref {
+                group: *group,
+                goto,
+            },
+            // A reference to a group that does not exist never matches
+            Transition::BackrefRelative(relative) => Inst::Backref {
+                group: nfa.resolve_relative(*relative).unwrap_or(u32::MAX),
+                goto,
+            },
+            Transition::StartAnchor => Inst::Assert {
+                assertion: Assertion::LineStart,
+                goto,
+            },
+            Transition::EndAnchor => Inst::Assert {
+                assertion: Assertion::LineEnd,
+                goto,
+            },
+            Transition::WordBoundary => Inst::Assert {
+                assertion: Assertion::WordBoundary,
+                goto,
+            },
+            Transition::NonWordBoundary => Inst::Assert {
+                assertion: Assertion::NotWordBoundary,
+                goto,
+            },
+            Transition::Lookahead(inner) => self.look(Look::Ahead, inner, goto),
+            Transition::NegativeLookahead(inner) => self.look(Look::NotAhead, inner, goto),
+            Transition::Lookbehind(inner) => self.look(Look::Behind, inner, goto),
+            Transition::NegativeLookbehind(inner) => self.look(Look::NotBehind, inner, goto),
+        }
+    }
+
+    fn look(&mut self, look: Look, inner: &Nfa, goto: u32) -> Inst {
+        self.looks.push(Program::new(inner));
+        Inst::Look {
+            look,
+            program: self.looks.len() as u32 - 1,
+            goto,
+        }
+    }
+
+    fn block(&self, state: u32) -> &[Inst] {
+        let state = state as usize;
+        &self.insts[self.blocks[state] as usize..self.blocks[state + 1] as usize]
+    }
+
+    fn epsilon_of(&self, state: u32) -> &[u32] {
+        let state = state as usize;
+        &self.epsilon[self.epsilon_blocks[state] as usize..self.epsilon_blocks[state + 1] as usize]
+    }
+
+    /// Find the longest match starting at `start`, giving up once more than
+    /// `budget` steps are taken or `deadline` passes
+    ///
+    /// Returns the match's end and group slots, and the steps taken.
+    pub(crate) fn run(
+        &self,
+        haystack: &Haystack<'_>,
+        start: usize,
+        cache: &mut Cache,
+        budget: usize,
+        deadline: &mut Deadline,
+    ) -> (Option<(usize, Vec<Slot>)>, usize) {
+        let Cache {
+            current,
+            next,
+            stack,
+            stack_slots,
+            best,
+            inner,
+        } = cache;
+        let len = haystack.bytes.len();
+        let width = 2 * self.groups;
+        let states = self.blocks.len() - 1;
+        let mut closer = Closer {
+            program: self,
+            haystack,
+            start,
+            stack,
+            stack_slots,
+            inner,
+        };
+
+        let mut pos = start;
+        let mut steps = 0usize;
+        let mut found = None;
+        next.reset(states);
+        next.states.push(self.start);
+        next.slots.resize(width, None);
+        closer.close(next, current, pos);
+        self.accept_into(current, width, pos, best, &mut found);
+
+        while pos < len {
+            steps = steps.saturating_add(current.len());
+            if steps > budget || deadline.tick(current.len()) {
+                return (None, steps);
+            }
+            let Some((c, width_of_c)) = haystack.at(pos) else {
+                break;
+            };
+
+            // Every thread that consumes input moves on by the same amount:
+            // one character, or the longest backreference that matched
+            next.reset(states);
+            let mut consumed = width_of_c;
+            for index in 0..current.len() {
+                let slots = current.slots(index, width);
+                for inst in self.block(current.states[index]) {
+                    let goto = match *inst {
+                        Inst::Char { c: expected, goto } => {
+                            let matched = if self.case_insensitive {
+                                expected.eq_ignore_ascii_case(&c)
+                            } else {
+                                expected == c
+                            };
+                            if !matched {
+                                continue;
+                            }
+                            goto
+                        }
+                        Inst::Any { goto } if self.dotall || c != '\n' => goto,
+                        Inst::Class { class, goto }
+                            if in_class(&self.classes[class as usize], c) =>
+                        {
+                            goto
+                        }
+                        Inst::Backref { group, goto } => {
+                            let Some(length) = backref(haystack, slots, group as usize, pos) else {
+                                continue;
+                            };
+                            consumed = consumed.max(length);
+                            goto
+                        }
+                        _ => continue,
+                    };
+                    next.push(goto, slots);
+                }
+            }
+            closer.close(next, current, pos + consumed);
+            if current.states.is_empty() {
+                break;
+            }
+            pos += consumed;
+
+            // Closing the closed states again can reach some of them along
+            // other paths first, which the NFA simulator does too
+            closer.close(current, next, pos);
+            core::mem::swap(current, next);
+            self.accept_into(current, width, pos, best, &mut found);
+        }
+
+        let found = found.map(|end| (end, best[..self.groups].to_vec()));
+        (found, steps)
+    }
+
+    /// Record a match ending at `pos` if a thread in `threads` accepts
+    fn accept_into(
+        &self,
+        threads: &Threads,
+        width: usize,
+        pos: usize,
+        best: &mut Vec<Slot>,
+        found: &mut Option<usize>,
+    ) {
+        if threads.contains(self.accept) {
+            let index = threads.sparse[self.accept as usize] as usize;
+            best.clear();
+            best.extend_from_slice(threads.slots(index, width));
+            *found = Some(pos);
+        }
+    }
+
+    /// Whether the lookaround `look` with program `program` holds at `pos`
+    fn look_holds(
+        &self,
+        haystack: &Haystack<'_>,
+        look: Look,
+        program: u32,
+        pos: usize,
+        cache: &mut Cache,
+    ) -> bool {
+        let program = &self.looks[program as usize];
+        let mut matches_from = |start| {
+            program
+                .run(haystack, start, cache, usize::MAX, &mut Deadline::default())
+                .0
+        };
+        match look {
+            Look::Ahead => matches_from(pos).is_some(),
+            Look::NotAhead => matches_from(pos).is_none(),
+            Look::Behind | Look::NotBehind => {
+                let behind = (0..=pos)
+                    .filter(|&start| haystack.is_boundary(start))
+                    .any(|start| matches!(matches_from(start), Some((end, _)) if end == pos));
+                behind == (look == Look::Behind)
+            }
+        }
+    }
+}
+
+/// Follows the edges that consume no input
+struct Closer<'p, 'h, 'c> {
+    program: &'p Program,
+    haystack: &'p Haystack<'h>,
+    /// Where the run started, where `^` matches outside multiline mode
+    start: usize,
+    stack: &'c mut Vec<u32>,
+    stack_slots: &'c mut Vec<Slot>,
+    inner: &'c mut Option<Box<Cache>>,
+}
+
+impl Closer<'_, '_, '_> {
+    /// Write to `to` the states reachable from `from` at `pos` without
+    /// consuming input, each once, with the slots of the first path found
+    ///
+    /// The paths are searched depth first from the last thread, the order
+    /// the NFA simulator searches them in.
+    fn close(&mut self, from: &Threads, to: &mut Threads, pos: usize) {
+        let program = self.program;
+        let width = 2 * program.groups;
+        to.reset(program.blocks.len() - 1);
+        self.stack.clear();
+        self.stack_slots.clear();
+        self.stack.extend_from_slice(&from.states);
+        self.stack_slots.extend_from_slice(&from.slots);
+
+        while let Some(state) = self.stack.pop() {
+            let top = self.stack_slots.len() - width;
+            if to.contains(state) {
+                self.stack_slots.truncate(top);
+                continue;
+            }
+            to.push(state, &self.stack_slots[top..]);
+            self.stack_slots.truncate(top);
+            let index = to.len() - 1;
+
+            for &target in program.epsilon_of(state) {
+                if !to.contains(target) {
+                    self.stack.push(target);
+                    self.stack_slots.extend_from_slice(to.slots(index, width));
+                }
+            }
+            for inst in program.block(state) {
+                let goto = match *inst {
+                    Inst::Open { goto, .. }
+                    | Inst::Close { goto, .. }
+                    | Inst::Assert { goto, .. }
+                    | Inst::Look { goto, .. } => goto,
+                    _ => continue,
+                };
+                if to.contains(goto) {
+                    continue;
+                }
+                let passed = match *inst {
+                    Inst::Assert { assertion, .. } => self.assertion(assertion, pos),
+                    Inst::Look {
+                        look,
+                        program: look_program,
+                        ..
+                    } => {
+                        let cache = self.inner.get_or_insert_with(Default::default);
+                        program.look_holds(self.haystack, look, look_program, pos, cache)
+                    }
+                    _ => true,
+                };
+                if !passed {
+                    continue;
+                }
+                let base = self.stack_slots.len();
+                self.stack.push(goto);
+                self.stack_slots.extend_from_slice(to.slots(index, width));
+                let slots = &mut self.stack_slots[base..];
+                match *inst {
+                    Inst::Open { group, .. } => {
+                        if let Some(pending) = slots.get_mut(program.groups + group as usize) {
+                            *pending = Some((pos, pos));
+                        }
+                    }
+                    // Completing a group closes it
+                    Inst::Close { group, .. } => {
+                        let pending = slots.get_mut(program.groups + group as usize);
+                        if let Some((start, _)) = pending.and_then(Option::take) {
+                            slots[group as usize] = Some((start, pos));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn assertion(&self, assertion: Assertion, pos: usize) -> bool {
+        let program = self.program;
+        let bytes = self.haystack.bytes;
+        match assertion {
+            Assertion::LineStart if program.multiline => pos == 0 || bytes[pos - 1] == b'\n',
+            Assertion::LineStart => self.start == 0 && pos == self.start,
+            Assertion::LineEnd if program.multiline => pos == bytes.len() || bytes[pos] == b'\n',
+            Assertion::LineEnd => pos == bytes.len(),
+            Assertion::WordBoundary | Assertion::NotWordBoundary => {
+                let boundary =
+                    (pos > 0 && self.haystack.is_word(pos - 1)) != self.haystack.is_word(pos);
+                boundary == (assertion == Assertion::WordBoundary)
+            }
+        }
+    }
+}
+
+/// Whether `c` is in the class with lookup table `class`
+fn in_class(class: &[u8; 32], c: char) -> bool {
+    let c = c as u32;
+    c <= 255 && class[c as usize / 8] & (1 << (c % 8)) != 0
+}
+
+/// How far the backreference to `group` consumes at `pos`, if the text
+/// there repeats what the group captured
+///
+/// An empty capture still consumes the character at `pos`, as the NFA
+/// simulator does.
+fn backref(haystack: &Haystack<'_>, slots: &[Slot], group: usize, pos: usize) -> Option<usize> {
+    let (start, end) = (*slots.get(group)?)?;
+    let bytes = haystack.bytes;
+    let captured = bytes.get(start..end)?;
+    let (_, width) = haystack.at(pos)?;
+    bytes[pos..]
+        .starts_with(captured)
+        .then(|| captured.len().max(width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Regex, RegexBuilder};
+    use alloc::format;
+
+    fn both(builder: RegexBuilder) -> (Regex, Regex) {
+        let nfa = builder.clone().build().unwrap();
+        let vm = builder.backend(Backend::Vm).build().unwrap();
+        assert_eq!((nfa.backend(), vm.backend()), (Backend::Nfa, Backend::Vm));
+        (nfa, vm)
+    }
+
+    /// Check that both backends find the same matches of `pattern`
+    fn check(builder: RegexBuilder, inputs: &[&str]) {
+        let (nfa, vm) = both(builder);
+        for input in inputs {
+            let label = format!("{:?} on {:?}", nfa.as_str(), input);
+            assert_eq!(nfa.find_all(input), vm.find_all(input), "{}", label);
+            for pos in (0..=input.len()).filter(|&pos| input.is_char_boundary(pos)) {
+                assert_eq!(
+                    nfa.try_match_at(input, pos),
+                    vm.try_match_at(input, pos),
+                    "{} at {}",
+                    label,
+                    pos
+                );
+            }
+            assert_eq!(
+                nfa.find_bytes_at(input.as_bytes(), 0).unwrap(),
+                vm.find_bytes_at(input.as_bytes(), 0).unwrap(),
+                "{} as bytes",
+                label
+            );
+        }
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        let inputs = [
+            "",
+            "aa",
+            "abcabc",
+            "foo bar\nbaz qux",
+            "x=1, y=22, z=333",
+            "héllo wörld",
+            "日本語 text 日本",
+        ];
+        for pattern in [
+            "a",
+            "(a*)(a*)",
+            r"(\w+)\s(\w+)",
+            r"(n:\d+)\,?",
+            "(abc)+|b",
+            r"(\w)\1",
+            r"(a|b|c)\g{-1}",
+            "^.+$",
+            r"\bba\w",
+            r"\Bo",
+            r"(>:\w\s)\w",
+            r"(>~:a)\w+",
+            r"(<:\=)\d+",
+            r"(<~:\d)\d",
+            "[^a-z ]+",
+            "[a-cx-z]{2,3}",
+            "é.",
+            "日+",
+            ".*?b",
+        ] {
+            check(RegexBuilder::new(pattern), &inputs);
+            check(RegexBuilder::new(pattern).case_insensitive(true), &inputs);
+            check(
+                RegexBuilder::new(pattern).multiline(true).dotall(true),
+                &inputs,
+            );
+        }
+    }
+
+    #[test]
+    fn test_step_limit_is_shared() {
+        let input = "one two three three";
+        let mut outcomes = Vec::new();
+        for limit in [1, 10, 50, 100, 200, 400, 800] {
+            let (nfa, vm) = both(RegexBuilder::new(r"(\w+)\s\1").step_limit(limit));
+            let (nfa, vm) = (nfa.try_find(input).ok(), vm.try_find(input).ok());
+            assert_eq!(nfa, vm, "limit {}", limit);
+            outcomes.push(nfa.map(|m| m.is_some()));
+        }
+        // The limits cover searches cut short as well as finished ones
+        assert!(outcomes.contains(&None) && outcomes.contains(&Some(true)));
+    }
+
+    #[test]
+    fn test_round_trip_and_trace() {
+        let (_, vm) = both(RegexBuilder::new(r"(k:\w+)\=(v:\d+)"));
+        let restored = Regex::from_bytes(&vm.to_bytes()).unwrap();
+        assert_eq!(restored.backend(), Backend::Vm);
+        let m = restored.find("a=b c=12").unwrap();
+        assert_eq!(m.named_group_str("a=b c=12", "v"), Some("12"));
+
+        // Traced searches fall back to the simulator, which reports the steps
+        let mut attempts = 0;
+        let found = vm
+            .trace("a=b c=12", |event| {
+                attempts += matches!(event, crate::TraceEvent::Attempt { .. }) as usize
+            })
+            .unwrap();
+        assert_eq!(found, vm.find("a=b c=12"));
+        assert_eq!(attempts, 5);
+    }
+}
@@ -3,45 +3,108 @@
 //! This module provides WASM bindings using wasm-bindgen for use in browsers
 //! and Node.js. Enable with the "wasm" feature flag.
 
+#[cfg(feature = "wasm")]
+use std::rc::Rc;
+
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+// Trade allocation speed for a smaller bundle
+#[cfg(all(feature = "wee_alloc", target_arch = "wasm32"))]
+#[global_allocator]
+static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+
 #[cfg(feature = "wasm")]
 use crate::engine::{Match, Regex};
+#[cfg(feature = "wasm")]
+use crate::error::{RegexError, Span};
+#[cfg(feature = "wasm")]
+use crate::lexer::{Lexer, Token};
+#[cfg(feature = "wasm")]
+use crate::nfa::json_string;
+#[cfg(feature = "wasm")]
+use crate::program::ProgramError;
+#[cfg(all(feature = "wasm", feature = "replace"))]
+use crate::replace::ReplacementError;
+#[cfg(feature = "wasm")]
+use crate::stream::{StreamMatch, StreamMatcher};
 
 /// JavaScript-facing structured error
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
+#[derive(Debug)]
 pub struct JsError {
-    /// Error type: "Lexer", "Parser", "Compile", "Runtime"
+    /// Error type: "Lexer", "Parser", "Compile", "Runtime", "Replacement",
+    /// "Program", "Dialect"
     error_type: String,
+    /// Stable error code such as "E004", empty for errors that do not come
+    /// from the pattern
+    code: String,
     /// Error message
     message: String,
-    /// Position in pattern where error occurred (for lexer/parser errors)
-    position: Option<usize>,
-    /// Additional context (e.g., invalid character for lexer errors)
+    /// Character range of the pattern the error refers to (for lexer/parser
+    /// errors)
+    span: Option<Span>,
+    /// The pattern with the error location marked by carets (if available)
     context: Option<String>,
 }
 
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
 impl JsError {
-    /// Create a new JS error from error message (internal helper)
-    fn from_message(error_type: &str, message: String) -> JsError {
+    /// Create a JS error from a pattern error, pointing into the pattern
+    /// when the error has a location (internal helper)
+    fn from_regex_error(pattern: &str, err: &RegexError) -> JsError {
+        let error_type = match err {
+            RegexError::Lexer { .. } => "Lexer",
+            RegexError::Parse(_) => "Parser",
+            RegexError::Compile(_) => "Compile",
+            RegexError::Runtime(_) => "Runtime",
+        };
+        let spanned = err.spanned();
+        JsError {
+            error_type: error_type.to_string(),
+            code: err.code().as_str().to_string(),
+            message: err.to_string(),
+            span: spanned.as_ref().map(|s| s.span),
+            context: spanned.map(|s| s.render(pattern)),
+        }
+    }
+
+    /// Create a JS error that has no code or location (internal helper)
+    fn without_location(error_type: &str, message: String) -> JsError {
         JsError {
             error_type: error_type.to_string(),
+            code: String::new(),
             message,
-            position: None,
+            span: None,
             context: None,
         }
     }
 
+    /// Create a JS error from a bad replacement template (internal helper)
+    #[cfg(feature = "replace")]
+    fn from_replacement_error(err: &ReplacementError) -> JsError {
+        JsError::without_location("Replacement", err.to_string())
+    }
+
+    /// Create a JS error from a bad compiled program (internal helper)
+    fn from_program_error(err: &ProgramError) -> JsError {
+        JsError::without_location("Program", err.to_string())
+    }
+
     /// Get error type
     #[wasm_bindgen(getter)]
     pub fn error_type(&self) -> String {
         self.error_type.clone()
     }
 
+    /// Get the stable error code
+    #[wasm_bindgen(getter)]
+    pub fn code(&self) -> String {
+        self.code.clone()
+    }
+
     /// Get error message
     #[wasm_bindgen(getter)]
     pub fn message(&self) -> String {
@@ -51,7 +114,20 @@ impl JsError {
     /// Get error position (if available)
     #[wasm_bindgen(getter)]
     pub fn position(&self) -> Option<usize> {
-        self.position
+        self.span.map(|span| span.start)
+    }
+
+    /// Get the range of the pattern the error refers to as `{ start, end }`
+    /// character offsets, with `end` exclusive (if available)
+    #[wasm_bindgen(getter)]
+    pub fn span(&self) -> Option<js_sys::Object> {
+        self.span.map(|span| {
+            let obj = js_sys::Object::new();
+            js_sys::Reflect::set(&obj, &"start".into(), &JsValue::from_f64(span.start as f64))
+                .unwrap();
+            js_sys::Reflect::set(&obj, &"end".into(), &JsValue::from_f64(span.end as f64)).unwrap();
+            obj
+        })
     }
 
     /// Get error context (if available)
@@ -63,19 +139,26 @@ impl JsError {
     /// Convert to JSON string for easy debugging
     #[wasm_bindgen(js_name = toJSON)]
     pub fn to_json(&self) -> String {
-        let pos = self
-            .position
-            .map(|p| p.to_string())
-            .unwrap_or_else(|| "null".to_string());
-        let ctx = self
-            .context
-            .clone()
-            .map(|c| format!("\"{}\"", c))
-            .unwrap_or_else(|| "null".to_string());
-        format!(
-            r#"{{"error_type":"{}","message":"{}","position":{},"context":{}}}"#,
-            self.error_type, self.message, pos, ctx
-        )
+        let mut json = String::from(r#"{"error_type":"#);
+        json_string(&mut json, &self.error_type);
+        json.push_str(r#","code":"#);
+        json_string(&mut json, &self.code);
+        json.push_str(r#","message":"#);
+        json_string(&mut json, &self.message);
+        match self.span {
+            Some(span) => json.push_str(&format!(
+                r#","position":{},"span":{{"start":{},"end":{}}}"#,
+                span.start, span.start, span.end
+            )),
+            None => json.push_str(r#","position":null,"span":null"#),
+        }
+        json.push_str(r#","context":"#);
+        match &self.context {
+            Some(context) => json_string(&mut json, context),
+            None => json.push_str("null"),
+        }
+        json.push('}');
+        json
     }
 }
 
@@ -83,7 +166,33 @@ impl JsError {
 #[cfg(feature = "wasm")]
 #[wasm_bindgen]
 pub struct JsRegex {
-    regex: Regex,
+    regex: Rc<Regex>,
+    /// Names of the named groups, shared with every match
+    group_names: Rc<[String]>,
+    /// Where the next exec() starts searching
+    last_index: usize,
+}
+
+#[cfg(feature = "wasm")]
+impl JsRegex {
+    fn from_regex(regex: Regex) -> JsRegex {
+        let group_names = regex.capture_names().flatten().map(String::from).collect();
+        JsRegex {
+            regex: Rc::new(regex),
+            group_names,
+            last_index: 0,
+        }
+    }
+
+    /// Wrap a match of this regex for JavaScript (internal helper)
+    fn to_js_match(&self, match_result: Match, input: &str) -> JsMatch {
+        JsMatch {
+            match_result,
+            input: input.to_string(),
+            offset: 0,
+            group_names: Rc::clone(&self.group_names),
+        }
+    }
 }
 
 #[cfg(feature = "wasm")]
@@ -95,8 +204,8 @@ impl JsRegex {
     #[wasm_bindgen(constructor)]
     pub fn new(pattern: &str) -> Result<JsRegex, JsError> {
         match Regex::new(pattern) {
-            Ok(regex) => Ok(JsRegex { regex }),
-            Err(e) => Err(JsError::from_message("Compile", e.to_string())),
+            Ok(regex) => Ok(JsRegex::from_regex(regex)),
+            Err(e) => Err(JsError::from_regex_error(pattern, &e)),
         }
     }
 
@@ -106,24 +215,42 @@ impl JsRegex {
     #[wasm_bindgen(js_name = newWithStringError)]
     pub fn new_with_string_error(pattern: &str) -> Result<JsRegex, JsValue> {
         match Regex::new(pattern) {
-            Ok(regex) => Ok(JsRegex { regex }),
+            Ok(regex) => Ok(JsRegex::from_regex(regex)),
             Err(e) => Err(JsValue::from_str(&e.to_string())),
         }
     }
 
+    /// Encode the compiled regex, for example to cache it in IndexedDB
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.regex.to_bytes()
+    }
+
+    /// Restore a regex from bytes returned by toBytes, skipping compilation
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<JsRegex, JsError> {
+        match Regex::from_bytes(bytes) {
+            Ok(regex) => Ok(JsRegex::from_regex(regex)),
+            Err(e) => Err(JsError::from_program_error(&e)),
+        }
+    }
+
     /// Check if the pattern matches anywhere in the input
     #[wasm_bindgen(js_name = isMatch)]
     pub fn is_match(&self, input: &str) -> bool {
         self.regex.is_match(input)
     }
 
+    /// Check if the input matches, or could once more characters are typed
+    #[wasm_bindgen(js_name = isPartialMatch)]
+    pub fn is_partial_match(&self, input: &str) -> bool {
+        self.regex.is_partial_match(input)
+    }
+
     /// Find the first match
     #[wasm_bindgen(js_name = find)]
     pub fn find(&self, input: &str) -> Option<JsMatch> {
-        self.regex.find(input).map(|m| JsMatch {
-            match_result: m,
-            input: input.to_string(),
-        })
+        self.regex.find(input).map(|m| self.to_js_match(m, input))
     }
 
     /// Find all matches
@@ -133,28 +260,143 @@ impl JsRegex {
         let array = js_sys::Array::new();
 
         for m in matches {
-            array.push(
-                &JsMatch {
-                    match_result: m,
-                    input: input.to_string(),
-                }
-                .into(),
-            );
+            array.push(&self.to_js_match(m, input).into());
         }
 
         array
     }
 
-    /// Transpile pattern to legacy syntax (structured error)
+    /// Split every match into pieces to color, one record per piece
+    ///
+    /// Each record has `start`, `end`, `match` (the match's index), `group`
+    /// (the innermost capture group covering the piece, or `null`) and
+    /// `name` (that group's name, or `null`).
+    #[wasm_bindgen(js_name = highlight)]
+    pub fn highlight(&self, input: &str) -> js_sys::Array {
+        self.regex
+            .highlight(input)
+            .into_iter()
+            .map(|span| {
+                let obj = js_sys::Object::new();
+                let group = span.group.map_or(JsValue::NULL, JsValue::from);
+                let name = span.name.map_or(JsValue::NULL, |n| JsValue::from_str(&n));
+                js_sys::Reflect::set(&obj, &"start".into(), &JsValue::from_f64(span.start as f64))
+                    .unwrap();
+                js_sys::Reflect::set(&obj, &"end".into(), &JsValue::from_f64(span.end as f64))
+                    .unwrap();
+                js_sys::Reflect::set(
+                    &obj,
+                    &"match".into(),
+                    &JsValue::from_f64(span.match_index as f64),
+                )
+                .unwrap();
+                js_sys::Reflect::set(&obj, &"group".into(), &group).unwrap();
+                js_sys::Reflect::set(&obj, &"name".into(), &name).unwrap();
+                JsValue::from(obj)
+            })
+            .collect()
+    }
+
+    /// Iterate over all matches, like `String.prototype.matchAll`
+    ///
+    /// The result works with `for...of` and spread syntax. Matches are
+    /// found up front, so later changes to `lastIndex` do not affect it.
+    #[wasm_bindgen(js_name = matchAll)]
+    pub fn match_all(&self, input: &str) -> js_sys::Iterator {
+        self.find_all(input).values()
+    }
+
+    /// Replace the first match using a replacement template
+    ///
+    /// The template supports numbered and named backreferences such as `\1`
+    /// and `\g{name}`.
+    #[cfg(feature = "replace")]
+    #[wasm_bindgen(js_name = replace)]
+    pub fn replace(&self, input: &str, template: &str) -> Result<String, JsError> {
+        self.regex
+            .replace(input, template)
+            .map_err(|e| JsError::from_replacement_error(&e))
+    }
+
+    /// Replace every match using a replacement template
+    #[cfg(feature = "replace")]
+    #[wasm_bindgen(js_name = replaceAll)]
+    pub fn replace_all(&self, input: &str, template: &str) -> Result<String, JsError> {
+        self.regex
+            .replace_all(input, template)
+            .map_err(|e| JsError::from_replacement_error(&e))
+    }
+
+    /// Find the next match at or after `lastIndex`, like `RegExp.exec` on a
+    /// global regex
+    ///
+    /// On a match, `lastIndex` moves to the end of it; otherwise it is reset
+    /// to 0 and null is returned. As with `RegExp`, an empty match leaves
+    /// `lastIndex` where it was, so loops must step past it themselves.
+    #[wasm_bindgen(js_name = exec)]
+    pub fn exec(&mut self, input: &str) -> Result<Option<JsMatch>, JsError> {
+        if self.last_index > input.len() {
+            self.last_index = 0;
+            return Ok(None);
+        }
+
+        match self.regex.try_find_at(input, self.last_index) {
+            Ok(Some(m)) => {
+                self.last_index = m.end;
+                Ok(Some(self.to_js_match(m, input)))
+            }
+            Ok(None) => {
+                self.last_index = 0;
+                Ok(None)
+            }
+            Err(e) => {
+                self.last_index = 0;
+                Err(JsError::from_regex_error("", &e))
+            }
+        }
+    }
+
+    /// Byte offset where the next exec() starts searching, in the same units
+    /// as `JsMatch.start`
+    #[wasm_bindgen(getter, js_name = lastIndex)]
+    pub fn last_index(&self) -> usize {
+        self.last_index
+    }
+
+    /// Set where the next exec() starts searching
+    #[wasm_bindgen(setter, js_name = lastIndex)]
+    pub fn set_last_index(&mut self, index: usize) {
+        self.last_index = index;
+    }
+
+    /// Transpile pattern to another dialect (structured error)
+    ///
+    /// `dialect` is `"pcre"` (the default), `"js"`, `"python"` or `"ogex"`.
+    /// JavaScript shares PCRE's group and backreference syntax, so `"js"`
+    /// produces the PCRE form; constructs JavaScript lacks, such as atomic
+    /// groups, are left in that form.
+    #[cfg(feature = "transpiler")]
     #[wasm_bindgen(js_name = transpile)]
-    pub fn transpile(pattern: &str) -> Result<String, JsError> {
-        match crate::transpile(pattern) {
+    pub fn transpile(pattern: &str, dialect: Option<String>) -> Result<String, JsError> {
+        let convert = match dialect.as_deref().unwrap_or("pcre") {
+            "pcre" | "js" => crate::transpile,
+            "python" => crate::transpile_to_python,
+            "ogex" => crate::transpile_to_ogex,
+            other => {
+                return Err(JsError::without_location(
+                    "Dialect",
+                    format!("unsupported dialect '{}'", other),
+                ));
+            }
+        };
+        match convert(pattern) {
             Ok(result) => Ok(result),
-            Err(e) => Err(JsError::from_message("Compile", e.to_string())),
+            Err(e) => Err(JsError::from_regex_error(pattern, &e)),
         }
     }
 
     /// Transpile pattern to legacy syntax (string error - legacy)
+    #[cfg(feature = "transpiler")]
     #[wasm_bindgen(js_name = transpileWithStringError)]
     pub fn transpile_with_string_error(pattern: &str) -> Result<String, JsValue> {
         match crate::transpile(pattern) {
@@ -170,6 +412,11 @@ impl JsRegex {
 pub struct JsMatch {
     match_result: Match,
     input: String,
+    /// Offset of `input` within the text that was searched, for matches
+    /// found in one line of a stream
+    offset: usize,
+    /// Names of the pattern's named groups, whether or not they matched
+    group_names: Rc<[String]>,
 }
 
 #[cfg(feature = "wasm")]
@@ -178,13 +425,13 @@ impl JsMatch {
     /// Get start position
     #[wasm_bindgen(getter)]
     pub fn start(&self) -> usize {
-        self.match_result.start
+        self.offset + self.match_result.start
     }
 
     /// Get end position
     #[wasm_bindgen(getter)]
     pub fn end(&self) -> usize {
-        self.match_result.end
+        self.offset + self.match_result.end
     }
 
     /// Get matched text
@@ -214,24 +461,160 @@ impl JsMatch {
     pub fn groups(&self) -> js_sys::Object {
         let obj = js_sys::Object::new();
 
-        for (idx, opt) in self.match_result.groups.iter().enumerate() {
-            if let Some((start, end)) = opt {
-                // idx 0 is unused (groups are 1-indexed), so use idx + 1
-                let group_idx = (idx + 1) as u32;
-                let text = &self.input[*start..*end];
-                js_sys::Reflect::set(
-                    &obj,
-                    &JsValue::from_f64(group_idx as f64),
-                    &JsValue::from_str(text),
-                )
-                .unwrap();
-            }
+        // Slot 0 is unused; groups that did not participate are null
+        for (idx, opt) in self.match_result.groups.iter().enumerate().skip(1) {
+            let value = match opt {
+                Some((start, end)) => JsValue::from_str(&self.input[*start..*end]),
+                None => JsValue::NULL,
+            };
+            js_sys::Reflect::set(&obj, &JsValue::from_f64(idx as f64), &value).unwrap();
+        }
+
+        obj
+    }
+
+    /// Get named groups as a JavaScript object keyed by name, like
+    /// `match.groups` on a native `RegExp` match
+    ///
+    /// Groups that did not participate in the match are `undefined`.
+    #[wasm_bindgen(getter, js_name = namedGroups)]
+    pub fn named_groups(&self) -> js_sys::Object {
+        let obj = js_sys::Object::new();
+
+        for name in self.group_names.iter() {
+            let value = match self.match_result.named_group(name) {
+                Some((start, end)) => JsValue::from_str(&self.input[start..end]),
+                None => JsValue::UNDEFINED,
+            };
+            js_sys::Reflect::set(&obj, &JsValue::from_str(name), &value).unwrap();
         }
 
         obj
     }
 }
 
+/// JavaScript-facing matcher for text that arrives in chunks, such as a
+/// File or ReadableStream decoded with TextDecoderStream
+///
+/// Lines are searched as soon as they are complete, so a match never spans
+/// a line break. Match positions are byte offsets into the whole stream.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+pub struct JsStreamMatcher {
+    matcher: StreamMatcher<Rc<Regex>>,
+    group_names: Rc<[String]>,
+}
+
+#[cfg(feature = "wasm")]
+impl JsStreamMatcher {
+    /// Convert stream matches to an array of JsMatch (internal helper)
+    fn to_array(group_names: &Rc<[String]>, found: Vec<StreamMatch>) -> js_sys::Array {
+        let array = js_sys::Array::new();
+        for m in found {
+            let js_match = JsMatch {
+                match_result: m.match_result,
+                input: m.line.to_string(),
+                offset: m.line_offset,
+                group_names: Rc::clone(group_names),
+            };
+            array.push(&js_match.into());
+        }
+        array
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl JsStreamMatcher {
+    /// Start matching a new stream with a compiled regex
+    #[wasm_bindgen(constructor)]
+    pub fn new(regex: &JsRegex) -> JsStreamMatcher {
+        JsStreamMatcher {
+            matcher: StreamMatcher::new(Rc::clone(&regex.regex)),
+            group_names: Rc::clone(&regex.group_names),
+        }
+    }
+
+    /// Feed the next chunk, returning the matches in every line it completes
+    #[wasm_bindgen(js_name = push)]
+    pub fn push(&mut self, chunk: &str) -> Result<js_sys::Array, JsError> {
+        match self.matcher.push(chunk) {
+            Ok(found) => Ok(JsStreamMatcher::to_array(&self.group_names, found)),
+            Err(e) => Err(JsError::from_regex_error("", &e)),
+        }
+    }
+
+    /// End the stream, returning the matches in its final line
+    ///
+    /// The matcher cannot be used afterwards.
+    #[wasm_bindgen(js_name = finish)]
+    pub fn finish(self) -> Result<js_sys::Array, JsError> {
+        match self.matcher.finish() {
+            Ok(found) => Ok(JsStreamMatcher::to_array(&self.group_names, found)),
+            Err(e) => Err(JsError::from_regex_error("", &e)),
+        }
+    }
+}
+
+/// Split a pattern into `{ start, end, kind }` records for syntax
+/// highlighting
+///
+/// Offsets are character indices into the pattern, with `end` exclusive.
+/// `kind` is the token's [`Token::kind`], except that everything inside a
+/// character class is `"class"` and everything inside a `{n,m}` quantifier is
+/// `"quantifier"`. Tokenizing never fails: if the pattern cannot be lexed,
+/// the rest of it from the error onwards is a single `"error"` record, so a
+/// half-typed pattern still highlights.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen(js_name = tokenize)]
+pub fn tokenize(pattern: &str) -> js_sys::Array {
+    let mut records = Vec::new();
+    let mut lexer = Lexer::new(pattern);
+    let mut context = None;
+    loop {
+        let spanned = match lexer.next_spanned() {
+            Ok(spanned) => spanned,
+            Err(e) => {
+                // Errors can point back at an earlier token, such as the `[`
+                // of an unclosed class
+                let start = e.span().map_or(0, |span| span.start);
+                records.retain(|(span, _): &(Span, &str)| span.end <= start);
+                records.push((Span::new(start, pattern.chars().count()), "error"));
+                break;
+            }
+        };
+        let kind = match (&spanned.token, context) {
+            (Token::Eof, _) => break,
+            (Token::LeftBracket, None) => {
+                context = Some("class");
+                "class"
+            }
+            (Token::LeftBrace, None) => {
+                context = Some("quantifier");
+                "quantifier"
+            }
+            (Token::RightBracket, Some("class")) | (Token::RightBrace, Some("quantifier")) => {
+                context.take().unwrap()
+            }
+            (_, Some(kind)) => kind,
+            (token, None) => token.kind(),
+        };
+        records.push((spanned.span, kind));
+    }
+
+    records
+        .into_iter()
+        .map(|(span, kind)| {
+            let obj = js_sys::Object::new();
+            js_sys::Reflect::set(&obj, &"start".into(), &JsValue::from_f64(span.start as f64))
+                .unwrap();
+            js_sys::Reflect::set(&obj, &"end".into(), &JsValue::from_f64(span.end as f64)).unwrap();
+            js_sys::Reflect::set(&obj, &"kind".into(), &JsValue::from_str(kind)).unwrap();
+            JsValue::from(obj)
+        })
+        .collect()
+}
+
 /// Initialize panic hook for better error messages in WASM
 #[cfg(feature = "wasm")]
 #[wasm_bindgen(start)]
@@ -239,13 +622,12 @@ pub fn start() {
     console_error_panic_hook::set_once();
 }
 
-#[cfg(all(test, feature = "wasm"))]
+// The tests call into JavaScript, so they run under `wasm-pack test --node`
+#[cfg(all(test, feature = "wasm", target_arch = "wasm32"))]
 mod tests {
     use super::*;
     use wasm_bindgen_test::*;
 
-    wasm_bindgen_test_configure!(run_in_browser);
-
     #[wasm_bindgen_test]
     fn test_wasm_regex_new() {
         let regex = JsRegex::new("abc").unwrap();
@@ -262,9 +644,131 @@ mod tests {
         assert_eq!(m.text(), "abc");
     }
 
+    #[cfg(feature = "replace")]
+    #[wasm_bindgen_test]
+    fn test_wasm_replace() {
+        let regex = JsRegex::new(r"(word:\w+)@(\d)").unwrap();
+        assert_eq!(regex.replace("a@1 b@2", r"\2\g{word}").unwrap(), "1a b@2");
+        assert_eq!(
+            regex.replace_all("a@1 b@2", r"\2\g{word}").unwrap(),
+            "1a 2b"
+        );
+
+        let err = regex.replace_all("a@1", r"\g{missing}").err().unwrap();
+        assert_eq!(err.error_type(), "Replacement");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_named_groups() {
+        let regex = JsRegex::new(r"(year:\d{4})-(month:\d+)?").unwrap();
+        let groups = regex.find("2024-").unwrap().named_groups();
+
+        let year = js_sys::Reflect::get(&groups, &JsValue::from_str("year")).unwrap();
+        assert_eq!(year.as_string().as_deref(), Some("2024"));
+        let month = js_sys::Reflect::get(&groups, &JsValue::from_str("month")).unwrap();
+        assert!(month.is_undefined());
+        assert!(js_sys::Reflect::has(&groups, &JsValue::from_str("month")).unwrap());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_exec() {
+        let mut regex = JsRegex::new(r"\d+").unwrap();
+        let first = regex.exec("a1 b22").ok().flatten().unwrap();
+        assert_eq!(first.text(), "1");
+        assert_eq!(regex.last_index(), 2);
+
+        let second = regex.exec("a1 b22").ok().flatten().unwrap();
+        assert_eq!(second.text(), "22");
+        assert!(regex.exec("a1 b22").ok().flatten().is_none());
+        assert_eq!(regex.last_index(), 0);
+
+        regex.set_last_index(4);
+        assert_eq!(regex.exec("a1 b22").ok().flatten().unwrap().start(), 4);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_match_all() {
+        let regex = JsRegex::new(r"\d+").unwrap();
+        assert_eq!(regex.match_all("a1 b22 c333").into_iter().count(), 3);
+        assert_eq!(regex.match_all("abc").into_iter().count(), 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_bytes_round_trip() {
+        let regex = JsRegex::new(r"(word:\w+)!").unwrap();
+        let restored = JsRegex::from_bytes(&regex.to_bytes()).ok().unwrap();
+        let m = restored.find("hi!").unwrap();
+        assert_eq!(m.named_group("word").as_deref(), Some("hi"));
+
+        let err = JsRegex::from_bytes(b"junk").err().unwrap();
+        assert_eq!(err.error_type(), "Program");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_error_span() {
+        let err = JsRegex::new("ab(c").err().unwrap();
+        assert_eq!(err.code(), "E101");
+        assert_eq!(err.position(), Some(4));
+        assert!(err.span().is_some());
+        assert!(err.to_json().contains(r#""span":{"start":4,"end":4}"#));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_error_json_escapes() {
+        let message = "bad \"\\q\"\n\u{1}é";
+        let err = JsError::without_location("Replacement", message.to_string());
+        let json = js_sys::JSON::parse(&err.to_json()).unwrap();
+        let parsed = js_sys::Reflect::get(&json, &"message".into()).unwrap();
+        assert_eq!(parsed.as_string().as_deref(), Some(message));
+
+        let err = JsRegex::new(r#""\(("#).err().unwrap();
+        let json = js_sys::JSON::parse(&err.to_json()).unwrap();
+        let context = js_sys::Reflect::get(&json, &"context".into()).unwrap();
+        assert_eq!(context.as_string(), err.context());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_stream_matcher() {
+        let regex = JsRegex::new(r"\d+").unwrap();
+        let mut stream = JsStreamMatcher::new(&regex);
+        assert_eq!(stream.push("a 1").ok().unwrap().length(), 0);
+        assert_eq!(stream.push("2\nb 3").ok().unwrap().length(), 1);
+        assert_eq!(stream.finish().ok().unwrap().length(), 1);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wasm_tokenize() {
+        let kind = |records: &js_sys::Array, i| {
+            js_sys::Reflect::get(&records.get(i), &"kind".into())
+                .unwrap()
+                .as_string()
+                .unwrap()
+        };
+        let records = tokenize(r"(y:[a-z]{2,3})+");
+        assert_eq!(records.length(), 13);
+        assert_eq!(kind(&records, 0), "groupName");
+        assert_eq!(kind(&records, 2), "class");
+        assert_eq!(kind(&records, 8), "quantifier");
+        assert_eq!(kind(&records, 12), "quantifier");
+
+        let records = tokenize("ab[c");
+        assert_eq!(records.length(), 3);
+        assert_eq!(kind(&records, 2), "error");
+    }
+
+    #[cfg(feature = "transpiler")]
     #[wasm_bindgen_test]
     fn test_wasm_transpile() {
-        let result = JsRegex::transpile("(name:abc)").unwrap();
+        let result = JsRegex::transpile("(name:abc)", None).unwrap();
         assert_eq!(result, "(?<name>abc)");
+        let result = JsRegex::transpile(r"(name:abc)\g{name}", Some("js".into())).unwrap();
+        assert_eq!(result, r"(?<name>abc)\k<name>");
+        let result = JsRegex::transpile("(name:abc)", Some("python".into())).unwrap();
+        assert_eq!(result, "(?P<name>abc)");
+
+        let err = JsRegex::transpile("abc", Some("perl6".into()))
+            .err()
+            .unwrap();
+        assert_eq!(err.error_type(), "Dialect");
     }
 }
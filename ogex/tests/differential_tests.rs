@@ -0,0 +1,223 @@
+//! Differential tests against the `regex` crate
+//!
+//! Random patterns are built from the syntax both engines share and run on
+//! random haystacks. Ogex reports the leftmost-longest match while the
+//! `regex` crate reports the leftmost-first one, so spans are not compared
+//! directly; instead the `regex` crate checks that Ogex's match starts where
+//! its own does, that the pattern matches exactly the reported text, that no
+//! longer match exists from the same start, and that each group captured text
+//! its own sub-pattern matches.
+//!
+//! These tests are slow, so they only run with the `differential` feature:
+//!
+//! ```text
+//! cargo test -p ogex --features differential --test differential_tests
+//! ```
+//!
+//! On a failure, proptest shrinks the pattern and haystack to a minimal
+//! counterexample and records it in `differential_tests.proptest-regressions`.
+
+use ogex::Regex;
+use proptest::prelude::*;
+
+/// A pattern in the syntax both engines share
+#[derive(Debug, Clone)]
+enum Node {
+    Literal(char),
+    Any,
+    Class(&'static str),
+    Shorthand(char),
+    Concat(Vec<Node>),
+    Alternation(Vec<Node>),
+    Repeat(Box<Node>, &'static str),
+    Group(Box<Node>),
+    NonCapturing(Box<Node>),
+}
+
+/// The syntaxes a pattern can be written in
+#[derive(Clone, Copy)]
+enum Syntax {
+    Ogex,
+    Reference,
+}
+
+impl Node {
+    /// Write the pattern, collecting the sub-pattern of each capture group in
+    /// the order the groups are numbered
+    fn write(&self, syntax: Syntax, out: &mut String, groups: &mut Vec<String>) {
+        match self {
+            Node::Literal(c) => out.push(*c),
+            Node::Any => out.push('.'),
+            Node::Class(class) => out.push_str(class),
+            Node::Shorthand(c) => {
+                out.push('\\');
+                out.push(*c);
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    match node {
+                        Node::Alternation(_) => node.write_grouped(syntax, out, groups),
+                        _ => node.write(syntax, out, groups),
+                    }
+                }
+            }
+            Node::Alternation(nodes) => {
+                for (i, node) in nodes.iter().enumerate() {
+                    if i > 0 {
+                        out.push('|');
+                    }
+                    node.write(syntax, out, groups);
+                }
+            }
+            Node::Repeat(node, quantifier) => {
+                match **node {
+                    Node::Concat(_) | Node::Alternation(_) | Node::Repeat(..) => {
+                        node.write_grouped(syntax, out, groups)
+                    }
+                    _ => node.write(syntax, out, groups),
+                }
+                out.push_str(quantifier);
+            }
+            Node::Group(node) => {
+                let index = groups.len();
+                groups.push(String::new());
+                let mut inner = String::new();
+                node.write(syntax, &mut inner, groups);
+                out.push('(');
+                out.push_str(&inner);
+                out.push(')');
+                groups[index] = node.to_string(Syntax::Reference);
+            }
+            Node::NonCapturing(node) => node.write_grouped(syntax, out, groups),
+        }
+    }
+
+    /// Write the pattern inside a non-capturing group
+    fn write_grouped(&self, syntax: Syntax, out: &mut String, groups: &mut Vec<String>) {
+        out.push_str(match syntax {
+            Syntax::Ogex => "(@?:",
+            Syntax::Reference => "(?:",
+        });
+        self.write(syntax, out, groups);
+        out.push(')');
+    }
+
+    fn to_string(&self, syntax: Syntax) -> String {
+        let mut out = String::new();
+        self.write(syntax, &mut out, &mut Vec::new());
+        out
+    }
+
+    /// The reference sub-pattern of each capture group, by group number
+    /// minus one
+    fn groups(&self) -> Vec<String> {
+        let mut groups = Vec::new();
+        self.write(Syntax::Reference, &mut String::new(), &mut groups);
+        groups
+    }
+}
+
+fn atom() -> impl Strategy<Value = Node> {
+    prop_oneof![
+        4 => prop::sample::select(vec!['a', 'b', '1']).prop_map(Node::Literal),
+        1 => Just(Node::Any),
+        1 => prop::sample::select(vec!["[ab]", "[^a]", "[a-b1]", "[^ 1]"]).prop_map(Node::Class),
+        1 => prop::sample::select(vec!['d', 'w', 's', 'D', 'W']).prop_map(Node::Shorthand),
+    ]
+}
+
+fn node() -> impl Strategy<Value = Node> {
+    atom().prop_recursive(4, 24, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 2..4).prop_map(Node::Concat),
+            prop::collection::vec(inner.clone(), 2..4).prop_map(Node::Alternation),
+            (
+                inner.clone(),
+                prop::sample::select(vec!["*", "+", "?", "{2}", "{1,3}", "{0,2}"])
+            )
+                .prop_map(|(node, quantifier)| Node::Repeat(Box::new(node), quantifier)),
+            inner.clone().prop_map(|node| Node::Group(Box::new(node))),
+            inner.prop_map(|node| Node::NonCapturing(Box::new(node))),
+        ]
+    })
+}
+
+/// A reference regex that matches exactly the text `pattern` does
+fn exact(pattern: &str) -> regex::Regex {
+    regex::Regex::new(&format!("^(?:{})$", pattern)).unwrap()
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(1000))]
+
+    #[test]
+    fn test_matches_agree_with_regex_crate(node in node(), haystack in "[ab1 ]{0,10}") {
+        let pattern = node.to_string(Syntax::Ogex);
+        let reference = node.to_string(Syntax::Reference);
+        let ours = Regex::new(&pattern).unwrap();
+        let theirs = regex::Regex::new(&reference).unwrap();
+
+        prop_assert_eq!(
+            ours.is_match(&haystack),
+            theirs.is_match(&haystack),
+            "is_match differs for {:?} on {:?}",
+            pattern,
+            haystack
+        );
+
+        match (ours.find(&haystack), theirs.find(&haystack)) {
+            (None, None) => {}
+            (Some(m), Some(r)) => {
+                prop_assert_eq!(m.start, r.start(), "start differs for {:?} on {:?}", pattern, haystack);
+                let whole = exact(&reference);
+                prop_assert!(
+                    whole.is_match(&haystack[m.start..m.end]),
+                    "{:?} does not match its reported text {:?}",
+                    pattern,
+                    &haystack[m.start..m.end]
+                );
+                for end in m.end + 1..=haystack.len() {
+                    prop_assert!(
+                        !whole.is_match(&haystack[m.start..end]),
+                        "{:?} on {:?} reported {}..{} but {}..{} is longer",
+                        pattern,
+                        haystack,
+                        m.start,
+                        m.end,
+                        m.start,
+                        end
+                    );
+                }
+
+                for (index, group) in node.groups().iter().enumerate() {
+                    let Some((start, end)) = m.group(index as u32 + 1) else {
+                        continue;
+                    };
+                    prop_assert!(
+                        m.start <= start && start <= end && end <= m.end,
+                        "group {} of {:?} on {:?} lies outside the match",
+                        index + 1,
+                        pattern,
+                        haystack
+                    );
+                    prop_assert!(
+                        exact(group).is_match(&haystack[start..end]),
+                        "group {} of {:?} on {:?} captured {:?}",
+                        index + 1,
+                        pattern,
+                        haystack,
+                        &haystack[start..end]
+                    );
+                }
+            }
+            (ours, theirs) => prop_assert!(
+                false,
+                "{:?} on {:?}: ogex found {:?}, regex found {:?}",
+                pattern,
+                haystack,
+                ours.map(|m| (m.start, m.end)),
+                theirs.map(|m| (m.start(), m.end()))
+            ),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Tests for host allocator hooks in the C API
+//!
+//! The allocator is process-wide and fixed by the first allocation, so these
+//! tests live in their own binary and run as a single test.
+
+use std::ffi::{CStr, CString, c_void};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use ogex::ffi::*;
+
+unsafe extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Makes every allocation fail while set
+static EXHAUSTED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn counting_malloc(size: usize) -> *mut c_void {
+    if EXHAUSTED.load(Ordering::SeqCst) {
+        return std::ptr::null_mut();
+    }
+    LIVE.fetch_add(1, Ordering::SeqCst);
+    unsafe { malloc(size) }
+}
+
+unsafe extern "C" fn counting_free(ptr: *mut c_void) {
+    LIVE.fetch_sub(1, Ordering::SeqCst);
+    unsafe { free(ptr) }
+}
+
+#[test]
+fn test_host_allocator() {
+    unsafe {
+        assert_eq!(ogex_set_allocator(counting_malloc, counting_free), 0);
+
+        let pattern = CString::new(r"(\d+)").unwrap();
+        let regex = ogex_compile(pattern.as_ptr(), std::ptr::null_mut());
+        assert!(!regex.is_null());
+        assert_eq!(LIVE.load(Ordering::SeqCst), 1);
+
+        let input = CString::new("abc 42").unwrap();
+        let m = ogex_find(regex, input.as_ptr(), std::ptr::null_mut());
+        let text = ogex_match_text(m);
+        assert_eq!(CStr::from_ptr(text).to_str().unwrap(), "42");
+        assert_eq!(LIVE.load(Ordering::SeqCst), 3);
+
+        let bad = CString::new("(a").unwrap();
+        let mut error = std::ptr::null_mut();
+        assert!(ogex_compile(bad.as_ptr(), &mut error).is_null());
+        assert!(!error.is_null());
+        assert_eq!(LIVE.load(Ordering::SeqCst), 4);
+
+        EXHAUSTED.store(true, Ordering::SeqCst);
+        let mut code = OgexErrorCode::Ok;
+        assert!(ogex_compile_code(pattern.as_ptr(), std::ptr::null_mut(), &mut code).is_null());
+        assert_eq!(code, OgexErrorCode::AllocationFailed);
+        EXHAUSTED.store(false, Ordering::SeqCst);
+        assert_eq!(LIVE.load(Ordering::SeqCst), 4);
+
+        ogex_free_error(error);
+        ogex_free_string(text);
+        ogex_free_match(m);
+        ogex_free_regex(regex);
+        assert_eq!(LIVE.load(Ordering::SeqCst), 0);
+
+        // The allocator cannot change once it has been used
+        assert_eq!(ogex_set_allocator(counting_malloc, counting_free), -1);
+    }
+}
@@ -2,7 +2,7 @@
 //!
 //! These tests verify invariants using randomly generated inputs.
 
-use ogex::{Regex, Replacement, compile, parse};
+use ogex::{Generator, Regex, Replacement, compile, parse};
 use proptest::prelude::*;
 
 // =============================================================================
@@ -181,6 +181,25 @@ proptest! {
     }
 }
 
+proptest! {
+    // Generated samples should always be matched by their pattern
+    #[test]
+    fn test_generated_samples_match(seed in any::<u64>(), max_repeat in 0u32..5) {
+        for pattern in [r"[a-z]+\d{2,}", r"(x:ab|c)-\g{x}", r"\w+@\w+\.(com|org)", r"[^0-9]\s?."] {
+            let regex = Regex::new(pattern).unwrap();
+            let mut generator = Generator::from_regex(&regex)
+                .unwrap()
+                .max_repeat(max_repeat)
+                .seed(seed);
+            let sample = generator.generate();
+            prop_assert!(sample.is_some(), "no sample for {}", pattern);
+            let sample = sample.unwrap();
+            let found = regex.find(&sample);
+            prop_assert!(found.is_some(), "{} does not match {:?}", pattern, sample);
+        }
+    }
+}
+
 // =============================================================================
 // Tests: Replacement invariants
 // =============================================================================
@@ -0,0 +1,115 @@
+//! Tests for the spans and events of the `tracing` feature
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use ogex::{Regex, RegexBuilder};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// Writes each span, field and event it sees as a line of text
+#[derive(Clone, Default)]
+struct Recorder {
+    lines: Arc<Mutex<Vec<String>>>,
+    next_id: Arc<Mutex<u64>>,
+}
+
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.push_str(&format!(" {}={:?}", field.name(), value));
+    }
+}
+
+impl Recorder {
+    fn push(&self, line: String) {
+        self.lines.lock().unwrap().push(line);
+    }
+
+    fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().clone()
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.target().starts_with("ogex")
+    }
+
+    fn max_level_hint(&self) -> Option<tracing::level_filters::LevelFilter> {
+        Some(Level::TRACE.into())
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields(format!("span {}", span.metadata().name()));
+        span.record(&mut fields);
+        self.push(fields.0);
+        let mut next_id = self.next_id.lock().unwrap();
+        *next_id += 1;
+        Id::from_u64(*next_id)
+    }
+
+    fn record(&self, _: &Id, values: &Record<'_>) {
+        let mut fields = Fields("record".to_string());
+        values.record(&mut fields);
+        self.push(fields.0);
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields("event".to_string());
+        event.record(&mut fields);
+        self.push(fields.0);
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+fn record(f: impl FnOnce()) -> Vec<String> {
+    let recorder = Recorder::default();
+    tracing::subscriber::with_default(recorder.clone(), f);
+    recorder.lines()
+}
+
+#[test]
+fn test_compile_reports_stages() {
+    let lines = record(|| {
+        Regex::new("(a)b|c").unwrap();
+    });
+    assert_eq!(lines[0], r#"span compile pattern=(a)b|c"#);
+    assert!(lines[1].starts_with("event message=parsed elapsed_us="));
+    assert!(lines[2].starts_with("event message=built automaton elapsed_us="));
+    assert!(lines[2].ends_with(" groups=1"));
+    assert!(lines[2].contains(" states="));
+}
+
+#[test]
+fn test_search_records_steps() {
+    let regex = Regex::new("b").unwrap();
+    let lines = record(|| {
+        assert!(regex.is_match("aab"));
+        assert!(!regex.is_match("x"));
+    });
+    assert_eq!(
+        lines,
+        vec![
+            "span search start=0",
+            "record steps=3",
+            "record matched=true",
+            "span search start=0",
+            "record steps=1",
+            "record matched=false",
+        ]
+    );
+
+    let regex = RegexBuilder::new("(a*)*b").step_limit(10).build().unwrap();
+    let lines = record(|| {
+        assert!(regex.find("aaaaaaaaaaaa").is_none());
+    });
+    assert!(lines.contains(&"event message=step limit exceeded steps=10".to_string()));
+}